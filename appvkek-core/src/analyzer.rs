@@ -0,0 +1,241 @@
+//! Post-scan analyzers which receive the full set of approvals, and append
+//! findings to them, so that custom risk rules e.g. organization's internal
+//! denylists can be plugged in without forking.
+//!
+//! Implement `Analyzer`, then register it into `AnalyzerRegistry`. Analyzers
+//! which are not written in Rust can be plugged in as an external command via
+//! `CommandAnalyzer`.
+
+use ::evmscan::prelude::ChainType;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::labels::Labels;
+use crate::report::build_report;
+use crate::risk::MAX_SCORE;
+use crate::types::*;
+
+/// Finding of an analyzer on a single approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    /// Token contract address of approval
+    pub token_address: String,
+
+    /// Spender address of approval
+    pub spender: String,
+
+    /// Score to add to risk score of approval; 0 for annotation only
+    #[serde(default)]
+    pub score: u32,
+
+    /// Short description of finding e.g. `org-denylist`
+    pub reason: String,
+}
+
+/// Information of the scan given to analyzers.
+pub struct AnalysisContext<'a> {
+    /// Chain type
+    pub chain: ChainType,
+
+    /// Owner wallet address; lowercased
+    pub owner_address: &'a str,
+
+    /// Labels of well-known addresses
+    pub labels: &'a Labels,
+}
+
+/// Analyzer of approvals run after risk assessment.
+pub trait Analyzer: Send + Sync {
+    /// Name of analyzer; used to prefix reasons of its findings.
+    fn name(&self) -> &str;
+
+    /// Analyze all approvals, and return findings.
+    ///
+    /// # Arguments
+    /// * `ctx` - information of the scan
+    /// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+    fn analyze(&self, ctx: &AnalysisContext<'_>, results: &[QueryResult]) -> Result<Vec<Finding>, String>;
+}
+
+/// Registry of analyzers to run in order of registration.
+#[derive(Default)]
+pub struct AnalyzerRegistry {
+    analyzers: Vec<Box<dyn Analyzer>>,
+}
+
+impl AnalyzerRegistry {
+    /// Register the specified analyzer.
+    ///
+    /// # Arguments
+    /// * `analyzer` - analyzer to register
+    pub fn register(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.analyzers.push(analyzer);
+    }
+
+    /// Return whether there is no registered analyzer.
+    pub fn is_empty(&self) -> bool {
+        self.analyzers.is_empty()
+    }
+
+    /// Run all registered analyzers, then apply their findings to the
+    /// matching approvals; score is added to risk score (capped at
+    /// `MAX_SCORE`), and reason is appended prefixed with analyzer's name.
+    /// Analyzers which failed are skipped with a warning, and findings which
+    /// match no approval are ignored.
+    ///
+    /// # Arguments
+    /// * `ctx` - information of the scan
+    /// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+    pub fn run(&self, ctx: &AnalysisContext<'_>, results: &mut [QueryResult]) {
+        for analyzer in self.analyzers.iter() {
+            let findings = match analyzer.analyze(ctx, results) {
+                Ok(res) => res,
+                Err(e) => {
+                    tracing::warn!("Error running analyzer ({}); err={}", analyzer.name(), e);
+                    continue;
+                }
+            };
+
+            for finding in findings {
+                let sa = results.iter_mut()
+                    .filter_map(|res| res.as_mut().ok())
+                    .filter(|r| r.address.eq_ignore_ascii_case(&finding.token_address))
+                    .find_map(|r| r.spender_allowances.iter_mut().find(|(spender, _)| spender.eq_ignore_ascii_case(&finding.spender)).map(|(_, sa)| sa));
+                match sa {
+                    Some(sa) => {
+                        sa.risk.score = sa.risk.score.saturating_add(finding.score).min(MAX_SCORE);
+                        sa.risk.reasons.push(format!("{}:{}", analyzer.name(), finding.reason));
+                    },
+                    None => tracing::debug!("Analyzer ({}) reported finding of unknown approval; token={}, spender={}", analyzer.name(), finding.token_address, finding.spender),
+                }
+            }
+        }
+    }
+}
+
+/// Analyzer running an external command.
+/// The command receives report of the scan in JSON (as of `report::Report`)
+/// via stdin, and has to print JSON array of `Finding` to stdout.
+pub struct CommandAnalyzer {
+    /// Path to executable
+    path: String,
+}
+
+impl CommandAnalyzer {
+    /// Create analyzer running the specified executable without arguments.
+    ///
+    /// # Arguments
+    /// * `path` - path to executable
+    pub fn new(path: &str) -> CommandAnalyzer {
+        CommandAnalyzer { path: path.to_owned() }
+    }
+}
+
+impl Analyzer for CommandAnalyzer {
+    fn name(&self) -> &str {
+        std::path::Path::new(&self.path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&self.path)
+    }
+
+    fn analyze(&self, ctx: &AnalysisContext<'_>, results: &[QueryResult]) -> Result<Vec<Finding>, String> {
+        let report = build_report(ctx.chain, ctx.owner_address, results, ctx.labels);
+        let input = match serde_json::to_vec(&report) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error serializing report; err={}", e)),
+        };
+
+        let mut child = match Command::new(&self.path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn() {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error spawning {}; err={}", self.path, e)),
+        };
+        // command may exit without reading all of its input, so ignore error here
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(&input);
+        }
+        let output = match child.wait_with_output() {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error waiting for {}; err={}", self.path, e)),
+        };
+        if !output.status.success() {
+            return Err(format!("Error {} exited with {}", self.path, output.status));
+        }
+
+        match serde_json::from_slice::<Vec<Finding>>(&output.stdout) {
+            Ok(res) => Ok(res),
+            Err(e) => Err(format!("Error parsing findings printed by {}; err={}", self.path, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static SPENDER: &str = "0x3333333333333333333333333333333333333333";
+
+    struct Denylist(u32);
+
+    impl Analyzer for Denylist {
+        fn name(&self) -> &str {
+            "denylist"
+        }
+
+        fn analyze(&self, _ctx: &AnalysisContext<'_>, results: &[QueryResult]) -> Result<Vec<Finding>, String> {
+            Ok(results.iter()
+                .filter_map(|res| res.as_ref().ok())
+                .flat_map(|r| r.spender_allowances.keys().map(move |spender| Finding {
+                    token_address: r.address.to_uppercase(),
+                    spender: spender.to_owned(),
+                    score: self.0,
+                    reason: "blocked".to_owned(),
+                }))
+                .collect())
+        }
+    }
+
+    fn results_with_score(score: u32) -> Vec<QueryResult> {
+        let mut sa = crate::scanner::make_spender_allowance(1.into(), 0, ApprovalRecord::default()).unwrap();
+        sa.risk.score = score;
+        vec![Ok(TokenContractWithSpenderAllowances {
+            name: "Token".to_owned(),
+            symbol: "TKN".to_owned(),
+            address: TOKEN.to_owned(),
+            decimals: 0,
+            balance: None,
+            total_supply: None,
+            spender_allowances: HashMap::from([(SPENDER.to_owned(), sa)]),
+        })]
+    }
+
+    fn run_denylist(finding_score: u32, results: &mut [QueryResult]) {
+        let mut registry = AnalyzerRegistry::default();
+        registry.register(Box::new(Denylist(finding_score)));
+        let labels = Labels::default();
+        let ctx = AnalysisContext { chain: ChainType::BSC, owner_address: "0x", labels: &labels };
+        registry.run(&ctx, results);
+    }
+
+    #[test]
+    fn run_applies_findings_to_matching_approvals() {
+        let mut results = results_with_score(40);
+        run_denylist(80, &mut results);
+
+        let risk = &results[0].as_ref().unwrap().spender_allowances[SPENDER].risk;
+        assert_eq!(risk.score, MAX_SCORE);
+        assert_eq!(risk.reasons, vec!["denylist:blocked".to_owned()]);
+    }
+
+    #[test]
+    fn run_caps_score_of_huge_finding() {
+        let mut results = results_with_score(40);
+        run_denylist(u32::MAX, &mut results);
+
+        let risk = &results[0].as_ref().unwrap().spender_allowances[SPENDER].risk;
+        assert_eq!(risk.score, MAX_SCORE);
+    }
+}
@@ -0,0 +1,153 @@
+use ::evmscan::prelude::*;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+
+use crate::error::AppvkekError;
+use crate::provider::ChainRpc;
+use crate::usage;
+use crate::util::*;
+
+/// Result of `eth_getCode` of an address at the time it was checked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeRecord {
+    /// Keccak-256 hash of code in hex string; `None` if address has no code
+    pub code_hash: Option<String>,
+
+    /// Unix timestamp of when it was checked
+    pub checked_at: u64,
+}
+
+/// Persistent cache of `eth_getCode` results of a single chain.
+/// It is stored at `$HOME/.appvkek/cache/code-<chain>.json`.
+///
+/// Entries expire after `ttl`, as contract can be deployed later at an
+/// address which has no code now, or destroyed.
+#[derive(Debug, Default)]
+pub struct CodeCache {
+    /// Path to cache file; `None` if cache is disabled
+    path: Option<String>,
+
+    /// Time-to-live of each entry in seconds
+    ttl_secs: u64,
+
+    /// Address (lowercased) to its code record
+    entries: HashMap<String, CodeRecord>,
+
+    /// Whether there are new entries not yet saved
+    is_dirty: bool,
+}
+
+impl CodeCache {
+    /// Load cache of the specified chain.
+    /// Corrupted cache file is ignored, and will be overwritten on next save.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `ttl` - time-to-live of each entry; zero disables cache
+    pub fn load(chain: ChainType, ttl: std::time::Duration) -> CodeCache {
+        if ttl.is_zero() {
+            return CodeCache::default();
+        }
+
+        let path = format!("{}/code-{}.json", cache_dir(), chain_name(chain));
+        let entries = std::fs::read_to_string(&path).ok()
+            .and_then(|content| serde_json::from_str::<HashMap<String, CodeRecord>>(&content).ok())
+            .unwrap_or_default();
+
+        CodeCache {
+            path: Some(path),
+            ttl_secs: ttl.as_secs(),
+            entries,
+            is_dirty: false,
+        }
+    }
+
+    /// Get non-expired code record of the specified address.
+    ///
+    /// # Arguments
+    /// * `address` - address to get
+    pub fn get(&self, address: &str) -> Option<&CodeRecord> {
+        let res = self.entries.get(&address.to_lowercase())
+            .filter(|r| unix_timestamp().saturating_sub(r.checked_at) < self.ttl_secs);
+        usage::record_cache_lookup(res.is_some());
+        res
+    }
+
+    /// Insert code record of the specified address.
+    ///
+    /// # Arguments
+    /// * `address` - address
+    /// * `record` - code record
+    pub fn insert(&mut self, address: &str, record: CodeRecord) {
+        if self.path.is_none() {
+            return;
+        }
+        self.entries.insert(address.to_lowercase(), record);
+        self.is_dirty = true;
+    }
+
+    /// Save cache to disk if there are new entries.
+    /// Expired entries are dropped.
+    pub fn save(&mut self) -> Result<(), AppvkekError> {
+        let path = match &self.path {
+            Some(path) if self.is_dirty => path,
+            _ => return Ok(()),
+        };
+
+        let now = unix_timestamp();
+        let ttl_secs = self.ttl_secs;
+        self.entries.retain(|_, r| now.saturating_sub(r.checked_at) < ttl_secs);
+
+        let content = match serde_json::to_string(&self.entries) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Io(format!("Error serializing code cache; err={}", e))),
+        };
+        write_file(path, &content)?;
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
+
+/// Check whether each of the specified addresses is an EOA, using cached
+/// results where possible. Addresses which failed to be checked are mapped
+/// to `None`.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `cache` - code cache to look up, and insert new results into
+/// * `addresses` - addresses to check; lowercased
+pub async fn check_is_eoa_all<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, cache: &mut CodeCache, addresses: &[String]) -> HashMap<String, Option<bool>> {
+    let mut ret: HashMap<String, Option<bool>> = HashMap::new();
+    let mut uncached: Vec<&String> = Vec::new();
+    for address in addresses {
+        match cache.get(address) {
+            Some(record) => {
+                ret.insert(address.to_owned(), Some(record.code_hash.is_none()));
+            },
+            None => uncached.push(address),
+        }
+    }
+
+    let code_hash_results = join_all(uncached.iter().map(|address| with_permit(semaphore, get_code_hash(rpc, address)))).await;
+    for (address, res) in uncached.into_iter().zip(code_hash_results) {
+        match res {
+            Ok(code_hash) => {
+                ret.insert(address.to_owned(), Some(code_hash.is_none()));
+                cache.insert(address, CodeRecord { code_hash, checked_at: unix_timestamp() });
+            },
+            Err(_) => {
+                ret.insert(address.to_owned(), None);
+            }
+        }
+    }
+
+    if let Err(e) = cache.save() {
+        tracing::warn!("{}", e);
+    }
+
+    ret
+}
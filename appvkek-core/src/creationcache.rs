@@ -0,0 +1,88 @@
+use ::evmscan::prelude::*;
+use std::collections::HashMap;
+
+use crate::error::AppvkekError;
+use crate::explorer::ContractCreation;
+use crate::usage;
+use crate::util::*;
+
+/// Persistent cache of creation records of contracts of a single chain.
+/// It is stored at `$HOME/.appvkek/cache/creation-<chain>.json`.
+///
+/// Entries never expire as creation record of a contract never changes once
+/// created. Addresses which have never been created as contract are not
+/// cached, as contract can be deployed at them later.
+#[derive(Debug, Default)]
+pub struct CreationCache {
+    /// Path to cache file; `None` if cache is only kept in memory
+    path: Option<String>,
+
+    /// Contract address (lowercased) to its creation record
+    entries: HashMap<String, ContractCreation>,
+
+    /// Whether there are new entries not yet saved
+    is_dirty: bool,
+}
+
+impl CreationCache {
+    /// Load cache of the specified chain.
+    /// Corrupted cache file is ignored, and will be overwritten on next save.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `enabled` - whether to use persistent cache; if `false`, cache starts
+    ///   empty, and is only kept in memory
+    pub fn load(chain: ChainType, enabled: bool) -> CreationCache {
+        if !enabled {
+            return CreationCache::default();
+        }
+
+        let path = format!("{}/creation-{}.json", cache_dir(), chain_name(chain));
+        let entries = std::fs::read_to_string(&path).ok()
+            .and_then(|content| serde_json::from_str::<HashMap<String, ContractCreation>>(&content).ok())
+            .unwrap_or_default();
+
+        CreationCache {
+            path: Some(path),
+            entries,
+            is_dirty: false,
+        }
+    }
+
+    /// Get creation record of the specified contract address.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    pub fn get(&self, address: &str) -> Option<&ContractCreation> {
+        let res = self.entries.get(&address.to_lowercase());
+        usage::record_cache_lookup(res.is_some());
+        res
+    }
+
+    /// Insert creation record of the specified contract address.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    /// * `creation` - creation record
+    pub fn insert(&mut self, address: &str, creation: ContractCreation) {
+        self.entries.insert(address.to_lowercase(), creation);
+        self.is_dirty = true;
+    }
+
+    /// Save cache to disk if there are new entries.
+    pub fn save(&mut self) -> Result<(), AppvkekError> {
+        let path = match &self.path {
+            Some(path) if self.is_dirty => path,
+            _ => return Ok(()),
+        };
+
+        let content = match serde_json::to_string(&self.entries) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Io(format!("Error serializing contract creation cache; err={}", e))),
+        };
+        write_file(path, &content)?;
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
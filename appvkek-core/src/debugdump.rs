@@ -0,0 +1,178 @@
+use jsonrpc_core::{Call, Value};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::error::AppvkekError;
+use crate::util::*;
+
+/// Whether dump is enabled; checked before building any entry
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// File to dump entries into
+static DUMP_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Placeholder of redacted secrets
+const REDACTED: &str = "<redacted>";
+
+/// A single request along with its response; a line of dump file.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DumpEntry {
+    /// Unix timestamp (in seconds) of when response was received
+    pub time: u64,
+
+    /// Either `rpc`, or `explorer`
+    pub kind: String,
+
+    /// JSON-RPC request, or URL of explorer API call
+    pub request: Value,
+
+    /// JSON-RPC result, or body of explorer API response; `None` on error
+    pub response: Option<Value>,
+
+    /// HTTP status of explorer API response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Error if request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Start dumping every JSON-RPC, and explorer API request along with its
+/// response into the specified file as JSON lines, for troubleshooting.
+/// Subsequent calls once started are no-op.
+///
+/// API keys in explorer URLs are redacted, and URL of RPC endpoint which may
+/// embed one is not dumped; its path is redacted from error messages.
+///
+/// # Arguments
+/// * `path` - path to dump file; truncated if exists
+pub fn open(path: &str) -> Result<(), AppvkekError> {
+    let mut dump_file = DUMP_FILE.lock().map_err(|_| AppvkekError::Config("Error locking dump file".to_owned()))?;
+    if dump_file.is_some() {
+        return Ok(());
+    }
+    match File::create(path) {
+        Ok(res) => *dump_file = Some(res),
+        Err(e) => return Err(AppvkekError::Config(format!("Error creating dump file ({}); err={}", path, e))),
+    }
+    IS_ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Return whether dump is enabled.
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Return the specified URL with value of `apikey` query parameter redacted.
+///
+/// # Arguments
+/// * `url` - URL of explorer API call
+pub fn redact_url(url: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some(res) => res,
+        None => return url.to_owned(),
+    };
+    let params: Vec<String> = query.split('&')
+        .map(|param| match param.split_once('=') {
+            Some((name, _)) if name.eq_ignore_ascii_case("apikey") => format!("{}={}", name, REDACTED),
+            _ => param.to_owned(),
+        })
+        .collect();
+    format!("{}?{}", base, params.join("&"))
+}
+
+/// Return the specified error message with path of any URL in it redacted,
+/// as RPC endpoint URL often embeds API key e.g. `/v2/<key>`.
+///
+/// # Arguments
+/// * `message` - error message
+fn redact_message(message: &str) -> String {
+    let re = regex::Regex::new(r"(https?://[^/\s)]+)/[^\s)]+").unwrap();
+    re.replace_all(message, format!("${{1}}/{}", REDACTED)).into_owned()
+}
+
+/// Append the specified entry to dump file.
+///
+/// # Arguments
+/// * `entry` - entry to dump
+fn write_entry(entry: &DumpEntry) {
+    let line = match serde_json::to_string(entry) {
+        Ok(res) => res,
+        Err(e) => {
+            tracing::warn!("Error serializing dump entry; err={}", e);
+            return;
+        }
+    };
+    if let Ok(mut dump_file) = DUMP_FILE.lock() {
+        if let Some(file) = dump_file.as_mut() {
+            if let Err(e) = writeln!(file, "{}", line) {
+                tracing::warn!("Error writing dump file; err={}", e);
+            }
+        }
+    }
+}
+
+/// Dump JSON-RPC request along with its result.
+///
+/// # Arguments
+/// * `call` - JSON-RPC request
+/// * `res` - its result
+pub(crate) fn record_rpc(call: &Call, res: &web3::error::Result<Value>) {
+    if !is_enabled() {
+        return;
+    }
+    let (response, error) = match res {
+        Ok(value) => (Some(value.clone()), None),
+        Err(e) => (None, Some(redact_message(&e.to_string()))),
+    };
+    write_entry(&DumpEntry {
+        time: unix_timestamp(),
+        kind: "rpc".to_owned(),
+        request: serde_json::to_value(call).unwrap_or(Value::Null),
+        response,
+        status: None,
+        error,
+    });
+}
+
+/// Dump explorer API call along with its response.
+///
+/// # Arguments
+/// * `url` - URL of API call; `apikey` is redacted
+/// * `res` - HTTP status, and body of response
+pub(crate) fn record_explorer(url: &str, res: Result<(u16, &str), &AppvkekError>) {
+    if !is_enabled() {
+        return;
+    }
+    let (status, response, error) = match res {
+        // keep body as is if it's not JSON e.g. HTML error page
+        Ok((status, body)) => (Some(status), Some(serde_json::from_str::<Value>(body).unwrap_or_else(|_| Value::String(body.to_owned()))), None),
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+    write_entry(&DumpEntry {
+        time: unix_timestamp(),
+        kind: "explorer".to_owned(),
+        request: Value::String(redact_url(url)),
+        response,
+        status,
+        error,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_hides_api_keys() {
+        assert_eq!(redact_url("https://api.bscscan.com/api?module=account&action=txlist&apikey=SECRET&page=1"), "https://api.bscscan.com/api?module=account&action=txlist&apikey=<redacted>&page=1");
+        assert_eq!(redact_url("https://api.bscscan.com/api"), "https://api.bscscan.com/api");
+        assert_eq!(redact_message("error sending request for url (https://eth.example.com/v2/SECRET): timed out"), "error sending request for url (https://eth.example.com/<redacted>): timed out");
+        assert_eq!(redact_message("error sending request for url (https://bsc-dataseed.binance.org/): timed out"), "error sending request for url (https://bsc-dataseed.binance.org/): timed out");
+    }
+}
@@ -0,0 +1,218 @@
+use futures::future::join_all;
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+use web3::ethabi::{self, ParamType, Token};
+use web3::types::Address;
+
+use crate::error::AppvkekError;
+use crate::provider::ChainRpc;
+use crate::util::*;
+
+/// ENS registry address; the same on Ethereum mainnet, and testnets
+static ENS_REGISTRY_ADDRESS: &str = "0x00000000000c2e074ec69a0dfb2997ba6c7d2e1e";
+
+/// Method-id of `resolver(bytes32)` of ENS registry
+static RESOLVER_METHOD_ID: [u8; 4] = [0x01, 0x78, 0xb8, 0xbf];
+
+/// Method-id of `addr(bytes32)` of ENS resolver
+static ADDR_METHOD_ID: [u8; 4] = [0x3b, 0x3b, 0x57, 0xde];
+
+/// Method-id of `name(bytes32)` of ENS reverse resolver
+static NAME_METHOD_ID: [u8; 4] = [0x69, 0x1f, 0x34, 0x31];
+
+/// Return whether the specified input looks like ENS name rather than
+/// address e.g. `vitalik.eth`.
+///
+/// # Arguments
+/// * `input` - wallet address or ENS name
+pub fn is_ens_name(input: &str) -> bool {
+    input.contains('.') && !validate_address_format(input)
+}
+
+/// Compute namehash of the specified ENS name as of EIP-137.
+/// Name is only lowercased, not fully normalized as of UTS-46.
+///
+/// # Arguments
+/// * `name` - ENS name e.g. `vitalik.eth`
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.to_lowercase().rsplit('.') {
+        let mut data = node.to_vec();
+        data.extend(web3::signing::keccak256(label.as_bytes()));
+        node = web3::signing::keccak256(&data);
+    }
+    node
+}
+
+/// Decode address from 32-bytes returned data; `None` if it's zero.
+///
+/// # Arguments
+/// * `bytes` - returned data
+fn decode_address(bytes: &[u8]) -> Option<Address> {
+    if bytes.len() < 32 || bytes[..32].iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(Address::from_slice(&bytes[12..32]))
+}
+
+/// Return resolver of the specified node if any.
+///
+/// # Arguments
+/// * `rpc` - chain RPC of Ethereum mainnet
+/// * `node` - namehash of name
+/// * `name` - name; for error message
+async fn get_resolver<R: ChainRpc + ?Sized>(rpc: &R, node: &Token, name: &str) -> Result<Option<Address>, AppvkekError> {
+    let mut data = RESOLVER_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(std::slice::from_ref(node)));
+    match rpc.call(get_address_from_str(ENS_REGISTRY_ADDRESS)?, data, None).await {
+        Ok(res) => Ok(decode_address(&res)),
+        Err(e) => Err(e.with_context(&format!("Error querying resolver of ENS name ({})", name))),
+    }
+}
+
+/// Resolve the specified ENS name into address via its resolver.
+/// Return `None` if the name has no resolver, or no address set.
+/// `rpc` has to be connected to Ethereum mainnet regardless of target chain.
+///
+/// # Arguments
+/// * `rpc` - chain RPC of Ethereum mainnet
+/// * `name` - ENS name e.g. `vitalik.eth`
+pub async fn resolve_name<R: ChainRpc + ?Sized>(rpc: &R, name: &str) -> Result<Option<String>, AppvkekError> {
+    let node = Token::FixedBytes(namehash(name).to_vec());
+    let resolver = match get_resolver(rpc, &node, name).await? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+
+    let mut data = ADDR_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[node]));
+    let output = match rpc.call(resolver, data, None).await {
+        Ok(res) => res,
+        Err(e) => return Err(e.with_context(&format!("Error resolving ENS name ({})", name))),
+    };
+
+    Ok(decode_address(&output).map(|a| format!("0x{}", hex::encode(a.as_bytes()))))
+}
+
+/// Look up primary ENS name of the specified address via reverse record.
+/// The name is only returned if it also resolves back to the address, as
+/// anyone can set reverse record of their address to any name.
+///
+/// # Arguments
+/// * `rpc` - chain RPC of Ethereum mainnet
+/// * `address` - address to look up; lowercased, prefixed with '0x'
+pub async fn lookup_address<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<Option<String>, AppvkekError> {
+    let reverse_name = format!("{}.addr.reverse", address.trim_start_matches("0x").to_lowercase());
+    let node = Token::FixedBytes(namehash(&reverse_name).to_vec());
+    let resolver = match get_resolver(rpc, &node, &reverse_name).await? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+
+    let mut data = NAME_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[node]));
+    let output = match rpc.call(resolver, data, None).await {
+        Ok(res) => res,
+        Err(e) => return Err(e.with_context(&format!("Error querying name of reverse record ({})", reverse_name))),
+    };
+    let name = match ethabi::decode(&[ParamType::String], &output).ok().and_then(|mut t| t.pop()) {
+        Some(Token::String(res)) if !res.is_empty() => res,
+        _ => return Ok(None),
+    };
+
+    // verify forward resolution
+    match resolve_name(rpc, &name).await? {
+        Some(resolved) if resolved.eq_ignore_ascii_case(address) => Ok(Some(name)),
+        _ => Ok(None),
+    }
+}
+
+/// Look up primary ENS names of all the specified addresses concurrently.
+/// Addresses without name, or which failed to be looked up are left out.
+///
+/// # Arguments
+/// * `rpc` - chain RPC of Ethereum mainnet
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `addresses` - addresses to look up; lowercased, prefixed with '0x'
+pub async fn lookup_addresses<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, addresses: &[String]) -> HashMap<String, String> {
+    let results = join_all(addresses.iter().map(|address| with_permit(semaphore, lookup_address(rpc, address)))).await;
+
+    let mut ret: HashMap<String, String> = HashMap::new();
+    for (address, res) in addresses.iter().zip(results) {
+        match res {
+            Ok(Some(name)) => {
+                ret.insert(address.to_lowercase(), name);
+            },
+            Ok(None) => (),
+            Err(e) => tracing::debug!("{}", e),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockChainRpc;
+
+    #[test]
+    fn namehash_matches_eip137() {
+        assert_eq!(hex::encode(namehash("")), "0000000000000000000000000000000000000000000000000000000000000000");
+        assert_eq!(hex::encode(namehash("eth")), "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae");
+        assert_eq!(hex::encode(namehash("foo.eth")), "de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f");
+    }
+
+    #[tokio::test]
+    async fn resolve_name_via_resolver() {
+        let resolver = "0x4976fb03c32e5b8cfe2b6ccb31c09ba78ebaba41";
+        let owner = "0xd8da6bf26964af9d7eed9e03e53415d37aa96045";
+        let node = Token::FixedBytes(namehash("vitalik.eth").to_vec());
+
+        let mut resolver_call = RESOLVER_METHOD_ID.to_vec();
+        resolver_call.extend(ethabi::encode(std::slice::from_ref(&node)));
+        let mut addr_call = ADDR_METHOD_ID.to_vec();
+        addr_call.extend(ethabi::encode(&[node]));
+        let mut unknown_call = RESOLVER_METHOD_ID.to_vec();
+        unknown_call.extend(ethabi::encode(&[Token::FixedBytes(namehash("nobody.eth").to_vec())]));
+        let rpc = MockChainRpc::default()
+            .with_call(ENS_REGISTRY_ADDRESS, resolver_call, ethabi::encode(&[Token::Address(get_address_from_str(resolver).unwrap())]))
+            .with_call(resolver, addr_call, ethabi::encode(&[Token::Address(get_address_from_str(owner).unwrap())]))
+            .with_call(ENS_REGISTRY_ADDRESS, unknown_call, ethabi::encode(&[Token::Address(Address::zero())]));
+
+        assert_eq!(resolve_name(&rpc, "Vitalik.eth").await.unwrap(), Some(owner.to_owned()));
+        assert_eq!(resolve_name(&rpc, "nobody.eth").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn lookup_address_verifies_forward_resolution() {
+        let resolver = "0x4976fb03c32e5b8cfe2b6ccb31c09ba78ebaba41";
+        let owner = "0xd8da6bf26964af9d7eed9e03e53415d37aa96045";
+        let impostor = "0x1111111111111111111111111111111111111111";
+
+        let mut rpc = MockChainRpc::default();
+        for (name, address) in [("vitalik.eth", owner), ("1111111111111111111111111111111111111111.addr.reverse", impostor), ("d8da6bf26964af9d7eed9e03e53415d37aa96045.addr.reverse", owner)] {
+            let node = Token::FixedBytes(namehash(name).to_vec());
+            let mut resolver_call = RESOLVER_METHOD_ID.to_vec();
+            resolver_call.extend(ethabi::encode(std::slice::from_ref(&node)));
+            rpc = rpc.with_call(ENS_REGISTRY_ADDRESS, resolver_call, ethabi::encode(&[Token::Address(get_address_from_str(resolver).unwrap())]));
+
+            if name.ends_with(".addr.reverse") {
+                // both claim to be vitalik.eth
+                let mut name_call = NAME_METHOD_ID.to_vec();
+                name_call.extend(ethabi::encode(&[node]));
+                rpc = rpc.with_call(resolver, name_call, ethabi::encode(&[Token::String("vitalik.eth".to_owned())]));
+            }
+            else {
+                let mut addr_call = ADDR_METHOD_ID.to_vec();
+                addr_call.extend(ethabi::encode(&[node]));
+                rpc = rpc.with_call(resolver, addr_call, ethabi::encode(&[Token::Address(get_address_from_str(address).unwrap())]));
+            }
+        }
+
+        assert_eq!(lookup_address(&rpc, owner).await.unwrap(), Some("vitalik.eth".to_owned()));
+        assert_eq!(lookup_address(&rpc, impostor).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,86 @@
+use web3::ethabi::{self, ParamType, Token};
+use web3::types::{Address, U256};
+
+/// Method-id of `name()`
+pub(crate) static NAME_METHOD_ID: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+
+/// Method-id of `symbol()`
+pub(crate) static SYMBOL_METHOD_ID: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+
+/// Method-id of `decimals()`
+pub(crate) static DECIMALS_METHOD_ID: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Method-id of `approve(address,uint256)`
+pub(crate) static APPROVE_METHOD_ID: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+
+/// Method-id of `increaseAllowance(address,uint256)`
+pub(crate) static INCREASE_ALLOWANCE_METHOD_ID: [u8; 4] = [0x39, 0x50, 0x93, 0x51];
+
+/// Method-id of `allowance(address,address)`
+pub(crate) static ALLOWANCE_METHOD_ID: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+
+/// Method-id of `totalSupply()`
+pub(crate) static TOTAL_SUPPLY_METHOD_ID: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+
+/// Method-id of `balanceOf(address)`
+pub(crate) static BALANCE_OF_METHOD_ID: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Encode calldata of `allowance(owner, spender)`.
+///
+/// # Arguments
+/// * `owner` - owner address
+/// * `spender` - spender address
+pub(crate) fn encode_allowance(owner: Address, spender: Address) -> Vec<u8> {
+    let mut data = ALLOWANCE_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[Token::Address(owner), Token::Address(spender)]));
+    data
+}
+
+/// Encode calldata of `balanceOf(owner)`.
+///
+/// # Arguments
+/// * `owner` - owner address
+pub(crate) fn encode_balance_of(owner: Address) -> Vec<u8> {
+    let mut data = BALANCE_OF_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[Token::Address(owner)]));
+    data
+}
+
+/// Decode string returned from `name()` or `symbol()`.
+/// Some old tokens return `bytes32` instead of `string`, so fall back to it.
+///
+/// # Arguments
+/// * `bytes` - returned data
+pub(crate) fn decode_string(bytes: &[u8]) -> Option<String> {
+    if let Ok(mut tokens) = ethabi::decode(&[ParamType::String], bytes) {
+        if let Some(Token::String(s)) = tokens.pop() {
+            return Some(s);
+        }
+    }
+    if bytes.len() == 32 {
+        let trimmed: Vec<u8> = bytes.iter().copied().take_while(|b| *b != 0).collect();
+        return String::from_utf8(trimmed).ok();
+    }
+    None
+}
+
+/// Decode `uint256` returned data.
+///
+/// # Arguments
+/// * `bytes` - returned data
+pub(crate) fn decode_uint(bytes: &[u8]) -> Option<U256> {
+    if bytes.len() < 32 {
+        return None;
+    }
+    Some(U256::from_big_endian(&bytes[..32]))
+}
+
+/// Decode `uint8` returned from `decimals()`.
+///
+/// # Arguments
+/// * `bytes` - returned data
+pub(crate) fn decode_decimals(bytes: &[u8]) -> Option<u8> {
+    decode_uint(bytes)
+        .filter(|d| *d <= U256::from(u8::MAX))
+        .map(|d| d.as_u32() as u8)
+}
@@ -0,0 +1,206 @@
+use crate::transport::{is_node_failure, RPC_LIMIT_EXCEEDED_CODE};
+
+/// Error of appvkek. Each variant carries a human-readable message in the
+/// same form as it would be printed.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum AppvkekError {
+    /// Address is not in the correct format
+    #[error("Error address is not in the correct format; addr={0}")]
+    AddressFormat(String),
+
+    /// RPC request failed for a reason not specific to the call itself e.g.
+    /// connection failure, HTTP 5xx, or node failing to process it
+    #[error("{0}")]
+    Rpc(String),
+
+    /// RPC request timed out either waiting for endpoint to respond, or in
+    /// node executing it
+    #[error("{0}")]
+    Timeout(String),
+
+    /// RPC endpoint rejected request due to rate limiting
+    #[error("{0}")]
+    RateLimited(String),
+
+    /// Contract call reverted, or node returned error for it
+    #[error("{0}")]
+    Reverted(String),
+
+    /// Returned data could not be decoded e.g. non-standard token
+    #[error("{0}")]
+    Decode(String),
+
+    /// Explorer API request failed
+    #[error("{0}")]
+    Explorer(String),
+
+    /// Reading or writing local file failed
+    #[error("{0}")]
+    Io(String),
+
+    /// Invalid configuration e.g. malformed RPC URL
+    #[error("{0}")]
+    Config(String),
+}
+
+impl AppvkekError {
+    /// Create error from error of web3 transport, classifying it by its kind.
+    ///
+    /// # Arguments
+    /// * `context` - what was being done e.g. `Error querying for code of 0x...`
+    /// * `err` - error from web3
+    pub fn from_web3(context: &str, err: &web3::Error) -> AppvkekError {
+        let msg = format!("{}; err={}", context, err);
+        match err {
+            web3::Error::Transport(web3::error::TransportError::Code(429)) => AppvkekError::RateLimited(msg),
+            web3::Error::Rpc(e) if e.code == jsonrpc_core::ErrorCode::ServerError(RPC_LIMIT_EXCEEDED_CODE) => AppvkekError::RateLimited(msg),
+            web3::Error::Rpc(e) if is_node_failure(e) && is_timeout(&e.message) => AppvkekError::Timeout(msg),
+            web3::Error::Rpc(e) if is_node_failure(e) => AppvkekError::Rpc(msg),
+            web3::Error::Rpc(_) => AppvkekError::Reverted(msg),
+            web3::Error::Decoder(_) | web3::Error::InvalidResponse(_) => AppvkekError::Decode(msg),
+            web3::Error::Transport(web3::error::TransportError::Message(m)) if is_timeout(m) => AppvkekError::Timeout(msg),
+            _ => AppvkekError::Rpc(msg),
+        }
+    }
+
+    /// Create error from error of contract call, classifying it by its kind.
+    ///
+    /// # Arguments
+    /// * `context` - what was being done e.g. `Error querying for allowance`
+    /// * `err` - error from web3's contract call
+    pub fn from_contract(context: &str, err: &web3::contract::Error) -> AppvkekError {
+        match err {
+            web3::contract::Error::Api(e) => AppvkekError::from_web3(context, e),
+            e => AppvkekError::Decode(format!("{}; err={}", context, e)),
+        }
+    }
+
+    /// Return error of the same kind with its message prefixed by context.
+    /// `AddressFormat` is returned as is as it already names the address.
+    ///
+    /// # Arguments
+    /// * `context` - what was being done
+    pub fn with_context(self, context: &str) -> AppvkekError {
+        match self {
+            AppvkekError::AddressFormat(_) => self,
+            AppvkekError::Rpc(m) => AppvkekError::Rpc(format!("{}; err={}", context, m)),
+            AppvkekError::Timeout(m) => AppvkekError::Timeout(format!("{}; err={}", context, m)),
+            AppvkekError::RateLimited(m) => AppvkekError::RateLimited(format!("{}; err={}", context, m)),
+            AppvkekError::Reverted(m) => AppvkekError::Reverted(format!("{}; err={}", context, m)),
+            AppvkekError::Decode(m) => AppvkekError::Decode(format!("{}; err={}", context, m)),
+            AppvkekError::Explorer(m) => AppvkekError::Explorer(format!("{}; err={}", context, m)),
+            AppvkekError::Io(m) => AppvkekError::Io(format!("{}; err={}", context, m)),
+            AppvkekError::Config(m) => AppvkekError::Config(format!("{}; err={}", context, m)),
+        }
+    }
+
+    /// Return whether the error is transient so the operation is worth
+    /// retrying later, and says nothing about the target itself.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, AppvkekError::Rpc(_) | AppvkekError::Timeout(_) | AppvkekError::RateLimited(_))
+    }
+
+    /// Return what the error likely means when calling `method` on token
+    /// contract failed with it, so that non-standard tokens aren't mistaken
+    /// for network failures, and vice versa; `None` if there's nothing to add.
+    ///
+    /// # Arguments
+    /// * `method` - name of method called e.g. `allowance`
+    pub fn token_call_hint(&self, method: &str) -> Option<String> {
+        match self {
+            AppvkekError::Reverted(_) => Some(format!("token reverts on {}(), likely non-standard", method)),
+            AppvkekError::Decode(_) => Some(format!("token returns invalid data for {}(), likely non-standard", method)),
+            AppvkekError::Rpc(_) => Some("network failure; queried again on next scan".to_owned()),
+            AppvkekError::Timeout(_) => Some("RPC endpoint timed out; queried again on next scan".to_owned()),
+            AppvkekError::RateLimited(_) => Some("rate limited by RPC endpoint; queried again on next scan".to_owned()),
+            _ => None,
+        }
+    }
+
+    /// Return name of error kind in snake case e.g. `rate_limited`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppvkekError::AddressFormat(_) => "address_format",
+            AppvkekError::Rpc(_) => "rpc",
+            AppvkekError::Timeout(_) => "rpc_timeout",
+            AppvkekError::RateLimited(_) => "rate_limited",
+            AppvkekError::Reverted(_) => "reverted",
+            AppvkekError::Decode(_) => "decode",
+            AppvkekError::Explorer(_) => "explorer",
+            AppvkekError::Io(_) => "io",
+            AppvkekError::Config(_) => "config",
+        }
+    }
+}
+
+/// Return whether the specified error message of transport, or node tells
+/// the request timed out e.g. `operation timed out`, or `execution aborted
+/// (timeout = 5s)`.
+///
+/// # Arguments
+/// * `message` - error message
+fn is_timeout(message: &str) -> bool {
+    let msg = message.to_lowercase();
+    msg.contains("timed out") || msg.contains("timeout")
+}
+
+/// Allow `?` on `AppvkekError` in functions which still return `String` error.
+impl From<AppvkekError> for String {
+    fn from(err: AppvkekError) -> String {
+        err.to_string()
+    }
+}
+
+/// Error of querying a single token contract.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{error}")]
+pub struct QueryError {
+    /// Token contract address
+    pub token_address: String,
+
+    /// Cause of error
+    pub error: AppvkekError,
+}
+
+/// Problem with a single transaction e.g. malformed calldata of approve call,
+/// which is skipped rather than failing the whole scan.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{error}")]
+pub struct ScanWarning {
+    /// Hash of transaction
+    pub tx_hash: String,
+
+    /// Cause of warning
+    pub error: AppvkekError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_error(code: i64, message: &str) -> web3::Error {
+        web3::Error::Rpc(jsonrpc_core::Error { code: jsonrpc_core::ErrorCode::from(code), message: message.to_owned(), data: None })
+    }
+
+    #[test]
+    fn from_web3_tells_reverts_from_node_failures() {
+        for (code, message) in [(3, "execution reverted"), (-32000, "execution reverted"), (-32000, "invalid opcode: INVALID"), (-32015, "VM execution error: Reverted"), (-32602, "invalid argument 0")] {
+            let err = AppvkekError::from_web3("Error calling contract", &rpc_error(code, message));
+            assert_eq!(err.kind(), "reverted", "{} {}", code, message);
+            assert!(!err.is_transient());
+        }
+        for (code, message) in [(-32000, "header not found"), (-32603, "internal error")] {
+            let err = AppvkekError::from_web3("Error calling contract", &rpc_error(code, message));
+            assert_eq!(err.kind(), "rpc", "{} {}", code, message);
+            assert!(err.is_transient());
+        }
+        for (code, message) in [(-32000, "execution aborted (timeout = 5s)"), (-32002, "request timed out")] {
+            let err = AppvkekError::from_web3("Error calling contract", &rpc_error(code, message));
+            assert_eq!(err.kind(), "rpc_timeout", "{} {}", code, message);
+            assert!(err.is_transient());
+        }
+        let err = AppvkekError::from_web3("Error calling contract", &web3::Error::Transport(web3::error::TransportError::Message("failed to send request: error sending request for url (http://localhost/): operation timed out".to_owned())));
+        assert_eq!(err.kind(), "rpc_timeout");
+        assert_eq!(AppvkekError::from_web3("Error calling contract", &rpc_error(RPC_LIMIT_EXCEEDED_CODE, "limit exceeded")).kind(), "rate_limited");
+    }
+}
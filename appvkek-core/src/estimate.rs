@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::failures::FailedContracts;
+use crate::metadata::MetadataCache;
+use crate::multicall;
+use crate::types::*;
+
+/// Estimated cost of querying collected approvals with `query_all()`, for
+/// deciding whether to proceed before a scan makes any RPC call.
+/// Risk assessment of spenders is not included as its cost depends on what
+/// spenders turn out to be.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryEstimate {
+    /// Number of token contracts to query
+    pub num_tokens: usize,
+
+    /// Number of token contracts skipped as they consistently failed before
+    pub num_skipped: usize,
+
+    /// Number of token contracts whose metadata is cached
+    pub num_cached: usize,
+
+    /// Number of approvals i.e. pairs of token contract and spender to query
+    pub num_approvals: usize,
+
+    /// Number of unique spenders
+    pub num_spenders: usize,
+
+    /// Number of JSON-RPC calls
+    pub rpc_calls: usize,
+}
+
+impl QueryEstimate {
+    /// Return estimated time querying takes; the slower of being bounded by
+    /// rate limit, and by round trips with limited concurrency. Retries are
+    /// not taken into account.
+    ///
+    /// # Arguments
+    /// * `rps` - rate limit of RPC calls per second; 0 means unlimited
+    /// * `concurrency` - maximum number of in-flight RPC calls
+    /// * `latency` - round trip time of a single RPC call
+    pub fn duration(&self, rps: f64, concurrency: usize, latency: Duration) -> Duration {
+        let rate_bound = if rps > 0.0 { Duration::from_secs_f64(self.rpc_calls as f64 / rps) } else { Duration::ZERO };
+        let round_trips = self.rpc_calls.div_ceil(concurrency.max(1));
+        rate_bound.max(latency * round_trips as u32)
+    }
+}
+
+/// Estimate cost of querying the specified approvals of each wallet.
+/// Each wallet is estimated separately as `query_all()` is called once for
+/// each of them.
+///
+/// # Arguments
+/// * `approvals` - collected approvals of each wallet
+/// * `use_multicall` - whether queries are batched via Multicall3 contract
+/// * `metadata_cache` - cache of token metadata
+/// * `failed_contracts` - record of failed token contracts
+pub fn estimate_query(approvals: &[ApprovalsMap], use_multicall: bool, metadata_cache: &MetadataCache, failed_contracts: &FailedContracts) -> QueryEstimate {
+    let mut estimate = QueryEstimate::default();
+    let mut spenders: HashSet<&str> = HashSet::new();
+    for ct_txs in approvals {
+        let mut token_calls: Vec<usize> = Vec::with_capacity(ct_txs.len());
+        for (ct, ct_spenders) in ct_txs {
+            if failed_contracts.should_skip(ct) {
+                estimate.num_skipped += 1;
+                continue;
+            }
+
+            // estimating doesn't count as cache lookups
+            let is_cached = metadata_cache.contains(ct);
+            estimate.num_tokens += 1;
+            estimate.num_cached += is_cached as usize;
+            estimate.num_approvals += ct_spenders.len();
+            spenders.extend(ct_spenders.keys().map(|s| s.as_str()));
+            token_calls.push(multicall::num_token_calls(is_cached, ct_spenders.len()));
+        }
+
+        estimate.rpc_calls += if use_multicall {
+            multicall::num_batches(token_calls)
+        }
+        else {
+            token_calls.iter().sum()
+        };
+    }
+    estimate.num_spenders = spenders.len();
+    estimate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn approvals(tokens: &[(&str, usize)]) -> ApprovalsMap {
+        tokens.iter()
+            .map(|(ct, num_spenders)| {
+                let spenders = (0..*num_spenders)
+                    .map(|i| (format!("0x{:040x}", i + 1), ApprovalRecord::default()))
+                    .collect::<HashMap<_, _>>();
+                (ct.to_string(), spenders)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn estimate_counts_calls_individually_and_batched() {
+        let mut metadata_cache = MetadataCache::default();
+        metadata_cache.update(&[Ok(TokenContractWithSpenderAllowances {
+            name: "A".to_owned(),
+            symbol: "A".to_owned(),
+            address: "0xaaaa".to_owned(),
+            decimals: 18,
+            balance: None,
+            total_supply: None,
+            spender_allowances: HashMap::new(),
+        })]);
+        let failed_contracts = FailedContracts::default();
+        let wallets = vec![approvals(&[("0xaaaa", 2), ("0xbbbb", 3)]), approvals(&[("0xcccc", 400)])];
+
+        // 2 allowances of cached token, 3 metadata + 3 allowances, then 3 metadata + 400 allowances
+        let res = estimate_query(&wallets, false, &metadata_cache, &failed_contracts);
+        assert_eq!(res, QueryEstimate { num_tokens: 3, num_skipped: 0, num_cached: 1, num_approvals: 405, num_spenders: 400, rpc_calls: 411 });
+
+        // first wallet fits in a single batch, second one needs its own oversized batch
+        let res = estimate_query(&wallets, true, &metadata_cache, &failed_contracts);
+        assert_eq!(res.rpc_calls, 2);
+    }
+
+    #[test]
+    fn duration_is_bounded_by_rate_limit_or_round_trips() {
+        let estimate = QueryEstimate { rpc_calls: 100, ..Default::default() };
+        assert_eq!(estimate.duration(25.0, 16, Duration::from_millis(100)), Duration::from_secs(4));
+        assert_eq!(estimate.duration(0.0, 16, Duration::from_millis(100)), Duration::from_millis(700));
+        assert_eq!(estimate.duration(0.0, 16, Duration::ZERO), Duration::ZERO);
+    }
+}
@@ -0,0 +1,86 @@
+use ::evmscan::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::util::*;
+
+/// Bundled dataset of exploited or compromised contracts
+static BUNDLED_EXPLOITS: &str = include_str!("../../data/exploits.json");
+
+/// Record of an exploited or compromised contract.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExploitRecord {
+    /// Contract address
+    pub address: String,
+
+    /// Name of the contract or protocol
+    pub name: String,
+
+    /// Date of the incident in `YYYY-MM-DD`
+    pub date: String,
+
+    /// Advisory link or short description of the incident
+    pub reference: String,
+}
+
+/// Dataset in form of chain name (or `*` for all chains) to list of records.
+type ExploitsFile = HashMap<String, Vec<ExploitRecord>>;
+
+/// Database of exploited or compromised contracts for a chain.
+#[derive(Debug, Default)]
+pub struct ExploitDb {
+    /// Lowercased address to its record
+    records: HashMap<String, ExploitRecord>,
+}
+
+impl ExploitDb {
+    /// Load records for the specified chain from the bundled dataset, and
+    /// from `extra_file` if specified.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `extra_file` - optional path to additional dataset in the same format
+    pub fn load(chain: ChainType, extra_file: Option<&str>) -> Result<ExploitDb, String> {
+        let mut db = ExploitDb::default();
+        db.merge(chain, BUNDLED_EXPLOITS)?;
+
+        if let Some(path) = extra_file {
+            match std::fs::read_to_string(path) {
+                Ok(content) => db.merge(chain, &content)?,
+                Err(e) => return Err(format!("Error reading exploits file ({}); err={}", path, e)),
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Merge records for the specified chain from content of dataset.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `content` - content of dataset
+    fn merge(&mut self, chain: ChainType, content: &str) -> Result<(), String> {
+        let mut file = match serde_json::from_str::<ExploitsFile>(content) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error parsing exploits file; err={}", e)),
+        };
+
+        for key in ["*", chain_name(chain)] {
+            if let Some(records) = file.remove(key) {
+                for record in records {
+                    self.records.insert(record.address.to_lowercase(), record);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return record of the specified address if it has been exploited.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn lookup(&self, address: &str) -> Option<&ExploitRecord> {
+        self.records.get(&address.to_lowercase())
+    }
+}
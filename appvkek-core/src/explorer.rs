@@ -0,0 +1,742 @@
+use ::evmscan::environ::Context;
+use ::evmscan::types::evm_types::*;
+use isahc::prelude::*;
+use isahc::{Body, Response};
+use isahc::http::Uri;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::debugdump;
+use crate::error::AppvkekError;
+use crate::provider::ExplorerApi;
+use crate::ratelimit::TokenBucket;
+use crate::replay;
+use crate::types::{EventLog, TokenTransfer};
+use crate::usage;
+use crate::util::*;
+
+/// Maximum number of transactions returned per page.
+/// Explorer APIs limit `page * offset` to be less than or equal to 10,000.
+const OFFSET: usize = 2000;
+
+/// Maximum number of event logs returned per page.
+/// Explorer APIs return at most 1,000 logs per call.
+const LOGS_OFFSET: usize = 1000;
+
+/// Topic of `Approval(address,address,uint256)` event
+pub const APPROVAL_EVENT_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+
+/// Topic of ERC-4337 EntryPoint's
+/// `UserOperationEvent(bytes32,address,address,uint256,bool,uint256,uint256)`
+/// event; same across EntryPoint v0.6 and v0.7
+pub const USER_OPERATION_EVENT_TOPIC: &str = "0x49628fd1471006c1482da88028e9ce4dbb080b815c9b0344d39e5a8e6ec1419f";
+
+/// Addresses of canonical ERC-4337 EntryPoint contracts (v0.6, and v0.7);
+/// lowercased. They are deployed at the same address on all chains.
+pub const ENTRY_POINT_ADDRESSES: [&str; 2] = [
+    "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789",
+    "0x0000000071727de22e5e9d8baf0edac6f37da032",
+];
+
+/// Maximum number of transactions that explorer APIs allow to be fetched for
+/// a single query range.
+const RATE_LIMIT: usize = 10_000;
+
+/// Timeout in seconds of each explorer API call
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_SECS);
+
+/// Default timeout in seconds of each explorer API call
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Set timeout of all subsequent explorer API calls.
+///
+/// # Arguments
+/// * `timeout` - timeout of each call
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT_SECS.store(timeout.as_secs(), Ordering::Relaxed);
+}
+
+/// Proxy to send explorer API calls through; see `set_proxy()`
+static PROXY: RwLock<Option<Uri>> = RwLock::new(None);
+
+/// Set proxy of all subsequent explorer API calls. Without it, proxy is still
+/// taken from environment variables as of curl e.g. `HTTPS_PROXY`.
+///
+/// # Arguments
+/// * `proxy` - proxy URL e.g. `socks5h://127.0.0.1:9050`
+pub fn set_proxy(proxy: &str) -> Result<(), AppvkekError> {
+    let uri = match proxy.parse::<Uri>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Config(format!("Error invalid proxy URL ({}); err={}", proxy, e))),
+    };
+    if let Ok(mut current) = PROXY.write() {
+        *current = Some(uri);
+    }
+    Ok(())
+}
+
+/// Default rate limit of explorer API calls as tuple of
+/// `(requests_per_second, burst)`; that of free tier of API key.
+pub const DEFAULT_RATE_LIMIT: (f64, u32) = (5.0, 5);
+
+/// Rate limiter of explorer API calls; see `set_rate_limit()`
+static RATE_LIMITER: RwLock<Option<Arc<TokenBucket>>> = RwLock::new(None);
+
+/// Set rate limit of all subsequent explorer API calls e.g. as of plan of
+/// API key. Without it, `DEFAULT_RATE_LIMIT` applies.
+///
+/// # Arguments
+/// * `requests_per_second` - number of calls allowed per second; non-positive disables limiting
+/// * `burst` - maximum number of calls allowed at once
+pub fn set_rate_limit(requests_per_second: f64, burst: u32) {
+    if let Ok(mut current) = RATE_LIMITER.write() {
+        *current = Some(Arc::new(TokenBucket::new(requests_per_second, burst)));
+    }
+}
+
+/// Return builder of HTTP GET request with timeout, and proxy applied.
+///
+/// # Arguments
+/// * `url` - URL to send request to
+fn get_request_builder(url: &str) -> isahc::http::request::Builder {
+    let mut builder = isahc::Request::get(url).timeout(Duration::from_secs(TIMEOUT_SECS.load(Ordering::Relaxed)));
+    // only override when set, as `None` disables proxy from environment variables
+    if let Some(proxy) = PROXY.read().ok().and_then(|p| p.clone()) {
+        builder = builder.proxy(proxy);
+    }
+    builder
+}
+
+/// Send HTTP GET request to other than explorer API e.g. to fetch malicious
+/// list with the same timeout, and proxy as explorer API calls; see
+/// `set_timeout()`, and `set_proxy()`. It's neither rate limited, nor counted
+/// into usage.
+///
+/// # Arguments
+/// * `url` - URL to send request to
+pub(crate) fn http_get_external(url: &str) -> Result<Response<Body>, isahc::Error> {
+    get_request_builder(url).body(())?.send()
+}
+
+/// Send HTTP GET request to explorer API with timeout, proxy, and rate limit applied,
+/// and count it into usage. Response is served from fixture instead if
+/// replaying; see `replay::open()`.
+///
+/// # Arguments
+/// * `url` - URL to send request to
+fn http_get(url: &str) -> Result<Response<Body>, AppvkekError> {
+    if replay::is_replaying() {
+        let (status, body) = replay::explorer_response(url)?;
+        usage::record_explorer_call(body.len());
+        return match Response::builder().status(status).body(Body::from(body)) {
+            Ok(res) => Ok(res),
+            Err(e) => Err(AppvkekError::Explorer(format!("Error in building HTTP response; err={}", e))),
+        };
+    }
+
+    let limiter = RATE_LIMITER.write().ok().map(|mut l| l.get_or_insert_with(|| Arc::new(TokenBucket::new(DEFAULT_RATE_LIMIT.0, DEFAULT_RATE_LIMIT.1))).clone());
+    if let Some(limiter) = limiter {
+        limiter.acquire_blocking(1);
+    }
+    let req = match get_request_builder(url).body(()) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in building HTTP request; err={}", e))),
+    };
+    let mut res = match req.send() {
+        Ok(res) => res,
+        Err(e) => {
+            usage::record_explorer_call(0);
+            let e = AppvkekError::Explorer(format!("Error in sending HTTP request; err={}", e));
+            debugdump::record_explorer(url, Err(&e));
+            return Err(e);
+        }
+    };
+
+    // body can only be read once, so count, and dump it then hand it back as a new response
+    let status = res.status();
+    let body = match res.text() {
+        Ok(res) => res,
+        Err(e) => {
+            usage::record_explorer_call(0);
+            let e = AppvkekError::Explorer(format!("Error in reading HTTP response; err={}", e));
+            debugdump::record_explorer(url, Err(&e));
+            return Err(e);
+        }
+    };
+    usage::record_explorer_call(body.len());
+    debugdump::record_explorer(url, Ok((status.as_u16(), &body)));
+    match Response::builder().status(status).body(Body::from(body)) {
+        Ok(res) => Ok(res),
+        Err(e) => Err(AppvkekError::Explorer(format!("Error in building HTTP response; err={}", e))),
+    }
+}
+
+/// Fetch a single page of normal transactions of specified address.
+/// Return empty vector if there is no transaction.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `address` - target address
+/// * `start_block` - starting block number (inclusive)
+/// * `page` - page number starting from 1
+/// * `offset` - number of transactions per page
+/// * `sort` - either `asc` or `desc` by block number
+pub(crate) fn fetch_txlist_page(ctx: &Context, address: &str, start_block: u64, page: usize, offset: usize, sort: &str) -> Result<Vec<EvmNormalTransactionResponseSuccessVariantResult>, AppvkekError> {
+    let url = format!("{}/api?module=account&action=txlist&address={}&startblock={}&endblock=99999999&page={}&offset={}&sort={}&apikey={}", Context::get_prefix_url(ctx.chain), address, start_block, page, offset, sort, ctx.api_key);
+    tracing::debug!("Fetching transactions of {} from block {}; page={}, offset={}", address, start_block, page, offset);
+
+    let mut res = http_get(&url)?;
+
+    // early return for non-200 HTTP returned code
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    let json = match res.json::<EvmTransactionResponse<EvmNormalTransactionResponseSuccessVariantResult>>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing JSON string; err={}", e))),
+    };
+
+    if json.status != "1" {
+        // exact text as returned when empty "result" is returned
+        if json.message == "No transactions found" {
+            return Ok(Vec::new());
+        }
+        return Err(AppvkekError::Explorer(format!("Error api response from upstream server: '{}'", json.message)));
+    }
+
+    match json.result {
+        GenericEvmTransactionResponseResult::Success(txs) => Ok(txs),
+        // this case should not happen
+        GenericEvmTransactionResponseResult::Failed(msg) => Err(AppvkekError::Explorer(format!("Error un-expected error for success case ({})", msg.unwrap_or_default()))),
+    }
+}
+
+/// Fetch normal transactions of specified address starting from the
+/// specified block number (inclusive) page by page, and pass each page to
+/// `on_page` in ascending order of block number. Only a single page is held
+/// in memory at a time.
+///
+/// Explorer APIs only allow to fetch the first 10,000 transactions of a
+/// query range, so once reached, fetching continues from the block of the
+/// last fetched transaction. Hence, transactions of such boundary block may
+/// be passed to `on_page` more than once.
+///
+/// This is similar to `evmscan::accounts().get_list_normal_transactions()` but
+/// allows to specify starting block number so that only newer transactions
+/// will be fetched.
+///
+/// # Arguments
+/// * `explorer` - explorer API e.g. evmscan's context
+/// * `address` - target wallet address
+/// * `start_block` - starting block number (inclusive)
+/// * `on_page` - callback to be called for each page of transactions
+pub fn for_each_normal_transactions_page<E, F>(explorer: &E, address: &str, start_block: u64, on_page: F) -> Result<(), AppvkekError>
+where
+    E: ExplorerApi + ?Sized,
+    F: FnMut(Vec<EvmNormalTransactionResponseSuccessVariantResult>) -> Result<(), AppvkekError>
+{
+    for_each_page(start_block, OFFSET, |start_block, page, offset| explorer.txlist_page(address, start_block, page, offset, "asc"), |tx| tx.block_number, on_page)
+}
+
+/// Fetch results of a query over block range page by page via `fetch`, and
+/// pass each page to `on_page`. Once the first 10,000 results of the query
+/// range are reached, fetching continues from the block of the last fetched
+/// result as a new query range.
+///
+/// # Arguments
+/// * `start_block` - starting block number (inclusive)
+/// * `offset` - number of results per page
+/// * `fetch` - function fetching a page given starting block, page number, and offset
+/// * `block_of` - function returning block number of a result
+/// * `on_page` - callback to be called for each page of results
+fn for_each_page<T, Fetch, BlockOf, F>(start_block: u64, offset: usize, mut fetch: Fetch, block_of: BlockOf, mut on_page: F) -> Result<(), AppvkekError>
+where
+    Fetch: FnMut(u64, usize, usize) -> Result<Vec<T>, AppvkekError>,
+    BlockOf: Fn(&T) -> u64,
+    F: FnMut(Vec<T>) -> Result<(), AppvkekError>
+{
+    let mut start_block = start_block;
+    let mut page_number = 1usize;
+
+    loop {
+        let items = fetch(start_block, page_number, offset)?;
+        let is_last_page = items.len() < offset;
+        let last_block = items.last().map(&block_of);
+        on_page(items)?;
+
+        if is_last_page {
+            break;
+        }
+
+        if (page_number + 1) * offset > RATE_LIMIT {
+            // continue from the last block as a new query range
+            match last_block {
+                Some(block) if block > start_block => {
+                    start_block = block;
+                    page_number = 1;
+                },
+                _ => {
+                    tracing::warn!("More than {} results in a single block!", RATE_LIMIT);
+                    break;
+                }
+            }
+        }
+        else {
+            page_number += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Raw event log as returned by explorer's `getLogs` endpoint.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawEventLog {
+    address: String,
+    topics: Vec<String>,
+    data: String,
+    block_number: String,
+    #[serde(rename = "timeStamp")]
+    timestamp: String,
+    transaction_hash: String,
+    log_index: String,
+}
+
+/// Response of explorer's `getLogs` endpoint.
+#[derive(Debug, serde::Deserialize)]
+struct EventLogsResponse {
+    status: String,
+    message: String,
+    result: serde_json::Value,
+}
+
+/// Parse hex string number prefixed with '0x' as returned by `getLogs`.
+///
+/// # Arguments
+/// * `value` - hex string e.g. `0x1b4`
+fn parse_hex_u64(value: &str) -> Result<u64, AppvkekError> {
+    let digits = value.trim_start_matches("0x");
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    match u64::from_str_radix(digits, 16) {
+        Ok(res) => Ok(res),
+        Err(e) => Err(AppvkekError::Explorer(format!("Error parsing hex number ({}); err={}", value, e))),
+    }
+}
+
+/// Fetch a single page of `Approval` event logs whose owner is the specified
+/// address, emitted by any contract. Return empty vector if there is no log.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `owner_address` - owner address; lowercased, prefixed with '0x'
+/// * `start_block` - starting block number (inclusive)
+/// * `page` - page number starting from 1
+/// * `offset` - number of logs per page
+pub(crate) fn fetch_approval_logs_page(ctx: &Context, owner_address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+    let owner_topic = format!("0x{:0>64}", owner_address.trim_start_matches("0x"));
+    let url = format!("{}/api?module=logs&action=getLogs&fromBlock={}&toBlock=latest&topic0={}&topic0_1_opr=and&topic1={}&page={}&offset={}&apikey={}", Context::get_prefix_url(ctx.chain), start_block, APPROVAL_EVENT_TOPIC, owner_topic, page, offset, ctx.api_key);
+    tracing::debug!("Fetching approval logs of {} from block {}; page={}, offset={}", owner_address, start_block, page, offset);
+    fetch_logs(&url)
+}
+
+/// Fetch a single page of ERC-4337 `UserOperationEvent` event logs whose
+/// sender is the specified smart account, emitted by any contract. Return
+/// empty vector if there is no log.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `sender` - smart account address; lowercased, prefixed with '0x'
+/// * `start_block` - starting block number (inclusive)
+/// * `page` - page number starting from 1
+/// * `offset` - number of logs per page
+pub(crate) fn fetch_user_operation_logs_page(ctx: &Context, sender: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+    let sender_topic = format!("0x{:0>64}", sender.trim_start_matches("0x"));
+    let url = format!("{}/api?module=logs&action=getLogs&fromBlock={}&toBlock=latest&topic0={}&topic0_2_opr=and&topic2={}&page={}&offset={}&apikey={}", Context::get_prefix_url(ctx.chain), start_block, USER_OPERATION_EVENT_TOPIC, sender_topic, page, offset, ctx.api_key);
+    tracing::debug!("Fetching user operation logs of {} from block {}; page={}, offset={}", sender, start_block, page, offset);
+    fetch_logs(&url)
+}
+
+/// Fetch event logs from the specified `getLogs` URL.
+///
+/// # Arguments
+/// * `url` - URL of `getLogs` endpoint including query
+fn fetch_logs(url: &str) -> Result<Vec<EventLog>, AppvkekError> {
+    let mut res = http_get(url)?;
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    let json = match res.json::<EventLogsResponse>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing JSON string; err={}", e))),
+    };
+    if json.status != "1" {
+        // exact text as returned when empty "result" is returned
+        if json.message == "No records found" {
+            return Ok(Vec::new());
+        }
+        return Err(AppvkekError::Explorer(format!("Error api response from upstream server: '{}'", json.message)));
+    }
+
+    let raw_logs = match serde_json::from_value::<Vec<RawEventLog>>(json.result) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing event logs; err={}", e))),
+    };
+    let mut logs = Vec::with_capacity(raw_logs.len());
+    for log in raw_logs {
+        logs.push(EventLog {
+            address: log.address.to_lowercase(),
+            topics: log.topics.iter().map(|t| t.to_lowercase()).collect(),
+            data: log.data,
+            block_number: parse_hex_u64(&log.block_number)?,
+            timestamp: parse_hex_u64(&log.timestamp)?,
+            transaction_hash: log.transaction_hash.to_lowercase(),
+            log_index: parse_hex_u64(&log.log_index)?,
+        });
+    }
+    Ok(logs)
+}
+
+/// Fetch `Approval` event logs of the specified owner starting from the
+/// specified block number (inclusive) page by page, and pass each page to
+/// `on_page` in ascending order of block number. As with
+/// `for_each_normal_transactions_page()`, logs of the boundary block of a
+/// query range may be passed to `on_page` more than once.
+///
+/// Unlike normal transactions, logs also cover approvals made via internal
+/// calls e.g. by contract wallets.
+///
+/// # Arguments
+/// * `explorer` - explorer API e.g. evmscan's context
+/// * `owner_address` - owner address; lowercased, prefixed with '0x'
+/// * `start_block` - starting block number (inclusive)
+/// * `on_page` - callback to be called for each page of logs
+pub fn for_each_approval_logs_page<E, F>(explorer: &E, owner_address: &str, start_block: u64, on_page: F) -> Result<(), AppvkekError>
+where
+    E: ExplorerApi + ?Sized,
+    F: FnMut(Vec<EventLog>) -> Result<(), AppvkekError>
+{
+    for_each_page(start_block, LOGS_OFFSET, |start_block, page, offset| explorer.approval_logs_page(owner_address, start_block, page, offset), |log| log.block_number, on_page)
+}
+
+/// Fetch ERC-4337 `UserOperationEvent` event logs of the specified smart
+/// account starting from the specified block number (inclusive) page by page,
+/// and pass each page to `on_page` in ascending order of block number. As
+/// with `for_each_normal_transactions_page()`, logs of the boundary block of
+/// a query range may be passed to `on_page` more than once.
+///
+/// Logs are not filtered by emitter, so callers should only trust those
+/// emitted by one of `ENTRY_POINT_ADDRESSES`.
+///
+/// # Arguments
+/// * `explorer` - explorer API e.g. evmscan's context
+/// * `sender` - smart account address; lowercased, prefixed with '0x'
+/// * `start_block` - starting block number (inclusive)
+/// * `on_page` - callback to be called for each page of logs
+pub fn for_each_user_operation_logs_page<E, F>(explorer: &E, sender: &str, start_block: u64, on_page: F) -> Result<(), AppvkekError>
+where
+    E: ExplorerApi + ?Sized,
+    F: FnMut(Vec<EventLog>) -> Result<(), AppvkekError>
+{
+    for_each_page(start_block, LOGS_OFFSET, |start_block, page, offset| explorer.user_operation_logs_page(sender, start_block, page, offset), |log| log.block_number, on_page)
+}
+
+/// Raw token transfer as returned by explorer's `tokentx` endpoint; only
+/// fields needed, as others are not consistently filled for all tokens.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTokenTransfer {
+    block_number: String,
+    from: String,
+    to: String,
+    contract_address: String,
+}
+
+/// Fetch a single page of ERC-20 token transfers to or from the specified
+/// address. Return empty vector if there is no transfer.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `address` - target address
+/// * `start_block` - starting block number (inclusive)
+/// * `page` - page number starting from 1
+/// * `offset` - number of transfers per page
+pub(crate) fn fetch_tokentx_page(ctx: &Context, address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<TokenTransfer>, AppvkekError> {
+    let url = format!("{}/api?module=account&action=tokentx&address={}&startblock={}&endblock=99999999&page={}&offset={}&sort=asc&apikey={}", Context::get_prefix_url(ctx.chain), address, start_block, page, offset, ctx.api_key);
+    tracing::debug!("Fetching token transfers of {} from block {}; page={}, offset={}", address, start_block, page, offset);
+
+    let mut res = http_get(&url)?;
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    // shares layout with `getLogs` response
+    let json = match res.json::<EventLogsResponse>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing JSON string; err={}", e))),
+    };
+    if json.status != "1" {
+        // exact text as returned when empty "result" is returned
+        if json.message == "No transactions found" {
+            return Ok(Vec::new());
+        }
+        return Err(AppvkekError::Explorer(format!("Error api response from upstream server: '{}'", json.message)));
+    }
+
+    let raw_transfers = match serde_json::from_value::<Vec<RawTokenTransfer>>(json.result) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing token transfers; err={}", e))),
+    };
+    let mut transfers = Vec::with_capacity(raw_transfers.len());
+    for t in raw_transfers {
+        let block_number = match t.block_number.parse::<u64>() {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Explorer(format!("Error parsing block number ({}); err={}", t.block_number, e))),
+        };
+        transfers.push(TokenTransfer {
+            token_address: t.contract_address.to_lowercase(),
+            from: t.from.to_lowercase(),
+            to: t.to.to_lowercase(),
+            block_number,
+        });
+    }
+    Ok(transfers)
+}
+
+/// Fetch ERC-20 token transfers to or from the specified address starting
+/// from the specified block number (inclusive) page by page, and pass each
+/// page to `on_page` in ascending order of block number. As with
+/// `for_each_normal_transactions_page()`, transfers of the boundary block of
+/// a query range may be passed to `on_page` more than once.
+///
+/// # Arguments
+/// * `explorer` - explorer API e.g. evmscan's context
+/// * `address` - target wallet address; lowercased, prefixed with '0x'
+/// * `start_block` - starting block number (inclusive)
+/// * `on_page` - callback to be called for each page of transfers
+pub fn for_each_token_transfers_page<E, F>(explorer: &E, address: &str, start_block: u64, on_page: F) -> Result<(), AppvkekError>
+where
+    E: ExplorerApi + ?Sized,
+    F: FnMut(Vec<TokenTransfer>) -> Result<(), AppvkekError>
+{
+    for_each_page(start_block, OFFSET, |start_block, page, offset| explorer.tokentx_page(address, start_block, page, offset), |t| t.block_number, on_page)
+}
+
+/// Number of most recent transactions to inspect for activity of an address
+const ACTIVITY_SAMPLE_SIZE: usize = 100;
+
+/// Number of days considered as recent for activity of an address
+pub const RECENT_ACTIVITY_DAYS: u64 = 30;
+
+/// Recent activity of an address.
+#[derive(Debug, Clone)]
+pub struct AddressActivity {
+    /// Unix timestamp of the latest transaction if any
+    pub last_active: Option<u64>,
+
+    /// Number of transactions within the last `RECENT_ACTIVITY_DAYS` days;
+    /// capped at 100
+    pub recent_tx_count: usize,
+}
+
+/// Get recent activity of the specified address from its most recent
+/// normal transactions.
+///
+/// # Arguments
+/// * `explorer` - explorer API e.g. evmscan's context
+/// * `address` - target address
+pub fn get_address_activity<E: ExplorerApi + ?Sized>(explorer: &E, address: &str) -> Result<AddressActivity, AppvkekError> {
+    let txs = explorer.txlist_page(address, 0, 1, ACTIVITY_SAMPLE_SIZE, "desc")?;
+    let recent_since = unix_timestamp().saturating_sub(RECENT_ACTIVITY_DAYS * 24 * 60 * 60);
+
+    Ok(AddressActivity {
+        last_active: txs.first().map(|tx| tx.timestamp),
+        recent_tx_count: txs.iter().filter(|tx| tx.timestamp >= recent_since).count(),
+    })
+}
+
+/// Response of explorer's `getsourcecode` endpoint.
+#[derive(Debug, serde::Deserialize)]
+struct SourceCodeResponse {
+    status: String,
+    message: String,
+    result: serde_json::Value,
+}
+
+/// Return whether source code of the specified contract address has been
+/// verified on explorer via its `getsourcecode` endpoint.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `address` - contract address
+pub fn is_contract_verified(ctx: &Context, address: &str) -> Result<bool, AppvkekError> {
+    let url = format!("{}/api?module=contract&action=getsourcecode&address={}&apikey={}", Context::get_prefix_url(ctx.chain), address, ctx.api_key);
+
+    let mut res = http_get(&url)?;
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    let json = match res.json::<SourceCodeResponse>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error getting source code of {}; err={}", address, e))),
+    };
+    if json.status != "1" {
+        return Err(AppvkekError::Explorer(format!("Error getting source code of {}; err=api response from upstream server: '{}'", address, json.message)));
+    }
+
+    // non-verified contract has empty source code
+    Ok(json.result.get(0)
+        .and_then(|c| c.get("SourceCode"))
+        .and_then(|c| c.as_str())
+        .map(|c| !c.is_empty())
+        .unwrap_or(false))
+}
+
+/// Response of explorer's `getcontractcreation` endpoint.
+#[derive(Debug, serde::Deserialize)]
+struct ContractCreationResponse {
+    status: String,
+    message: String,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+/// Creation record of a contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ContractCreation {
+    /// Address which deployed the contract; lowercased
+    pub creator: String,
+
+    /// Hash of transaction which created the contract
+    pub tx_hash: String,
+
+    /// Unix timestamp of creation; `None` if explorer doesn't return it
+    pub timestamp: Option<u64>,
+}
+
+/// Get creation record of the specified address via explorer's
+/// `getcontractcreation` endpoint. Return `None` if it has never been
+/// created as a contract. This works even if such contract has been
+/// destroyed since then.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+/// * `address` - address to check
+pub fn get_contract_creation(ctx: &Context, address: &str) -> Result<Option<ContractCreation>, AppvkekError> {
+    let url = format!("{}/api?module=contract&action=getcontractcreation&contractaddresses={}&apikey={}", Context::get_prefix_url(ctx.chain), address, ctx.api_key);
+
+    let mut res = http_get(&url)?;
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    let json = match res.json::<ContractCreationResponse>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing JSON string; err={}", e))),
+    };
+
+    if json.status == "1" {
+        return Ok(Some(parse_contract_creation(&json.result)));
+    }
+    // exact text as returned when address is not a contract
+    if json.message == "No data found" {
+        return Ok(None);
+    }
+    Err(AppvkekError::Explorer(format!("Error api response from upstream server: '{}'", json.message)))
+}
+
+/// Parse creation record from result of `getcontractcreation`. Only some
+/// explorers return `timestamp`, and all fields are strings.
+///
+/// # Arguments
+/// * `result` - `result` field of response
+fn parse_contract_creation(result: &serde_json::Value) -> ContractCreation {
+    let field = |name: &str| result.get(0).and_then(|c| c.get(name)).and_then(|v| v.as_str()).unwrap_or_default();
+    ContractCreation {
+        creator: field("contractCreator").to_lowercase(),
+        tx_hash: field("txHash").to_owned(),
+        timestamp: field("timestamp").parse::<u64>().ok(),
+    }
+}
+
+/// Check whether API key of the specified context is accepted by explorer,
+/// by making a cheap call; balance of zero address.
+///
+/// # Arguments
+/// * `ctx` - evmscan's context
+pub fn check_api_key(ctx: &Context) -> Result<(), AppvkekError> {
+    let url = format!("{}/api?module=account&action=balance&address=0x0000000000000000000000000000000000000000&tag=latest&apikey={}", Context::get_prefix_url(ctx.chain), ctx.api_key);
+
+    let mut res = http_get(&url)?;
+    if res.status() != 200 {
+        return Err(AppvkekError::Explorer(format!("Error API response, with HTTP {} returned", res.status().as_str())));
+    }
+
+    // shares layout with `getLogs` response
+    let json = match res.json::<EventLogsResponse>() {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Explorer(format!("Error in parsing JSON string; err={}", e))),
+    };
+    if json.status == "1" {
+        return Ok(());
+    }
+    // reason e.g. 'Invalid API Key' is in "result"
+    Err(AppvkekError::Explorer(format!("Error api response from upstream server: '{}' ({})", json.message, json.result.as_str().unwrap_or_default())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    static OWNER: &str = "0x1111111111111111111111111111111111111111";
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+
+    #[test]
+    fn for_each_page_continues_past_result_window() {
+        // 12,000 transactions over 6,000 blocks; more than a single query range holds
+        let explorer = (0..12_000u64).fold(MockExplorer::default(), |e, i| e.with_tx(make_tx(OWNER, TOKEN, "0x", i / 2)));
+
+        let mut blocks: Vec<u64> = Vec::new();
+        for_each_normal_transactions_page(&explorer, OWNER, 0, |txs| {
+            blocks.extend(txs.iter().map(|tx| tx.block_number));
+            Ok(())
+        }).unwrap();
+
+        // transactions of the boundary block are passed again
+        blocks.dedup();
+        assert_eq!(blocks, (0..6_000).collect::<Vec<u64>>());
+        assert_eq!(explorer.num_page_requests(), 7);
+    }
+
+    #[test]
+    fn address_activity_counts_recent_transactions() {
+        let explorer = MockExplorer::default()
+            .with_tx(make_tx(OWNER, TOKEN, "0x", 1))
+            .with_tx(make_tx(TOKEN, OWNER, "0x", 2));
+
+        let activity = get_address_activity(&explorer, TOKEN).unwrap();
+
+        assert_eq!(activity.last_active, Some(1_600_000_006));
+        assert_eq!(activity.recent_tx_count, 0);
+    }
+
+    #[test]
+    fn parse_contract_creation_tolerates_missing_timestamp() {
+        let result = serde_json::json!([{"contractAddress": TOKEN, "contractCreator": "0xABCDEF0000000000000000000000000000000000", "txHash": "0x01", "timestamp": "1700000000"}]);
+        assert_eq!(parse_contract_creation(&result), ContractCreation {
+            creator: "0xabcdef0000000000000000000000000000000000".to_owned(),
+            tx_hash: "0x01".to_owned(),
+            timestamp: Some(1_700_000_000),
+        });
+
+        let result = serde_json::json!([{"contractAddress": TOKEN, "contractCreator": OWNER, "txHash": "0x01"}]);
+        assert_eq!(parse_contract_creation(&result).timestamp, None);
+    }
+}
@@ -0,0 +1,207 @@
+use ::evmscan::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::error::AppvkekError;
+use crate::types::*;
+use crate::util::*;
+
+/// Number of consecutive failed scans after which a token contract is skipped
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// Class of error a token contract failed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    /// Querying `name()` or `decimals()` reverted or returned invalid data
+    Metadata,
+
+    /// Querying `allowance()` reverted or returned invalid data
+    Allowance,
+
+    /// Any other error e.g. invalid address
+    Other,
+}
+
+/// Classify the specified error of a token contract.
+/// Return `None` if it is transient e.g. connection failure, or rate limiting,
+/// which says nothing about the token contract itself.
+///
+/// # Arguments
+/// * `err` - error from `query()`
+pub fn classify_error(err: &AppvkekError) -> Option<FailureClass> {
+    if err.is_transient() {
+        return None;
+    }
+
+    let err_msg = err.to_string();
+    if err_msg.contains("top-level query") {
+        Some(FailureClass::Metadata)
+    }
+    else if err_msg.contains("allowance balance") {
+        Some(FailureClass::Allowance)
+    }
+    else {
+        Some(FailureClass::Other)
+    }
+}
+
+/// Record of a token contract which failed to be queried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureRecord {
+    /// Class of the latest error
+    pub class: FailureClass,
+
+    /// The latest error message
+    pub last_error: String,
+
+    /// Number of consecutive scans it failed
+    pub count: u32,
+
+    /// Unix timestamp of the latest failure
+    pub last_failed: u64,
+}
+
+/// Persistent record of token contracts which consistently fail to be
+/// queried, so that they can be skipped on subsequent scans.
+/// It is stored at `$HOME/.appvkek/cache/failed-contracts-<chain>.json`.
+#[derive(Debug, Default)]
+pub struct FailedContracts {
+    /// Path to file; `None` if it is only kept in memory
+    path: Option<String>,
+
+    /// Token contract address (lowercased) to its failure record
+    entries: HashMap<String, FailureRecord>,
+
+    /// Whether to skip token contracts which consistently failed
+    skip_failed: bool,
+
+    /// Whether entries have been changed but not yet saved
+    is_dirty: bool,
+}
+
+impl FailedContracts {
+    /// Load failed contracts of the specified chain.
+    /// Corrupted file is ignored, and will be overwritten on next save.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `skip_failed` - whether to skip token contracts which consistently failed;
+    ///   if `false`, they are queried again but still recorded
+    pub fn load(chain: ChainType, skip_failed: bool) -> FailedContracts {
+        let path = format!("{}/failed-contracts-{}.json", cache_dir(), chain_name(chain));
+        let entries = std::fs::read_to_string(&path).ok()
+            .and_then(|content| serde_json::from_str::<HashMap<String, FailureRecord>>(&content).ok())
+            .unwrap_or_default();
+
+        FailedContracts {
+            path: Some(path),
+            entries,
+            skip_failed,
+            is_dirty: false,
+        }
+    }
+
+    /// Return in-memory copy of this record which is never saved to disk.
+    /// This is for querying concurrently, then merging results back into
+    /// this record with `update()`.
+    pub fn detached(&self) -> FailedContracts {
+        FailedContracts {
+            path: None,
+            entries: self.entries.clone(),
+            skip_failed: self.skip_failed,
+            is_dirty: false,
+        }
+    }
+
+    /// Return whether the specified token contract should be skipped as it
+    /// has failed for at least `FAILURE_THRESHOLD` consecutive scans.
+    ///
+    /// # Arguments
+    /// * `address` - token contract address
+    pub fn should_skip(&self, address: &str) -> bool {
+        self.skip_failed && self.entries.get(&address.to_lowercase())
+            .map(|r| r.count >= FAILURE_THRESHOLD)
+            .unwrap_or(false)
+    }
+
+    /// Update records from results of a scan. Successful token contracts are
+    /// removed, and failed ones have their count increased unless the error
+    /// is transient. A token contract found more than once e.g. from scans
+    /// of multiple wallets is counted once.
+    ///
+    /// # Arguments
+    /// * `results` - results from `query_all()`
+    pub fn update(&mut self, results: &[QueryResult]) {
+        let mut counted: HashSet<String> = HashSet::new();
+        for res in results {
+            match res {
+                Ok(r) => {
+                    if self.entries.remove(&r.address.to_lowercase()).is_some() {
+                        self.is_dirty = true;
+                    }
+                },
+                Err(e) => {
+                    let class = match classify_error(&e.error) {
+                        Some(res) => res,
+                        None => continue,
+                    };
+                    if !counted.insert(e.token_address.to_lowercase()) {
+                        continue;
+                    }
+                    let record = self.entries.entry(e.token_address.to_lowercase()).or_insert(FailureRecord {
+                        class,
+                        last_error: String::new(),
+                        count: 0,
+                        last_failed: 0,
+                    });
+                    record.class = class;
+                    record.last_error = e.error.to_string();
+                    record.count += 1;
+                    record.last_failed = unix_timestamp();
+                    self.is_dirty = true;
+                },
+            }
+        }
+    }
+
+    /// Save to disk if there are changes.
+    pub fn save(&mut self) -> Result<(), AppvkekError> {
+        let path = match &self.path {
+            Some(path) if self.is_dirty => path,
+            _ => return Ok(()),
+        };
+
+        let content = match serde_json::to_string(&self.entries) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Io(format!("Error serializing failed contracts; err={}", e))),
+        };
+        write_file(path, &content)?;
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::QueryError;
+
+    #[test]
+    fn update_counts_token_once_per_scan() {
+        let failed: QueryResult = Err(QueryError {
+            token_address: "0x2222222222222222222222222222222222222222".to_owned(),
+            error: AppvkekError::Reverted("Error in querying top-level query (name); err=execution reverted".to_owned()),
+        });
+
+        let mut failed_contracts = FailedContracts { skip_failed: true, ..Default::default() };
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            failed_contracts.update(&[failed.clone(), failed.clone()]);
+        }
+        assert!(!failed_contracts.should_skip("0x2222222222222222222222222222222222222222"));
+
+        failed_contracts.update(&[failed]);
+        assert!(failed_contracts.should_skip("0x2222222222222222222222222222222222222222"));
+    }
+}
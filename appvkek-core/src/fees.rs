@@ -0,0 +1,153 @@
+use web3::types::U256;
+
+use crate::error::AppvkekError;
+use crate::mempool::bump_fee;
+use crate::provider::ChainRpc;
+
+/// Number of the latest blocks to derive priority fee from
+pub const FEE_HISTORY_BLOCKS: u64 = 20;
+
+/// How fast transactions are to be included; see `--fee-preset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeePreset {
+    /// Cheapest; may wait for quiet blocks
+    Slow,
+
+    /// Included in the next few blocks as usual
+    Normal,
+
+    /// Ahead of most transactions
+    Fast,
+
+    /// Ahead of nearly all transactions, and safe against base fee spikes;
+    /// for incident response e.g. racing a drainer
+    Urgent,
+}
+
+impl FeePreset {
+    /// Parse preset from its name as used in `--fee-preset`.
+    ///
+    /// # Arguments
+    /// * `value` - either `slow`, `normal`, `fast`, or `urgent`
+    pub fn parse(value: &str) -> Result<FeePreset, String> {
+        match value.to_lowercase().as_str() {
+            "slow" => Ok(FeePreset::Slow),
+            "normal" => Ok(FeePreset::Normal),
+            "fast" => Ok(FeePreset::Fast),
+            "urgent" => Ok(FeePreset::Urgent),
+            _ => Err(format!("Error invalid value for --fee-preset ({}).
+Possible values are 'slow', 'normal', 'fast', or 'urgent'.", value)),
+        }
+    }
+
+    /// Return percentile of priority fees paid in the latest blocks to pay.
+    fn percentile(self) -> f64 {
+        match self {
+            FeePreset::Slow => 10.0,
+            FeePreset::Normal => 50.0,
+            FeePreset::Fast => 75.0,
+            FeePreset::Urgent => 95.0,
+        }
+    }
+
+    /// Return multiple of base fee to allow in max fee per gas, so that the
+    /// transaction stays includable while base fee rises; it rises by at most
+    /// 12.5% per block.
+    fn base_fee_multiplier(self) -> u64 {
+        match self {
+            FeePreset::Slow => 1,
+            FeePreset::Normal => 2,
+            FeePreset::Fast => 2,
+            FeePreset::Urgent => 3,
+        }
+    }
+}
+
+/// EIP-1559 fees to send a transaction with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fees {
+    /// Base fee per gas of the next block in wei
+    pub base_fee_per_gas: U256,
+
+    /// Max fee per gas in wei
+    pub max_fee_per_gas: U256,
+
+    /// Max priority fee per gas in wei
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl Fees {
+    /// Return price per gas expected to be paid in wei if included in the
+    /// next block.
+    pub fn expected_gas_price(&self) -> U256 {
+        std::cmp::min(self.base_fee_per_gas + self.max_priority_fee_per_gas, self.max_fee_per_gas)
+    }
+
+    /// Return fees bumped by 25% to replace a pending transaction.
+    pub fn bumped(&self) -> Fees {
+        Fees {
+            base_fee_per_gas: self.base_fee_per_gas,
+            max_fee_per_gas: bump_fee(self.max_fee_per_gas),
+            max_priority_fee_per_gas: bump_fee(self.max_priority_fee_per_gas),
+        }
+    }
+}
+
+/// Suggest fees of the specified preset from `eth_feeHistory` of the latest
+/// `FEE_HISTORY_BLOCKS` blocks. Priority fee is median of the preset's
+/// percentile of priority fees paid in each block, so that a single outlier
+/// block doesn't skew it; empty blocks are left out.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `preset` - fee preset
+pub async fn suggest_fees<R: ChainRpc + ?Sized>(rpc: &R, preset: FeePreset) -> Result<Fees, AppvkekError> {
+    let history = rpc.fee_history(FEE_HISTORY_BLOCKS, preset.percentile()).await?;
+    let base_fee_per_gas = match history.base_fee_per_gas.last() {
+        Some(res) => *res,
+        None => return Err(AppvkekError::Decode("Error querying fee history; err=no base fee returned".to_owned())),
+    };
+
+    let mut rewards: Vec<U256> = history.reward.unwrap_or_default().into_iter()
+        .filter_map(|r| r.first().copied())
+        .filter(|r| !r.is_zero())
+        .collect();
+    rewards.sort();
+    let max_priority_fee_per_gas = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+
+    Ok(Fees {
+        base_fee_per_gas,
+        max_fee_per_gas: base_fee_per_gas * preset.base_fee_multiplier() + max_priority_fee_per_gas,
+        max_priority_fee_per_gas,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    #[tokio::test]
+    async fn suggest_fees_takes_median_priority_fee_of_non_empty_blocks() {
+        let rpc = MockChainRpc::default()
+            .with_block_fees(10, 0)
+            .with_block_fees(10, 3)
+            .with_block_fees(12, 1)
+            .with_block_fees(14, 100)
+            .with_block_fees(20, 2);
+
+        let fees = suggest_fees(&rpc, FeePreset::Normal).await.unwrap();
+
+        assert_eq!(fees, Fees { base_fee_per_gas: 20.into(), max_fee_per_gas: 43.into(), max_priority_fee_per_gas: 3.into() });
+        assert_eq!(fees.expected_gas_price(), 23.into());
+
+        let fees = suggest_fees(&rpc, FeePreset::Urgent).await.unwrap();
+        assert_eq!(fees.max_fee_per_gas, 63.into());
+    }
+
+    #[test]
+    fn parse_rejects_unknown_preset() {
+        assert_eq!(FeePreset::parse("URGENT"), Ok(FeePreset::Urgent));
+        assert!(FeePreset::parse("instant").is_err());
+    }
+}
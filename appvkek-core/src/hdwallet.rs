@@ -0,0 +1,306 @@
+use hmac::{Hmac, Mac};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use sha2::Sha512;
+
+use crate::error::AppvkekError;
+
+/// Offset of hardened child index
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// Version bytes of mainnet (`xpub`), and testnet (`tpub`) extended public key
+static XPUB_VERSIONS: [[u8; 4]; 2] = [[0x04, 0x88, 0xb2, 0x1e], [0x04, 0x35, 0x87, 0xcf]];
+
+/// Placeholder of address index in derivation path pattern
+pub static INDEX_PLACEHOLDER: &str = "{i}";
+
+/// Default derivation path pattern of mnemonic; first account of BIP-44
+/// Ethereum path as used by most wallets
+pub static DEFAULT_MNEMONIC_PATH: &str = "m/44'/60'/0'/0/{i}";
+
+/// Default derivation path pattern of extended public key; relative to it
+/// as exported at account level e.g. `m/44'/60'/0'`
+pub static DEFAULT_XPUB_PATH: &str = "0/{i}";
+
+/// Key of extended key; either private, or public only.
+/// It doesn't implement `Debug` so private key never ends up in logs.
+#[derive(Clone, Copy)]
+enum Key {
+    Private(SecretKey),
+    Public(PublicKey),
+}
+
+/// BIP-32 extended key to derive child keys from.
+#[derive(Clone)]
+pub struct ExtendedKey {
+    /// Private or public key
+    key: Key,
+
+    /// Chain code
+    chain_code: [u8; 32],
+}
+
+/// Derived wallet address along with where it's derived at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedAddress {
+    /// Index substituted into derivation path pattern
+    pub index: u32,
+
+    /// Derivation path e.g. `m/44'/60'/0'/0/3`
+    pub path: String,
+
+    /// Address; lowercased, prefixed with '0x'
+    pub address: String,
+}
+
+/// Return HMAC-SHA512 of the specified data.
+///
+/// # Arguments
+/// * `key` - HMAC key
+/// * `data` - data
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts key of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Decode base58check-encoded string, and return its payload without checksum.
+///
+/// # Arguments
+/// * `s` - base58check-encoded string
+fn base58check_decode(s: &str) -> Result<Vec<u8>, AppvkekError> {
+    match bs58::decode(s).with_check(None).into_vec() {
+        Ok(res) => Ok(res),
+        Err(bs58::decode::Error::InvalidChecksum { .. }) => Err(AppvkekError::Config("Error extended public key has invalid checksum".to_owned())),
+        Err(e) => Err(AppvkekError::Config(format!("Error invalid extended public key; err={}", e))),
+    }
+}
+
+impl ExtendedKey {
+    /// Parse extended public key e.g. `xpub6C...`.
+    ///
+    /// # Arguments
+    /// * `xpub` - base58check-encoded extended public key
+    pub fn from_xpub(xpub: &str) -> Result<ExtendedKey, AppvkekError> {
+        let payload = base58check_decode(xpub.trim())?;
+        if payload.len() != 78 {
+            return Err(AppvkekError::Config(format!("Error extended public key has invalid length; len={}", payload.len())));
+        }
+        if !XPUB_VERSIONS.iter().any(|v| payload[..4] == *v) {
+            return Err(AppvkekError::Config("Error not an extended public key; only xpub, or tpub is supported".to_owned()));
+        }
+
+        let public_key = match PublicKey::from_slice(&payload[45..78]) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid public key in extended public key; err={}", e))),
+        };
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&payload[13..45]);
+
+        Ok(ExtendedKey { key: Key::Public(public_key), chain_code })
+    }
+
+    /// Derive master key from BIP-39 mnemonic in English. Mnemonic with a
+    /// word not in the word list, or with invalid checksum e.g. due to a typo
+    /// is rejected rather than silently deriving a different wallet.
+    ///
+    /// # Arguments
+    /// * `mnemonic` - mnemonic words separated by whitespace
+    /// * `passphrase` - optional BIP-39 passphrase; empty if none
+    pub fn from_mnemonic(mnemonic: &str, passphrase: &str) -> Result<ExtendedKey, AppvkekError> {
+        let words: Vec<&str> = mnemonic.split_whitespace().collect();
+        if ![12, 15, 18, 21, 24].contains(&words.len()) {
+            return Err(AppvkekError::Config(format!("Error mnemonic must have 12, 15, 18, 21, or 24 words; got {}", words.len())));
+        }
+
+        // error never includes the words themselves
+        let mnemonic = match bip39::Mnemonic::parse_in(bip39::Language::English, words.join(" ")) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid mnemonic; err={}", e))),
+        };
+        ExtendedKey::from_seed(&mnemonic.to_seed(passphrase))
+    }
+
+    /// Derive master key from seed.
+    ///
+    /// # Arguments
+    /// * `seed` - seed bytes
+    fn from_seed(seed: &[u8]) -> Result<ExtendedKey, AppvkekError> {
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let secret_key = match SecretKey::from_slice(&i[..32]) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid master key derived from seed; err={}", e))),
+        };
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&i[32..]);
+
+        Ok(ExtendedKey { key: Key::Private(secret_key), chain_code })
+    }
+
+    /// Whether it has private key, so hardened children can be derived.
+    pub fn is_private(&self) -> bool {
+        matches!(self.key, Key::Private(_))
+    }
+
+    /// Return public key.
+    fn public_key(&self) -> PublicKey {
+        match self.key {
+            Key::Private(sk) => PublicKey::from_secret_key(&Secp256k1::signing_only(), &sk),
+            Key::Public(pk) => pk,
+        }
+    }
+
+    /// Derive child key at the specified index.
+    ///
+    /// # Arguments
+    /// * `index` - child index; hardened if at least `2^31`
+    fn derive_child(&self, index: u32) -> Result<ExtendedKey, AppvkekError> {
+        let mut data = match (self.key, index >= HARDENED_OFFSET) {
+            (Key::Private(sk), true) => {
+                let mut data = vec![0u8];
+                data.extend(sk.as_ref());
+                data
+            },
+            (Key::Public(_), true) => return Err(AppvkekError::Config("Error hardened child can't be derived from extended public key".to_owned())),
+            (_, false) => self.public_key().serialize().to_vec(),
+        };
+        data.extend(index.to_be_bytes());
+
+        let i = hmac_sha512(&self.chain_code, &data);
+        let key = match self.key {
+            Key::Private(mut sk) => sk.add_assign(&i[..32]).map(|_| Key::Private(sk)),
+            Key::Public(mut pk) => pk.add_exp_assign(&Secp256k1::verification_only(), &i[..32]).map(|_| Key::Public(pk)),
+        };
+        let key = match key {
+            Ok(res) => res,
+            // probability is lower than 1 in 2^127
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid child key at index {}; err={}", index, e))),
+        };
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&i[32..]);
+
+        Ok(ExtendedKey { key, chain_code })
+    }
+
+    /// Derive key at the specified path relative to this key.
+    ///
+    /// # Arguments
+    /// * `path` - path components; hardened if at least `2^31`
+    fn derive_path(&self, path: &[u32]) -> Result<ExtendedKey, AppvkekError> {
+        let mut key = self.clone();
+        for index in path {
+            key = key.derive_child(*index)?;
+        }
+        Ok(key)
+    }
+
+    /// Return Ethereum address of this key.
+    pub fn address(&self) -> String {
+        let public_key = self.public_key().serialize_uncompressed();
+        let hash = web3::signing::keccak256(&public_key[1..]);
+        format!("0x{}", hex::encode(&hash[12..]))
+    }
+}
+
+/// Parse a single component of derivation path e.g. `44'`, `44h`, or `0`.
+///
+/// # Arguments
+/// * `component` - path component
+fn parse_path_component(component: &str) -> Result<u32, AppvkekError> {
+    let (number, is_hardened) = match component.strip_suffix('\'').or_else(|| component.strip_suffix('h')) {
+        Some(res) => (res, true),
+        None => (component, false),
+    };
+    match number.parse::<u32>() {
+        Ok(res) if res < HARDENED_OFFSET => Ok(if is_hardened { res + HARDENED_OFFSET } else { res }),
+        _ => Err(AppvkekError::Config(format!("Error invalid derivation path component '{}'", component))),
+    }
+}
+
+/// Derive addresses at the specified derivation path pattern, substituting
+/// `{i}` with each index from 0 to `count - 1`.
+/// Pattern starts with `m/` if `root` is master key from mnemonic, otherwise
+/// it's relative to extended public key, and can't have hardened components.
+///
+/// # Arguments
+/// * `root` - master key, or extended public key
+/// * `pattern` - derivation path pattern having `{i}` exactly once e.g. `m/44'/60'/0'/0/{i}`
+/// * `count` - number of addresses to derive
+pub fn derive_addresses(root: &ExtendedKey, pattern: &str, count: u32) -> Result<Vec<DerivedAddress>, AppvkekError> {
+    let components: Vec<&str> = pattern.trim().split('/').collect();
+    let components = match (root.is_private(), components.split_first()) {
+        (true, Some((&"m", rest))) => rest,
+        (true, _) => return Err(AppvkekError::Config(format!("Error derivation path of mnemonic must start with m/; path={}", pattern))),
+        (false, Some((&"m", _))) => return Err(AppvkekError::Config(format!("Error derivation path of extended public key must be relative to it e.g. 0/{{i}}; path={}", pattern))),
+        (false, _) => &components[..],
+    };
+    if components.iter().filter(|c| c.starts_with(INDEX_PLACEHOLDER)).count() != 1 {
+        return Err(AppvkekError::Config(format!("Error derivation path must have {} exactly once; path={}", INDEX_PLACEHOLDER, pattern)));
+    }
+
+    let mut addresses = Vec::new();
+    for index in 0..count {
+        let path = components.iter()
+            .map(|c| parse_path_component(&c.replace(INDEX_PLACEHOLDER, &index.to_string())))
+            .collect::<Result<Vec<u32>, AppvkekError>>()?;
+        let path_str = components.join("/").replace(INDEX_PLACEHOLDER, &index.to_string());
+
+        addresses.push(DerivedAddress {
+            index,
+            path: if root.is_private() { format!("m/{}", path_str) } else { path_str },
+            address: root.derive_path(&path)?.address(),
+        });
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn derive_addresses_from_mnemonic_matches_well_known_accounts() {
+        let root = ExtendedKey::from_mnemonic(TEST_MNEMONIC, "").unwrap();
+
+        let addresses = derive_addresses(&root, DEFAULT_MNEMONIC_PATH, 2).unwrap();
+
+        assert_eq!(addresses[0].path, "m/44'/60'/0'/0/0");
+        assert_eq!(addresses[0].address, "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+        assert_eq!(addresses[1].address, "0x70997970c51812dc3a010c7d01b50e0d17dc79c8");
+    }
+
+    #[test]
+    fn derive_addresses_from_public_key_matches_private_derivation() {
+        let root = ExtendedKey::from_mnemonic(TEST_MNEMONIC, "").unwrap();
+        let account = root.derive_path(&[44 + HARDENED_OFFSET, 60 + HARDENED_OFFSET, HARDENED_OFFSET]).unwrap();
+        let xpub = ExtendedKey { key: Key::Public(account.public_key()), chain_code: account.chain_code };
+
+        let addresses = derive_addresses(&xpub, DEFAULT_XPUB_PATH, 2).unwrap();
+
+        assert_eq!(addresses[1].path, "0/1");
+        assert_eq!(addresses[1].address, "0x70997970c51812dc3a010c7d01b50e0d17dc79c8");
+        assert!(derive_addresses(&xpub, "0'/{i}", 1).is_err());
+    }
+
+    #[test]
+    fn from_xpub_parses_bip32_test_vector() {
+        // BIP-32 test vector 1, chain m
+        let xpub = ExtendedKey::from_xpub("xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8").unwrap();
+        let master = ExtendedKey::from_seed(&hex::decode("000102030405060708090a0b0c0d0e0f").unwrap()).unwrap();
+
+        assert_eq!(xpub.address(), master.address());
+        assert_eq!(xpub.chain_code, master.chain_code);
+        assert!(ExtendedKey::from_xpub("xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet9").is_err());
+    }
+
+    #[test]
+    fn from_mnemonic_rejects_invalid_checksum_or_word() {
+        // last word swapped for another one in the word list
+        assert!(ExtendedKey::from_mnemonic("test test test test test test test test test test test test", "").is_err());
+        assert!(ExtendedKey::from_mnemonic("test test test test test test test test test test test junkk", "").is_err());
+        assert!(ExtendedKey::from_mnemonic("test test test test test test test test test test junk", "").is_err());
+        // words are separated by any whitespace
+        assert!(ExtendedKey::from_mnemonic(" test test test test test test\ttest test test test test\njunk ", "").is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use web3::types::U256;
+
+use crate::error::AppvkekError;
+use crate::explorer::{self, APPROVAL_EVENT_TOPIC};
+use crate::provider::ExplorerApi;
+
+/// Kind of change of allowance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowanceEventKind {
+    /// The first approval seen
+    Approve,
+
+    /// Allowance is raised
+    Increase,
+
+    /// Allowance is lowered but not to zero; this also covers allowance spent
+    /// via `transferFrom()` on tokens which emit `Approval` event on it
+    Decrease,
+
+    /// Allowance is set to zero
+    Revoke,
+}
+
+impl AllowanceEventKind {
+    /// Return name of kind e.g. `increase`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AllowanceEventKind::Approve => "approve",
+            AllowanceEventKind::Increase => "increase",
+            AllowanceEventKind::Decrease => "decrease",
+            AllowanceEventKind::Revoke => "revoke",
+        }
+    }
+}
+
+/// A single change of allowance of a spender on a token contract.
+#[derive(Debug, Clone)]
+pub struct AllowanceEvent {
+    /// Kind of change
+    pub kind: AllowanceEventKind,
+
+    /// Raw allowance balance after the change
+    pub amount: U256,
+
+    /// Block number of the transaction
+    pub block_number: u64,
+
+    /// Unix timestamp (in seconds) of the block containing the transaction
+    pub timestamp: u64,
+
+    /// Hash of the transaction
+    pub tx_hash: String,
+}
+
+/// Return every change of allowance of the specified spender on the
+/// specified token contract in chronological order. Changes are taken from
+/// `Approval` event logs which carry the new allowance, so they also cover
+/// approvals made via internal calls e.g. by contract wallets.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `owner_address` - owner wallet address; lowercased, prefixed with '0x'
+/// * `token_address` - token contract address; lowercased, prefixed with '0x'
+/// * `spender` - spender address; lowercased, prefixed with '0x'
+pub fn get_allowance_history<E: ExplorerApi + ?Sized>(explorer: &E, owner_address: &str, token_address: &str, spender: &str) -> Result<Vec<AllowanceEvent>, AppvkekError> {
+    let spender_topic = format!("0x{:0>64}", spender.trim_start_matches("0x"));
+    let mut seen: HashSet<(String, u64)> = HashSet::new();
+    let mut events: Vec<AllowanceEvent> = Vec::new();
+    let mut previous: Option<U256> = None;
+
+    explorer::for_each_approval_logs_page(explorer, owner_address, 0, |logs| {
+        for log in logs {
+            // ERC-721's `Approval` has token id indexed as well
+            if log.address != token_address || log.topics.len() != 3 || log.topics[0] != APPROVAL_EVENT_TOPIC || log.topics[2] != spender_topic {
+                continue;
+            }
+            // logs of the boundary block of a query range may be seen twice
+            if !seen.insert((log.transaction_hash.to_owned(), log.log_index)) {
+                continue;
+            }
+
+            let data = match hex::decode(log.data.trim_start_matches("0x")) {
+                Ok(res) if res.len() >= 32 => res,
+                _ => return Err(AppvkekError::Decode(format!("Error decoding amount of Approval event in tx {}", log.transaction_hash))),
+            };
+            let amount = U256::from_big_endian(&data[..32]);
+
+            let kind = match previous {
+                _ if amount.is_zero() => AllowanceEventKind::Revoke,
+                None => AllowanceEventKind::Approve,
+                Some(prev) if prev.is_zero() => AllowanceEventKind::Approve,
+                Some(prev) if amount > prev => AllowanceEventKind::Increase,
+                Some(_) => AllowanceEventKind::Decrease,
+            };
+            previous = Some(amount);
+
+            events.push(AllowanceEvent {
+                kind,
+                amount,
+                block_number: log.block_number,
+                timestamp: log.timestamp,
+                tx_hash: log.transaction_hash,
+            });
+        }
+        Ok(())
+    })?;
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockExplorer;
+
+    static OWNER: &str = "0x1111111111111111111111111111111111111111";
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static SPENDER: &str = "0x3333333333333333333333333333333333333333";
+    static OTHER_SPENDER: &str = "0x4444444444444444444444444444444444444444";
+
+    #[test]
+    fn history_classifies_changes_in_order() {
+        let explorer = MockExplorer::default()
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(100), 10)
+            .with_approval_log(OWNER, TOKEN, OTHER_SPENDER, U256::from(5), 11)
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(150), 12)
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(40), 13)
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::zero(), 14)
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(7), 15);
+
+        let events = get_allowance_history(&explorer, OWNER, TOKEN, SPENDER).unwrap();
+        let kinds: Vec<AllowanceEventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![AllowanceEventKind::Approve, AllowanceEventKind::Increase, AllowanceEventKind::Decrease, AllowanceEventKind::Revoke, AllowanceEventKind::Approve]);
+        assert_eq!(events[1].amount, U256::from(150));
+        assert_eq!(events[3].block_number, 14);
+    }
+}
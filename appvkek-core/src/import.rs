@@ -0,0 +1,183 @@
+use ::evmscan::prelude::*;
+use serde::Deserialize;
+
+use crate::error::AppvkekError;
+use crate::types::*;
+use crate::util::*;
+
+/// Approval to import; a subset of fields of `report::ApprovalEntry`, so
+/// that JSON output of `export` can be imported as well as third-party data
+/// with only token and spender.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedApproval {
+    /// Chain name e.g. `bsc`; the approval applies to any chain if not
+    /// specified
+    #[serde(default)]
+    pub chain: Option<String>,
+
+    /// Owner wallet address; the approval applies to any wallet if not
+    /// specified
+    #[serde(default)]
+    pub owner: Option<String>,
+
+    /// Token contract address
+    pub token_address: String,
+
+    /// Spender address
+    pub spender: String,
+
+    /// Block number of the latest approve transaction; 0 if unknown e.g.
+    /// third-party data with only token and spender
+    #[serde(default)]
+    pub approval_block: u64,
+
+    /// Unix timestamp (in seconds) of the latest approve transaction; 0 if
+    /// unknown, in which case the approval is not considered stale
+    #[serde(default)]
+    pub approval_timestamp: u64,
+
+    /// Hash of the latest approve transaction
+    #[serde(default)]
+    pub approval_tx_hash: Option<String>,
+
+    /// Issue in calldata of the latest approve transaction
+    #[serde(default)]
+    pub approval_issue: Option<String>,
+
+    /// Hash of ERC-4337 user operation which made the latest approval
+    #[serde(default)]
+    pub approval_user_op_hash: Option<String>,
+}
+
+/// Report to import approvals from; as of JSON output of `export`.
+#[derive(Debug, Deserialize)]
+struct ImportedReport {
+    approvals: Vec<ImportedApproval>,
+}
+
+/// Content of import file; report of a single wallet as of JSON output of
+/// `export`, array of them, or plain array of approvals.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ImportFile {
+    Single(ImportedReport),
+    Multiple(Vec<ImportedReport>),
+    Approvals(Vec<ImportedApproval>),
+}
+
+/// Approvals imported from a previous export, or third-party data, to be
+/// queried for allowances instead of collecting them from transaction
+/// history.
+#[derive(Debug, Default)]
+pub struct ImportedApprovals {
+    approvals: Vec<ImportedApproval>,
+}
+
+impl ImportedApprovals {
+    /// Load approvals from the specified file.
+    ///
+    /// # Arguments
+    /// * `path` - path to import file
+    pub fn load(path: &str) -> Result<ImportedApprovals, AppvkekError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Config(format!("Error reading import file ({}); err={}", path, e))),
+        };
+        ImportedApprovals::parse(&content).map_err(|e| AppvkekError::Decode(format!("{} ({})", e, path)))
+    }
+
+    /// Parse approvals from content of import file.
+    ///
+    /// # Arguments
+    /// * `content` - content of import file
+    pub fn parse(content: &str) -> Result<ImportedApprovals, AppvkekError> {
+        let approvals = match serde_json::from_str::<ImportFile>(content) {
+            Ok(ImportFile::Single(report)) => report.approvals,
+            Ok(ImportFile::Multiple(reports)) => reports.into_iter().flat_map(|r| r.approvals).collect(),
+            Ok(ImportFile::Approvals(approvals)) => approvals,
+            Err(e) => return Err(AppvkekError::Decode(format!("Error parsing import file; err={}", e))),
+        };
+        for a in approvals.iter() {
+            for address in [&a.token_address, &a.spender] {
+                if !validate_address_format(address) {
+                    return Err(AppvkekError::Decode(format!("Error invalid address format in import file ({})", address)));
+                }
+            }
+        }
+        Ok(ImportedApprovals { approvals })
+    }
+
+    /// Return approvals of the specified wallet on the specified chain in
+    /// form of collected approvals, along with those without chain or owner.
+    /// The latest one is taken if the same approval is listed more than
+    /// once.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `owner_address` - owner wallet address
+    pub fn approvals_of(&self, chain: ChainType, owner_address: &str) -> ApprovalsMap {
+        let mut ret = ApprovalsMap::new();
+        for a in self.approvals.iter() {
+            let is_chain = a.chain.as_deref().map(|c| c.eq_ignore_ascii_case(chain_name(chain))).unwrap_or(true);
+            let is_owner = a.owner.as_deref().map(|o| o.eq_ignore_ascii_case(owner_address)).unwrap_or(true);
+            if !is_chain || !is_owner {
+                continue;
+            }
+
+            let spenders = ret.entry(a.token_address.to_lowercase()).or_default();
+            let spender = a.spender.to_lowercase();
+            if spenders.get(&spender).map(|r| r.block_number > a.approval_block).unwrap_or(false) {
+                continue;
+            }
+            spenders.insert(spender, ApprovalRecord {
+                block_number: a.approval_block,
+                timestamp: a.approval_timestamp,
+                tx_hash: a.approval_tx_hash.clone().unwrap_or_default(),
+                issue: a.approval_issue.clone(),
+                user_op_hash: a.approval_user_op_hash.clone(),
+            });
+        }
+        ret
+    }
+
+    /// Return number of imported approvals.
+    pub fn len(&self) -> usize {
+        self.approvals.len()
+    }
+
+    /// Return whether there is no imported approval.
+    pub fn is_empty(&self) -> bool {
+        self.approvals.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approvals_are_imported_from_export_or_plain_list() {
+        let (owner, token, spender) = ("0x1111111111111111111111111111111111111111", "0x10ED43C718714eb63d5aA57B78B54704E256024E", "0x2222222222222222222222222222222222222222");
+
+        let exported = format!(r#"{{"schema_version": 2, "chain": "bsc", "owner": "{owner}", "generated_at": 0, "errors": [], "approvals": [
+            {{"chain": "bsc", "owner": "{owner}", "token_address": "{token}", "spender": "{spender}", "allowance": 1.0, "approval_block": 10, "approval_timestamp": 100, "approval_tx_hash": "0xabc"}},
+            {{"chain": "ethereum", "owner": "{owner}", "token_address": "{token}", "spender": "{owner}", "allowance": 1.0, "approval_block": 10, "approval_timestamp": 100}}
+        ]}}"#);
+        let imported = ImportedApprovals::parse(&exported).unwrap();
+        assert_eq!(imported.len(), 2);
+        let approvals = imported.approvals_of(ChainType::BSC, owner);
+        assert_eq!(approvals.len(), 1);
+        let record = &approvals[&token.to_lowercase()][spender];
+        assert_eq!(record.block_number, 10);
+        assert_eq!(record.tx_hash, "0xabc");
+        assert!(imported.approvals_of(ChainType::BSC, spender).is_empty());
+
+        let plain = format!(r#"[{{"token_address": "{token}", "spender": "{spender}"}}]"#);
+        let imported = ImportedApprovals::parse(&plain).unwrap();
+        assert_eq!(imported.approvals_of(ChainType::Polygon, spender)[&token.to_lowercase()].len(), 1);
+
+        assert!(matches!(ImportedApprovals::parse(r#"[{"token_address": "0x123", "spender": "0x456"}]"#), Err(AppvkekError::Decode(_))));
+        assert!(matches!(ImportedApprovals::parse("not json"), Err(AppvkekError::Decode(_))));
+        assert!(matches!(ImportedApprovals::load("/nonexistent/import.json"), Err(AppvkekError::Config(_))));
+    }
+}
@@ -0,0 +1,240 @@
+use ::evmscan::prelude::*;
+use std::collections::HashMap;
+
+use crate::explorer::http_get_external;
+use crate::util::*;
+
+/// Bundled label database of well-known spender addresses
+static BUNDLED_LABELS: &str = include_str!("../../data/labels.json");
+
+/// URL to fetch the latest label database from when updating
+pub static LABELS_UPDATE_URL: &str = "https://raw.githubusercontent.com/haxpor/appvkek/master/data/labels.json";
+
+/// Label database in form of chain name (or `*` for all chains) to
+/// `HashMap` of lowercased address to its label.
+type LabelsFile = HashMap<String, HashMap<String, String>>;
+
+/// Address book in form of address to its user-provided label; the same
+/// across all chains.
+type AddressBookFile = HashMap<String, String>;
+
+/// Labels of well-known addresses for a chain.
+#[derive(Debug, Clone, Default)]
+pub struct Labels {
+    /// Lowercased address to its label
+    labels: HashMap<String, String>,
+
+    /// Lowercased address to its primary ENS name
+    ens_names: HashMap<String, String>,
+
+    /// Lowercased address to its label from user's address book
+    address_book: HashMap<String, String>,
+}
+
+impl Labels {
+    /// Load labels for the specified chain from the bundled database, then
+    /// from `$HOME/.appvkek/labels.json` (as updated by `--update-labels`)
+    /// if exists, and lastly from `extra_file` if specified.
+    /// Labels loaded later override the earlier ones.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `extra_file` - optional path to additional labels file in the same format
+    pub fn load(chain: ChainType, extra_file: Option<&str>) -> Result<Labels, String> {
+        let mut labels = Labels::default();
+        labels.merge(chain, BUNDLED_LABELS)?;
+
+        if let Ok(content) = std::fs::read_to_string(user_labels_file_path()) {
+            labels.merge(chain, &content)?;
+        }
+
+        if let Some(path) = extra_file {
+            match std::fs::read_to_string(path) {
+                Ok(content) => labels.merge(chain, &content)?,
+                Err(e) => return Err(format!("Error reading labels file ({}); err={}", path, e)),
+            }
+        }
+
+        Ok(labels)
+    }
+
+    /// Merge labels for the specified chain from content of labels file.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `content` - content of labels file
+    fn merge(&mut self, chain: ChainType, content: &str) -> Result<(), String> {
+        let mut file = match serde_json::from_str::<LabelsFile>(content) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error parsing labels file; err={}", e)),
+        };
+
+        for key in ["*", chain_name(chain)] {
+            if let Some(entries) = file.remove(key) {
+                for (address, label) in entries {
+                    self.labels.insert(address.to_lowercase(), label);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return label of the specified address if any.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn get(&self, address: &str) -> Option<&str> {
+        self.labels.get(&address.to_lowercase()).map(|s| s.as_str())
+    }
+
+    /// Return whether the specified address is known i.e. it has a label
+    /// either in label database, or in user's address book.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn is_known(&self, address: &str) -> bool {
+        self.get(address).is_some() || self.address_book_label(address).is_some()
+    }
+
+    /// Load user's address book from `path` if specified, otherwise from
+    /// `$HOME/.appvkek/addressbook.json` if exists. Labels in it are only
+    /// for display, and don't make addresses considered well-known.
+    ///
+    /// # Arguments
+    /// * `path` - optional path to address book file
+    pub fn load_address_book(&mut self, path: Option<&str>) -> Result<(), String> {
+        let content = match path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(res) => res,
+                Err(e) => return Err(format!("Error reading address book ({}); err={}", path, e)),
+            },
+            None => match std::fs::read_to_string(address_book_file_path()) {
+                Ok(res) => res,
+                Err(_) => return Ok(()),
+            },
+        };
+
+        let file = match serde_json::from_str::<AddressBookFile>(&content) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error parsing address book; err={}", e)),
+        };
+        for (address, label) in file {
+            if !validate_address_format(&address) {
+                return Err(format!("Error address book contains invalid address ({})", address));
+            }
+            self.address_book.insert(address.to_lowercase(), label);
+        }
+
+        Ok(())
+    }
+
+    /// Return label of the specified address from user's address book if any.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn address_book_label(&self, address: &str) -> Option<&str> {
+        self.address_book.get(&address.to_lowercase()).map(|s| s.as_str())
+    }
+
+    /// Add primary ENS names of addresses from `ens::lookup_addresses()`.
+    ///
+    /// # Arguments
+    /// * `names` - lowercased address to its ENS name
+    pub fn add_ens_names(&mut self, names: HashMap<String, String>) {
+        self.ens_names.extend(names);
+    }
+
+    /// Return primary ENS name of the specified address if any.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn ens_name(&self, address: &str) -> Option<&str> {
+        self.ens_names.get(&address.to_lowercase()).map(|s| s.as_str())
+    }
+
+    /// Return the specified address EIP-55 checksummed along with its label
+    /// if any e.g. `0x10ED...024E (PancakeSwap Router V2)`, otherwise along
+    /// with its ENS name if any e.g. `nick.eth (0xb8c2...67D5)`. Label from
+    /// user's address book takes precedence over the well-known one.
+    ///
+    /// # Arguments
+    /// * `address` - address to format
+    pub fn format(&self, address: &str) -> String {
+        let checksum_address = to_checksum_address(address);
+        match (self.address_book_label(address).or_else(|| self.get(address)), self.ens_name(address)) {
+            (Some(label), _) => format!("{} ({})", checksum_address, label),
+            (None, Some(name)) => format!("{} ({})", name, checksum_address),
+            (None, None) => checksum_address,
+        }
+    }
+}
+
+/// Return name of protocol which the specified label of well-known address
+/// belongs to. Labels in label database are named by protocol followed by
+/// contract e.g. `Uniswap V3 Router`, so it's the first word of label.
+///
+/// # Arguments
+/// * `label` - label of well-known address
+pub fn protocol_of(label: &str) -> &str {
+    label.split_whitespace().next().unwrap_or(label)
+}
+
+/// Return path to user's labels file; `$HOME/.appvkek/labels.json`.
+pub fn user_labels_file_path() -> String {
+    format!("{}/labels.json", appvkek_dir())
+}
+
+/// Return path to user's address book; `$HOME/.appvkek/addressbook.json`.
+pub fn address_book_file_path() -> String {
+    format!("{}/addressbook.json", appvkek_dir())
+}
+
+/// Fetch the latest label database, and save it as user's labels file.
+pub fn update_labels() -> Result<(), String> {
+    let content = match http_get_external(LABELS_UPDATE_URL) {
+        Ok(mut res) => {
+            if res.status() != 200 {
+                return Err(format!("Error HTTP {} returned from {}", res.status().as_str(), LABELS_UPDATE_URL));
+            }
+            match isahc::ReadResponseExt::text(&mut res) {
+                Ok(res) => res,
+                Err(e) => return Err(format!("Error reading response body; err={}", e)),
+            }
+        },
+        Err(e) => return Err(format!("Error in sending HTTP request; err={}", e)),
+    };
+
+    // validate before saving
+    if let Err(e) = serde_json::from_str::<LabelsFile>(&content) {
+        return Err(format!("Error parsing fetched labels file; err={}", e));
+    }
+
+    write_file(&user_labels_file_path(), &content)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_book_label_takes_precedence() {
+        let router = "0x10ed43c718714eb63d5aa57b78b54704e256024e";
+        let path = std::env::temp_dir().join(format!("appvkek-addressbook-{}.json", std::process::id()));
+        std::fs::write(&path, format!(r#"{{"{}": "old router", "0xD8dA6BF26964aF9D7eEd9e03E53415D37aA96045": "my hot wallet"}}"#, router.to_uppercase().replace("0X", "0x"))).unwrap();
+
+        let mut labels = Labels::load(ChainType::BSC, None).unwrap();
+        assert!(labels.get(router).is_some());
+        labels.load_address_book(path.to_str()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(labels.format(router), "0x10ED43C718714eb63d5aA57B78B54704E256024E (old router)");
+        assert_eq!(labels.address_book_label("0xd8da6bf26964af9d7eed9e03e53415d37aa96045"), Some("my hot wallet"));
+        // well-known label is kept for risk assessment
+        assert_ne!(labels.get(router), Some("old router"));
+        assert!(labels.is_known("0xd8da6bf26964af9d7eed9e03e53415d37aa96045"));
+        assert!(!labels.is_known("0x1111111111111111111111111111111111111111"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Core of appvkek; scanner of token approvals made by a wallet on EVM-based
+//! chains, along with risk assessment of their spenders.
+//!
+//! A typical scan consists of
+//! 1. collecting approvals from wallet's transaction history via explorer API
+//!    with `scanner::collect_approvals()`
+//! 2. querying current allowance of each approval from chain with
+//!    `scanner::query_all()`
+//! 3. optionally assessing risk of each spender with `risk::assess_all()`
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//! use appvkek_core::evmscan::environ::Context;
+//! use appvkek_core::evmscan::prelude::ChainType;
+//! use appvkek_core::failures::FailedContracts;
+//! use appvkek_core::metadata::MetadataCache;
+//! use appvkek_core::ratelimit::TokenBucket;
+//! use appvkek_core::scanner;
+//! use appvkek_core::transport::RetryPolicy;
+//! use appvkek_core::types::ApprovalsMap;
+//! use appvkek_core::util::*;
+//!
+//! # async fn run() {
+//! let chain = ChainType::BSC;
+//! let owner = "0x...";
+//! let retry = RetryPolicy { max_retries: 3, base_delay: std::time::Duration::from_millis(500), max_delay: RPC_MAX_RETRY_DELAY };
+//! let (rps, burst) = default_rpc_rate_limit(chain);
+//! let web3 = create_web3(chain, &[], true, retry, std::time::Duration::from_secs(30), TokenBucket::new(rps, burst), &HashMap::new(), None).unwrap();
+//! let semaphore = tokio::sync::Semaphore::new(16);
+//! let ctx = Context::create(chain, "<explorer-api-key>".to_owned());
+//!
+//! let mut approvals: ApprovalsMap = HashMap::new();
+//! scanner::collect_approvals(&ctx, owner, 0, &mut approvals, None).unwrap();
+//!
+//! let mut metadata_cache = MetadataCache::load(chain, true);
+//! let mut failed_contracts = FailedContracts::default();
+//! let results = scanner::query_all(&web3, &semaphore, approvals, owner, true, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+//! for r in results.iter().flatten() {
+//!     for (spender, allowance) in r.spender_allowances.iter() {
+//!         println!("{} {} {}", r.address, spender, allowance.allowance);
+//!     }
+//! }
+//! # }
+//! ```
+
+pub use evmscan;
+pub use web3;
+
+pub mod analyzer;
+pub mod codecache;
+pub mod creationcache;
+pub mod debugdump;
+pub mod ens;
+mod erc20;
+pub mod error;
+pub mod estimate;
+pub mod exploits;
+pub mod explorer;
+pub mod failures;
+pub mod fees;
+pub mod hdwallet;
+pub mod import;
+pub mod history;
+pub mod labels;
+pub mod malicious;
+pub mod mempool;
+pub mod metadata;
+pub mod mock;
+pub mod multicall;
+pub mod notify;
+pub mod prices;
+pub mod provider;
+pub mod proxy;
+pub mod ratelimit;
+pub mod relay;
+pub mod replay;
+pub mod report;
+pub mod revoke;
+pub mod risk;
+pub mod scanner;
+pub mod state;
+pub mod transport;
+pub mod types;
+pub mod usage;
+pub mod util;
@@ -0,0 +1,101 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::explorer::http_get_external;
+use crate::util::*;
+
+/// ScamSniffer's published list of drainer/scam addresses
+pub static SCAMSNIFFER_ADDRESS_LIST_URL: &str = "https://raw.githubusercontent.com/scamsniffer/scam-database/main/blacklist/address.json";
+
+/// How long a fetched list will be cached before fetching again
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Database of known-malicious addresses combined from multiple sources.
+#[derive(Debug, Default)]
+pub struct MaliciousDb {
+    /// Lowercased address to the source that lists it
+    addresses: HashMap<String, String>,
+}
+
+impl MaliciousDb {
+    /// Load known-malicious addresses from all specified sources.
+    /// Each source can be either URL (`http://` or `https://`) or local file path.
+    /// Fetched content from URL will be cached for 24 hours.
+    ///
+    /// Sources that fail to load are reported as warning, and skipped.
+    ///
+    /// # Arguments
+    /// * `sources` - list of URLs or file paths
+    pub fn load(sources: &[String]) -> MaliciousDb {
+        let mut db = MaliciousDb::default();
+
+        for source in sources {
+            match load_source(source) {
+                Ok(content) => {
+                    for address in extract_addresses(&content) {
+                        db.addresses.entry(address).or_insert_with(|| source.to_owned());
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("skipped malicious address list ({}); err={}", source, e);
+                }
+            }
+        }
+
+        db
+    }
+
+    /// Return the source listing the specified address as malicious if any.
+    ///
+    /// # Arguments
+    /// * `address` - address to look up
+    pub fn lookup(&self, address: &str) -> Option<&str> {
+        self.addresses.get(&address.to_lowercase()).map(|s| s.as_str())
+    }
+}
+
+/// Extract all addresses from content of a list.
+/// Published lists come in various formats (JSON array, CSV, plain text), so
+/// all strings in address format are extracted regardless of the format.
+///
+/// # Arguments
+/// * `content` - content of a list
+fn extract_addresses(content: &str) -> Vec<String> {
+    let regex: Regex = Regex::new(r#"0x[0-9a-fA-F]{40}"#).unwrap();
+    regex.find_iter(content).map(|m| m.as_str().to_lowercase()).collect()
+}
+
+/// Load content of the specified source, using cache if source is URL.
+///
+/// # Arguments
+/// * `source` - URL or file path
+fn load_source(source: &str) -> Result<String, String> {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return std::fs::read_to_string(source).map_err(|e| format!("Error reading file; err={}", e));
+    }
+
+    let cache_path = format!("{}/malicious-{}", cache_dir(), sanitize_filename(source));
+    if let Some(content) = read_cache(&cache_path, CACHE_TTL) {
+        return Ok(content);
+    }
+
+    let content = match http_get_external(source) {
+        Ok(mut res) => {
+            if res.status() != 200 {
+                return Err(format!("Error HTTP {} returned", res.status().as_str()));
+            }
+            match isahc::ReadResponseExt::text(&mut res) {
+                Ok(res) => res,
+                Err(e) => return Err(format!("Error reading response body; err={}", e)),
+            }
+        },
+        Err(e) => return Err(format!("Error in sending HTTP request; err={}", e)),
+    };
+
+    if let Err(e) = write_file(&cache_path, &content) {
+        tracing::warn!("{}", e);
+    }
+
+    Ok(content)
+}
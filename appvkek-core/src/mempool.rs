@@ -0,0 +1,289 @@
+use futures::StreamExt;
+use web3::ethabi::{self, ParamType, Token};
+use web3::transports::WebSocket;
+use web3::types::{Address, Bytes, CallRequest, Transaction, TransactionId, H256, U256};
+
+use crate::erc20::*;
+use crate::error::AppvkekError;
+use crate::revoke::{build_revoke_tx, RevokeTx};
+use crate::scanner::to_token_amount;
+use crate::util::*;
+
+/// Maximum number of pending transactions looked up concurrently
+const MAX_CONCURRENT_LOOKUPS: usize = 16;
+
+/// Approve transaction of owner wallet seen in mempool, not yet confirmed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingApproval {
+    /// Transaction hash; prefixed with '0x'
+    pub tx_hash: String,
+
+    /// Token contract address; lowercased, prefixed with '0x'
+    pub token_address: String,
+
+    /// Spender address; lowercased, prefixed with '0x'
+    pub spender: String,
+
+    /// Amount approved, or added to allowance if `is_increase`; in its
+    /// smallest unit
+    pub amount: U256,
+
+    /// Whether it's `increaseAllowance()` rather than `approve()`
+    pub is_increase: bool,
+
+    /// Nonce of transaction
+    pub nonce: U256,
+
+    /// Gas price of legacy transaction
+    pub gas_price: Option<U256>,
+
+    /// Max fee per gas of EIP-1559 transaction
+    pub max_fee_per_gas: Option<U256>,
+
+    /// Max priority fee per gas of EIP-1559 transaction
+    pub max_priority_fee_per_gas: Option<U256>,
+
+    /// Token contract name if it can be queried
+    pub token_name: Option<String>,
+
+    /// Token decimals if it can be queried
+    pub decimals: Option<u8>,
+}
+
+impl PendingApproval {
+    /// Return amount in token unit if decimals is known.
+    pub fn token_amount(&self) -> Option<f64> {
+        self.decimals.and_then(|d| to_token_amount(self.amount, d).ok())
+    }
+}
+
+/// Unsigned `approve(spender, 0)` transaction which replaces a pending
+/// approval by taking its nonce with higher fees.
+#[derive(Debug, Clone)]
+pub struct ReplacementTx {
+    /// Revoke transaction to send
+    pub revoke: RevokeTx,
+
+    /// Nonce of the pending approval to replace
+    pub nonce: U256,
+
+    /// Gas price for legacy transaction
+    pub gas_price: Option<U256>,
+
+    /// Max fee per gas for EIP-1559 transaction
+    pub max_fee_per_gas: Option<U256>,
+
+    /// Max priority fee per gas for EIP-1559 transaction
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+/// Return the specified fee bumped by 25%.
+/// Nodes only accept replacement with fees at least 10% higher, so it leaves
+/// room for fees to rise meanwhile.
+///
+/// # Arguments
+/// * `fee` - fee to bump
+pub fn bump_fee(fee: U256) -> U256 {
+    fee.saturating_add(fee / 4).saturating_add(U256::one())
+}
+
+/// Parse pending approval out of the specified transaction.
+/// Return `None` if it's not sent by owner, nor is `approve()`, or
+/// `increaseAllowance()` with non-zero amount.
+///
+/// # Arguments
+/// * `tx` - pending transaction
+/// * `owner` - owner wallet address
+pub fn parse_pending_approval(tx: &Transaction, owner: Address) -> Option<PendingApproval> {
+    if tx.from != Some(owner) || tx.input.0.len() < 4 {
+        return None;
+    }
+    let token_address = tx.to?;
+
+    let is_increase = match &tx.input.0[..4] {
+        id if id == APPROVE_METHOD_ID => false,
+        id if id == INCREASE_ALLOWANCE_METHOD_ID => true,
+        _ => return None,
+    };
+    let (spender, amount) = match ethabi::decode(&[ParamType::Address, ParamType::Uint(256)], &tx.input.0[4..]).ok()?.as_slice() {
+        [Token::Address(spender), Token::Uint(amount)] => (*spender, *amount),
+        _ => return None,
+    };
+    if amount.is_zero() {
+        return None;
+    }
+
+    Some(PendingApproval {
+        tx_hash: format!("{:?}", tx.hash),
+        token_address: format!("{:?}", token_address),
+        spender: format!("{:?}", spender),
+        amount,
+        is_increase,
+        nonce: tx.nonce,
+        gas_price: tx.gas_price,
+        max_fee_per_gas: tx.max_fee_per_gas,
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+        token_name: None,
+        decimals: None,
+    })
+}
+
+/// Build unsigned `approve(spender, 0)` transaction which replaces the
+/// specified pending approval. It takes the same nonce with fees bumped by
+/// 25%, so whichever gets mined first wins; sent quickly, it's the revoke.
+///
+/// # Arguments
+/// * `pending` - pending approval to replace
+pub fn build_replacement_tx(pending: &PendingApproval) -> Result<ReplacementTx, AppvkekError> {
+    Ok(ReplacementTx {
+        revoke: build_revoke_tx(&pending.token_address, &pending.spender)?,
+        nonce: pending.nonce,
+        gas_price: pending.gas_price.map(bump_fee),
+        max_fee_per_gas: pending.max_fee_per_gas.map(bump_fee),
+        max_priority_fee_per_gas: pending.max_priority_fee_per_gas.map(bump_fee),
+    })
+}
+
+/// Call a getter of token contract without arguments.
+/// Return `None` if it fails for any reason.
+///
+/// # Arguments
+/// * `web3` - web3 instance over websocket
+/// * `token_address` - token contract address
+/// * `method_id` - method-id of getter
+async fn call_getter(web3: &web3::Web3<WebSocket>, token_address: Address, method_id: &[u8; 4]) -> Option<Vec<u8>> {
+    let req = CallRequest {
+        to: Some(token_address),
+        data: Some(Bytes(method_id.to_vec())),
+        ..Default::default()
+    };
+    web3.eth().call(req, None).await.ok().map(|b| b.0)
+}
+
+/// Look up the specified pending transaction, and return it as pending
+/// approval if it's one of owner, along with name and decimals of its token.
+///
+/// # Arguments
+/// * `web3` - web3 instance over websocket
+/// * `tx_hash` - pending transaction hash
+/// * `owner` - owner wallet address
+async fn lookup_pending_approval(web3: &web3::Web3<WebSocket>, tx_hash: H256, owner: Address) -> Result<Option<PendingApproval>, AppvkekError> {
+    let tx = match web3.eth().transaction(TransactionId::Hash(tx_hash)).await {
+        // transaction may have been dropped, or replaced since announced
+        Ok(Some(res)) => res,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(AppvkekError::from_web3(&format!("Error getting pending transaction {:?}", tx_hash), &e)),
+    };
+    let mut pending = match parse_pending_approval(&tx, owner) {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+
+    let token_address = get_address_from_str(&pending.token_address)?;
+    pending.token_name = call_getter(web3, token_address, &NAME_METHOD_ID).await.and_then(|b| decode_string(&b));
+    pending.decimals = call_getter(web3, token_address, &DECIMALS_METHOD_ID).await.and_then(|b| decode_decimals(&b));
+
+    Ok(Some(pending))
+}
+
+/// Subscribe to pending transactions via the specified websocket endpoint,
+/// and call `on_approval` for each pending approval of owner wallet.
+/// Endpoint announces hash of every pending transaction, so each of them is
+/// looked up to find the ones sent by owner.
+///
+/// It only returns once connection or subscription fails, so caller may
+/// reconnect.
+///
+/// # Arguments
+/// * `ws_url` - websocket URL of RPC endpoint; prefixed with `ws://`, or `wss://`
+/// * `owner_address` - owner wallet address
+/// * `on_approval` - callback for each pending approval found
+pub async fn monitor_pending_approvals<F, Fut>(ws_url: &str, owner_address: &str, mut on_approval: F) -> Result<(), AppvkekError>
+where
+    F: FnMut(PendingApproval) -> Fut,
+    Fut: std::future::Future<Output = ()>
+{
+    let owner = get_address_from_str(owner_address)?;
+
+    let transport = match WebSocket::new(ws_url).await {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Rpc(format!("Error connecting to websocket endpoint ({}); err={}", ws_url, e))),
+    };
+    let web3 = web3::Web3::new(transport);
+    let subscription = match web3.eth_subscribe().subscribe_new_pending_transactions().await {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Rpc(format!("Error subscribing to pending transactions via {}; err={}", ws_url, e))),
+    };
+
+    let mut lookups = subscription
+        .map(|res| async {
+            match res {
+                Ok(tx_hash) => Ok(lookup_pending_approval(&web3, tx_hash, owner).await),
+                Err(e) => Err(e),
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_LOOKUPS);
+
+    while let Some(res) = lookups.next().await {
+        match res {
+            Ok(Ok(Some(pending))) => on_approval(pending).await,
+            Ok(Ok(None)) => (),
+            // failing to look up a single transaction shouldn't end monitoring
+            Ok(Err(e)) => tracing::debug!("{}", e),
+            Err(e) => return Err(AppvkekError::Rpc(format!("Error receiving pending transaction via {}; err={}", ws_url, e))),
+        }
+    }
+
+    Err(AppvkekError::Rpc(format!("Error subscription to pending transactions via {} has ended", ws_url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static OWNER: &str = "0x1111111111111111111111111111111111111111";
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static SPENDER: &str = "0x3333333333333333333333333333333333333333";
+
+    fn make_tx(from: &str, method_id: &[u8; 4], amount: U256) -> Transaction {
+        let mut input = method_id.to_vec();
+        input.extend(ethabi::encode(&[Token::Address(get_address_from_str(SPENDER).unwrap()), Token::Uint(amount)]));
+        Transaction {
+            from: Some(get_address_from_str(from).unwrap()),
+            to: Some(get_address_from_str(TOKEN).unwrap()),
+            nonce: U256::from(7),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(8)),
+            input: Bytes(input),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_pending_approval_only_takes_approvals_of_owner() {
+        let owner = get_address_from_str(OWNER).unwrap();
+
+        let pending = parse_pending_approval(&make_tx(OWNER, &INCREASE_ALLOWANCE_METHOD_ID, U256::from(5)), owner).unwrap();
+        assert_eq!(pending.token_address, TOKEN);
+        assert_eq!(pending.spender, SPENDER);
+        assert_eq!(pending.amount, U256::from(5));
+        assert!(pending.is_increase);
+
+        assert!(parse_pending_approval(&make_tx(SPENDER, &APPROVE_METHOD_ID, U256::from(5)), owner).is_none());
+        assert!(parse_pending_approval(&make_tx(OWNER, &APPROVE_METHOD_ID, U256::zero()), owner).is_none());
+        assert!(parse_pending_approval(&make_tx(OWNER, &NAME_METHOD_ID, U256::from(5)), owner).is_none());
+    }
+
+    #[test]
+    fn build_replacement_tx_takes_nonce_with_higher_fees() {
+        let pending = parse_pending_approval(&make_tx(OWNER, &APPROVE_METHOD_ID, U256::MAX), get_address_from_str(OWNER).unwrap()).unwrap();
+
+        let tx = build_replacement_tx(&pending).unwrap();
+
+        assert_eq!(tx.nonce, U256::from(7));
+        assert_eq!(tx.gas_price, None);
+        assert_eq!(tx.max_fee_per_gas, Some(U256::from(126)));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(U256::from(11)));
+        assert_eq!(tx.revoke.spender, SPENDER);
+    }
+}
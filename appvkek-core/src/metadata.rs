@@ -0,0 +1,240 @@
+use ::evmscan::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::AppvkekError;
+use crate::types::*;
+use crate::usage;
+use crate::util::*;
+
+/// Metadata of a token contract which never changes once deployed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    /// Token name
+    pub name: String,
+
+    /// Token symbol; empty if token doesn't implement `symbol()`
+    pub symbol: String,
+
+    /// Number of decimals
+    pub decimals: u8,
+}
+
+/// User-provided metadata of a token contract which takes precedence over
+/// what the token contract returns e.g. for old tokens which report
+/// nonsense decimals.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataOverride {
+    /// Number of decimals
+    pub decimals: u8,
+
+    /// Token name; the one returned by token contract if not set
+    pub name: Option<String>,
+
+    /// Token symbol; the one returned by token contract if not set
+    pub symbol: Option<String>,
+}
+
+/// Persistent cache of token metadata of a single chain.
+/// It is stored at `$HOME/.appvkek/cache/token-metadata-<chain>.json`.
+///
+/// Overrides set by `set_overrides()` are applied on top of cached entries,
+/// but never saved into the cache file.
+#[derive(Debug, Default)]
+pub struct MetadataCache {
+    /// Path to cache file; `None` if cache is only kept in memory
+    path: Option<String>,
+
+    /// Token contract address (lowercased) to its metadata
+    entries: HashMap<String, TokenMetadata>,
+
+    /// Token contract address (lowercased) to its user-provided metadata
+    overrides: HashMap<String, MetadataOverride>,
+
+    /// Whether there are new entries not yet saved
+    is_dirty: bool,
+}
+
+impl MetadataCache {
+    /// Load cache of the specified chain.
+    /// Corrupted cache file is ignored, and will be overwritten on next save.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `enabled` - whether to use persistent cache; if `false`, cache starts
+    ///   empty, and is only kept in memory
+    pub fn load(chain: ChainType, enabled: bool) -> MetadataCache {
+        if !enabled {
+            return MetadataCache::default();
+        }
+
+        let path = format!("{}/token-metadata-{}.json", cache_dir(), chain_name(chain));
+        let entries = std::fs::read_to_string(&path).ok()
+            .and_then(|content| serde_json::from_str::<HashMap<String, TokenMetadata>>(&content).ok())
+            .unwrap_or_default();
+
+        MetadataCache {
+            path: Some(path),
+            entries,
+            overrides: HashMap::new(),
+            is_dirty: false,
+        }
+    }
+
+    /// Set user-provided metadata of token contracts to be used instead of
+    /// what they return. Tokens which have it aren't queried for metadata at
+    /// all, so a token whose `decimals()` reverts can be queried as well.
+    ///
+    /// # Arguments
+    /// * `overrides` - token contract address to its metadata override
+    pub fn set_overrides(&mut self, overrides: HashMap<String, MetadataOverride>) -> Result<(), AppvkekError> {
+        let mut res: HashMap<String, MetadataOverride> = HashMap::with_capacity(overrides.len());
+        for (address, o) in overrides {
+            get_address_from_str(&address)?;
+            res.insert(address.to_lowercase(), o);
+        }
+        self.overrides = res;
+        Ok(())
+    }
+
+    /// Return in-memory copy of this cache which is never saved to disk.
+    /// This is for querying concurrently, then merging results back into
+    /// this cache with `update()`.
+    pub fn detached(&self) -> MetadataCache {
+        MetadataCache {
+            path: None,
+            entries: self.entries.clone(),
+            overrides: self.overrides.clone(),
+            is_dirty: false,
+        }
+    }
+
+    /// Get metadata of the specified token contract address with its
+    /// override applied if any.
+    ///
+    /// # Arguments
+    /// * `address` - token contract address
+    pub fn get(&self, address: &str) -> Option<TokenMetadata> {
+        let res = self.resolve(&address.to_lowercase());
+        usage::record_cache_lookup(res.is_some());
+        res
+    }
+
+    /// Return whether metadata of the specified token contract address is
+    /// known without querying it. Unlike `get()`, it's not counted as a cache
+    /// lookup.
+    ///
+    /// # Arguments
+    /// * `address` - token contract address
+    pub fn contains(&self, address: &str) -> bool {
+        let address = address.to_lowercase();
+        self.entries.contains_key(&address) || self.overrides.contains_key(&address)
+    }
+
+    /// Return metadata of all the specified token contract addresses which
+    /// are known, with their overrides applied, keyed by lowercased address.
+    /// Unlike `get()`, it's not counted as cache lookups.
+    ///
+    /// # Arguments
+    /// * `addresses` - token contract addresses
+    pub fn get_all<'a>(&self, addresses: impl IntoIterator<Item = &'a String>) -> HashMap<String, TokenMetadata> {
+        addresses.into_iter()
+            .map(|address| address.to_lowercase())
+            .filter_map(|address| self.resolve(&address).map(|m| (address, m)))
+            .collect()
+    }
+
+    /// Return metadata of the specified token contract address with its
+    /// override applied if any. Overridden token which isn't cached has
+    /// empty name unless set by its override.
+    ///
+    /// # Arguments
+    /// * `address` - token contract address; lowercased
+    fn resolve(&self, address: &str) -> Option<TokenMetadata> {
+        let cached = self.entries.get(address);
+        match self.overrides.get(address) {
+            Some(o) => Some(TokenMetadata {
+                name: o.name.clone().or_else(|| cached.map(|m| m.name.to_owned())).unwrap_or_default(),
+                symbol: o.symbol.clone().or_else(|| cached.map(|m| m.symbol.to_owned())).unwrap_or_default(),
+                decimals: o.decimals,
+            }),
+            None => cached.cloned(),
+        }
+    }
+
+    /// Add metadata of all successfully queried token contracts.
+    ///
+    /// # Arguments
+    /// * `results` - results from `query_all()`
+    pub fn update(&mut self, results: &[QueryResult]) {
+        for r in results.iter().flatten() {
+            // overridden metadata isn't what token contract returns
+            let address = r.address.to_lowercase();
+            if self.entries.contains_key(&address) || self.overrides.contains_key(&address) {
+                continue;
+            }
+            self.entries.insert(address, TokenMetadata {
+                name: r.name.to_owned(),
+                symbol: r.symbol.to_owned(),
+                decimals: r.decimals,
+            });
+            self.is_dirty = true;
+        }
+    }
+
+    /// Save cache to disk if there are new entries.
+    pub fn save(&mut self) -> Result<(), AppvkekError> {
+        let path = match &self.path {
+            Some(path) if self.is_dirty => path,
+            _ => return Ok(()),
+        };
+
+        let content = match serde_json::to_string(&self.entries) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Io(format!("Error serializing token metadata cache; err={}", e))),
+        };
+        write_file(path, &content)?;
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static OTHER_TOKEN: &str = "0x3333333333333333333333333333333333333333";
+
+    #[test]
+    fn overrides_take_precedence_but_are_never_cached() {
+        let mut cache = MetadataCache::default();
+        cache.entries.insert(TOKEN.to_owned(), TokenMetadata { name: "Token".to_owned(), symbol: "TKN".to_owned(), decimals: 77 });
+        cache.set_overrides(HashMap::from([
+            (TOKEN.to_owned(), MetadataOverride { decimals: 18, ..Default::default() }),
+            (OTHER_TOKEN.to_owned(), MetadataOverride { decimals: 6, name: Some("Other".to_owned()), symbol: None }),
+        ])).unwrap();
+
+        let m = cache.get(TOKEN).unwrap();
+        assert_eq!((m.name.as_str(), m.symbol.as_str(), m.decimals), ("Token", "TKN", 18));
+        let m = cache.get(OTHER_TOKEN).unwrap();
+        assert_eq!((m.name.as_str(), m.symbol.as_str(), m.decimals), ("Other", "", 6));
+        assert!(cache.contains(OTHER_TOKEN));
+
+        cache.update(&[Ok(TokenContractWithSpenderAllowances {
+            name: "Other".to_owned(),
+            symbol: String::new(),
+            address: OTHER_TOKEN.to_owned(),
+            decimals: 6,
+            balance: None,
+            total_supply: None,
+            spender_allowances: HashMap::new(),
+        })]);
+        assert!(!cache.entries.contains_key(OTHER_TOKEN));
+        assert_eq!(cache.entries[TOKEN].decimals, 77);
+
+        assert!(cache.set_overrides(HashMap::from([("0x1234".to_owned(), MetadataOverride::default())])).is_err());
+    }
+}
@@ -0,0 +1,624 @@
+//! Mock implementations of `ChainRpc` and `ExplorerApi` which serve canned
+//! responses, so that scanning logic can be tested without hitting live
+//! networks.
+//!
+//! Helpers taking addresses as string literals panic if they are malformed.
+
+use ::evmscan::prelude::U256 as BSCU256;
+use ::evmscan::types::evm_types::EvmNormalTransactionResponseSuccessVariantResult;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use web3::ethabi::{self, ParamType, Token};
+use web3::types::{Address, BlockNumber, FeeHistory, H256, U256};
+
+use crate::erc20::*;
+use crate::error::AppvkekError;
+use crate::multicall::{AGGREGATE3_METHOD_ID, MULTICALL3_ADDRESS};
+use crate::explorer::{ContractCreation, APPROVAL_EVENT_TOPIC, USER_OPERATION_EVENT_TOPIC};
+use crate::provider::{ChainRpc, ExplorerApi};
+use crate::types::{EventLog, TokenTransfer};
+use crate::util::get_address_from_str;
+
+/// Maximum number of transactions explorer returns for a single query range
+const EXPLORER_RESULT_WINDOW: usize = 10000;
+
+/// Mock of chain RPC serving canned code, storage, and call results.
+/// Calls without canned result revert.
+#[derive(Debug, Default)]
+pub struct MockChainRpc {
+    /// Code of each address; addresses not in here have no code
+    code: HashMap<Address, Vec<u8>>,
+
+    /// Value of each storage slot; slots not in here are zero
+    storage: HashMap<(Address, U256), H256>,
+
+    /// Returned data of each call keyed by target address and calldata
+    calls: HashMap<(Address, Vec<u8>), Vec<u8>>,
+
+    /// Returned data of calls made at a historical block keyed by target
+    /// address, calldata, and block number; those not in here fall back to
+    /// `calls`
+    historical_calls: HashMap<(Address, Vec<u8>, u64), Vec<u8>>,
+
+    /// Addresses whose calls fail with transport error
+    rpc_errors: HashSet<Address>,
+
+    /// Whether Multicall3 contract is available
+    has_multicall: bool,
+
+    /// Number of `eth_call` made so far; calls batched via Multicall3 are not
+    /// counted individually
+    num_calls: AtomicUsize,
+
+    /// Estimated gas of each transaction keyed by target address and
+    /// calldata; those not in here revert
+    gas_estimates: HashMap<(Address, Vec<u8>), u64>,
+
+    /// Current gas price in wei; querying it fails if `None`
+    gas_price: Option<U256>,
+
+    /// Number of mined, and of all including pending transactions sent from
+    /// each address; addresses not in here have sent none
+    transaction_counts: HashMap<Address, (u64, u64)>,
+
+    /// Base fee, and priority fee of each of the latest blocks in ascending
+    /// order of block number; querying fee history fails if empty
+    fees: Vec<(U256, U256)>,
+}
+
+impl MockChainRpc {
+    /// Set code of the specified address.
+    ///
+    /// # Arguments
+    /// * `address` - address prefixed with '0x'
+    /// * `code` - code
+    pub fn with_code(mut self, address: &str, code: Vec<u8>) -> Self {
+        self.code.insert(get_address_from_str(address).unwrap(), code);
+        self
+    }
+
+    /// Set value of the specified storage slot of the specified contract.
+    ///
+    /// # Arguments
+    /// * `address` - contract address prefixed with '0x'
+    /// * `slot` - storage slot
+    /// * `value` - value of storage slot
+    pub fn with_storage(mut self, address: &str, slot: U256, value: H256) -> Self {
+        self.storage.insert((get_address_from_str(address).unwrap(), slot), value);
+        self
+    }
+
+    /// Set returned data of the specified call.
+    ///
+    /// # Arguments
+    /// * `to` - contract address prefixed with '0x'
+    /// * `data` - calldata
+    /// * `output` - returned data
+    pub fn with_call(mut self, to: &str, data: Vec<u8>, output: Vec<u8>) -> Self {
+        self.calls.insert((get_address_from_str(to).unwrap(), data), output);
+        self
+    }
+
+    /// Deploy an ERC-20 token contract with the specified metadata.
+    /// Empty `symbol` makes `symbol()` revert as it's optional in ERC-20.
+    ///
+    /// # Arguments
+    /// * `address` - token contract address prefixed with '0x'
+    /// * `name` - token name
+    /// * `symbol` - token symbol
+    /// * `decimals` - number of decimals
+    pub fn with_erc20(self, address: &str, name: &str, symbol: &str, decimals: u8) -> Self {
+        let mut ret = self
+            .with_code(address, vec![0x60, 0x80])
+            .with_call(address, NAME_METHOD_ID.to_vec(), ethabi::encode(&[Token::String(name.to_owned())]))
+            .with_call(address, DECIMALS_METHOD_ID.to_vec(), ethabi::encode(&[Token::Uint(decimals.into())]));
+        if !symbol.is_empty() {
+            ret = ret.with_call(address, SYMBOL_METHOD_ID.to_vec(), ethabi::encode(&[Token::String(symbol.to_owned())]));
+        }
+        ret
+    }
+
+    /// Set allowance of the specified spender on the specified token contract.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address prefixed with '0x'
+    /// * `owner` - owner address prefixed with '0x'
+    /// * `spender` - spender address prefixed with '0x'
+    /// * `allowance` - raw allowance balance
+    pub fn with_allowance(self, token_address: &str, owner: &str, spender: &str, allowance: U256) -> Self {
+        let data = encode_allowance(get_address_from_str(owner).unwrap(), get_address_from_str(spender).unwrap());
+        self.with_call(token_address, data, ethabi::encode(&[Token::Uint(allowance)]))
+    }
+
+    /// Set balance of the specified owner on the specified token contract.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address prefixed with '0x'
+    /// * `owner` - owner address prefixed with '0x'
+    /// * `balance` - raw balance
+    pub fn with_balance(self, token_address: &str, owner: &str, balance: U256) -> Self {
+        let data = encode_balance_of(get_address_from_str(owner).unwrap());
+        self.with_call(token_address, data, ethabi::encode(&[Token::Uint(balance)]))
+    }
+
+    /// Set total supply of the specified token contract.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address prefixed with '0x'
+    /// * `total_supply` - raw total supply
+    pub fn with_total_supply(self, token_address: &str, total_supply: U256) -> Self {
+        self.with_call(token_address, TOTAL_SUPPLY_METHOD_ID.to_vec(), ethabi::encode(&[Token::Uint(total_supply)]))
+    }
+
+    /// Set allowance of the specified spender on the specified token contract
+    /// as of the specified block.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address prefixed with '0x'
+    /// * `owner` - owner address prefixed with '0x'
+    /// * `spender` - spender address prefixed with '0x'
+    /// * `allowance` - raw allowance balance
+    /// * `block` - block number
+    pub fn with_allowance_at(mut self, token_address: &str, owner: &str, spender: &str, allowance: U256, block: u64) -> Self {
+        let data = encode_allowance(get_address_from_str(owner).unwrap(), get_address_from_str(spender).unwrap());
+        self.historical_calls.insert((get_address_from_str(token_address).unwrap(), data, block), ethabi::encode(&[Token::Uint(allowance)]));
+        self
+    }
+
+    /// Make all calls to the specified address fail with transport error.
+    ///
+    /// # Arguments
+    /// * `address` - address prefixed with '0x'
+    pub fn with_rpc_error(mut self, address: &str) -> Self {
+        self.rpc_errors.insert(get_address_from_str(address).unwrap());
+        self
+    }
+
+    /// Set estimated gas of transaction with the specified calldata to the
+    /// specified contract.
+    ///
+    /// # Arguments
+    /// * `to` - contract address prefixed with '0x'
+    /// * `data` - calldata
+    /// * `gas` - estimated gas
+    pub fn with_gas_estimate(mut self, to: &str, data: Vec<u8>, gas: u64) -> Self {
+        self.gas_estimates.insert((get_address_from_str(to).unwrap(), data), gas);
+        self
+    }
+
+    /// Set current gas price.
+    ///
+    /// # Arguments
+    /// * `gas_price` - gas price in wei
+    pub fn with_gas_price(mut self, gas_price: U256) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
+    /// Set number of transactions sent from the specified address.
+    ///
+    /// # Arguments
+    /// * `address` - address prefixed with '0x'
+    /// * `mined` - number of mined transactions
+    /// * `pending` - number of transactions including those pending
+    pub fn with_transaction_count(mut self, address: &str, mined: u64, pending: u64) -> Self {
+        self.transaction_counts.insert(get_address_from_str(address).unwrap(), (mined, pending));
+        self
+    }
+
+    /// Append a block with the specified fees as the latest one. Its base fee
+    /// also stands for that of the next block. Priority fee is served
+    /// whichever percentile is asked.
+    ///
+    /// # Arguments
+    /// * `base_fee` - base fee per gas in wei
+    /// * `priority_fee` - priority fee per gas in wei
+    pub fn with_block_fees(mut self, base_fee: u64, priority_fee: u64) -> Self {
+        self.fees.push((base_fee.into(), priority_fee.into()));
+        self
+    }
+
+    /// Deploy Multicall3 contract which serves `aggregate3` from canned call
+    /// results.
+    pub fn with_multicall(mut self) -> Self {
+        self.has_multicall = true;
+        self
+    }
+
+    /// Return number of `eth_call` made so far.
+    pub fn num_calls(&self) -> usize {
+        self.num_calls.load(Ordering::Relaxed)
+    }
+
+    /// Return canned result of the specified call.
+    ///
+    /// # Arguments
+    /// * `to` - contract address
+    /// * `data` - calldata
+    fn lookup_call(&self, to: Address, data: &[u8]) -> Result<Vec<u8>, AppvkekError> {
+        if self.rpc_errors.contains(&to) {
+            return Err(AppvkekError::Rpc(format!("Error calling contract ({:?}); err=connection refused", to)));
+        }
+        match self.calls.get(&(to, data.to_vec())) {
+            Some(res) => Ok(res.clone()),
+            None => Err(AppvkekError::Reverted(format!("Error calling contract ({:?}); err=execution reverted", to))),
+        }
+    }
+
+    /// Serve `aggregate3` call by looking up each batched call.
+    ///
+    /// # Arguments
+    /// * `data` - calldata of `aggregate3` without method-id
+    fn aggregate3(&self, data: &[u8]) -> Result<Vec<u8>, AppvkekError> {
+        let calls = match ethabi::decode(&[ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes])))], data) {
+            Ok(mut res) => match res.pop() {
+                Some(Token::Array(calls)) => calls,
+                _ => return Err(AppvkekError::Reverted("Error calling aggregate3; err=execution reverted".to_owned())),
+            },
+            Err(_) => return Err(AppvkekError::Reverted("Error calling aggregate3; err=execution reverted".to_owned())),
+        };
+
+        let mut results = Vec::with_capacity(calls.len());
+        for call in calls {
+            let (target, calldata) = match call {
+                Token::Tuple(fields) => match (&fields[0], &fields[2]) {
+                    (Token::Address(target), Token::Bytes(calldata)) => (*target, calldata.clone()),
+                    _ => return Err(AppvkekError::Reverted("Error calling aggregate3; err=execution reverted".to_owned())),
+                },
+                _ => return Err(AppvkekError::Reverted("Error calling aggregate3; err=execution reverted".to_owned())),
+            };
+            let result = match self.lookup_call(target, &calldata) {
+                Ok(output) => Token::Tuple(vec![Token::Bool(true), Token::Bytes(output)]),
+                Err(_) => Token::Tuple(vec![Token::Bool(false), Token::Bytes(Vec::new())]),
+            };
+            results.push(result);
+        }
+
+        Ok(ethabi::encode(&[Token::Array(results)]))
+    }
+}
+
+#[async_trait]
+impl ChainRpc for MockChainRpc {
+    async fn code(&self, address: Address, _block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        if self.rpc_errors.contains(&address) {
+            return Err(AppvkekError::Rpc(format!("Error awaiting result for code from address ({:?}); err=connection refused", address)));
+        }
+        Ok(self.code.get(&address).cloned().unwrap_or_default())
+    }
+
+    async fn call(&self, to: Address, data: Vec<u8>, block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        self.num_calls.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(output) = block.and_then(|b| self.historical_calls.get(&(to, data.clone(), b))) {
+            return Ok(output.clone());
+        }
+        if self.has_multicall && !self.rpc_errors.contains(&to) && to == get_address_from_str(MULTICALL3_ADDRESS)? && data.starts_with(&AGGREGATE3_METHOD_ID) {
+            return self.aggregate3(&data[AGGREGATE3_METHOD_ID.len()..]);
+        }
+        self.lookup_call(to, &data)
+    }
+
+    async fn storage(&self, address: Address, slot: U256, _block: Option<u64>) -> Result<H256, AppvkekError> {
+        if self.rpc_errors.contains(&address) {
+            return Err(AppvkekError::Rpc(format!("Error reading storage slot of {:?}; err=connection refused", address)));
+        }
+        Ok(self.storage.get(&(address, slot)).copied().unwrap_or_default())
+    }
+
+    async fn estimate_gas(&self, _from: Address, to: Address, data: Vec<u8>) -> Result<u64, AppvkekError> {
+        if self.rpc_errors.contains(&to) {
+            return Err(AppvkekError::Rpc(format!("Error estimating gas of transaction to {:?}; err=connection refused", to)));
+        }
+        match self.gas_estimates.get(&(to, data)) {
+            Some(res) => Ok(*res),
+            None => Err(AppvkekError::Reverted(format!("Error estimating gas of transaction to {:?}; err=execution reverted", to))),
+        }
+    }
+
+    async fn gas_price(&self) -> Result<U256, AppvkekError> {
+        match self.gas_price {
+            Some(res) => Ok(res),
+            None => Err(AppvkekError::Rpc("Error querying gas price; err=connection refused".to_owned())),
+        }
+    }
+
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<u64, AppvkekError> {
+        if self.rpc_errors.contains(&address) {
+            return Err(AppvkekError::Rpc(format!("Error querying transaction count of {:?}; err=connection refused", address)));
+        }
+        let (mined, all) = self.transaction_counts.get(&address).copied().unwrap_or_default();
+        Ok(if pending { all } else { mined })
+    }
+
+    async fn fee_history(&self, block_count: u64, _percentile: f64) -> Result<FeeHistory, AppvkekError> {
+        let last = match self.fees.last() {
+            Some(res) => *res,
+            None => return Err(AppvkekError::Rpc("Error querying fee history; err=method not found".to_owned())),
+        };
+        let blocks = &self.fees[self.fees.len().saturating_sub(block_count as usize)..];
+        Ok(FeeHistory {
+            oldest_block: BlockNumber::Number((self.fees.len() - blocks.len()).into()),
+            base_fee_per_gas: blocks.iter().map(|(base_fee, _)| *base_fee).chain(std::iter::once(last.0)).collect(),
+            gas_used_ratio: vec![0.5; blocks.len()],
+            reward: Some(blocks.iter().map(|(_, priority_fee)| vec![*priority_fee]).collect()),
+        })
+    }
+}
+
+/// Mock of explorer API serving canned transactions.
+/// Like the real one, at most the first 10,000 transactions of a query range
+/// can be fetched.
+#[derive(Debug, Default)]
+pub struct MockExplorer {
+    /// All transactions on chain; in ascending order of block number
+    txs: Vec<EvmNormalTransactionResponseSuccessVariantResult>,
+
+    /// All event logs on chain; in ascending order of block number
+    logs: Vec<EventLog>,
+
+    /// All ERC-20 token transfers on chain; in ascending order of block number
+    transfers: Vec<TokenTransfer>,
+
+    /// Contract addresses with verified source code; lowercased
+    verified: HashSet<String>,
+
+    /// Addresses created as contract (lowercased) to their creation record
+    created: HashMap<String, ContractCreation>,
+
+    /// Number of transaction pages fetched so far
+    num_page_requests: AtomicUsize,
+}
+
+impl MockExplorer {
+    /// Add the specified transaction.
+    ///
+    /// # Arguments
+    /// * `tx` - normal transaction
+    pub fn with_tx(mut self, tx: EvmNormalTransactionResponseSuccessVariantResult) -> Self {
+        let pos = self.txs.partition_point(|t| t.block_number <= tx.block_number);
+        self.txs.insert(pos, tx);
+        self
+    }
+
+    /// Add `approve(spender, amount)` transaction made by owner.
+    ///
+    /// # Arguments
+    /// * `owner` - owner address; lowercased, prefixed with '0x'
+    /// * `token_address` - token contract address; lowercased, prefixed with '0x'
+    /// * `spender` - spender address; lowercased, prefixed with '0x'
+    /// * `amount` - raw allowance balance
+    /// * `block_number` - block number of transaction
+    pub fn with_approval(self, owner: &str, token_address: &str, spender: &str, amount: U256, block_number: u64) -> Self {
+        let mut input = "0x095ea7b3".to_owned();
+        input.push_str(&hex::encode(ethabi::encode(&[Token::Address(get_address_from_str(spender).unwrap()), Token::Uint(amount)])));
+        self.with_tx(make_tx(owner, token_address, &input, block_number))
+    }
+
+    /// Add the specified event log.
+    ///
+    /// # Arguments
+    /// * `log` - event log
+    pub fn with_log(mut self, log: EventLog) -> Self {
+        let pos = self.logs.partition_point(|l| l.block_number <= log.block_number);
+        self.logs.insert(pos, log);
+        self
+    }
+
+    /// Add `Approval(owner, spender, amount)` event log emitted by token
+    /// contract e.g. as a result of internal call made by contract wallet.
+    ///
+    /// # Arguments
+    /// * `owner` - owner address; lowercased, prefixed with '0x'
+    /// * `token_address` - token contract address; lowercased, prefixed with '0x'
+    /// * `spender` - spender address; lowercased, prefixed with '0x'
+    /// * `amount` - raw allowance balance
+    /// * `block_number` - block number of transaction
+    pub fn with_approval_log(self, owner: &str, token_address: &str, spender: &str, amount: U256, block_number: u64) -> Self {
+        self.with_log(EventLog {
+            address: token_address.to_owned(),
+            topics: vec![
+                APPROVAL_EVENT_TOPIC.to_owned(),
+                format!("0x{:0>64}", owner.trim_start_matches("0x")),
+                format!("0x{:0>64}", spender.trim_start_matches("0x")),
+            ],
+            data: format!("0x{}", hex::encode(ethabi::encode(&[Token::Uint(amount)]))),
+            block_number,
+            timestamp: 1_600_000_000 + block_number * 3,
+            transaction_hash: format!("0x{:064x}", block_number),
+            log_index: 0,
+        })
+    }
+
+    /// Add ERC-4337 `UserOperationEvent` event log emitted by EntryPoint
+    /// contract as a result of executing user operation of smart account.
+    /// It shares transaction hash with `Approval` event logs added by
+    /// `with_approval_log()` at the same block.
+    ///
+    /// # Arguments
+    /// * `entry_point` - EntryPoint contract address; lowercased, prefixed with '0x'
+    /// * `sender` - smart account address; lowercased, prefixed with '0x'
+    /// * `user_op_hash` - hash of user operation; prefixed with '0x'
+    /// * `success` - whether execution of user operation succeeded
+    /// * `block_number` - block number of bundle transaction
+    pub fn with_user_operation(self, entry_point: &str, sender: &str, user_op_hash: &str, success: bool, block_number: u64) -> Self {
+        self.with_log(EventLog {
+            address: entry_point.to_owned(),
+            topics: vec![
+                USER_OPERATION_EVENT_TOPIC.to_owned(),
+                user_op_hash.to_owned(),
+                format!("0x{:0>64}", sender.trim_start_matches("0x")),
+                format!("0x{:0>64}", ""),
+            ],
+            data: format!("0x{}", hex::encode(ethabi::encode(&[Token::Uint(U256::zero()), Token::Bool(success), Token::Uint(U256::zero()), Token::Uint(U256::zero())]))),
+            block_number,
+            timestamp: 1_600_000_000 + block_number * 3,
+            transaction_hash: format!("0x{:064x}", block_number),
+            log_index: 1,
+        })
+    }
+
+    /// Add ERC-20 token transfer.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address; lowercased, prefixed with '0x'
+    /// * `from` - sender address; lowercased, prefixed with '0x'
+    /// * `to` - recipient address; lowercased, prefixed with '0x'
+    /// * `block_number` - block number of transaction
+    pub fn with_token_transfer(mut self, token_address: &str, from: &str, to: &str, block_number: u64) -> Self {
+        let pos = self.transfers.partition_point(|t| t.block_number <= block_number);
+        self.transfers.insert(pos, TokenTransfer {
+            token_address: token_address.to_owned(),
+            from: from.to_owned(),
+            to: to.to_owned(),
+            block_number,
+        });
+        self
+    }
+
+    /// Mark the specified contract as having verified source code.
+    ///
+    /// # Arguments
+    /// * `address` - contract address prefixed with '0x'
+    pub fn with_verified(mut self, address: &str) -> Self {
+        self.verified.insert(address.to_lowercase());
+        self
+    }
+
+    /// Mark the specified address as created as contract.
+    ///
+    /// # Arguments
+    /// * `address` - address prefixed with '0x'
+    pub fn with_created(mut self, address: &str) -> Self {
+        self.created.insert(address.to_lowercase(), ContractCreation::default());
+        self
+    }
+
+    /// Mark the specified address as created as contract by `creator` at
+    /// `timestamp`.
+    ///
+    /// # Arguments
+    /// * `address` - address prefixed with '0x'
+    /// * `creator` - creator address; lowercased, prefixed with '0x'
+    /// * `timestamp` - unix timestamp of creation
+    pub fn with_creation(mut self, address: &str, creator: &str, timestamp: u64) -> Self {
+        let creation = ContractCreation { creator: creator.to_owned(), tx_hash: format!("0x{:064x}", timestamp), timestamp: Some(timestamp) };
+        self.created.insert(address.to_lowercase(), creation);
+        self
+    }
+
+    /// Return number of transaction, and log pages fetched so far.
+    pub fn num_page_requests(&self) -> usize {
+        self.num_page_requests.load(Ordering::Relaxed)
+    }
+}
+
+impl ExplorerApi for MockExplorer {
+    fn txlist_page(&self, address: &str, start_block: u64, page: usize, offset: usize, sort: &str) -> Result<Vec<EvmNormalTransactionResponseSuccessVariantResult>, AppvkekError> {
+        self.num_page_requests.fetch_add(1, Ordering::Relaxed);
+
+        if page * offset > EXPLORER_RESULT_WINDOW {
+            return Err(AppvkekError::Explorer("Error fetching transactions; err=Result window is too large, PageNo x Offset size must be less than or equal to 10000".to_owned()));
+        }
+
+        let address = address.to_lowercase();
+        let mut txs: Vec<&EvmNormalTransactionResponseSuccessVariantResult> = self.txs.iter()
+            .filter(|tx| tx.block_number >= start_block && (tx.from == address || tx.to == address))
+            .collect();
+        if sort == "desc" {
+            txs.reverse();
+        }
+
+        Ok(txs.into_iter()
+            .skip(page.saturating_sub(1) * offset)
+            .take(offset)
+            .cloned()
+            .collect())
+    }
+
+    fn approval_logs_page(&self, owner_address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        self.num_page_requests.fetch_add(1, Ordering::Relaxed);
+
+        if page * offset > EXPLORER_RESULT_WINDOW {
+            return Err(AppvkekError::Explorer("Error fetching logs; err=Result window is too large, PageNo x Offset size must be less than or equal to 10000".to_owned()));
+        }
+
+        let owner_topic = format!("0x{:0>64}", owner_address.trim_start_matches("0x").to_lowercase());
+        Ok(self.logs.iter()
+            .filter(|log| log.block_number >= start_block && log.topics.first().map(|t| t.as_str()) == Some(APPROVAL_EVENT_TOPIC) && log.topics.get(1) == Some(&owner_topic))
+            .skip(page.saturating_sub(1) * offset)
+            .take(offset)
+            .cloned()
+            .collect())
+    }
+
+    fn user_operation_logs_page(&self, sender: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        self.num_page_requests.fetch_add(1, Ordering::Relaxed);
+
+        if page * offset > EXPLORER_RESULT_WINDOW {
+            return Err(AppvkekError::Explorer("Error fetching logs; err=Result window is too large, PageNo x Offset size must be less than or equal to 10000".to_owned()));
+        }
+
+        let sender_topic = format!("0x{:0>64}", sender.trim_start_matches("0x").to_lowercase());
+        Ok(self.logs.iter()
+            .filter(|log| log.block_number >= start_block && log.topics.first().map(|t| t.as_str()) == Some(USER_OPERATION_EVENT_TOPIC) && log.topics.get(2) == Some(&sender_topic))
+            .skip(page.saturating_sub(1) * offset)
+            .take(offset)
+            .cloned()
+            .collect())
+    }
+
+    fn tokentx_page(&self, address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<TokenTransfer>, AppvkekError> {
+        self.num_page_requests.fetch_add(1, Ordering::Relaxed);
+
+        if page * offset > EXPLORER_RESULT_WINDOW {
+            return Err(AppvkekError::Explorer("Error fetching token transfers; err=Result window is too large, PageNo x Offset size must be less than or equal to 10000".to_owned()));
+        }
+
+        let address = address.to_lowercase();
+        Ok(self.transfers.iter()
+            .filter(|t| t.block_number >= start_block && (t.from == address || t.to == address))
+            .skip(page.saturating_sub(1) * offset)
+            .take(offset)
+            .cloned()
+            .collect())
+    }
+
+    fn is_contract_verified(&self, address: &str) -> Result<bool, AppvkekError> {
+        Ok(self.verified.contains(&address.to_lowercase()))
+    }
+
+    fn contract_creation(&self, address: &str) -> Result<Option<ContractCreation>, AppvkekError> {
+        Ok(self.created.get(&address.to_lowercase()).cloned())
+    }
+}
+
+/// Make successful normal transaction with no value transferred.
+///
+/// # Arguments
+/// * `from` - sender address; lowercased, prefixed with '0x'
+/// * `to` - recipient address; lowercased, prefixed with '0x'
+/// * `input` - calldata in hex string prefixed with '0x'
+/// * `block_number` - block number of transaction
+pub fn make_tx(from: &str, to: &str, input: &str, block_number: u64) -> EvmNormalTransactionResponseSuccessVariantResult {
+    EvmNormalTransactionResponseSuccessVariantResult {
+        block_number,
+        timestamp: 1_600_000_000 + block_number * 3,
+        hash: format!("0x{:064x}", block_number),
+        nonce: 0,
+        transaction_index: 0,
+        from: from.to_owned(),
+        to: to.to_owned(),
+        value: BSCU256::zero(),
+        gas: 50000,
+        gas_price: 5_000_000_000,
+        is_error: false,
+        txreceipt_status: "1".to_owned(),
+        input: input.to_owned(),
+        contract_address: String::new(),
+        cumulative_gas_used: 50000,
+        gas_used: 50000,
+        confirmations: 1,
+    }
+}
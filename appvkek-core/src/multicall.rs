@@ -0,0 +1,331 @@
+use futures::stream::StreamExt;
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+use web3::ethabi::{self, ParamType, Token};
+use web3::types::{Address, U256};
+
+use crate::erc20::*;
+use crate::error::{AppvkekError, QueryError};
+use crate::metadata::TokenMetadata;
+use crate::provider::ChainRpc;
+use crate::types::*;
+use crate::util::*;
+
+/// Multicall3 contract address; deployed at the same address on all supported chains
+pub(crate) static MULTICALL3_ADDRESS: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+
+/// Method-id of `aggregate3((address,bool,bytes)[])`
+pub(crate) static AGGREGATE3_METHOD_ID: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+
+/// Maximum number of calls batched into a single `aggregate3` call.
+/// Public endpoints cap gas of `eth_call`, so don't make it too large.
+const CALLS_PER_BATCH: usize = 300;
+
+/// A single call to be batched.
+struct Call {
+    /// Target contract address
+    target: Address,
+
+    /// Encoded calldata
+    data: Vec<u8>,
+}
+
+/// Result of a single batched call; `None` if such call reverted.
+type CallResult = Option<Vec<u8>>;
+
+/// Token contract address along with its spenders, and cached metadata if any.
+type BatchEntry = (String, HashMap<String, ApprovalRecord>, Option<TokenMetadata>);
+
+/// Return number of calls needed to query a token contract; metadata takes
+/// 3 calls unless cached, and each spender takes 1 call.
+///
+/// # Arguments
+/// * `is_cached` - whether its metadata is cached
+/// * `num_spenders` - number of its spenders
+pub(crate) fn num_token_calls(is_cached: bool, num_spenders: usize) -> usize {
+    (if is_cached { 0 } else { 3 }) + num_spenders
+}
+
+/// Return number of `aggregate3` calls needed to query token contracts
+/// grouped the same way as `query_all_multicall()` does.
+///
+/// # Arguments
+/// * `token_calls` - number of calls needed for each token contract
+pub(crate) fn num_batches(token_calls: impl IntoIterator<Item = usize>) -> usize {
+    let mut num_batches = 0;
+    let mut batch_len = 0;
+    for num_calls in token_calls {
+        if num_batches == 0 || (batch_len > 0 && batch_len + num_calls > CALLS_PER_BATCH) {
+            num_batches += 1;
+            batch_len = 0;
+        }
+        batch_len += num_calls;
+    }
+    num_batches
+}
+
+/// Make `aggregate3` call to Multicall3 contract with failures allowed for
+/// each individual call.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `calls` - calls to batch
+async fn aggregate3<R: ChainRpc + ?Sized>(rpc: &R, calls: &[Call]) -> Result<Vec<CallResult>, AppvkekError> {
+    let tokens = calls.iter()
+        .map(|c| Token::Tuple(vec![Token::Address(c.target), Token::Bool(true), Token::Bytes(c.data.clone())]))
+        .collect();
+    let mut data = AGGREGATE3_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[Token::Array(tokens)]));
+
+    let output = match rpc.call(get_address_from_str(MULTICALL3_ADDRESS)?, data, None).await {
+        Ok(res) => res,
+        Err(e) => return Err(e.with_context("Error calling aggregate3 on Multicall3")),
+    };
+
+    let decoded = match ethabi::decode(&[ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])))], &output) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Decode(format!("Error decoding result of aggregate3; err={}", e))),
+    };
+
+    let results = match decoded.into_iter().next() {
+        Some(Token::Array(results)) => results,
+        _ => return Err(AppvkekError::Decode("Error decoding result of aggregate3; unexpected output".to_owned())),
+    };
+
+    Ok(results.into_iter().map(|r| match r {
+        Token::Tuple(mut fields) if fields.len() == 2 => {
+            match (fields.remove(0), fields.remove(0)) {
+                (Token::Bool(true), Token::Bytes(bytes)) => Some(bytes),
+                _ => None,
+            }
+        },
+        _ => None,
+    }).collect())
+}
+
+/// Query metadata and allowance balances of all collected approvals by
+/// batching them into `aggregate3` calls to Multicall3 contract.
+/// Batches are made concurrently, but the number of in-flight RPC calls is
+/// bounded by `semaphore`.
+///
+/// Token contracts whose metadata is already cached only have their allowance
+/// balances queried. Token contracts whose batch failed as a whole are queried
+/// individually via `query()` instead.
+///
+/// Results are passed to `on_result` as soon as their batch completes.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `ct_txs` - collected approvals from `collect_approvals()`
+/// * `owner_address` - owner wallet address; lowercased
+/// * `cached_metadata` - cached token metadata keyed by lowercased token contract address
+/// * `on_result` - callback to be called for each result
+pub async fn query_all_multicall<R, F>(rpc: &R, semaphore: &Semaphore, ct_txs: ApprovalsMap, owner_address: &str, cached_metadata: &HashMap<String, TokenMetadata>, mut on_result: F)
+where
+    R: ChainRpc + ?Sized,
+    F: FnMut(QueryResult)
+{
+    let owner = match get_address_from_str(owner_address) {
+        Ok(res) => res,
+        Err(error) => {
+            for ct in ct_txs.into_keys() {
+                on_result(Err(QueryError { token_address: ct, error: error.clone() }));
+            }
+            return;
+        }
+    };
+
+    // group calls of each token together so that a token never spans two batches
+    // unless it alone has more calls than a batch can hold
+    let mut batches: Vec<Vec<BatchEntry>> = vec![Vec::new()];
+    let mut batch_len = 0;
+    for (ct, spenders) in ct_txs {
+        if get_address_from_str(&ct).is_err() || spenders.keys().any(|s| get_address_from_str(s).is_err()) {
+            on_result(Err(QueryError { token_address: ct.to_owned(), error: AppvkekError::AddressFormat(ct) }));
+            continue;
+        }
+
+        let metadata = cached_metadata.get(&ct.to_lowercase()).cloned();
+        let num_calls = num_token_calls(metadata.is_some(), spenders.len());
+        if batch_len > 0 && batch_len + num_calls > CALLS_PER_BATCH {
+            batches.push(Vec::new());
+            batch_len = 0;
+        }
+        batch_len += num_calls;
+        batches.last_mut().unwrap().push((ct, spenders, metadata));
+    }
+
+    let mut batch_futs = futures::stream::iter(batches)
+        .filter(|batch| futures::future::ready(!batch.is_empty()))
+        .map(|batch| query_batch(rpc, semaphore, batch, owner, owner_address))
+        .buffer_unordered(MAX_PENDING_QUERIES);
+    while let Some(batch_results) = batch_futs.next().await {
+        for res in batch_results {
+            on_result(res);
+        }
+    }
+}
+
+/// Query a single batch of token contracts via Multicall3.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `batch` - token contracts along with their spenders, and cached metadata if any
+/// * `owner` - owner wallet address
+/// * `owner_address` - owner wallet address; lowercased string
+async fn query_batch<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, batch: Vec<BatchEntry>, owner: Address, owner_address: &str) -> Vec<QueryResult> {
+    // validated by caller already
+    let mut calls: Vec<Call> = Vec::new();
+    #[allow(clippy::type_complexity)]
+    let batch: Vec<(String, Vec<(String, ApprovalRecord)>, Option<TokenMetadata>)> = batch.into_iter()
+        .map(|(ct, spenders, metadata)| (ct, spenders.into_iter().collect(), metadata))
+        .collect();
+
+    for (ct, spenders, metadata) in batch.iter() {
+        let target = get_address_from_str(ct).unwrap();
+        if metadata.is_none() {
+            calls.push(Call { target, data: NAME_METHOD_ID.to_vec() });
+            calls.push(Call { target, data: SYMBOL_METHOD_ID.to_vec() });
+            calls.push(Call { target, data: DECIMALS_METHOD_ID.to_vec() });
+        }
+        for (spender, _) in spenders {
+            calls.push(Call { target, data: encode_allowance(owner, get_address_from_str(spender).unwrap()) });
+        }
+    }
+
+    let call_results = match with_permit(semaphore, aggregate3(rpc, &calls)).await {
+        Ok(res) if res.len() == calls.len() => res,
+        res => {
+            if let Err(e) = res {
+                tracing::debug!("Multicall batch of {} calls failed, querying {} token contracts individually; err={}", calls.len(), batch.len(), e);
+            }
+            // fall back to query each token individually
+            let futs = batch.into_iter()
+                .map(|(ct, spenders, metadata)| crate::scanner::query(rpc, semaphore, ct, owner_address.to_owned(), spenders.into_iter().collect(), metadata));
+            return futures::future::join_all(futs).await;
+        }
+    };
+
+    let mut call_results = call_results.into_iter();
+    let mut results: Vec<QueryResult> = Vec::with_capacity(batch.len());
+
+    for (ct, spenders, metadata) in batch {
+        let (name, symbol, decimals) = match metadata {
+            Some(m) => (Some(m.name), Some(m.symbol), Some(m.decimals)),
+            None => (
+                call_results.next().flatten().and_then(|b| decode_string(&b)),
+                call_results.next().flatten().and_then(|b| decode_string(&b)),
+                call_results.next().flatten().and_then(|b| decode_decimals(&b)),
+            ),
+        };
+        // failed call reverted, whereas output which can't be decoded is invalid
+        let allowances: Vec<Result<U256, AppvkekError>> = spenders.iter()
+            .map(|_| match call_results.next().flatten() {
+                Some(b) => decode_uint(&b).ok_or_else(|| AppvkekError::Decode("invalid returned data".to_owned())),
+                None => Err(AppvkekError::Reverted("call reverted".to_owned())),
+            })
+            .collect();
+
+        let name = match name {
+            Some(res) => res,
+            None => {
+                let error = AppvkekError::Reverted("Error in querying top-level query (name); call reverted or returned invalid data".to_owned());
+                results.push(Err(QueryError { token_address: ct, error }));
+                continue;
+            }
+        };
+        let decimals = match decimals {
+            Some(res) => res,
+            None => {
+                let error = AppvkekError::Reverted("Error in querying top-level query (decimals); call reverted or returned invalid data".to_owned());
+                results.push(Err(QueryError { token_address: ct, error }));
+                continue;
+            }
+        };
+
+        let mut result_struct = TokenContractWithSpenderAllowances {
+            name,
+            symbol: symbol.unwrap_or_default(),
+            address: ct.to_owned(),
+            decimals,
+            balance: None,
+            total_supply: None,
+            spender_allowances: HashMap::new(),
+        };
+
+        let mut err: Option<AppvkekError> = None;
+        for ((spender, approval), allowance) in spenders.into_iter().zip(allowances) {
+            let allowance = match allowance {
+                Ok(res) => res,
+                Err(e) => {
+                    let context = format!("Error querying for allowance balance for contract-addr={}, owner-addr={}, spender-addr={}", ct, owner_address, spender);
+                    err = Some(crate::scanner::token_call_error(e, &context, "allowance"));
+                    break;
+                }
+            };
+            match crate::scanner::make_spender_allowance(allowance, decimals, approval) {
+                Ok(res) => {
+                    result_struct.spender_allowances.insert(spender, res);
+                },
+                Err(e) => {
+                    err = Some(e);
+                    break;
+                }
+            }
+        }
+
+        match err {
+            Some(error) => results.push(Err(QueryError { token_address: ct, error })),
+            None => results.push(Ok(result_struct)),
+        }
+    }
+
+    results
+}
+
+/// Query owner's balance, and total supply of the specified token contracts
+/// by batching them into `aggregate3` calls to Multicall3 contract.
+/// Token contracts whose batch failed as a whole are queried individually
+/// instead.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `tokens` - token contracts to fill in `balance` and `total_supply`
+/// * `owner` - owner wallet address
+pub(crate) async fn query_balance_and_supply_multicall<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, tokens: &mut [&mut TokenContractWithSpenderAllowances], owner: Address) {
+    // each token takes 2 calls
+    let batch_futs = tokens.chunks_mut(CALLS_PER_BATCH / 2)
+        .map(|batch| async move {
+            // addresses of successfully queried token contracts are valid
+            let calls: Vec<Call> = batch.iter()
+                .flat_map(|r| {
+                    let target = get_address_from_str(&r.address).unwrap();
+                    [Call { target, data: encode_balance_of(owner) }, Call { target, data: TOTAL_SUPPLY_METHOD_ID.to_vec() }]
+                })
+                .collect();
+
+            match with_permit(semaphore, aggregate3(rpc, &calls)).await {
+                Ok(res) if res.len() == calls.len() => {
+                    let mut call_results = res.into_iter();
+                    for r in batch.iter_mut() {
+                        r.balance = call_results.next().flatten().and_then(|b| crate::scanner::decode_token_amount(&b, r.decimals));
+                        r.total_supply = call_results.next().flatten().and_then(|b| crate::scanner::decode_token_amount(&b, r.decimals));
+                    }
+                },
+                res => {
+                    if let Err(e) = res {
+                        tracing::debug!("Multicall batch of {} calls failed, querying {} token contracts individually; err={}", calls.len(), batch.len(), e);
+                    }
+                    crate::scanner::query_balance_and_supply_individually(rpc, semaphore, batch, owner).await;
+                },
+            }
+        });
+    futures::stream::iter(batch_futs)
+        .buffer_unordered(MAX_PENDING_QUERIES)
+        .collect::<Vec<()>>()
+        .await;
+}
+
@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+/// Kind of event to be notified via webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    /// A new approval has been detected since the last scan
+    NewApproval,
+
+    /// An existing allowance crossed the configured threshold
+    ThresholdCrossed,
+
+    /// Risk score of an existing approval crossed the configured minimum
+    RiskCrossed,
+
+    /// An approve transaction of owner is seen in mempool, not yet confirmed
+    PendingApproval,
+}
+
+/// JSON payload to be sent to webhook URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    /// Kind of event
+    pub event: WebhookEventKind,
+
+    /// Chain name e.g. `bsc`, `ethereum`, or `polygon`
+    pub chain: String,
+
+    /// Owner wallet address; EIP-55 checksummed
+    pub owner: String,
+
+    /// Token contract address; EIP-55 checksummed
+    pub token_address: String,
+
+    /// Token contract name
+    pub token_name: String,
+
+    /// Spender address; EIP-55 checksummed
+    pub spender: String,
+
+    /// Current allowance balance (in token unit)
+    pub allowance: f64,
+
+    /// Allowance balance as seen from the previous scan if any
+    pub previous_allowance: Option<f64>,
+
+    /// Risk score of approval; `None` for `PendingApproval` as it's not
+    /// assessed until confirmed
+    pub risk_score: Option<u32>,
+
+    /// Risk score of approval as seen from the previous scan if any
+    pub previous_risk_score: Option<u32>,
+
+    /// Hash of pending approve transaction; only for `PendingApproval`
+    pub tx_hash: Option<String>,
+
+    /// Unix timestamp (in seconds) of when the event has been detected
+    pub timestamp: u64,
+}
+
+/// POST the specified event as JSON payload to webhook URL.
+///
+/// # Arguments
+/// * `client` - HTTP client
+/// * `url` - webhook URL
+/// * `event` - event to send; usually `WebhookEvent`
+pub async fn post_webhook<T: Serialize + ?Sized>(client: &reqwest::Client, url: &str, event: &T) -> Result<(), String> {
+    let resp = match client.post(url).json(event).send().await {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error sending webhook request to {}; err={}", url, e)),
+    };
+
+    if !resp.status().is_success() {
+        return Err(format!("Error webhook responded with non-success status; url={}, status={}", url, resp.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use ::evmscan::prelude::ChainType;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::util::chain_name;
+
+/// Endpoint of DefiLlama to query current USD price of tokens; followed by
+/// comma-separated `<chain>:<token address>`
+pub static DEFILLAMA_PRICES_URL: &str = "https://coins.llama.fi/prices/current";
+
+/// Maximum number of tokens to query in a single request, to keep URL short
+pub const MAX_TOKENS_PER_REQUEST: usize = 100;
+
+/// Price of a single token in response of DefiLlama.
+#[derive(Debug, Deserialize)]
+struct CoinPrice {
+    price: f64,
+}
+
+/// Response of DefiLlama to price query.
+#[derive(Debug, Deserialize)]
+struct PricesResponse {
+    coins: HashMap<String, CoinPrice>,
+}
+
+/// Parse response of DefiLlama into USD price of each token keyed by its
+/// lowercased address. Tokens which DefiLlama doesn't know are just absent.
+///
+/// # Arguments
+/// * `body` - response body in JSON
+fn parse_prices(body: &str) -> Result<HashMap<String, f64>, String> {
+    let resp: PricesResponse = match serde_json::from_str(body) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error parsing response of DefiLlama; err={}", e)),
+    };
+    Ok(resp.coins.into_iter()
+        .filter_map(|(key, coin)| key.split_once(':').map(|(_, address)| (address.to_lowercase(), coin.price)))
+        .collect())
+}
+
+/// Query current USD price of the specified tokens from DefiLlama.
+/// Return price of each token keyed by its lowercased address; tokens without
+/// known price e.g. spam ones are absent.
+///
+/// # Arguments
+/// * `client` - HTTP client
+/// * `chain` - chain type
+/// * `token_addresses` - token contract addresses
+pub async fn fetch_usd_prices(client: &reqwest::Client, chain: ChainType, token_addresses: &[String]) -> Result<HashMap<String, f64>, String> {
+    let mut prices: HashMap<String, f64> = HashMap::new();
+    for chunk in token_addresses.chunks(MAX_TOKENS_PER_REQUEST) {
+        let coins: Vec<String> = chunk.iter().map(|address| format!("{}:{}", chain_name(chain), address.to_lowercase())).collect();
+        let url = format!("{}/{}", DEFILLAMA_PRICES_URL, coins.join(","));
+        let resp = match client.get(&url).send().await {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error sending request to DefiLlama; err={}", e)),
+        };
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(format!("Error DefiLlama responded with non-success status; status={}", status));
+        }
+        let body = match resp.text().await {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error reading response of DefiLlama; err={}", e)),
+        };
+        prices.extend(parse_prices(&body)?);
+    }
+    Ok(prices)
+}
+
+/// Return USD value which an approval exposes i.e. allowance capped at
+/// owner's balance, as spender can't take more than that is held. `None` if
+/// either balance, or price is unknown.
+///
+/// # Arguments
+/// * `allowance` - allowance in token unit
+/// * `balance` - balance of owner in token unit
+/// * `price` - USD price of token
+pub fn exposure_usd(allowance: f64, balance: Option<f64>, price: Option<f64>) -> Option<f64> {
+    Some(allowance.min(balance?) * price?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_prices_keys_by_lowercased_address() {
+        let body = r#"{"coins":{"bsc:0x55d398326f99059fF775485246999027B3197955":{"decimals":18,"symbol":"USDT","price":0.9998,"timestamp":1700000000,"confidence":0.99}}}"#;
+        let prices = parse_prices(body).unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices["0x55d398326f99059ff775485246999027b3197955"], 0.9998);
+
+        assert!(parse_prices(r#"{"coins":{}}"#).unwrap().is_empty());
+        assert!(parse_prices("not json").is_err());
+    }
+
+    #[test]
+    fn exposure_usd_is_capped_at_balance() {
+        assert_eq!(exposure_usd(f64::MAX, Some(10.0), Some(2.0)), Some(20.0));
+        assert_eq!(exposure_usd(5.0, Some(10.0), Some(2.0)), Some(10.0));
+        assert_eq!(exposure_usd(5.0, None, Some(2.0)), None);
+        assert_eq!(exposure_usd(5.0, Some(10.0), None), None);
+    }
+}
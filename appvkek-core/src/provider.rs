@@ -0,0 +1,261 @@
+use ::evmscan::environ::Context;
+use ::evmscan::types::evm_types::EvmNormalTransactionResponseSuccessVariantResult;
+use async_trait::async_trait;
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, FeeHistory, H256, U256};
+
+use crate::error::AppvkekError;
+use crate::explorer::{self, ContractCreation};
+use crate::types::{EventLog, TokenTransfer};
+use crate::util::Web3Type;
+
+/// Interactions with chain via RPC endpoint needed by the scanner.
+/// `Web3Type` is the live implementation, and `mock::MockChainRpc` is for
+/// testing without hitting live networks.
+#[async_trait]
+pub trait ChainRpc: Send + Sync {
+    /// Return code at the specified address; empty if it has no code.
+    ///
+    /// # Arguments
+    /// * `address` - address to get code of
+    /// * `block` - block number to get code at; latest block if `None`
+    async fn code(&self, address: Address, block: Option<u64>) -> Result<Vec<u8>, AppvkekError>;
+
+    /// Make `eth_call` to the specified contract, and return its returned data.
+    ///
+    /// # Arguments
+    /// * `to` - contract address
+    /// * `data` - calldata
+    /// * `block` - block number to call at; latest block if `None`
+    async fn call(&self, to: Address, data: Vec<u8>, block: Option<u64>) -> Result<Vec<u8>, AppvkekError>;
+
+    /// Return value of the specified storage slot of the specified contract.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    /// * `slot` - storage slot
+    /// * `block` - block number to read at; latest block if `None`
+    async fn storage(&self, address: Address, slot: U256, block: Option<u64>) -> Result<H256, AppvkekError>;
+
+    /// Return estimated gas of transaction sent from the specified address
+    /// with zero value at the latest block; error if it would revert.
+    ///
+    /// # Arguments
+    /// * `from` - sender address
+    /// * `to` - contract address
+    /// * `data` - calldata
+    async fn estimate_gas(&self, from: Address, to: Address, data: Vec<u8>) -> Result<u64, AppvkekError>;
+
+    /// Return current gas price in wei.
+    async fn gas_price(&self) -> Result<U256, AppvkekError>;
+
+    /// Return number of transactions sent from the specified address i.e.
+    /// its next nonce.
+    ///
+    /// # Arguments
+    /// * `address` - sender address
+    /// * `pending` - whether to include those pending in mempool rather than
+    ///   only mined ones
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<u64, AppvkekError>;
+
+    /// Return base fees, and priority fees at the specified percentile of the
+    /// latest blocks via `eth_feeHistory`.
+    ///
+    /// # Arguments
+    /// * `block_count` - number of the latest blocks
+    /// * `percentile` - percentile of priority fees paid in each block (0-100)
+    async fn fee_history(&self, block_count: u64, percentile: f64) -> Result<FeeHistory, AppvkekError>;
+}
+
+#[async_trait]
+impl ChainRpc for Web3Type {
+    async fn code(&self, address: Address, block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        match self.eth().code(address, block.map(|b| BlockNumber::Number(b.into()))).await {
+            Ok(res) => Ok(res.0),
+            Err(e) => Err(AppvkekError::from_web3(&format!("Error awaiting result for code from address ({:?})", address), &e)),
+        }
+    }
+
+    async fn call(&self, to: Address, data: Vec<u8>, block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        let req = CallRequest::builder()
+            .to(to)
+            .data(Bytes(data))
+            .build();
+        match self.eth().call(req, block.map(|b| BlockId::Number(BlockNumber::Number(b.into())))).await {
+            Ok(res) => Ok(res.0),
+            Err(e) => Err(AppvkekError::from_web3(&format!("Error calling contract ({:?})", to), &e)),
+        }
+    }
+
+    async fn storage(&self, address: Address, slot: U256, block: Option<u64>) -> Result<H256, AppvkekError> {
+        match self.eth().storage(address, slot, block.map(|b| BlockNumber::Number(b.into()))).await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(AppvkekError::from_web3(&format!("Error reading storage slot of {:?}", address), &e)),
+        }
+    }
+
+    async fn estimate_gas(&self, from: Address, to: Address, data: Vec<u8>) -> Result<u64, AppvkekError> {
+        let req = CallRequest::builder()
+            .from(from)
+            .to(to)
+            .data(Bytes(data))
+            .build();
+        match self.eth().estimate_gas(req, None).await {
+            Ok(res) => Ok(res.low_u64()),
+            Err(e) => Err(AppvkekError::from_web3(&format!("Error estimating gas of transaction to {:?}", to), &e)),
+        }
+    }
+
+    async fn gas_price(&self) -> Result<U256, AppvkekError> {
+        match self.eth().gas_price().await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(AppvkekError::from_web3("Error querying gas price", &e)),
+        }
+    }
+
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<u64, AppvkekError> {
+        let block = if pending { BlockNumber::Pending } else { BlockNumber::Latest };
+        match self.eth().transaction_count(address, Some(block)).await {
+            Ok(res) => Ok(res.low_u64()),
+            Err(e) => Err(AppvkekError::from_web3(&format!("Error querying transaction count of {:?}", address), &e)),
+        }
+    }
+
+    async fn fee_history(&self, block_count: u64, percentile: f64) -> Result<FeeHistory, AppvkekError> {
+        match self.eth().fee_history(block_count.into(), BlockNumber::Latest, Some(vec![percentile])).await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(AppvkekError::from_web3("Error querying fee history", &e)),
+        }
+    }
+}
+
+/// Chain RPC which makes all calls at the specified block unless a block is
+/// given explicitly, so that existing queries can be evaluated against a
+/// historical block without passing it around.
+pub struct AtBlock<'a, R: ChainRpc + ?Sized> {
+    /// Chain RPC to delegate to
+    pub inner: &'a R,
+
+    /// Block number to make calls at; latest block if `None`
+    pub block: Option<u64>,
+}
+
+#[async_trait]
+impl<R: ChainRpc + ?Sized> ChainRpc for AtBlock<'_, R> {
+    async fn code(&self, address: Address, block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        self.inner.code(address, block.or(self.block)).await
+    }
+
+    async fn call(&self, to: Address, data: Vec<u8>, block: Option<u64>) -> Result<Vec<u8>, AppvkekError> {
+        self.inner.call(to, data, block.or(self.block)).await
+    }
+
+    async fn storage(&self, address: Address, slot: U256, block: Option<u64>) -> Result<H256, AppvkekError> {
+        self.inner.storage(address, slot, block.or(self.block)).await
+    }
+
+    // transactions are only ever sent on top of the latest block
+    async fn estimate_gas(&self, from: Address, to: Address, data: Vec<u8>) -> Result<u64, AppvkekError> {
+        self.inner.estimate_gas(from, to, data).await
+    }
+
+    async fn gas_price(&self) -> Result<U256, AppvkekError> {
+        self.inner.gas_price().await
+    }
+
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<u64, AppvkekError> {
+        self.inner.transaction_count(address, pending).await
+    }
+
+    async fn fee_history(&self, block_count: u64, percentile: f64) -> Result<FeeHistory, AppvkekError> {
+        self.inner.fee_history(block_count, percentile).await
+    }
+}
+
+/// Interactions with explorer API needed by the scanner.
+/// evmscan's `Context` is the live implementation, and
+/// `mock::MockExplorer` is for testing without hitting live networks.
+pub trait ExplorerApi {
+    /// Fetch a single page of normal transactions of the specified address.
+    /// Return empty vector if there is no transaction.
+    ///
+    /// # Arguments
+    /// * `address` - target address
+    /// * `start_block` - starting block number (inclusive)
+    /// * `page` - page number starting from 1
+    /// * `offset` - number of transactions per page
+    /// * `sort` - either `asc` or `desc` by block number
+    fn txlist_page(&self, address: &str, start_block: u64, page: usize, offset: usize, sort: &str) -> Result<Vec<EvmNormalTransactionResponseSuccessVariantResult>, AppvkekError>;
+
+    /// Fetch a single page of `Approval` event logs whose owner is the
+    /// specified address, emitted by any contract; in ascending order of
+    /// block number. Return empty vector if there is no log.
+    ///
+    /// # Arguments
+    /// * `owner_address` - owner address; lowercased, prefixed with '0x'
+    /// * `start_block` - starting block number (inclusive)
+    /// * `page` - page number starting from 1
+    /// * `offset` - number of logs per page
+    fn approval_logs_page(&self, owner_address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError>;
+
+    /// Fetch a single page of ERC-4337 `UserOperationEvent` event logs whose
+    /// sender is the specified smart account; in ascending order of block
+    /// number. Return empty vector if there is no log.
+    ///
+    /// # Arguments
+    /// * `sender` - smart account address; lowercased, prefixed with '0x'
+    /// * `start_block` - starting block number (inclusive)
+    /// * `page` - page number starting from 1
+    /// * `offset` - number of logs per page
+    fn user_operation_logs_page(&self, sender: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError>;
+
+    /// Fetch a single page of ERC-20 token transfers to or from the specified
+    /// address; in ascending order of block number. Return empty vector if
+    /// there is no transfer.
+    ///
+    /// # Arguments
+    /// * `address` - target address; lowercased, prefixed with '0x'
+    /// * `start_block` - starting block number (inclusive)
+    /// * `page` - page number starting from 1
+    /// * `offset` - number of transfers per page
+    fn tokentx_page(&self, address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<TokenTransfer>, AppvkekError>;
+
+    /// Return whether source code of the specified contract address has been
+    /// verified.
+    ///
+    /// # Arguments
+    /// * `address` - contract address
+    fn is_contract_verified(&self, address: &str) -> Result<bool, AppvkekError>;
+
+    /// Return creation record of the specified address if it has ever been
+    /// created as a contract, even if it has been destroyed since then.
+    ///
+    /// # Arguments
+    /// * `address` - address to check
+    fn contract_creation(&self, address: &str) -> Result<Option<ContractCreation>, AppvkekError>;
+}
+
+impl ExplorerApi for Context {
+    fn txlist_page(&self, address: &str, start_block: u64, page: usize, offset: usize, sort: &str) -> Result<Vec<EvmNormalTransactionResponseSuccessVariantResult>, AppvkekError> {
+        explorer::fetch_txlist_page(self, address, start_block, page, offset, sort)
+    }
+
+    fn approval_logs_page(&self, owner_address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        explorer::fetch_approval_logs_page(self, owner_address, start_block, page, offset)
+    }
+
+    fn user_operation_logs_page(&self, sender: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        explorer::fetch_user_operation_logs_page(self, sender, start_block, page, offset)
+    }
+
+    fn tokentx_page(&self, address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<TokenTransfer>, AppvkekError> {
+        explorer::fetch_tokentx_page(self, address, start_block, page, offset)
+    }
+
+    fn is_contract_verified(&self, address: &str) -> Result<bool, AppvkekError> {
+        explorer::is_contract_verified(self, address)
+    }
+
+    fn contract_creation(&self, address: &str) -> Result<Option<ContractCreation>, AppvkekError> {
+        explorer::get_contract_creation(self, address)
+    }
+}
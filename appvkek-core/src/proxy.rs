@@ -0,0 +1,304 @@
+use web3::types::{Address, H256, U256};
+
+use crate::error::AppvkekError;
+use crate::provider::ChainRpc;
+use crate::util::*;
+
+/// EIP-1967 implementation slot; `bytes32(uint256(keccak256('eip1967.proxy.implementation')) - 1)`
+static EIP1967_IMPLEMENTATION_SLOT: &str = "360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+/// EIP-1967 beacon slot; `bytes32(uint256(keccak256('eip1967.proxy.beacon')) - 1)`
+static EIP1967_BEACON_SLOT: &str = "a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+
+/// Legacy ZeppelinOS implementation slot; `keccak256('org.zeppelinos.proxy.implementation')`
+static ZEPPELINOS_IMPLEMENTATION_SLOT: &str = "7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3";
+
+/// EIP-1967 admin slot; `bytes32(uint256(keccak256('eip1967.proxy.admin')) - 1)`
+static EIP1967_ADMIN_SLOT: &str = "b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+
+/// Legacy ZeppelinOS admin slot; `keccak256('org.zeppelinos.proxy.admin')`
+static ZEPPELINOS_ADMIN_SLOT: &str = "10d6a54a4754c8869d6886b5f5d7fbfa5b4522237ea5c60d11bc4e7a1ff9390b";
+
+/// Method-id of `implementation()` as called on beacon contract
+static IMPLEMENTATION_METHOD_ID: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+
+/// Method-id of `owner()` of Ownable contract
+static OWNER_METHOD_ID: [u8; 4] = [0x8d, 0xa5, 0xcb, 0x5b];
+
+/// EIP-1167 minimal proxy bytecode before the 20-bytes implementation address
+static MINIMAL_PROXY_PREFIX: [u8; 10] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+
+/// EIP-1167 minimal proxy bytecode after the 20-bytes implementation address
+static MINIMAL_PROXY_SUFFIX: [u8; 15] = [0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3];
+
+/// Kind of proxy pattern.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProxyKind {
+    /// EIP-1967 transparent or UUPS proxy
+    Eip1967,
+
+    /// EIP-1967 beacon proxy
+    Beacon,
+
+    /// EIP-1167 minimal proxy (clone)
+    MinimalProxy,
+
+    /// Legacy ZeppelinOS proxy
+    ZeppelinOS,
+}
+
+impl std::fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            ProxyKind::Eip1967 => write!(f, "EIP-1967"),
+            ProxyKind::Beacon => write!(f, "EIP-1967 beacon"),
+            ProxyKind::MinimalProxy => write!(f, "EIP-1167 minimal proxy"),
+            ProxyKind::ZeppelinOS => write!(f, "ZeppelinOS"),
+        }
+    }
+}
+
+/// Information of a proxy contract.
+#[derive(Debug, Clone)]
+pub struct ProxyInfo {
+    /// Kind of proxy
+    pub kind: ProxyKind,
+
+    /// Implementation address; lowercased, prefixed with '0x'
+    pub implementation: String,
+}
+
+/// Addresses which control a contract.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ControlInfo {
+    /// Owner as returned by Ownable's `owner()`; lowercased, prefixed with '0x'
+    pub owner: Option<String>,
+
+    /// Proxy admin who can upgrade implementation; lowercased, prefixed with
+    /// '0x'
+    pub admin: Option<String>,
+}
+
+impl ControlInfo {
+    /// Whether no controlling address is found.
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none() && self.admin.is_none()
+    }
+}
+
+/// Return address stored in the specified storage slot if any.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - contract address
+/// * `slot` - storage slot in hex string without '0x' prefix
+async fn read_address_slot<R: ChainRpc + ?Sized>(rpc: &R, address: Address, slot: &str) -> Result<Option<Address>, AppvkekError> {
+    let slot = match U256::from_str_radix(slot, 16) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Decode(format!("Error parsing storage slot ({}); err={}", slot, e))),
+    };
+
+    Ok(address_from_word(rpc.storage(address, slot, None).await?))
+}
+
+/// Return address from a 32-bytes word if it's non-zero.
+///
+/// # Arguments
+/// * `word` - 32-bytes word
+fn address_from_word(word: H256) -> Option<Address> {
+    if word.is_zero() {
+        return None;
+    }
+    Some(Address::from_slice(&word.as_bytes()[12..]))
+}
+
+/// Format address into lowercased hex string prefixed with '0x'.
+///
+/// # Arguments
+/// * `address` - address to format
+fn format_address(address: &Address) -> String {
+    format!("0x{}", hex::encode(address.as_bytes()))
+}
+
+/// Resolve implementation address of the specified contract if it's a proxy.
+/// Return `None` if it's not a proxy of any supported patterns.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - contract address; prefixed with '0x'
+pub async fn resolve_implementation<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<Option<ProxyInfo>, AppvkekError> {
+    let contract_address = get_address_from_str(address)?;
+
+    // minimal proxy has implementation address embedded in its bytecode
+    let code = rpc.code(contract_address, None).await?;
+    if code.len() == MINIMAL_PROXY_PREFIX.len() + 20 + MINIMAL_PROXY_SUFFIX.len() && code.starts_with(&MINIMAL_PROXY_PREFIX) && code.ends_with(&MINIMAL_PROXY_SUFFIX) {
+        let implementation = Address::from_slice(&code[MINIMAL_PROXY_PREFIX.len()..MINIMAL_PROXY_PREFIX.len() + 20]);
+        return Ok(Some(ProxyInfo { kind: ProxyKind::MinimalProxy, implementation: format_address(&implementation) }));
+    }
+
+    if let Some(implementation) = read_address_slot(rpc, contract_address, EIP1967_IMPLEMENTATION_SLOT).await? {
+        return Ok(Some(ProxyInfo { kind: ProxyKind::Eip1967, implementation: format_address(&implementation) }));
+    }
+
+    if let Some(beacon) = read_address_slot(rpc, contract_address, EIP1967_BEACON_SLOT).await? {
+        let output = match rpc.call(beacon, IMPLEMENTATION_METHOD_ID.to_vec(), None).await {
+            Ok(res) => res,
+            Err(e) => return Err(e.with_context(&format!("Error calling implementation() on beacon {:?}", beacon))),
+        };
+        if output.len() >= 32 {
+            if let Some(implementation) = address_from_word(H256::from_slice(&output[..32])) {
+                return Ok(Some(ProxyInfo { kind: ProxyKind::Beacon, implementation: format_address(&implementation) }));
+            }
+        }
+    }
+
+    if let Some(implementation) = read_address_slot(rpc, contract_address, ZEPPELINOS_IMPLEMENTATION_SLOT).await? {
+        return Ok(Some(ProxyInfo { kind: ProxyKind::ZeppelinOS, implementation: format_address(&implementation) }));
+    }
+
+    Ok(None)
+}
+
+/// Resolve addresses which control the specified contract; its Ownable owner,
+/// and proxy admin in either EIP-1967 or legacy ZeppelinOS admin slot.
+/// Contract without `owner()`, or renounced ownership is treated as having no
+/// owner.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - contract address; prefixed with '0x'
+pub async fn resolve_control<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<ControlInfo, AppvkekError> {
+    let contract_address = get_address_from_str(address)?;
+
+    let owner = match rpc.call(contract_address, OWNER_METHOD_ID.to_vec(), None).await {
+        // address is returned as a word with its upper 12 bytes being zero
+        Ok(output) if output.len() == 32 && output[..12].iter().all(|b| *b == 0) => address_from_word(H256::from_slice(&output)),
+        Ok(_) | Err(AppvkekError::Reverted(_)) => None,
+        Err(e) => return Err(e.with_context(&format!("Error calling owner() on {}", address))),
+    };
+
+    let mut admin = read_address_slot(rpc, contract_address, EIP1967_ADMIN_SLOT).await?;
+    if admin.is_none() {
+        admin = read_address_slot(rpc, contract_address, ZEPPELINOS_ADMIN_SLOT).await?;
+    }
+
+    Ok(ControlInfo {
+        owner: owner.map(|a| format_address(&a)),
+        admin: admin.map(|a| format_address(&a)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    static CONTRACT: &str = "0x3333333333333333333333333333333333333333";
+    static OWNER: &str = "0x4444444444444444444444444444444444444444";
+    static ADMIN: &str = "0x5555555555555555555555555555555555555555";
+    static IMPLEMENTATION: &str = "0x6666666666666666666666666666666666666666";
+    static BEACON: &str = "0x7777777777777777777777777777777777777777";
+
+    fn word(address: &str) -> H256 {
+        H256::from(get_address_from_str(address).unwrap())
+    }
+
+    fn slot(slot: &str) -> U256 {
+        U256::from_str_radix(slot, 16).unwrap()
+    }
+
+    #[test]
+    fn slots_match_their_preimages() {
+        let hash = |preimage: &str| U256::from_big_endian(&web3::signing::keccak256(preimage.as_bytes()));
+
+        assert_eq!(slot(EIP1967_IMPLEMENTATION_SLOT), hash("eip1967.proxy.implementation") - 1);
+        assert_eq!(slot(EIP1967_BEACON_SLOT), hash("eip1967.proxy.beacon") - 1);
+        assert_eq!(slot(EIP1967_ADMIN_SLOT), hash("eip1967.proxy.admin") - 1);
+        assert_eq!(slot(ZEPPELINOS_IMPLEMENTATION_SLOT), hash("org.zeppelinos.proxy.implementation"));
+        assert_eq!(slot(ZEPPELINOS_ADMIN_SLOT), hash("org.zeppelinos.proxy.admin"));
+    }
+
+    #[tokio::test]
+    async fn resolve_implementation_of_minimal_proxy() {
+        let mut code = MINIMAL_PROXY_PREFIX.to_vec();
+        code.extend(get_address_from_str(IMPLEMENTATION).unwrap().as_bytes());
+        code.extend(MINIMAL_PROXY_SUFFIX);
+        let rpc = MockChainRpc::default().with_code(CONTRACT, code);
+
+        let proxy = resolve_implementation(&rpc, CONTRACT).await.unwrap().unwrap();
+
+        assert_eq!(proxy.kind, ProxyKind::MinimalProxy);
+        assert_eq!(proxy.implementation, IMPLEMENTATION);
+    }
+
+    #[tokio::test]
+    async fn resolve_implementation_of_eip1967_proxy() {
+        let rpc = MockChainRpc::default()
+            .with_code(CONTRACT, vec![0x60, 0x80])
+            .with_storage(CONTRACT, slot(EIP1967_IMPLEMENTATION_SLOT), word(IMPLEMENTATION));
+
+        let proxy = resolve_implementation(&rpc, CONTRACT).await.unwrap().unwrap();
+
+        assert_eq!(proxy.kind, ProxyKind::Eip1967);
+        assert_eq!(proxy.implementation, IMPLEMENTATION);
+    }
+
+    #[tokio::test]
+    async fn resolve_implementation_of_beacon_proxy() {
+        let rpc = MockChainRpc::default()
+            .with_code(CONTRACT, vec![0x60, 0x80])
+            .with_storage(CONTRACT, slot(EIP1967_BEACON_SLOT), word(BEACON))
+            .with_call(BEACON, IMPLEMENTATION_METHOD_ID.to_vec(), word(IMPLEMENTATION).as_bytes().to_vec());
+
+        let proxy = resolve_implementation(&rpc, CONTRACT).await.unwrap().unwrap();
+
+        assert_eq!(proxy.kind, ProxyKind::Beacon);
+        assert_eq!(proxy.implementation, IMPLEMENTATION);
+    }
+
+    #[tokio::test]
+    async fn resolve_implementation_of_zeppelinos_proxy() {
+        let rpc = MockChainRpc::default()
+            .with_code(CONTRACT, vec![0x60, 0x80])
+            .with_storage(CONTRACT, slot(ZEPPELINOS_IMPLEMENTATION_SLOT), word(IMPLEMENTATION));
+
+        let proxy = resolve_implementation(&rpc, CONTRACT).await.unwrap().unwrap();
+
+        assert_eq!(proxy.kind, ProxyKind::ZeppelinOS);
+        assert_eq!(proxy.implementation, IMPLEMENTATION);
+    }
+
+    #[tokio::test]
+    async fn resolve_implementation_of_non_proxy_is_none() {
+        let rpc = MockChainRpc::default().with_code(CONTRACT, vec![0x60, 0x80]);
+        assert!(resolve_implementation(&rpc, CONTRACT).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_control_reads_owner_and_admin() {
+        let rpc = MockChainRpc::default()
+            .with_call(CONTRACT, OWNER_METHOD_ID.to_vec(), word(OWNER).as_bytes().to_vec())
+            .with_storage(CONTRACT, slot(EIP1967_ADMIN_SLOT), word(ADMIN));
+
+        let control = resolve_control(&rpc, CONTRACT).await.unwrap();
+
+        assert_eq!(control.owner.as_deref(), Some(OWNER));
+        assert_eq!(control.admin.as_deref(), Some(ADMIN));
+    }
+
+    #[tokio::test]
+    async fn resolve_control_ignores_non_ownable_and_renounced() {
+        let rpc = MockChainRpc::default();
+        assert!(resolve_control(&rpc, CONTRACT).await.unwrap().is_empty());
+
+        let rpc = MockChainRpc::default()
+            .with_call(CONTRACT, OWNER_METHOD_ID.to_vec(), vec![0; 32]);
+        assert!(resolve_control(&rpc, CONTRACT).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resolve_control_propagates_rpc_error() {
+        let rpc = MockChainRpc::default().with_rpc_error(CONTRACT);
+        assert!(resolve_control(&rpc, CONTRACT).await.is_err());
+    }
+}
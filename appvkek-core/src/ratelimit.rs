@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token-bucket rate limiter.
+///
+/// Bucket holds up to `burst` tokens, and is refilled at `rate` tokens per
+/// second. Each request takes one token. Requests taking more tokens than
+/// available put the bucket into debt, which subsequent requests wait for
+/// to be paid back, so that a large batch never starves forever.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Number of tokens refilled per second; non-positive disables limiting
+    rate: f64,
+
+    /// Maximum number of tokens bucket can hold
+    burst: f64,
+
+    /// Number of tokens currently available (negative when in debt), and the
+    /// time it was last refilled
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// Create a new full bucket.
+    ///
+    /// # Arguments
+    /// * `rate` - number of requests allowed per second; non-positive disables limiting
+    /// * `burst` - maximum number of requests allowed at once
+    pub fn new(rate: f64, burst: u32) -> TokenBucket {
+        let burst = f64::from(burst.max(1));
+        TokenBucket {
+            rate,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Take the specified number of tokens if available, otherwise return
+    /// how long to wait before trying again.
+    ///
+    /// # Arguments
+    /// * `count` - number of tokens to take
+    fn try_take(&self, count: usize) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.0 = (state.0 + now.duration_since(state.1).as_secs_f64() * self.rate).min(self.burst);
+        state.1 = now;
+
+        // only wait for as many tokens as bucket can hold; the rest becomes debt
+        let needed = (count as f64).min(self.burst);
+        if state.0 >= needed {
+            state.0 -= count as f64;
+            None
+        }
+        else {
+            Some(Duration::from_secs_f64((needed - state.0) / self.rate))
+        }
+    }
+
+    /// Wait until the specified number of tokens is available, then take them.
+    ///
+    /// # Arguments
+    /// * `count` - number of tokens to take
+    pub async fn acquire(&self, count: usize) {
+        if self.rate <= 0.0 {
+            return;
+        }
+        while let Some(wait) = self.try_take(count) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Block the current thread until the specified number of tokens is
+    /// available, then take them; for synchronous callers e.g. explorer API
+    /// calls.
+    ///
+    /// # Arguments
+    /// * `count` - number of tokens to take
+    pub fn acquire_blocking(&self, count: usize) {
+        if self.rate <= 0.0 {
+            return;
+        }
+        while let Some(wait) = self.try_take(count) {
+            std::thread::sleep(wait);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+
+use crate::error::AppvkekError;
+use crate::revoke::RevokeTx;
+
+/// Endpoint of Gelato Relay to submit sponsored call i.e. transaction whose
+/// gas is paid by sponsor's Gas Tank
+pub static GELATO_SPONSORED_CALL_URL: &str = "https://api.gelato.digital/relays/v2/sponsored-call";
+
+/// Endpoint of Gelato Relay to query status of task; followed by task id
+pub static GELATO_TASK_STATUS_URL: &str = "https://api.gelato.digital/tasks/status";
+
+/// Response of Gelato Relay to submitted call.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SponsoredCallResponse {
+    task_id: String,
+}
+
+/// Submit the specified transaction to Gelato Relay as sponsored call, so
+/// that it's sent by relayer with gas paid by sponsor rather than owner
+/// wallet. Only transactions which anyone can send on behalf of owner e.g.
+/// EIP-2612 permit are meaningful to relay.
+/// Return task id to track its status via `GELATO_TASK_STATUS_URL`.
+/// Rejection of the call e.g. invalid API key, or unsupported chain is
+/// returned as `AppvkekError::Config`, while failures worth retrying are
+/// `AppvkekError::Rpc`, or `AppvkekError::RateLimited`.
+///
+/// # Arguments
+/// * `client` - HTTP client
+/// * `chain_id` - EIP-155 chain id
+/// * `tx` - transaction to relay
+/// * `api_key` - sponsor API key of Gelato Relay
+pub async fn submit_gelato_sponsored_call(client: &reqwest::Client, chain_id: u64, tx: &RevokeTx, api_key: &str) -> Result<String, AppvkekError> {
+    let body = serde_json::json!({
+        "chainId": chain_id.to_string(),
+        "target": tx.token_address,
+        "data": tx.data,
+        "sponsorApiKey": api_key,
+    });
+    let resp = match client.post(GELATO_SPONSORED_CALL_URL).json(&body).send().await {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Rpc(format!("Error sending request to Gelato Relay; err={}", e))),
+    };
+
+    let status = resp.status();
+    if !status.is_success() {
+        let message = resp.text().await.unwrap_or_default();
+        let msg = format!("Error Gelato Relay responded with non-success status; status={}, message={}", status, message);
+        return Err(match status {
+            reqwest::StatusCode::TOO_MANY_REQUESTS => AppvkekError::RateLimited(msg),
+            s if s.is_server_error() => AppvkekError::Rpc(msg),
+            _ => AppvkekError::Config(msg),
+        });
+    }
+    match resp.json::<SponsoredCallResponse>().await {
+        Ok(res) => Ok(res.task_id),
+        Err(e) => Err(AppvkekError::Decode(format!("Error parsing response of Gelato Relay; err={}", e))),
+    }
+}
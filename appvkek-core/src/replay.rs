@@ -0,0 +1,168 @@
+use jsonrpc_core::{Call, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::debugdump::{self, DumpEntry};
+use crate::error::AppvkekError;
+
+/// Whether responses are served from fixture instead of network
+static IS_REPLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Recorded responses to serve
+static FIXTURE: Mutex<Option<Fixture>> = Mutex::new(None);
+
+/// Responses recorded for the same request, served in recorded order. The
+/// last one is served repeatedly once all have been served.
+#[derive(Debug, Default)]
+struct Responses {
+    /// Recorded entries
+    entries: Vec<DumpEntry>,
+
+    /// Index of the next entry to serve
+    next: usize,
+}
+
+impl Responses {
+    /// Return the next recorded entry.
+    fn next(&mut self) -> &DumpEntry {
+        let i = self.next.min(self.entries.len() - 1);
+        self.next += 1;
+        &self.entries[i]
+    }
+}
+
+/// Recorded responses keyed by request; see `rpc_key()`, and
+/// `debugdump::redact_url()`.
+#[derive(Debug, Default)]
+struct Fixture {
+    /// JSON-RPC request to its responses
+    rpc: HashMap<String, Responses>,
+
+    /// Explorer API URL to its responses
+    explorer: HashMap<String, Responses>,
+}
+
+/// Return key identifying JSON-RPC request regardless of its id; method
+/// along with params.
+///
+/// # Arguments
+/// * `request` - JSON-RPC request as serialized
+fn rpc_key(request: &Value) -> String {
+    format!("{}{}", request["method"], request["params"])
+}
+
+/// Start serving every JSON-RPC, and explorer API response from the specified
+/// fixture instead of sending requests over network, so that a scan can be
+/// re-run offline, and deterministically. Fixture is dump file recorded via
+/// `debugdump::open()`. Subsequent calls once started are no-op.
+///
+/// # Arguments
+/// * `path` - path to fixture file
+pub fn open(path: &str) -> Result<(), AppvkekError> {
+    let mut fixture = FIXTURE.lock().map_err(|_| AppvkekError::Config("Error locking fixture".to_owned()))?;
+    if fixture.is_some() {
+        return Ok(());
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Config(format!("Error reading fixture ({}); err={}", path, e))),
+    };
+    *fixture = Some(parse_fixture(&content).map_err(|e| AppvkekError::Config(format!("Error parsing fixture ({}); {}", path, e)))?);
+    IS_REPLAYING.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Return whether responses are served from fixture.
+pub fn is_replaying() -> bool {
+    IS_REPLAYING.load(Ordering::Relaxed)
+}
+
+/// Parse fixture from content of dump file.
+///
+/// # Arguments
+/// * `content` - JSON lines of dump entries
+fn parse_fixture(content: &str) -> Result<Fixture, String> {
+    let mut fixture = Fixture::default();
+    for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let entry = match serde_json::from_str::<DumpEntry>(line) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("line {}; err={}", i + 1, e)),
+        };
+        let (responses, key) = match entry.kind.as_str() {
+            "rpc" => (&mut fixture.rpc, rpc_key(&entry.request)),
+            "explorer" => (&mut fixture.explorer, entry.request.as_str().unwrap_or_default().to_owned()),
+            kind => return Err(format!("line {}; err=unknown kind '{}'", i + 1, kind)),
+        };
+        responses.entry(key).or_default().entries.push(entry);
+    }
+    Ok(fixture)
+}
+
+/// Return recorded result of the specified JSON-RPC request. It's an error
+/// if there is none.
+///
+/// # Arguments
+/// * `call` - JSON-RPC request
+pub(crate) fn rpc_response(call: &Call) -> web3::error::Result<Value> {
+    let request = serde_json::to_value(call).unwrap_or(Value::Null);
+    let key = rpc_key(&request);
+    let mut fixture = FIXTURE.lock().map_err(|_| web3::error::Error::Internal)?;
+    let entry = match fixture.as_mut().and_then(|f| f.rpc.get_mut(&key)) {
+        Some(res) => res.next(),
+        None => return Err(web3::error::Error::Transport(web3::error::TransportError::Message(format!("no recorded response for {}", key)))),
+    };
+    match (&entry.response, &entry.error) {
+        (_, Some(e)) => Err(web3::error::Error::Transport(web3::error::TransportError::Message(e.to_owned()))),
+        (Some(value), None) => Ok(value.clone()),
+        (None, None) => Ok(Value::Null),
+    }
+}
+
+/// Return recorded HTTP status, and body of the specified explorer API call.
+/// It's an error if there is none.
+///
+/// # Arguments
+/// * `url` - URL of API call
+pub(crate) fn explorer_response(url: &str) -> Result<(u16, String), AppvkekError> {
+    let key = debugdump::redact_url(url);
+    let mut fixture = FIXTURE.lock().map_err(|_| AppvkekError::Explorer("Error locking fixture".to_owned()))?;
+    let entry = match fixture.as_mut().and_then(|f| f.explorer.get_mut(&key)) {
+        Some(res) => res.next(),
+        None => return Err(AppvkekError::Explorer(format!("Error no recorded response for {}", key))),
+    };
+    if let Some(e) = &entry.error {
+        return Err(AppvkekError::Explorer(e.to_owned()));
+    }
+    // body which is not JSON is recorded as is
+    let body = match &entry.response {
+        Some(Value::String(body)) => body.to_owned(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    Ok((entry.status.unwrap_or(200), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fixture_keys_requests_regardless_of_id_and_api_key() {
+        let content = r#"{"time":1,"kind":"rpc","request":{"id":0,"jsonrpc":"2.0","method":"eth_getCode","params":["0x11","latest"]},"response":"0x"}
+{"time":2,"kind":"rpc","request":{"id":7,"jsonrpc":"2.0","method":"eth_getCode","params":["0x11","latest"]},"response":"0x60"}
+{"time":3,"kind":"explorer","request":"https://api.bscscan.com/api?module=account&apikey=<redacted>","response":{"status":"1"},"status":200}
+"#;
+        let mut fixture = parse_fixture(content).unwrap();
+
+        let call: Call = serde_json::from_str(r#"{"id":42,"jsonrpc":"2.0","method":"eth_getCode","params":["0x11","latest"]}"#).unwrap();
+        let responses = fixture.rpc.get_mut(&rpc_key(&serde_json::to_value(&call).unwrap())).unwrap();
+        assert_eq!(responses.next().response, Some(Value::from("0x")));
+        assert_eq!(responses.next().response, Some(Value::from("0x60")));
+        assert_eq!(responses.next().response, Some(Value::from("0x60")));
+
+        let key = debugdump::redact_url("https://api.bscscan.com/api?module=account&apikey=OTHER");
+        assert_eq!(fixture.explorer[&key].entries[0].status, Some(200));
+    }
+}
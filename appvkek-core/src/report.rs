@@ -0,0 +1,685 @@
+//! Machine-readable output of a scan.
+//!
+//! Types in this module define the JSON, and CSV output of `appvkek export`.
+//! Their layout is versioned by `SCHEMA_VERSION`; within the same version,
+//! fields are only ever added, never renamed, removed, or changed in meaning.
+//! Consumers should ignore fields they don't know about.
+//!
+//! Since version 2, all addresses are EIP-55 checksummed.
+
+use ::evmscan::prelude::ChainType;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::error::ScanWarning;
+use crate::labels::{protocol_of, Labels};
+use crate::types::*;
+use crate::usage::Usage;
+use crate::util::*;
+
+/// Version of output schema. It is bumped whenever an existing field is
+/// renamed, removed, or changes its meaning.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Full report of a scan of a single wallet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    /// Version of output schema; see `SCHEMA_VERSION`
+    pub schema_version: u32,
+
+    /// Chain name e.g. `bsc`
+    pub chain: String,
+
+    /// Owner wallet address; EIP-55 checksummed
+    pub owner: String,
+
+    /// Unix timestamp (in seconds) of when the report was generated
+    pub generated_at: u64,
+
+    /// All approvals, riskiest first
+    pub approvals: Vec<ApprovalEntry>,
+
+    /// Token contracts which failed to be queried
+    pub errors: Vec<ErrorEntry>,
+
+    /// Transactions skipped while collecting approvals; only set in output
+    /// of `export`
+    #[serde(default)]
+    pub warnings: Vec<WarningEntry>,
+
+    /// Block number which allowances were queried at; `None` if the latest
+    /// block
+    #[serde(default)]
+    pub at_block: Option<u64>,
+
+    /// Address of contract which owner has delegated to via EIP-7702;
+    /// EIP-55 checksummed. `None` if owner has no active delegation
+    #[serde(default)]
+    pub delegation: Option<String>,
+
+    /// Usage of explorer API, and RPC endpoints made by the run; only set in
+    /// output of `export`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// A single approval of a spender on a token contract.
+/// This is also a single row of CSV output, with columns in field order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalEntry {
+    /// Version of output schema; see `SCHEMA_VERSION`
+    pub schema_version: u32,
+
+    /// Chain name e.g. `bsc`
+    pub chain: String,
+
+    /// Owner wallet address; EIP-55 checksummed
+    pub owner: String,
+
+    /// Token contract address; EIP-55 checksummed
+    pub token_address: String,
+
+    /// Token name
+    pub token_name: String,
+
+    /// Token symbol; empty if token doesn't implement it
+    pub token_symbol: String,
+
+    /// Spender address; EIP-55 checksummed
+    pub spender: String,
+
+    /// Label of spender if it's a well-known address
+    pub spender_label: Option<String>,
+
+    /// Current allowance in token unit i.e. divided by `10^decimals`
+    pub allowance: f64,
+
+    /// Whether the allowance is effectively unlimited
+    pub is_unlimited: bool,
+
+    /// Risk score; higher is riskier
+    pub risk_score: u32,
+
+    /// Factors contributing to risk score e.g. `unlimited`; `;`-separated in CSV
+    pub risk_reasons: Vec<String>,
+
+    /// Block number of the latest approve transaction
+    pub approval_block: u64,
+
+    /// Unix timestamp (in seconds) of the latest approve transaction
+    pub approval_timestamp: u64,
+
+    /// Implementation address if spender is a proxy contract; EIP-55 checksummed
+    pub implementation: Option<String>,
+
+    /// Primary ENS name of spender if any; only looked up on Ethereum
+    pub spender_ens: Option<String>,
+
+    /// Hash of the latest approve transaction; `None` if unknown as collected
+    /// by older versions until `--full-rescan`
+    pub approval_tx_hash: Option<String>,
+
+    /// URL of the latest approve transaction on explorer website
+    pub approval_tx_url: Option<String>,
+
+    /// Issue in calldata of the latest approve transaction which was
+    /// tolerated e.g. truncated arguments
+    #[serde(default)]
+    pub approval_issue: Option<String>,
+
+    /// Hash of ERC-4337 user operation which made the latest approval if
+    /// owner is a smart account; `approval_tx_hash` is then the bundle
+    /// transaction which executed it
+    #[serde(default)]
+    pub approval_user_op_hash: Option<String>,
+}
+
+/// Empty entry of the current schema version; base for building entries with
+/// struct update syntax e.g. in tests.
+impl Default for ApprovalEntry {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            chain: String::new(),
+            owner: String::new(),
+            token_address: String::new(),
+            token_name: String::new(),
+            token_symbol: String::new(),
+            spender: String::new(),
+            spender_label: None,
+            allowance: 0.0,
+            is_unlimited: false,
+            risk_score: 0,
+            risk_reasons: Vec::new(),
+            approval_block: 0,
+            approval_timestamp: 0,
+            implementation: None,
+            spender_ens: None,
+            approval_tx_hash: None,
+            approval_tx_url: None,
+            approval_issue: None,
+            approval_user_op_hash: None,
+        }
+    }
+}
+
+/// A token contract which failed to be queried.
+/// Fields are named after those of `ApprovalEntry`, and `WarningEntry` so
+/// that entries of a report share a vocabulary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorEntry {
+    /// Token contract address; EIP-55 checksummed
+    pub token_address: String,
+
+    /// Kind of error e.g. `reverted`; see `AppvkekError::kind()`
+    pub kind: String,
+
+    /// Human-readable error message
+    pub message: String,
+}
+
+/// A transaction which was skipped while collecting approvals e.g. as its
+/// calldata can't be parsed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarningEntry {
+    /// Hash of transaction
+    pub tx_hash: String,
+
+    /// Kind of error e.g. `decode`; see `AppvkekError::kind()`
+    pub kind: String,
+
+    /// Human-readable error message
+    pub message: String,
+}
+
+impl From<&ScanWarning> for WarningEntry {
+    fn from(warning: &ScanWarning) -> WarningEntry {
+        WarningEntry {
+            tx_hash: warning.tx_hash.to_owned(),
+            kind: warning.error.kind().to_owned(),
+            message: warning.error.to_string(),
+        }
+    }
+}
+
+/// Header row of CSV output; names of fields of `ApprovalEntry` in order.
+pub static CSV_HEADER: &str = "schema_version,chain,owner,token_address,token_name,token_symbol,spender,spender_label,allowance,is_unlimited,risk_score,risk_reasons,approval_block,approval_timestamp,implementation,spender_ens,approval_tx_hash,approval_tx_url,approval_issue,approval_user_op_hash";
+
+/// Build report from the specified results.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+/// * `results` - results from `query_all()` which may have been assessed by `risk::assess_all()`
+/// * `labels` - labels of well-known addresses
+pub fn build_report(chain: ChainType, owner_address: &str, results: &[QueryResult], labels: &Labels) -> Report {
+    let mut approvals: Vec<ApprovalEntry> = results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .flat_map(|r| r.spender_allowances.iter().map(move |(spender, sa)| ApprovalEntry {
+            schema_version: SCHEMA_VERSION,
+            chain: chain_name(chain).to_owned(),
+            owner: to_checksum_address(owner_address),
+            token_address: to_checksum_address(&r.address),
+            token_name: r.name.to_owned(),
+            token_symbol: r.symbol.to_owned(),
+            spender: to_checksum_address(spender),
+            spender_label: labels.get(spender).map(|l| l.to_owned()),
+            allowance: sa.allowance,
+            is_unlimited: sa.is_unlimited,
+            risk_score: sa.risk.score,
+            risk_reasons: sa.risk.reasons.to_owned(),
+            approval_block: sa.approval.block_number,
+            approval_timestamp: sa.approval.timestamp,
+            implementation: sa.proxy.as_ref().map(|p| to_checksum_address(&p.implementation)),
+            spender_ens: labels.ens_name(spender).map(|n| n.to_owned()),
+            approval_tx_hash: Some(sa.approval.tx_hash.to_owned()).filter(|h| !h.is_empty()),
+            approval_tx_url: Some(&sa.approval.tx_hash).filter(|h| !h.is_empty()).map(|h| explorer_tx_url(chain, h)),
+            approval_issue: sa.approval.issue.to_owned(),
+            approval_user_op_hash: sa.approval.user_op_hash.to_owned(),
+        }))
+        .collect();
+    approvals.sort_by(|a, b| b.risk_score.cmp(&a.risk_score).then_with(|| a.token_address.cmp(&b.token_address)).then_with(|| a.spender.cmp(&b.spender)));
+
+    let mut errors: Vec<ErrorEntry> = results.iter()
+        .filter_map(|res| res.as_ref().err())
+        .map(|e| ErrorEntry {
+            token_address: to_checksum_address(&e.token_address),
+            kind: e.error.kind().to_owned(),
+            message: e.error.to_string(),
+        })
+        .collect();
+    errors.sort_by(|a, b| a.token_address.cmp(&b.token_address));
+
+    Report {
+        schema_version: SCHEMA_VERSION,
+        chain: chain_name(chain).to_owned(),
+        owner: to_checksum_address(owner_address),
+        generated_at: unix_timestamp(),
+        approvals,
+        errors,
+        warnings: Vec::new(),
+        at_block: None,
+        delegation: None,
+        usage: None,
+    }
+}
+
+/// Approval whose allowance, or risk score has changed between two reports.
+#[derive(Debug, Clone)]
+pub struct ApprovalChange {
+    /// Approval as of the older report
+    pub before: ApprovalEntry,
+
+    /// Approval as of the newer report
+    pub after: ApprovalEntry,
+}
+
+/// Difference of approvals between two reports. Approvals with zero
+/// allowance are regarded as absent, so revoked ones are reported as removed.
+#[derive(Debug, Clone, Default)]
+pub struct ReportDiff {
+    /// Approvals only in the newer report
+    pub added: Vec<ApprovalEntry>,
+
+    /// Approvals only in the older report
+    pub removed: Vec<ApprovalEntry>,
+
+    /// Approvals in both whose allowance, or risk score has changed
+    pub changed: Vec<ApprovalChange>,
+}
+
+impl ReportDiff {
+    /// Return whether there is no difference.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Return key identifying an approval across reports regardless of case of
+/// addresses, as reports of schema version 1 have them lowercased.
+///
+/// # Arguments
+/// * `entry` - approval
+fn approval_key(entry: &ApprovalEntry) -> (String, String, String, String) {
+    (entry.chain.to_owned(), entry.owner.to_lowercase(), entry.token_address.to_lowercase(), entry.spender.to_lowercase())
+}
+
+/// Compare approvals of older reports against newer ones e.g. snapshots of
+/// the same wallets taken a month apart. Each side can have reports of
+/// multiple wallets.
+///
+/// # Arguments
+/// * `old` - older reports
+/// * `new` - newer reports
+pub fn diff_reports(old: &[Report], new: &[Report]) -> ReportDiff {
+    let index = |reports: &[Report]| -> HashMap<(String, String, String, String), ApprovalEntry> {
+        reports.iter()
+            .flat_map(|r| r.approvals.iter())
+            .filter(|a| a.allowance > 0.0)
+            .map(|a| (approval_key(a), a.clone()))
+            .collect()
+    };
+    let old_approvals = index(old);
+    let mut new_approvals = index(new);
+
+    let mut diff = ReportDiff::default();
+    for (key, before) in old_approvals {
+        match new_approvals.remove(&key) {
+            Some(after) => {
+                if before.allowance != after.allowance || before.risk_score != after.risk_score {
+                    diff.changed.push(ApprovalChange { before, after });
+                }
+            },
+            None => diff.removed.push(before),
+        }
+    }
+    diff.added = new_approvals.into_values().collect();
+
+    diff.added.sort_by_key(approval_key);
+    diff.removed.sort_by_key(approval_key);
+    diff.changed.sort_by_key(|c| approval_key(&c.after));
+    diff
+}
+
+/// Exposure to a single spender address aggregated across chains, as EVM
+/// chains share the same address space, and the same deployer e.g. of a
+/// drainer often operates at the same address on several of them.
+#[derive(Debug, Clone, Default)]
+pub struct SpenderExposure {
+    /// Spender address; EIP-55 checksummed
+    pub spender: String,
+
+    /// Label of spender if it's a well-known address on any of chains
+    pub spender_label: Option<String>,
+
+    /// Chain names the spender has non-zero allowance on; sorted
+    pub chains: Vec<String>,
+
+    /// Number of approvals with non-zero allowance across all chains
+    pub num_approvals: usize,
+
+    /// Number of those approvals which are effectively unlimited
+    pub num_unlimited: usize,
+
+    /// Highest risk score among those approvals
+    pub max_risk_score: u32,
+}
+
+/// Aggregate approvals with non-zero allowance of the specified reports by
+/// spender address regardless of chain, and return exposure to each spender;
+/// those on the most chains first, then riskiest first.
+///
+/// # Arguments
+/// * `reports` - reports of the same wallet on different chains
+pub fn aggregate_by_spender(reports: &[Report]) -> Vec<SpenderExposure> {
+    let mut exposures: HashMap<String, SpenderExposure> = HashMap::new();
+    for a in reports.iter().flat_map(|r| r.approvals.iter()).filter(|a| a.allowance > 0.0) {
+        let exposure = exposures.entry(a.spender.to_lowercase()).or_insert_with(|| SpenderExposure {
+            spender: to_checksum_address(&a.spender.to_lowercase()),
+            ..Default::default()
+        });
+        if exposure.spender_label.is_none() {
+            exposure.spender_label = a.spender_label.to_owned();
+        }
+        if !exposure.chains.contains(&a.chain) {
+            exposure.chains.push(a.chain.to_owned());
+        }
+        exposure.num_approvals += 1;
+        if a.is_unlimited {
+            exposure.num_unlimited += 1;
+        }
+        exposure.max_risk_score = exposure.max_risk_score.max(a.risk_score);
+    }
+
+    let mut exposures: Vec<SpenderExposure> = exposures.into_values().collect();
+    for e in exposures.iter_mut() {
+        e.chains.sort();
+    }
+    exposures.sort_by(|a, b| b.chains.len().cmp(&a.chains.len())
+        .then_with(|| b.max_risk_score.cmp(&a.max_risk_score))
+        .then_with(|| b.num_approvals.cmp(&a.num_approvals))
+        .then_with(|| a.spender.cmp(&b.spender)));
+    exposures
+}
+
+/// Exposure to a single protocol i.e. all of its well-known spender
+/// contracts, as security reviews are conducted per protocol rather than per
+/// contract.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolExposure {
+    /// Protocol name e.g. `Uniswap` as of `labels::protocol_of()`; `None` for
+    /// spenders which aren't well-known
+    pub protocol: Option<String>,
+
+    /// Number of spender contracts with non-zero allowance
+    pub num_spenders: usize,
+
+    /// Number of approvals with non-zero allowance
+    pub num_approvals: usize,
+
+    /// Number of those approvals which are effectively unlimited
+    pub num_unlimited: usize,
+
+    /// Highest risk score among those approvals
+    pub max_risk_score: u32,
+
+    /// Approvals with non-zero allowance; by chain, token, then spender
+    pub approvals: Vec<ApprovalEntry>,
+}
+
+/// Aggregate approvals with non-zero allowance of the specified reports by
+/// protocol of their spender's label, and return exposure to each protocol;
+/// riskiest first, then those with the most approvals. Spenders which aren't
+/// well-known are grouped together last among equals.
+///
+/// # Arguments
+/// * `reports` - reports to aggregate
+pub fn aggregate_by_protocol(reports: &[Report]) -> Vec<ProtocolExposure> {
+    let mut exposures: HashMap<Option<String>, ProtocolExposure> = HashMap::new();
+    for a in reports.iter().flat_map(|r| r.approvals.iter()).filter(|a| a.allowance > 0.0) {
+        let protocol = a.spender_label.as_deref().map(|l| protocol_of(l).to_owned());
+        let exposure = exposures.entry(protocol.clone()).or_insert_with(|| ProtocolExposure {
+            protocol,
+            ..Default::default()
+        });
+        exposure.num_approvals += 1;
+        if a.is_unlimited {
+            exposure.num_unlimited += 1;
+        }
+        exposure.max_risk_score = exposure.max_risk_score.max(a.risk_score);
+        exposure.approvals.push(a.clone());
+    }
+
+    let mut exposures: Vec<ProtocolExposure> = exposures.into_values().collect();
+    for e in exposures.iter_mut() {
+        e.num_spenders = e.approvals.iter().map(|a| a.spender.to_lowercase()).collect::<HashSet<String>>().len();
+        e.approvals.sort_by_cached_key(|a| (a.chain.to_owned(), a.token_address.to_lowercase(), a.spender.to_lowercase()));
+    }
+    exposures.sort_by(|a, b| b.max_risk_score.cmp(&a.max_risk_score)
+        .then_with(|| b.num_approvals.cmp(&a.num_approvals))
+        .then_with(|| a.protocol.is_none().cmp(&b.protocol.is_none()))
+        .then_with(|| a.protocol.cmp(&b.protocol)));
+    exposures
+}
+
+/// Summary of approvals of a wallet to a single spender across all tokens,
+/// so that a spender approved on many tokens e.g. Permit2 is seen as one.
+#[derive(Debug, Clone, Default)]
+pub struct SpenderSummary {
+    /// Spender address; EIP-55 checksummed
+    pub spender: String,
+
+    /// Label of spender if it's a well-known address
+    pub spender_label: Option<String>,
+
+    /// Number of tokens approved with non-zero allowance
+    pub num_tokens: usize,
+
+    /// Number of those approvals which are effectively unlimited
+    pub num_unlimited: usize,
+
+    /// Highest risk score among those approvals
+    pub max_risk_score: u32,
+
+    /// Total USD value the spender can take i.e. allowance capped at owner's
+    /// balance of each token; only of tokens whose value is known
+    pub exposure_usd: f64,
+
+    /// Number of tokens whose value is unknown as either balance, or price
+    /// isn't known, so that they're not in `exposure_usd`
+    pub num_unpriced: usize,
+}
+
+/// Summarize approvals with non-zero allowance of the specified report by
+/// spender, and return summary of each spender; the most exposed first, then
+/// those with the most tokens.
+///
+/// # Arguments
+/// * `report` - report of a single wallet
+/// * `balances` - balance of owner in token unit keyed by lowercased token address
+/// * `prices` - USD price keyed by lowercased token address
+pub fn summarize_by_spender(report: &Report, balances: &HashMap<String, f64>, prices: &HashMap<String, f64>) -> Vec<SpenderSummary> {
+    let mut summaries: HashMap<String, SpenderSummary> = HashMap::new();
+    for a in report.approvals.iter().filter(|a| a.allowance > 0.0) {
+        let summary = summaries.entry(a.spender.to_lowercase()).or_insert_with(|| SpenderSummary {
+            spender: to_checksum_address(&a.spender.to_lowercase()),
+            spender_label: a.spender_label.to_owned(),
+            ..Default::default()
+        });
+        summary.num_tokens += 1;
+        if a.is_unlimited {
+            summary.num_unlimited += 1;
+        }
+        summary.max_risk_score = summary.max_risk_score.max(a.risk_score);
+
+        let token_address = a.token_address.to_lowercase();
+        match crate::prices::exposure_usd(a.allowance, balances.get(&token_address).copied(), prices.get(&token_address).copied()) {
+            Some(value) => summary.exposure_usd += value,
+            None => summary.num_unpriced += 1,
+        }
+    }
+
+    let mut summaries: Vec<SpenderSummary> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| b.exposure_usd.total_cmp(&a.exposure_usd)
+        .then_with(|| b.num_tokens.cmp(&a.num_tokens))
+        .then_with(|| b.max_risk_score.cmp(&a.max_risk_score))
+        .then_with(|| a.spender.cmp(&b.spender)));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_header_matches_approval_entry_fields() {
+        let entry = ApprovalEntry {
+            chain: "bsc".to_owned(),
+            ..Default::default()
+        };
+
+        // serialized fields are in order of declaration
+        let value = serde_json::to_string(&entry).unwrap();
+        let fields: Vec<&str> = regex::Regex::new(r#""(\w+)":"#).unwrap()
+            .captures_iter(&value)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect();
+        assert_eq!(fields.join(","), CSV_HEADER);
+    }
+
+    #[test]
+    fn diff_reports_ignores_case_and_zero_allowance() {
+        let entry = |token: &str, spender: &str, allowance: f64| -> ApprovalEntry {
+            ApprovalEntry {
+                chain: "bsc".to_owned(),
+                owner: "0x1111111111111111111111111111111111111111".to_owned(),
+                token_address: token.to_owned(),
+                spender: spender.to_owned(),
+                allowance,
+                ..Default::default()
+            }
+        };
+        let report = |approvals: Vec<ApprovalEntry>| -> Report {
+            let mut report = build_report(ChainType::BSC, "0x1111111111111111111111111111111111111111", &[], &Labels::default());
+            report.approvals = approvals;
+            report
+        };
+        let (token, spender, other) = ("0x10ed43c718714eb63d5aa57b78b54704e256024e", "0x2222222222222222222222222222222222222222", "0x3333333333333333333333333333333333333333");
+
+        let old = report(vec![entry(token, spender, 10.0), entry(token, other, 5.0)]);
+        let new = report(vec![entry(&to_checksum_address(token), spender, 20.0), entry(token, other, 0.0), entry(other, spender, 1.0)]);
+        let diff = diff_reports(&[old], &[new]);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].token_address, other);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].spender, other);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].after.allowance, 20.0);
+    }
+
+    #[test]
+    fn aggregate_by_spender_merges_chains() {
+        let entry = |chain: &str, spender: &str, allowance: f64, risk_score: u32| -> ApprovalEntry {
+            ApprovalEntry {
+                chain: chain.to_owned(),
+                owner: "0x1111111111111111111111111111111111111111".to_owned(),
+                token_address: "0x10ed43c718714eb63d5aa57b78b54704e256024e".to_owned(),
+                spender: spender.to_owned(),
+                allowance,
+                is_unlimited: allowance > 100.0,
+                risk_score,
+                ..Default::default()
+            }
+        };
+        let report = |chain: ChainType, approvals: Vec<ApprovalEntry>| -> Report {
+            let mut report = build_report(chain, "0x1111111111111111111111111111111111111111", &[], &Labels::default());
+            report.approvals = approvals;
+            report
+        };
+        let (drainer, other) = ("0x10ed43c718714eb63d5aa57b78b54704e256024e", "0x2222222222222222222222222222222222222222");
+
+        let bsc = report(ChainType::BSC, vec![entry("bsc", drainer, 1000.0, 50), entry("bsc", other, 1.0, 90)]);
+        let ethereum = report(ChainType::Ethereum, vec![entry("ethereum", &to_checksum_address(drainer), 1.0, 30), entry("ethereum", other, 0.0, 0)]);
+        let exposures = aggregate_by_spender(&[bsc, ethereum]);
+
+        assert_eq!(exposures.len(), 2);
+        assert_eq!(exposures[0].spender, to_checksum_address(drainer));
+        assert_eq!(exposures[0].chains, vec!["bsc", "ethereum"]);
+        assert_eq!(exposures[0].num_approvals, 2);
+        assert_eq!(exposures[0].num_unlimited, 1);
+        assert_eq!(exposures[0].max_risk_score, 50);
+        assert_eq!(exposures[1].chains, vec!["bsc"]);
+    }
+
+    #[test]
+    fn aggregate_by_protocol_groups_contracts_of_protocol() {
+        let entry = |token: &str, spender: &str, label: Option<&str>, allowance: f64, risk_score: u32| -> ApprovalEntry {
+            ApprovalEntry {
+                chain: "ethereum".to_owned(),
+                owner: "0x1111111111111111111111111111111111111111".to_owned(),
+                token_address: token.to_owned(),
+                spender: spender.to_owned(),
+                spender_label: label.map(|l| l.to_owned()),
+                allowance,
+                is_unlimited: allowance > 100.0,
+                risk_score,
+                ..Default::default()
+            }
+        };
+        let (token, other_token) = ("0x2222222222222222222222222222222222222222", "0x3333333333333333333333333333333333333333");
+        let (v2, v3, aave, unknown) = ("0x4444444444444444444444444444444444444444", "0x5555555555555555555555555555555555555555", "0x6666666666666666666666666666666666666666", "0x7777777777777777777777777777777777777777");
+
+        let mut report = build_report(ChainType::Ethereum, "0x1111111111111111111111111111111111111111", &[], &Labels::default());
+        report.approvals = vec![
+            entry(other_token, v3, Some("Uniswap V3 Router"), 1000.0, 10),
+            entry(token, v2, Some("Uniswap V2 Router"), 5.0, 0),
+            entry(token, v3, Some("Uniswap V3 Router"), 0.0, 0),
+            entry(token, aave, Some("Aave V3 Pool"), 1.0, 10),
+            entry(token, unknown, None, 1.0, 10),
+        ];
+        let exposures = aggregate_by_protocol(&[report]);
+
+        assert_eq!(exposures.iter().map(|e| e.protocol.as_deref()).collect::<Vec<_>>(), vec![Some("Uniswap"), Some("Aave"), None]);
+        let uniswap = &exposures[0];
+        assert_eq!((uniswap.num_spenders, uniswap.num_approvals, uniswap.num_unlimited, uniswap.max_risk_score), (2, 2, 1, 10));
+        assert_eq!(uniswap.approvals[0].token_address, token);
+        assert_eq!(uniswap.approvals[1].token_address, other_token);
+    }
+
+    #[test]
+    fn summarize_by_spender_sorts_by_exposure() {
+        let entry = |token: &str, spender: &str, allowance: f64| -> ApprovalEntry {
+            ApprovalEntry {
+                chain: "bsc".to_owned(),
+                owner: "0x1111111111111111111111111111111111111111".to_owned(),
+                token_address: to_checksum_address(token),
+                spender: to_checksum_address(spender),
+                allowance,
+                is_unlimited: allowance > 100.0,
+                ..Default::default()
+            }
+        };
+        let (token, other_token, unpriced_token) = ("0x2222222222222222222222222222222222222222", "0x3333333333333333333333333333333333333333", "0x4444444444444444444444444444444444444444");
+        let (router, other) = ("0x5555555555555555555555555555555555555555", "0x6666666666666666666666666666666666666666");
+
+        let mut report = build_report(ChainType::BSC, "0x1111111111111111111111111111111111111111", &[], &Labels::default());
+        report.approvals = vec![
+            entry(token, router, 1000.0),
+            entry(other_token, router, 1.0),
+            entry(unpriced_token, router, 1000.0),
+            entry(token, other, 0.0),
+            entry(other_token, other, 1000.0),
+        ];
+        let balances = HashMap::from([(token.to_owned(), 10.0), (other_token.to_owned(), 100.0), (unpriced_token.to_owned(), 10.0)]);
+        let prices = HashMap::from([(token.to_owned(), 2.0), (other_token.to_owned(), 1.0)]);
+        let summaries = summarize_by_spender(&report, &balances, &prices);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].spender, to_checksum_address(other));
+        assert_eq!((summaries[0].num_tokens, summaries[0].exposure_usd), (1, 100.0));
+        assert_eq!(summaries[1].spender, to_checksum_address(router));
+        assert_eq!((summaries[1].num_tokens, summaries[1].num_unlimited, summaries[1].num_unpriced), (3, 2, 1));
+        assert_eq!(summaries[1].exposure_usd, 21.0);
+    }
+}
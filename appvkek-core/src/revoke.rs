@@ -0,0 +1,663 @@
+use futures::future::join_all;
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+use web3::ethabi::{self, Token};
+use web3::signing::{keccak256, recover};
+use web3::types::{Address, U256};
+
+use crate::erc20::{decode_string, APPROVE_METHOD_ID, NAME_METHOD_ID};
+use crate::error::AppvkekError;
+use crate::fees::Fees;
+use crate::mempool::bump_fee;
+use crate::provider::ChainRpc;
+use crate::util::*;
+
+/// Method-id of `paused()` of Pausable contract
+static PAUSED_METHOD_ID: [u8; 4] = [0x5c, 0x97, 0x5a, 0xbb];
+
+/// Method-ids of common getters telling whether an address is blacklisted;
+/// `isBlacklisted(address)` of USDC, `isBlackListed(address)` of USDT, and
+/// `isFrozen(address)` of PAX-style tokens
+static BLACKLIST_METHOD_IDS: [[u8; 4]; 3] = [
+    [0xfe, 0x57, 0x5a, 0x87],
+    [0xe4, 0x7d, 0x60, 0x60],
+    [0xe5, 0x83, 0x98, 0x36],
+];
+
+/// Method-id of `DOMAIN_SEPARATOR()` of EIP-2612
+static DOMAIN_SEPARATOR_METHOD_ID: [u8; 4] = [0x36, 0x44, 0xe5, 0x15];
+
+/// Method-id of `nonces(address)` of EIP-2612
+static NONCES_METHOD_ID: [u8; 4] = [0x7e, 0xce, 0xbe, 0x00];
+
+/// Method-id of `version()` as exposed by EIP-712 domain of most tokens
+static VERSION_METHOD_ID: [u8; 4] = [0x54, 0xfd, 0x4d, 0x50];
+
+/// Method-id of `PERMIT_TYPEHASH()` as exposed by some tokens
+static PERMIT_TYPEHASH_METHOD_ID: [u8; 4] = [0x30, 0xad, 0xf8, 0x1f];
+
+/// Method-id of `permit(address,address,uint256,uint256,uint8,bytes32,bytes32)`
+/// of EIP-2612
+static PERMIT_METHOD_ID: [u8; 4] = [0xd5, 0x05, 0xac, 0xcf];
+
+/// Type of EIP-712 domain of EIP-2612 tokens
+static EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// Type of EIP-2612 permit message
+static PERMIT_TYPE: &str = "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+
+/// Unsigned transaction which revokes an approval by setting its allowance
+/// to zero.
+#[derive(Debug, Clone)]
+pub struct RevokeTx {
+    /// Token contract address; destination of transaction
+    pub token_address: String,
+
+    /// Spender address whose allowance is revoked
+    pub spender: String,
+
+    /// Calldata of `approve(spender, 0)`; hex string prefixed with '0x'
+    pub data: String,
+}
+
+impl RevokeTx {
+    /// Return EIP-681 URI of this transaction on the specified chain e.g.
+    /// `ethereum:0x...@56/approve?address=0x...&uint256=0`, which mobile
+    /// wallets open as a transaction to sign when scanned as QR code.
+    ///
+    /// # Arguments
+    /// * `chain_id` - EIP-155 chain id
+    pub fn to_eip681_uri(&self, chain_id: u64) -> String {
+        format!("ethereum:{}@{}/approve?address={}&uint256=0", to_checksum_address(&self.token_address), chain_id, to_checksum_address(&self.spender))
+    }
+}
+
+/// Build unsigned transaction which revokes approval of the specified spender
+/// on the specified token contract.
+///
+/// # Arguments
+/// * `token_address` - token contract address
+/// * `spender` - spender address to revoke approval of
+pub fn build_revoke_tx(token_address: &str, spender: &str) -> Result<RevokeTx, AppvkekError> {
+    get_address_from_str(token_address)?;
+    let spender_address = get_address_from_str(spender)?;
+
+    let mut data = APPROVE_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[Token::Address(spender_address), Token::Uint(U256::zero())]));
+
+    Ok(RevokeTx {
+        token_address: token_address.to_lowercase(),
+        spender: spender.to_lowercase(),
+        data: format!("0x{}", hex::encode(data)),
+    })
+}
+
+/// EIP-712 domain of token contract supporting EIP-2612 permit, along with
+/// the current permit nonce of owner wallet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermitDomain {
+    /// Token contract address; lowercased
+    pub token_address: String,
+
+    /// Name of domain; name of token
+    pub name: String,
+
+    /// Version of domain; `1` unless token tells otherwise via `version()`
+    pub version: String,
+
+    /// EIP-155 chain id
+    pub chain_id: u64,
+
+    /// Current permit nonce of owner wallet
+    pub nonce: U256,
+
+    /// Domain separator as returned by `DOMAIN_SEPARATOR()`
+    pub separator: [u8; 32],
+}
+
+/// Compute EIP-712 domain separator of EIP-2612 token.
+///
+/// # Arguments
+/// * `name` - name of domain
+/// * `version` - version of domain
+/// * `chain_id` - EIP-155 chain id
+/// * `token_address` - token contract address
+fn domain_separator(name: &str, version: &str, chain_id: u64, token_address: Address) -> [u8; 32] {
+    keccak256(&ethabi::encode(&[
+        Token::FixedBytes(keccak256(EIP712_DOMAIN_TYPE.as_bytes()).to_vec()),
+        Token::FixedBytes(keccak256(name.as_bytes()).to_vec()),
+        Token::FixedBytes(keccak256(version.as_bytes()).to_vec()),
+        Token::Uint(U256::from(chain_id)),
+        Token::Address(token_address),
+    ]))
+}
+
+/// Call a getter of token contract. Return `None` if contract doesn't have
+/// it i.e. reverted.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `token_address` - token contract address
+/// * `data` - calldata
+async fn call_optional_getter<R: ChainRpc + ?Sized>(rpc: &R, token_address: Address, data: Vec<u8>) -> Result<Option<Vec<u8>>, AppvkekError> {
+    match rpc.call(token_address, data, None).await {
+        Ok(output) => Ok(Some(output)),
+        Err(AppvkekError::Reverted(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Query EIP-712 domain of the specified token contract, and permit nonce of
+/// owner wallet. Return `None` if the token doesn't support EIP-2612 permit,
+/// or its domain can't be reconstructed e.g. it has `salt`, or its permit
+/// differs from EIP-2612 as of DAI, so that signature made for it would be
+/// rejected.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `token_address` - token contract address
+/// * `owner_address` - owner wallet address
+/// * `chain_id` - EIP-155 chain id
+pub async fn query_permit_domain<R: ChainRpc + ?Sized>(rpc: &R, token_address: &str, owner_address: &str, chain_id: u64) -> Result<Option<PermitDomain>, AppvkekError> {
+    let token = get_address_from_str(token_address)?;
+    let owner = get_address_from_str(owner_address)?;
+    let context = |e: AppvkekError| e.with_context(&format!("Error querying permit domain of token contract {}", token_address));
+
+    let separator = match call_optional_getter(rpc, token, DOMAIN_SEPARATOR_METHOD_ID.to_vec()).await.map_err(context)? {
+        Some(output) if output.len() == 32 => output,
+        _ => return Ok(None),
+    };
+    let mut data = NONCES_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[Token::Address(owner)]));
+    let nonce = match call_optional_getter(rpc, token, data).await.map_err(context)? {
+        Some(output) if output.len() == 32 => U256::from_big_endian(&output),
+        _ => return Ok(None),
+    };
+    if let Some(output) = call_optional_getter(rpc, token, PERMIT_TYPEHASH_METHOD_ID.to_vec()).await.map_err(context)? {
+        if output.len() == 32 && output != keccak256(PERMIT_TYPE.as_bytes()) {
+            return Ok(None);
+        }
+    }
+    let name = match call_optional_getter(rpc, token, NAME_METHOD_ID.to_vec()).await.map_err(context)?.and_then(|output| decode_string(&output)) {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    let version = call_optional_getter(rpc, token, VERSION_METHOD_ID.to_vec()).await.map_err(context)?
+        .and_then(|output| decode_string(&output))
+        .unwrap_or_else(|| "1".to_owned());
+
+    if domain_separator(&name, &version, chain_id, token)[..] != separator[..] {
+        return Ok(None);
+    }
+
+    let mut ret = [0u8; 32];
+    ret.copy_from_slice(&separator);
+    Ok(Some(PermitDomain {
+        token_address: token_address.to_lowercase(),
+        name,
+        version,
+        chain_id,
+        nonce,
+        separator: ret,
+    }))
+}
+
+/// Query permit domains of all the specified token contracts concurrently.
+/// Token contracts failed to be queried are warned, and left out.
+/// Return map of token contract address (lowercased) to its permit domain;
+/// only those supporting EIP-2612 permit are included.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore limiting concurrent requests
+/// * `token_addresses` - token contract addresses
+/// * `owner_address` - owner wallet address
+/// * `chain_id` - EIP-155 chain id
+pub async fn query_permit_domains<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, token_addresses: &[String], owner_address: &str, chain_id: u64) -> HashMap<String, PermitDomain> {
+    let results = join_all(token_addresses.iter().map(|t| with_permit(semaphore, query_permit_domain(rpc, t, owner_address, chain_id)))).await;
+
+    let mut domains = HashMap::new();
+    for (token_address, res) in token_addresses.iter().zip(results) {
+        match res {
+            Ok(Some(domain)) => {
+                domains.insert(token_address.to_lowercase(), domain);
+            },
+            Ok(None) => (),
+            Err(e) => {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+    domains
+}
+
+/// Build EIP-712 typed data of permit which sets allowance of the specified
+/// spender to zero, to be signed by owner wallet via `eth_signTypedData_v4`.
+///
+/// # Arguments
+/// * `domain` - permit domain of token contract
+/// * `owner_address` - owner wallet address
+/// * `spender` - spender address to revoke approval of
+/// * `deadline` - Unix timestamp (in seconds) after which permit is rejected
+pub fn build_permit_typed_data(domain: &PermitDomain, owner_address: &str, spender: &str, deadline: u64) -> serde_json::Value {
+    serde_json::json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            "Permit": [
+                { "name": "owner", "type": "address" },
+                { "name": "spender", "type": "address" },
+                { "name": "value", "type": "uint256" },
+                { "name": "nonce", "type": "uint256" },
+                { "name": "deadline", "type": "uint256" },
+            ],
+        },
+        "primaryType": "Permit",
+        "domain": {
+            "name": domain.name,
+            "version": domain.version,
+            "chainId": domain.chain_id,
+            "verifyingContract": to_checksum_address(&domain.token_address),
+        },
+        "message": {
+            "owner": to_checksum_address(owner_address),
+            "spender": to_checksum_address(spender),
+            "value": "0",
+            "nonce": domain.nonce.to_string(),
+            "deadline": deadline.to_string(),
+        },
+    })
+}
+
+/// Build transaction which revokes approval of the specified spender via
+/// EIP-2612 permit signed by owner wallet. As anyone can submit it, it can be
+/// relayed on behalf of owner wallet which has no native token for gas.
+/// Return error if signature isn't made by owner wallet for the permit, as
+/// such transaction would revert.
+///
+/// # Arguments
+/// * `domain` - permit domain of token contract
+/// * `owner_address` - owner wallet address
+/// * `spender` - spender address to revoke approval of
+/// * `deadline` - deadline as signed
+/// * `signature` - 65-byte signature as returned by `eth_signTypedData_v4`;
+///   hex string with or without '0x' prefix
+pub fn build_permit_revoke_tx(domain: &PermitDomain, owner_address: &str, spender: &str, deadline: u64, signature: &str) -> Result<RevokeTx, AppvkekError> {
+    let owner = get_address_from_str(owner_address)?;
+    let spender_address = get_address_from_str(spender)?;
+    let signature = match hex::decode(signature.trim_start_matches("0x")) {
+        Ok(res) if res.len() == 65 => res,
+        _ => return Err(AppvkekError::Decode("Error signature must be 65 bytes in hex".to_owned())),
+    };
+    let v = match signature[64] {
+        v @ (27 | 28) => v,
+        v @ (0 | 1) => v + 27,
+        v => return Err(AppvkekError::Decode(format!("Error invalid recovery id of signature ({})", v))),
+    };
+
+    let struct_hash = keccak256(&ethabi::encode(&[
+        Token::FixedBytes(keccak256(PERMIT_TYPE.as_bytes()).to_vec()),
+        Token::Address(owner),
+        Token::Address(spender_address),
+        Token::Uint(U256::zero()),
+        Token::Uint(domain.nonce),
+        Token::Uint(U256::from(deadline)),
+    ]));
+    let mut message = vec![0x19, 0x01];
+    message.extend(domain.separator);
+    message.extend(struct_hash);
+    match recover(&keccak256(&message), &signature[..64], (v - 27) as i32) {
+        Ok(signer) if signer == owner => (),
+        _ => return Err(AppvkekError::Decode(format!("Error signature is not made by {} for permit of token contract {} with nonce {}, and deadline {}", to_checksum_address(owner_address), to_checksum_address(&domain.token_address), domain.nonce, deadline))),
+    }
+
+    let mut data = PERMIT_METHOD_ID.to_vec();
+    data.extend(ethabi::encode(&[
+        Token::Address(owner),
+        Token::Address(spender_address),
+        Token::Uint(U256::zero()),
+        Token::Uint(U256::from(deadline)),
+        Token::Uint(U256::from(v)),
+        Token::FixedBytes(signature[..32].to_vec()),
+        Token::FixedBytes(signature[32..64].to_vec()),
+    ]));
+
+    Ok(RevokeTx {
+        token_address: domain.token_address.to_owned(),
+        spender: spender.to_lowercase(),
+        data: format!("0x{}", hex::encode(data)),
+    })
+}
+
+/// Restrictions of a token contract which can make revoke of its approvals
+/// revert.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenRestriction {
+    /// Whether token contract is paused
+    pub is_paused: bool,
+
+    /// Whether token contract has blacklisted the owner wallet
+    pub is_owner_blacklisted: bool,
+}
+
+impl TokenRestriction {
+    /// Whether any restriction applies.
+    pub fn is_restricted(&self) -> bool {
+        self.is_paused || self.is_owner_blacklisted
+    }
+}
+
+/// Call a getter returning `bool`.
+/// Return `false` if contract doesn't have it i.e. reverted, or returned
+/// something else.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `token_address` - token contract address
+/// * `data` - calldata
+async fn call_bool_getter<R: ChainRpc + ?Sized>(rpc: &R, token_address: &str, data: Vec<u8>) -> Result<bool, AppvkekError> {
+    match rpc.call(get_address_from_str(token_address)?, data, None).await {
+        Ok(output) if output.len() == 32 => Ok(U256::from_big_endian(&output) == U256::one()),
+        Ok(_) | Err(AppvkekError::Reverted(_)) => Ok(false),
+        Err(e) => Err(e.with_context(&format!("Error checking restriction of token contract {}", token_address))),
+    }
+}
+
+/// Check whether the specified token contract is paused, or has blacklisted
+/// the owner wallet via common getters. Token contract without such getters
+/// is treated as not restricted.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `token_address` - token contract address
+/// * `owner_address` - owner wallet address
+pub async fn check_restriction<R: ChainRpc + ?Sized>(rpc: &R, token_address: &str, owner_address: &str) -> Result<TokenRestriction, AppvkekError> {
+    let owner = get_address_from_str(owner_address)?;
+
+    let is_paused = call_bool_getter(rpc, token_address, PAUSED_METHOD_ID.to_vec()).await?;
+    let mut is_owner_blacklisted = false;
+    for method_id in BLACKLIST_METHOD_IDS.iter() {
+        let mut data = method_id.to_vec();
+        data.extend(ethabi::encode(&[Token::Address(owner)]));
+        if call_bool_getter(rpc, token_address, data).await? {
+            is_owner_blacklisted = true;
+            break;
+        }
+    }
+
+    Ok(TokenRestriction { is_paused, is_owner_blacklisted })
+}
+
+/// Check restrictions of all the specified token contracts concurrently.
+/// Token contracts failed to be checked are warned, and left out.
+/// Return map of token contract address (lowercased) to its restriction; only
+/// restricted ones are included.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore limiting concurrent requests
+/// * `token_addresses` - token contract addresses
+/// * `owner_address` - owner wallet address
+pub async fn check_restrictions<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, token_addresses: &[String], owner_address: &str) -> HashMap<String, TokenRestriction> {
+    let results = join_all(token_addresses.iter().map(|t| with_permit(semaphore, check_restriction(rpc, t, owner_address)))).await;
+
+    let mut restrictions = HashMap::new();
+    for (token_address, res) in token_addresses.iter().zip(results) {
+        match res {
+            Ok(restriction) if restriction.is_restricted() => {
+                restrictions.insert(token_address.to_lowercase(), restriction);
+            },
+            Ok(_) => (),
+            Err(e) => {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+    restrictions
+}
+
+/// Estimated cost of sending revoke transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevokeCost {
+    /// Estimated gas
+    pub gas: u64,
+
+    /// Estimated fee in native token at current gas price, or at fees to send
+    /// with; `None` if gas price couldn't be queried
+    pub fee: Option<f64>,
+}
+
+/// Estimate cost of sending each of the specified transactions from the
+/// specified owner wallet concurrently. Estimation fails if the transaction
+/// would revert, which is worth catching before it's sent.
+/// Return results in the same order as `txs`.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore limiting concurrent requests
+/// * `owner_address` - owner wallet address
+/// * `txs` - transactions to estimate
+/// * `fees` - fees to send transactions with; current gas price if `None`
+pub async fn estimate_revoke_costs<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, owner_address: &str, txs: &[RevokeTx], fees: Option<&Fees>) -> Vec<Result<RevokeCost, AppvkekError>> {
+    let owner = match get_address_from_str(owner_address) {
+        Ok(res) => res,
+        Err(e) => return txs.iter().map(|_| Err(e.clone())).collect(),
+    };
+    let gas_price = match fees {
+        Some(fees) => Some(fees.expected_gas_price()),
+        None => match with_permit(semaphore, rpc.gas_price()).await {
+            Ok(res) => Some(res),
+            Err(e) => {
+                tracing::warn!("{}", e);
+                None
+            }
+        },
+    };
+
+    join_all(txs.iter().map(|tx| async move {
+        let to = get_address_from_str(&tx.token_address)?;
+        let data = match hex::decode(tx.data.trim_start_matches("0x")) {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Decode(format!("Error decoding calldata ({}); err={}", tx.data, e))),
+        };
+        let gas = with_permit(semaphore, rpc.estimate_gas(owner, to, data)).await?;
+        Ok(RevokeCost {
+            gas,
+            fee: gas_price.map(|p| (p * U256::from(gas)).as_u128() as f64 / 1e18),
+        })
+    })).await
+}
+
+/// Nonces to send revoke transactions with from owner wallet, one after
+/// another in the order they are printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoncePlan {
+    /// Nonce of the first revoke transaction
+    pub next: u64,
+
+    /// Number of transactions of owner wallet pending in mempool; those
+    /// stuck e.g. underpriced hold back anything sent after them
+    pub num_pending: u64,
+
+    /// Number of the first revoke transactions which replace pending ones by
+    /// taking their nonces; 0 unless replacing
+    pub num_replacing: u64,
+
+    /// Gas price in wei for replacing transactions; 25% higher than the
+    /// current one. `None` if nothing is replaced, or gas price couldn't be
+    /// queried
+    pub replacement_gas_price: Option<U256>,
+}
+
+impl NoncePlan {
+    /// Return nonce of the specified revoke transaction.
+    ///
+    /// # Arguments
+    /// * `index` - index of revoke transaction in the order they are sent
+    pub fn nonce_of(&self, index: usize) -> u64 {
+        self.next + index as u64
+    }
+
+    /// Return whether the specified revoke transaction replaces a pending
+    /// transaction, so has to be sent with higher fees.
+    ///
+    /// # Arguments
+    /// * `index` - index of revoke transaction in the order they are sent
+    pub fn is_replacing(&self, index: usize) -> bool {
+        (index as u64) < self.num_replacing
+    }
+}
+
+/// Plan nonces of revoke transactions from mined, and pending transaction
+/// counts of owner wallet. They follow pending transactions unless
+/// `replace`, in which case they start right after mined ones instead, so
+/// that stuck transactions are replaced by revokes sent with higher fees.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `owner_address` - owner wallet address
+/// * `replace` - whether to replace pending transactions
+pub async fn plan_nonces<R: ChainRpc + ?Sized>(rpc: &R, owner_address: &str, replace: bool) -> Result<NoncePlan, AppvkekError> {
+    let owner = get_address_from_str(owner_address)?;
+    let (mined, pending) = futures::try_join!(rpc.transaction_count(owner, false), rpc.transaction_count(owner, true))?;
+    let num_pending = pending.saturating_sub(mined);
+    if !replace || num_pending == 0 {
+        return Ok(NoncePlan { next: pending, num_pending, num_replacing: 0, replacement_gas_price: None });
+    }
+
+    let replacement_gas_price = match rpc.gas_price().await {
+        Ok(res) => Some(bump_fee(res)),
+        Err(e) => {
+            tracing::warn!("{}", e);
+            None
+        }
+    };
+    Ok(NoncePlan { next: mined, num_pending, num_replacing: num_pending, replacement_gas_price })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+
+    static OWNER: &str = "0x1111111111111111111111111111111111111111";
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static OTHER_TOKEN: &str = "0x3333333333333333333333333333333333333333";
+
+    #[tokio::test]
+    async fn check_restrictions_detects_paused_and_blacklisted() {
+        let mut is_blacklisted = BLACKLIST_METHOD_IDS[1].to_vec();
+        is_blacklisted.extend(ethabi::encode(&[Token::Address(get_address_from_str(OWNER).unwrap())]));
+        let rpc = MockChainRpc::default()
+            .with_call(TOKEN, PAUSED_METHOD_ID.to_vec(), ethabi::encode(&[Token::Bool(true)]))
+            .with_call(TOKEN, is_blacklisted, ethabi::encode(&[Token::Bool(true)]))
+            .with_call(OTHER_TOKEN, PAUSED_METHOD_ID.to_vec(), ethabi::encode(&[Token::Bool(false)]));
+        let semaphore = Semaphore::new(4);
+
+        let restrictions = check_restrictions(&rpc, &semaphore, &[TOKEN.to_owned(), OTHER_TOKEN.to_owned()], OWNER).await;
+
+        assert_eq!(restrictions.len(), 1);
+        assert_eq!(restrictions[TOKEN], TokenRestriction { is_paused: true, is_owner_blacklisted: true });
+    }
+
+    #[tokio::test]
+    async fn estimate_revoke_costs_prices_gas_and_fails_reverting_ones() {
+        let tx = build_revoke_tx(TOKEN, OTHER_TOKEN).unwrap();
+        let reverting_tx = build_revoke_tx(OTHER_TOKEN, TOKEN).unwrap();
+        let rpc = MockChainRpc::default()
+            .with_gas_estimate(TOKEN, hex::decode(&tx.data[2..]).unwrap(), 46_000)
+            .with_gas_price(U256::from(5_000_000_000u64));
+        let semaphore = Semaphore::new(4);
+
+        let costs = estimate_revoke_costs(&rpc, &semaphore, OWNER, &[tx, reverting_tx], None).await;
+
+        assert_eq!(costs[0].as_ref().unwrap(), &RevokeCost { gas: 46_000, fee: Some(0.00023) });
+        assert!(matches!(costs[1], Err(AppvkekError::Reverted(_))));
+    }
+
+    #[tokio::test]
+    async fn plan_nonces_follows_or_replaces_pending_transactions() {
+        let rpc = MockChainRpc::default()
+            .with_transaction_count(OWNER, 7, 9)
+            .with_gas_price(U256::from(4_000_000_000u64));
+
+        let plan = plan_nonces(&rpc, OWNER, false).await.unwrap();
+        assert_eq!(plan, NoncePlan { next: 9, num_pending: 2, num_replacing: 0, replacement_gas_price: None });
+
+        let plan = plan_nonces(&rpc, OWNER, true).await.unwrap();
+        assert_eq!(plan.next, 7);
+        assert_eq!(plan.replacement_gas_price, Some(U256::from(5_000_000_001u64)));
+        assert_eq!((plan.nonce_of(1), plan.is_replacing(1)), (8, true));
+        assert_eq!((plan.nonce_of(2), plan.is_replacing(2)), (9, false));
+    }
+
+    #[test]
+    fn revoke_tx_to_eip681_uri() {
+        let tx = build_revoke_tx(TOKEN, OWNER).unwrap();
+
+        assert_eq!(tx.to_eip681_uri(56), format!("ethereum:{}@56/approve?address={}&uint256=0", to_checksum_address(TOKEN), to_checksum_address(OWNER)));
+    }
+
+    #[test]
+    fn permit_method_ids_match_signatures() {
+        for (method_id, signature) in [
+            (DOMAIN_SEPARATOR_METHOD_ID, "DOMAIN_SEPARATOR()"),
+            (NONCES_METHOD_ID, "nonces(address)"),
+            (VERSION_METHOD_ID, "version()"),
+            (PERMIT_TYPEHASH_METHOD_ID, "PERMIT_TYPEHASH()"),
+            (PERMIT_METHOD_ID, "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)"),
+        ] {
+            assert_eq!(method_id[..], keccak256(signature.as_bytes())[..4], "{}", signature);
+        }
+    }
+
+    #[tokio::test]
+    async fn permit_revoke_is_built_from_owner_signature() {
+        use web3::signing::{Key, SecretKeyRef};
+
+        let key = secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let owner = format!("{:?}", SecretKeyRef::new(&key).address());
+        let token = get_address_from_str(TOKEN).unwrap();
+        let mut nonces = NONCES_METHOD_ID.to_vec();
+        nonces.extend(ethabi::encode(&[Token::Address(get_address_from_str(&owner).unwrap())]));
+        let rpc = MockChainRpc::default()
+            .with_call(TOKEN, DOMAIN_SEPARATOR_METHOD_ID.to_vec(), domain_separator("Test Token", "2", 1, token).to_vec())
+            .with_call(TOKEN, nonces, ethabi::encode(&[Token::Uint(U256::from(3))]))
+            .with_call(TOKEN, NAME_METHOD_ID.to_vec(), ethabi::encode(&[Token::String("Test Token".to_owned())]))
+            .with_call(TOKEN, VERSION_METHOD_ID.to_vec(), ethabi::encode(&[Token::String("2".to_owned())]))
+            // domain of unknown version can't be reconstructed
+            .with_call(OTHER_TOKEN, DOMAIN_SEPARATOR_METHOD_ID.to_vec(), domain_separator("Test Token", "2", 1, token).to_vec())
+            .with_call(OTHER_TOKEN, NAME_METHOD_ID.to_vec(), ethabi::encode(&[Token::String("Test Token".to_owned())]));
+
+        assert!(query_permit_domain(&rpc, OTHER_TOKEN, &owner, 1).await.unwrap().is_none());
+        let domain = query_permit_domain(&rpc, TOKEN, &owner, 1).await.unwrap().unwrap();
+        assert_eq!((domain.version.as_str(), domain.nonce), ("2", U256::from(3)));
+        let typed_data = build_permit_typed_data(&domain, &owner, OTHER_TOKEN, 1_700_000_000);
+        assert_eq!(typed_data["message"]["nonce"], "3");
+
+        // sign as wallet would via `eth_signTypedData_v4`
+        let struct_hash = keccak256(&ethabi::encode(&[
+            Token::FixedBytes(keccak256(PERMIT_TYPE.as_bytes()).to_vec()),
+            Token::Address(get_address_from_str(&owner).unwrap()),
+            Token::Address(get_address_from_str(OTHER_TOKEN).unwrap()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::from(3)),
+            Token::Uint(U256::from(1_700_000_000u64)),
+        ]));
+        let mut message = vec![0x19, 0x01];
+        message.extend(domain.separator);
+        message.extend(struct_hash);
+        let signature = SecretKeyRef::new(&key).sign(&keccak256(&message), None).unwrap();
+        let signature = format!("0x{}{}{:02x}", hex::encode(signature.r), hex::encode(signature.s), signature.v);
+
+        let tx = build_permit_revoke_tx(&domain, &owner, OTHER_TOKEN, 1_700_000_000, &signature).unwrap();
+        assert_eq!(tx.token_address, TOKEN);
+        assert!(tx.data.starts_with("0xd505accf"));
+        assert!(build_permit_revoke_tx(&domain, &owner, OTHER_TOKEN, 1_700_000_001, &signature).is_err());
+        assert!(build_permit_revoke_tx(&domain, OWNER, OTHER_TOKEN, 1_700_000_000, &signature).is_err());
+    }
+}
@@ -0,0 +1,571 @@
+use futures::future::join_all;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Semaphore;
+
+use crate::codecache::*;
+use crate::creationcache::CreationCache;
+use crate::exploits::ExploitDb;
+use crate::explorer::*;
+use crate::labels::Labels;
+use crate::malicious::MaliciousDb;
+use crate::prices::exposure_usd;
+use crate::provider::{ChainRpc, ExplorerApi};
+use crate::proxy::*;
+use crate::types::*;
+use crate::util::*;
+
+/// Score for allowance that is effectively unlimited
+const SCORE_UNLIMITED: u32 = 40;
+
+/// Score for spender that is an EOA instead of a contract
+const SCORE_EOA_SPENDER: u32 = 30;
+
+/// Score for spender which has no code now, but was a contract at approval
+/// time; a new contract could be redeployed at that address
+const SCORE_DESTROYED_SPENDER: u32 = 60;
+
+/// Score for spender contract which has no verified source code
+const SCORE_UNVERIFIED_SPENDER: u32 = 25;
+
+/// Additional score for unlimited allowance granted to unverified spender
+/// contract which is the classic drainer signature
+const SCORE_DRAINER_SIGNATURE: u32 = 20;
+
+/// Score for unknown spender which has been dormant
+const SCORE_DORMANT_SPENDER: u32 = 15;
+
+/// Score for approval which has been granted long ago
+const SCORE_STALE: u32 = 10;
+
+/// Score for spender contract which has been deployed only recently
+const SCORE_YOUNG_SPENDER: u32 = 20;
+
+/// Number of days for spender contract to be considered young
+const YOUNG_SPENDER_DAYS: u64 = 30;
+
+/// Score for high value at risk
+const SCORE_HIGH_VALUE_AT_RISK: u32 = 20;
+
+/// Score for medium value at risk
+const SCORE_MEDIUM_VALUE_AT_RISK: u32 = 10;
+
+/// Value at risk (in USD) to be considered high
+const HIGH_VALUE_AT_RISK: f64 = 10_000.0;
+
+/// Value at risk (in USD) to be considered medium
+const MEDIUM_VALUE_AT_RISK: f64 = 1_000.0;
+
+/// Score for spender which has been exploited or compromised
+const SCORE_EXPLOITED: u32 = 100;
+
+/// Score for spender which is found in known-malicious address lists
+const SCORE_KNOWN_MALICIOUS: u32 = 100;
+
+/// Maximum score
+pub const MAX_SCORE: u32 = 100;
+
+/// Factors of an approval that contribute to its risk score.
+/// Optional fields are `None` when such information is not available.
+#[derive(Debug, Clone, Default)]
+pub struct RiskFactors {
+    /// Whether the allowance is zero e.g. revoked; nothing is at risk then
+    pub is_zero_allowance: bool,
+
+    /// Whether the allowance is effectively unlimited
+    pub is_unlimited: bool,
+
+    /// Whether spender is an EOA
+    pub is_eoa_spender: Option<bool>,
+
+    /// Whether spender has no code now, but was a contract at approval time
+    pub is_destroyed_spender: bool,
+
+    /// Whether spender contract has verified source code
+    pub is_verified_spender: Option<bool>,
+
+    /// Age of spender contract in days
+    pub spender_age_days: Option<u64>,
+
+    /// Value at risk in USD
+    pub value_at_risk: Option<f64>,
+
+    /// Whether spender is found in known-malicious address lists
+    pub is_known_malicious: bool,
+
+    /// Whether spender has been exploited or compromised
+    pub is_exploited: bool,
+
+    /// Whether approval has been granted longer than the stale threshold;
+    /// false if when it was granted is unknown
+    pub is_stale: bool,
+
+    /// Whether unknown spender has had no activity longer than the dormant threshold
+    pub is_dormant_spender: bool,
+}
+
+/// Result of risk assessment of an approval.
+#[derive(Debug, Clone, Default)]
+pub struct RiskAssessment {
+    /// Score from 0 to `MAX_SCORE`; higher means riskier
+    pub score: u32,
+
+    /// Short descriptions of factors contributing to the score
+    pub reasons: Vec<String>,
+}
+
+/// Compute risk score from the specified factors. Approval with zero
+/// allowance scores 0 regardless of the others as it can't be abused.
+///
+/// # Arguments
+/// * `factors` - risk factors of an approval
+pub fn score(factors: &RiskFactors) -> RiskAssessment {
+    let mut assessment = RiskAssessment::default();
+    if factors.is_zero_allowance {
+        return assessment;
+    }
+
+    if factors.is_known_malicious {
+        assessment.score += SCORE_KNOWN_MALICIOUS;
+        assessment.reasons.push("KNOWN-MALICIOUS".to_owned());
+    }
+    if factors.is_exploited {
+        assessment.score += SCORE_EXPLOITED;
+        assessment.reasons.push("EXPLOITED".to_owned());
+    }
+    if factors.is_unlimited {
+        assessment.score += SCORE_UNLIMITED;
+        assessment.reasons.push("unlimited".to_owned());
+    }
+    if factors.is_destroyed_spender {
+        assessment.score += SCORE_DESTROYED_SPENDER;
+        assessment.reasons.push("destroyed-spender".to_owned());
+    }
+    else if factors.is_eoa_spender == Some(true) {
+        assessment.score += SCORE_EOA_SPENDER;
+        assessment.reasons.push("eoa-spender".to_owned());
+    }
+    if factors.is_verified_spender == Some(false) {
+        assessment.score += SCORE_UNVERIFIED_SPENDER;
+        assessment.reasons.push("unverified-spender".to_owned());
+
+        if factors.is_unlimited {
+            assessment.score += SCORE_DRAINER_SIGNATURE;
+            assessment.reasons.push("drainer-signature".to_owned());
+        }
+    }
+    if let Some(age_days) = factors.spender_age_days {
+        if age_days < YOUNG_SPENDER_DAYS {
+            assessment.score += SCORE_YOUNG_SPENDER;
+            assessment.reasons.push("young-spender".to_owned());
+        }
+    }
+    if factors.is_dormant_spender {
+        assessment.score += SCORE_DORMANT_SPENDER;
+        assessment.reasons.push("dormant-spender".to_owned());
+    }
+    if factors.is_stale {
+        assessment.score += SCORE_STALE;
+        assessment.reasons.push("stale".to_owned());
+    }
+    if let Some(value) = factors.value_at_risk {
+        if value >= HIGH_VALUE_AT_RISK {
+            assessment.score += SCORE_HIGH_VALUE_AT_RISK;
+            assessment.reasons.push("high-value-at-risk".to_owned());
+        }
+        else if value >= MEDIUM_VALUE_AT_RISK {
+            assessment.score += SCORE_MEDIUM_VALUE_AT_RISK;
+            assessment.reasons.push("medium-value-at-risk".to_owned());
+        }
+    }
+
+    assessment.score = assessment.score.min(MAX_SCORE);
+    assessment
+}
+
+/// Options for risk assessment.
+#[derive(Debug, Clone)]
+pub struct RiskOptions {
+    /// Whether to check verification status of spender contracts via explorer
+    pub check_verification: bool,
+
+    /// Number of days after which an approval is considered stale
+    pub stale_days: u64,
+
+    /// Whether to check recent activity of unknown spenders via explorer
+    pub check_activity: bool,
+
+    /// Number of days without activity after which a spender is considered dormant
+    pub dormant_days: u64,
+
+    /// Whether to fetch creation record of unknown spender contracts via
+    /// explorer
+    pub check_creation: bool,
+}
+
+/// Sources of information used in risk assessment.
+pub struct RiskSources<'a> {
+    /// Known-malicious address database
+    pub malicious_db: &'a MaliciousDb,
+
+    /// Exploited-contract database
+    pub exploit_db: &'a ExploitDb,
+
+    /// Labels of well-known addresses
+    pub labels: &'a Labels,
+
+    /// USD price of tokens keyed by lowercased address, to value what is at
+    /// risk along with balance of owner; `None` if not fetched
+    pub prices: Option<&'a HashMap<String, f64>>,
+}
+
+/// Return whether spender of the specified approval is unknown i.e. it's
+/// neither labeled in label database nor in user's address book, nor a
+/// contract with verified source code as checked by `assess_all()`. Spender
+/// whose verification wasn't checked is regarded as unverified.
+///
+/// # Arguments
+/// * `labels` - labels of well-known addresses, and user's address book
+/// * `spender` - spender address
+/// * `sa` - allowance of spender
+pub fn is_unknown_spender(labels: &Labels, spender: &str, sa: &SpenderAllowance) -> bool {
+    !labels.is_known(spender) && sa.is_verified != Some(true)
+}
+
+/// Assess risk of all approvals in the specified results, then fill in
+/// `risk` field of each spender's allowance.
+///
+/// This makes an `eth_getCode` query for each unique spender address, checks
+/// whether spenders without code were contracts at their latest approval time
+/// (selfdestructed or metamorphic contracts), resolves
+/// implementation of spender contracts which are proxies, and makes an
+/// explorer's `getsourcecode` query for each unique spender contract if
+/// `opts.check_verification` is `true`.
+/// For proxy spenders, verification status is that of their implementation,
+/// and both proxy and implementation are checked against known-malicious
+/// address lists.
+/// Recent activity of unknown (unlabeled) spenders is also queried via explorer
+/// if `opts.check_activity` is `true`.
+/// Spenders found in known-malicious address lists or exploited-contract
+/// dataset are also warned loudly to stderr.
+/// Value at risk is only scored if `sources.prices` is set, and balance of
+/// owner has been queried by `scanner::query_balance_and_supply()`.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `explorer` - explorer API
+/// * `results` - results from `query_all()`
+/// * `sources` - sources of information e.g. known-malicious address database
+/// * `opts` - options for risk assessment
+/// * `code_cache` - cache of `eth_getCode` results of spenders
+/// * `creation_cache` - cache of creation records of spender contracts
+#[allow(clippy::too_many_arguments)]
+pub async fn assess_all<R, E>(rpc: &R, semaphore: &Semaphore, explorer: &E, results: &mut [QueryResult], sources: &RiskSources<'_>, opts: &RiskOptions, code_cache: &mut CodeCache, creation_cache: &mut CreationCache)
+where
+    R: ChainRpc + ?Sized,
+    E: ExplorerApi + ?Sized
+{
+    let malicious_db = sources.malicious_db;
+    let exploit_db = sources.exploit_db;
+
+    let mut spenders: Vec<String> = results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .flat_map(|r| r.spender_allowances.keys().cloned())
+        .collect();
+    spenders.sort();
+    spenders.dedup();
+
+    let is_eoa_map = check_is_eoa_all(rpc, semaphore, code_cache, &spenders).await;
+
+    // spenders with no code now but were contracts at approval time
+    // check code at approval block first which requires archive node, then
+    // fall back to explorer's contract creation record
+    let mut latest_approval_blocks: HashMap<&String, u64> = HashMap::new();
+    for (spender, sa) in results.iter().filter_map(|res| res.as_ref().ok()).flat_map(|r| r.spender_allowances.iter()) {
+        let block = latest_approval_blocks.entry(spender).or_insert(0);
+        *block = (*block).max(sa.approval.block_number);
+    }
+    let eoa_spenders: Vec<&String> = is_eoa_map.iter()
+        .filter(|(_, is_eoa)| **is_eoa == Some(true))
+        .map(|(spender, _)| spender)
+        .collect();
+    // block is unknown e.g. approval by permit, or imported without it, for
+    // which code at genesis says nothing, so explorer is asked right away
+    let had_code_results = join_all(eoa_spenders.iter().map(|spender| {
+        let block = latest_approval_blocks.get(spender).copied().filter(|b| *b > 0);
+        async move {
+            match block {
+                Some(block) => Some(with_permit(semaphore, has_code_at_block(rpc, spender, block)).await),
+                None => None,
+            }
+        }
+    })).await;
+    let mut destroyed_spenders: HashSet<String> = HashSet::new();
+    for (spender, res) in eoa_spenders.iter().zip(had_code_results) {
+        let was_contract = match res {
+            Some(Ok(res)) => res,
+            Some(Err(_)) | None => {
+                match run_blocking(|| explorer.contract_creation(spender)) {
+                    Ok(res) => res.is_some(),
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        false
+                    }
+                }
+            }
+        };
+        if was_contract {
+            destroyed_spenders.insert(spender.to_string());
+        }
+    }
+
+    let contract_spenders: Vec<&String> = is_eoa_map.iter()
+        .filter(|(_, is_eoa)| **is_eoa == Some(false))
+        .map(|(spender, _)| spender)
+        .collect();
+    let proxy_results = join_all(contract_spenders.iter().map(|spender| with_permit(semaphore, resolve_implementation(rpc, spender)))).await;
+    let mut proxy_map: HashMap<String, ProxyInfo> = HashMap::new();
+    for (spender, res) in contract_spenders.iter().zip(proxy_results) {
+        match res {
+            Ok(Some(info)) => {
+                proxy_map.insert(spender.to_string(), info);
+            },
+            Ok(None) => (),
+            Err(e) => {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+
+    let control_results = join_all(contract_spenders.iter().map(|spender| with_permit(semaphore, resolve_control(rpc, spender)))).await;
+    let mut control_map: HashMap<String, ControlInfo> = HashMap::new();
+    for (spender, res) in contract_spenders.iter().zip(control_results) {
+        match res {
+            Ok(info) if !info.is_empty() => {
+                control_map.insert(spender.to_string(), info);
+            },
+            Ok(_) => (),
+            Err(e) => {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+
+    // explorer calls are made sequentially, and paced by its rate limiter;
+    // they block, so are run off the worker not to stall other tasks e.g.
+    // monitoring mempool in watch mode
+    let mut is_verified_map: HashMap<String, bool> = HashMap::new();
+    if opts.check_verification {
+        for spender in contract_spenders.iter() {
+            // check the implementation instead as that is what will be executed
+            let target = proxy_map.get(*spender).map(|p| &p.implementation).unwrap_or(spender);
+
+            match run_blocking(|| explorer.is_contract_verified(target)) {
+                Ok(res) => {
+                    is_verified_map.insert(spender.to_string(), res);
+                },
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                }
+            }
+        }
+    }
+
+    let mut activity_map: HashMap<String, AddressActivity> = HashMap::new();
+    if opts.check_activity {
+        for spender in contract_spenders.iter().filter(|s| sources.labels.get(s).is_none()) {
+            match run_blocking(|| get_address_activity(explorer, spender)) {
+                Ok(res) => {
+                    activity_map.insert(spender.to_string(), res);
+                },
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                }
+            }
+        }
+    }
+
+    // creation record never changes, so it's cached regardless of age
+    let mut creation_map: HashMap<String, ContractCreation> = HashMap::new();
+    if opts.check_creation {
+        for spender in contract_spenders.iter().filter(|s| sources.labels.get(s).is_none()) {
+            if let Some(creation) = creation_cache.get(spender) {
+                creation_map.insert(spender.to_string(), creation.clone());
+                continue;
+            }
+            match run_blocking(|| explorer.contract_creation(spender)) {
+                Ok(Some(res)) => {
+                    creation_cache.insert(spender, res.clone());
+                    creation_map.insert(spender.to_string(), res);
+                },
+                Ok(None) => (),
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                }
+            }
+        }
+        if let Err(e) = creation_cache.save() {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    for r in results.iter_mut().filter_map(|res| res.as_mut().ok()) {
+        for (spender, spender_allowance) in r.spender_allowances.iter_mut() {
+            spender_allowance.proxy = proxy_map.get(spender).cloned();
+            spender_allowance.control = control_map.get(spender).cloned();
+            spender_allowance.activity = activity_map.get(spender).cloned();
+            spender_allowance.is_verified = is_verified_map.get(spender).copied();
+            spender_allowance.creation = creation_map.get(spender).cloned();
+
+            let implementation = spender_allowance.proxy.as_ref().map(|p| p.implementation.as_str());
+            // revoked approvals are harmless however their spender is known
+            let malicious_source = malicious_db.lookup(spender)
+                .or_else(|| implementation.and_then(|i| malicious_db.lookup(i)))
+                .filter(|_| spender_allowance.allowance > 0.0);
+            if let Some(source) = malicious_source {
+                tracing::error!("!!! WARNING: spender {} approved on token [{}] {} is listed as MALICIOUS by {} - revoke it immediately !!!", spender, r.name, r.address, source);
+            }
+
+            spender_allowance.exploit = exploit_db.lookup(spender)
+                .or_else(|| implementation.and_then(|i| exploit_db.lookup(i)))
+                .filter(|_| spender_allowance.allowance > 0.0)
+                .cloned();
+            if let Some(exploit) = &spender_allowance.exploit {
+                tracing::error!("!!! CRITICAL: spender {} approved on token [{}] {} is {} which was EXPLOITED on {} ({}) - revoke it immediately !!!", spender, r.name, r.address, exploit.name, exploit.date, exploit.reference);
+            }
+
+            let factors = RiskFactors {
+                is_zero_allowance: spender_allowance.allowance == 0.0,
+                is_unlimited: spender_allowance.is_unlimited,
+                is_eoa_spender: is_eoa_map.get(spender).copied().flatten(),
+                is_destroyed_spender: destroyed_spenders.contains(spender),
+                is_verified_spender: spender_allowance.is_verified,
+                spender_age_days: spender_allowance.creation.as_ref().and_then(|c| c.timestamp).map(days_since),
+                is_known_malicious: malicious_source.is_some(),
+                is_exploited: spender_allowance.exploit.is_some(),
+                is_stale: spender_allowance.approval.timestamp > 0 && days_since(spender_allowance.approval.timestamp) >= opts.stale_days,
+                is_dormant_spender: spender_allowance.activity.as_ref()
+                    .map(|a| a.last_active.map(|t| days_since(t) >= opts.dormant_days).unwrap_or(true))
+                    .unwrap_or(false),
+                value_at_risk: sources.prices.and_then(|p| exposure_usd(spender_allowance.allowance, r.balance, p.get(&r.address.to_lowercase()).copied())),
+            };
+            spender_allowance.risk = score(&factors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failures::FailedContracts;
+    use crate::metadata::MetadataCache;
+    use crate::mock::*;
+    use crate::scanner::query_all;
+    use web3::types::U256;
+
+    const OWNER: &str = "0x1111111111111111111111111111111111111111";
+    const TOKEN: &str = "0x55d398326f99059ff775485246999027b3197955";
+    const SPENDER: &str = "0x2222222222222222222222222222222222222222";
+
+    /// Assess the single approval of `SPENDER` made at the specified block,
+    /// which has no code now but explorer knows as created contract.
+    ///
+    /// # Arguments
+    /// * `block_number` - block number of approval; 0 if unknown
+    async fn assess_destroyed_spender(block_number: u64) -> RiskAssessment {
+        let rpc = MockChainRpc::default()
+            .with_erc20(TOKEN, "Tether USD", "USDT", 18)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::from(1));
+        let explorer = MockExplorer::default().with_created(SPENDER);
+        let semaphore = Semaphore::new(4);
+
+        let approvals = HashMap::from([(TOKEN.to_owned(), HashMap::from([(SPENDER.to_owned(), ApprovalRecord { block_number, ..Default::default() })]))]);
+        let mut results = query_all(&rpc, &semaphore, approvals, OWNER, false, &mut MetadataCache::default(), &mut FailedContracts::default(), |_| {}).await;
+
+        let (malicious_db, exploit_db, labels) = (MaliciousDb::default(), ExploitDb::default(), Labels::default());
+        let sources = RiskSources { malicious_db: &malicious_db, exploit_db: &exploit_db, labels: &labels, prices: None };
+        let opts = RiskOptions { check_verification: false, stale_days: 365, check_activity: false, dormant_days: 180, check_creation: false };
+        assess_all(&rpc, &semaphore, &explorer, &mut results, &sources, &opts, &mut CodeCache::default(), &mut CreationCache::default()).await;
+
+        results[0].as_ref().unwrap().spender_allowances[SPENDER].risk.clone()
+    }
+
+    // explorer calls are run via `block_in_place()` which needs multi-threaded runtime
+    #[tokio::test(flavor = "multi_thread")]
+    async fn assess_all_asks_explorer_if_spender_was_contract_at_unknown_block() {
+        assert!(assess_destroyed_spender(0).await.reasons.contains(&"destroyed-spender".to_owned()));
+
+        // code at known approval block is trusted over explorer
+        assert!(!assess_destroyed_spender(100).await.reasons.contains(&"destroyed-spender".to_owned()));
+    }
+
+    #[test]
+    fn score_adds_up_each_factor() {
+        let cases: Vec<(RiskFactors, u32, &str)> = vec![
+            (RiskFactors { is_unlimited: true, ..Default::default() }, SCORE_UNLIMITED, "unlimited"),
+            (RiskFactors { is_eoa_spender: Some(true), ..Default::default() }, SCORE_EOA_SPENDER, "eoa-spender"),
+            (RiskFactors { is_destroyed_spender: true, is_eoa_spender: Some(true), ..Default::default() }, SCORE_DESTROYED_SPENDER, "destroyed-spender"),
+            (RiskFactors { is_verified_spender: Some(false), ..Default::default() }, SCORE_UNVERIFIED_SPENDER, "unverified-spender"),
+            (RiskFactors { spender_age_days: Some(YOUNG_SPENDER_DAYS - 1), ..Default::default() }, SCORE_YOUNG_SPENDER, "young-spender"),
+            (RiskFactors { is_dormant_spender: true, ..Default::default() }, SCORE_DORMANT_SPENDER, "dormant-spender"),
+            (RiskFactors { is_stale: true, ..Default::default() }, SCORE_STALE, "stale"),
+            (RiskFactors { value_at_risk: Some(HIGH_VALUE_AT_RISK), ..Default::default() }, SCORE_HIGH_VALUE_AT_RISK, "high-value-at-risk"),
+            (RiskFactors { value_at_risk: Some(MEDIUM_VALUE_AT_RISK), ..Default::default() }, SCORE_MEDIUM_VALUE_AT_RISK, "medium-value-at-risk"),
+            (RiskFactors { is_known_malicious: true, ..Default::default() }, SCORE_KNOWN_MALICIOUS, "KNOWN-MALICIOUS"),
+            (RiskFactors { is_exploited: true, ..Default::default() }, SCORE_EXPLOITED, "EXPLOITED"),
+        ];
+        for (factors, expected_score, reason) in cases {
+            let assessment = score(&factors);
+            assert_eq!(assessment.score, expected_score, "{}", reason);
+            assert_eq!(assessment.reasons, vec![reason.to_owned()]);
+        }
+
+        // below thresholds, or unknown
+        let factors = RiskFactors {
+            is_eoa_spender: Some(false),
+            is_verified_spender: Some(true),
+            spender_age_days: Some(YOUNG_SPENDER_DAYS),
+            value_at_risk: Some(MEDIUM_VALUE_AT_RISK - 1.0),
+            ..Default::default()
+        };
+        assert_eq!(score(&factors).score, 0);
+        assert_eq!(score(&RiskFactors::default()).score, 0);
+    }
+
+    #[test]
+    fn score_adds_drainer_signature_to_unlimited_unverified() {
+        let assessment = score(&RiskFactors { is_unlimited: true, is_verified_spender: Some(false), ..Default::default() });
+        assert_eq!(assessment.score, SCORE_UNLIMITED + SCORE_UNVERIFIED_SPENDER + SCORE_DRAINER_SIGNATURE);
+        assert_eq!(assessment.reasons, vec!["unlimited", "unverified-spender", "drainer-signature"]);
+    }
+
+    #[test]
+    fn score_is_capped_at_max_score() {
+        let factors = RiskFactors {
+            is_unlimited: true,
+            is_verified_spender: Some(false),
+            is_stale: true,
+            value_at_risk: Some(HIGH_VALUE_AT_RISK),
+            ..Default::default()
+        };
+        let assessment = score(&factors);
+        assert_eq!(assessment.score, MAX_SCORE);
+        assert_eq!(assessment.reasons.len(), 5);
+
+        assert_eq!(score(&RiskFactors { is_known_malicious: true, is_exploited: true, ..Default::default() }).score, MAX_SCORE);
+    }
+
+    #[test]
+    fn score_of_zero_allowance_is_zero() {
+        let factors = RiskFactors {
+            is_zero_allowance: true,
+            is_known_malicious: true,
+            is_eoa_spender: Some(true),
+            is_stale: true,
+            ..Default::default()
+        };
+        let assessment = score(&factors);
+        assert_eq!(assessment.score, 0);
+        assert!(assessment.reasons.is_empty());
+    }
+}
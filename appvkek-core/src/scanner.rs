@@ -0,0 +1,890 @@
+use ::evmscan::prelude::U256 as BSCU256;    // for floating-point representation for U256
+use ::evmscan::types::evm_types::EvmNormalTransactionResponseSuccessVariantResult;
+use futures::stream::StreamExt;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Semaphore;
+use web3::types::Address;
+
+use crate::erc20::*;
+use crate::error::{AppvkekError, QueryError, ScanWarning};
+use crate::failures::{self, FailedContracts};
+use crate::metadata::{MetadataCache, TokenMetadata};
+use crate::provider::{ChainRpc, ExplorerApi};
+use crate::types::*;
+use crate::util::*;
+use crate::{explorer, multicall};
+
+/// Make query for information towards token contract address, and associated
+/// spender addresses with their allowance balances.
+///
+/// Return `TokenContractWithSpenderAllowances` structure, otherwise return
+/// `QueryError` carrying token contract address along with the cause.
+///
+/// # Note
+/// As the query needs to live long enough, thus its function's arguments need
+/// to live long enough as well e.g. address is in `String` not `&str`.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `contract_address` - token contract address to interact with
+/// * `owner_address` - owner wallet address
+/// * `spenders` - all spender addresses associated with such token contract address
+///   along with their latest approval record
+/// * `metadata` - cached metadata of token contract if any; if set, `name`,
+///   `symbol`, and `decimals` won't be queried
+pub async fn query<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, contract_address: String, owner_address: String, spenders: HashMap<String, ApprovalRecord>, metadata: Option<TokenMetadata>) -> QueryResult {
+    let (contract, owner) = match get_address_from_str(&contract_address).and_then(|c| Ok((c, get_address_from_str(&owner_address)?))) {
+        Ok(res) => res,
+        Err(error) => {
+            return Err(QueryError { token_address: contract_address, error });
+        }
+    };
+
+    // 1. multiple top-level queries starting from here...
+    let metadata = match metadata {
+        Some(res) => res,
+        None => {
+            let name_f = with_permit(semaphore, rpc.call(contract, NAME_METHOD_ID.to_vec(), None));
+            let symbol_f = with_permit(semaphore, rpc.call(contract, SYMBOL_METHOD_ID.to_vec(), None));
+            let decimals_f = with_permit(semaphore, rpc.call(contract, DECIMALS_METHOD_ID.to_vec(), None));
+
+            let (name, symbol, decimals) = futures::join!(name_f, symbol_f, decimals_f);
+
+            let name = match name.and_then(|b| decode_string(&b).ok_or_else(|| AppvkekError::Decode("invalid returned data".to_owned()))) {
+                Ok(res) => res,
+                Err(e) => {
+                    let error = token_call_error(e, "Error in querying top-level query (name)", "name");
+                    return Err(QueryError { token_address: contract_address, error });
+                }
+            };
+            let decimals = match decimals.and_then(|b| decode_decimals(&b).ok_or_else(|| AppvkekError::Decode("invalid returned data".to_owned()))) {
+                Ok(res) => res,
+                Err(e) => {
+                    let error = token_call_error(e, "Error in querying top-level query (decimals)", "decimals");
+                    return Err(QueryError { token_address: contract_address, error });
+                }
+            };
+
+            TokenMetadata {
+                name,
+                // symbol is optional in ERC-20, so don't fail on it
+                symbol: symbol.ok().and_then(|b| decode_string(&b)).unwrap_or_default(),
+                decimals,
+            }
+        }
+    };
+
+    let mut result_struct = TokenContractWithSpenderAllowances {
+        name: metadata.name,
+        symbol: metadata.symbol,
+        address: contract_address.to_owned(),
+        decimals: metadata.decimals,
+        balance: None,
+        total_supply: None,
+        spender_allowances: HashMap::new(),
+    };
+
+    // 2. spender' allowances
+    // make queries to get current allowanced balance of all spenders concurrently
+    let spenders: Vec<(String, ApprovalRecord)> = spenders.into_iter().collect();
+    let allowance_balance_futs = spenders.iter()
+        .map(|(spender, _)| async move {
+            let spender = get_address_from_str(spender)?;
+            let output = with_permit(semaphore, rpc.call(contract, encode_allowance(owner, spender), None)).await?;
+            decode_uint(&output).ok_or_else(|| AppvkekError::Decode("invalid returned data".to_owned()))
+        });
+    let allowance_balance_results = futures::future::join_all(allowance_balance_futs).await;
+
+    for ((spender, approval), allowance_balance_res) in spenders.into_iter().zip(allowance_balance_results) {
+        // check back results
+        let allowance_balance = match allowance_balance_res {
+            Ok(res) => res,
+            Err(e) => {
+                let context = format!("Error querying for allowance balance for contract-addr={}, owner-addr={}, spender-addr={}", contract_address, owner_address, &spender);
+                let error = token_call_error(e, &context, "allowance");
+                return Err(QueryError { token_address: contract_address, error });
+            }
+        };
+
+        match make_spender_allowance(allowance_balance, result_struct.decimals, approval) {
+            Ok(res) => {
+                result_struct.spender_allowances.insert(spender, res);
+            },
+            Err(error) => return Err(QueryError { token_address: contract_address, error }),
+        }
+    }
+
+    Ok(result_struct)
+}
+
+/// Return error of failed call on token contract with the specified context
+/// along with hint of what it likely means; see
+/// `AppvkekError::token_call_hint()`.
+///
+/// # Arguments
+/// * `err` - error of the call
+/// * `context` - what was being done
+/// * `method` - name of method called e.g. `allowance`
+pub(crate) fn token_call_error(err: AppvkekError, context: &str, method: &str) -> AppvkekError {
+    match err.token_call_hint(method) {
+        Some(hint) => err.with_context(&format!("{} ({})", context, hint)),
+        None => err.with_context(context),
+    }
+}
+
+/// Create `SpenderAllowance` from raw allowance balance.
+///
+/// # Arguments
+/// * `allowance_balance` - raw allowance balance
+/// * `decimals` - number of decimals of token
+/// * `approval` - record of the latest approve transaction
+pub fn make_spender_allowance(allowance_balance: web3::types::U256, decimals: u8, approval: ApprovalRecord) -> Result<SpenderAllowance, AppvkekError> {
+    Ok(SpenderAllowance {
+        allowance: to_token_amount(allowance_balance, decimals)?,
+        is_unlimited: is_unlimited_allowance(allowance_balance),
+        risk: Default::default(),
+        proxy: None,
+        approval,
+        exploit: None,
+        activity: None,
+        is_verified: None,
+        creation: None,
+        control: None,
+    })
+}
+
+/// Convert raw amount into token unit.
+///
+/// # Arguments
+/// * `amount` - raw amount
+/// * `decimals` - number of decimals of token
+pub(crate) fn to_token_amount(amount: web3::types::U256, decimals: u8) -> Result<f64, AppvkekError> {
+    // floating-point ready representation for U256
+    let amount_fp = match BSCU256::from_dec_str(&amount.to_string()) {
+        Ok(res) => res,
+        Err(e) => {
+            return Err(AppvkekError::Decode(format!("Error converting from web3::types::U256 to bscscan::prelude::U256 for floating-point representation ability; err={}", e)));
+        }
+    };
+    Ok(amount_fp.to_f64_lossy() / 10_f64.powf(decimals.into()))
+}
+
+/// Collect all token contracts which owner has made approval to, along with
+/// all spender addresses approved under each of them, then merge them into
+/// the specified `ct_txs`.
+///
+/// Transactions which can't be parsed e.g. malformed approve calldata are
+/// skipped, and recorded into `warnings`. If `warnings` is `None`, the first
+/// of them fails the whole collection instead.
+///
+/// Return the highest block number of transactions seen if any, otherwise
+/// return error.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `owner_address` - owner wallet address; lowercased
+/// * `start_block` - starting block number (inclusive) to collect transactions from
+/// * `ct_txs` - `HashMap` of token contract address to `HashMap` of spender
+///   addresses to merge collected approvals into
+/// * `warnings` - vector to record skipped transactions into; `None` to fail fast
+pub fn collect_approvals<E: ExplorerApi + ?Sized>(explorer: &E, owner_address: &str, start_block: u64, ct_txs: &mut ApprovalsMap, mut warnings: Option<&mut Vec<ScanWarning>>) -> Result<Option<u64>, AppvkekError> {
+    let mut last_block: Option<u64> = None;
+
+    // process transactions page by page, so that wallets with huge history
+    // don't need to have all of them in memory at once
+    explorer::for_each_normal_transactions_page(explorer, owner_address, start_block, |txs| {
+        for tx in txs {
+            last_block = last_block.max(Some(tx.block_number));
+            if let Err(error) = collect_approval(&tx, owner_address, ct_txs) {
+                match warnings.as_deref_mut() {
+                    Some(warnings) => warnings.push(ScanWarning { tx_hash: tx.hash.to_owned(), error }),
+                    None => return Err(error),
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(last_block)
+}
+
+/// Collect approval made in the specified transaction if any into `ct_txs`.
+///
+/// # Arguments
+/// * `tx` - normal transaction
+/// * `owner_address` - owner wallet address; lowercased
+/// * `ct_txs` - `HashMap` of token contract address to `HashMap` of spender
+///   addresses to merge collected approval into
+pub fn collect_approval(tx: &EvmNormalTransactionResponseSuccessVariantResult, owner_address: &str, ct_txs: &mut ApprovalsMap) -> Result<(), AppvkekError> {
+    // 0x095ea7b3 is method-id for approve method
+    if tx.from == owner_address && !tx.is_error && tx.input.starts_with("0x095ea7b3") {
+        // get the spender from the first argument; calldata which is merely
+        // short or oddly padded still made a successful approval on chain,
+        // so it's recorded along with the issue rather than failed
+        let parsed = match parse_256_method_arguments(&tx.input) {
+            Ok(res) => res,
+            Err(e) => {
+                return Err(e.with_context(&format!("Error parsing arguments of {}", tx.to)));
+            }
+        };
+        let mut issues: Vec<String> = parsed.issue.into_iter().collect();
+        let arguments = parsed.arguments;
+        if arguments.is_empty() {
+            return Err(AppvkekError::Decode(format!("Error parsing arguments of {}; approve() call has no arguments", tx.to)));
+        }
+        if arguments.len() < 2 {
+            issues.push("amount argument is missing".to_owned());
+        }
+
+        // cleanup first argument to get address (64 chars to 40 chars
+        // by remove first 24 chars); token contract which accepted dirty
+        // upper bytes takes the lower 20 bytes as well
+        if arguments[0][..24].chars().any(|c| c != '0') {
+            issues.push("spender argument has non-zero upper bytes; lower 20 bytes are taken".to_owned());
+        }
+        let mut spender_addr = arguments[0][24..].to_lowercase();
+        spender_addr.insert_str(0, "0x");
+
+        // use index-0 as it is spender address
+        // transactions are in ascending order, so the latest approval wins
+        let issue = if issues.is_empty() { None } else { Some(issues.join("; ")) };
+        ct_txs.entry(tx.to.to_owned())
+            .or_default()
+            .insert(spender_addr, ApprovalRecord { block_number: tx.block_number, timestamp: tx.timestamp, tx_hash: tx.hash.to_owned(), issue, user_op_hash: None });
+    }
+
+    Ok(())
+}
+
+/// Collect all token contracts which owner has made approval to via their
+/// `Approval` event logs, then merge them into the specified `ct_txs`.
+/// Unlike `collect_approvals()`, this also covers approvals made via internal
+/// calls, so it works for contract wallets e.g. Safe whose approve calls are
+/// executed by the wallet contract itself.
+///
+/// Return the highest block number of logs seen if any.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `owner_address` - owner wallet address; lowercased
+/// * `start_block` - starting block number (inclusive) to collect logs from
+/// * `ct_txs` - `HashMap` of token contract address to `HashMap` of spender
+///   addresses to merge collected approvals into
+pub fn collect_approval_logs<E: ExplorerApi + ?Sized>(explorer: &E, owner_address: &str, start_block: u64, ct_txs: &mut ApprovalsMap) -> Result<Option<u64>, AppvkekError> {
+    let mut last_block: Option<u64> = None;
+
+    explorer::for_each_approval_logs_page(explorer, owner_address, start_block, |logs| {
+        for log in logs {
+            last_block = last_block.max(Some(log.block_number));
+            collect_approval_log(&log, ct_txs);
+        }
+        Ok(())
+    })?;
+
+    Ok(last_block)
+}
+
+/// Collect all token contracts which ERC-4337 smart account has made
+/// approval to via their `Approval` event logs as of
+/// `collect_approval_logs()`, then attribute those made through user
+/// operations to them. As user operations are executed in transactions sent
+/// by bundlers, the transaction hash alone points at bundler rather than the
+/// smart account. If a bundle executes more than one user operation of the
+/// same smart account, approvals in it are attributed to the last one.
+///
+/// Return the highest block number of `Approval` event logs seen if any.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `owner_address` - smart account address; lowercased
+/// * `start_block` - starting block number (inclusive) to collect logs from
+/// * `ct_txs` - `HashMap` of token contract address to `HashMap` of spender
+///   addresses to merge collected approvals into
+pub fn collect_user_operation_approvals<E: ExplorerApi + ?Sized>(explorer: &E, owner_address: &str, start_block: u64, ct_txs: &mut ApprovalsMap) -> Result<Option<u64>, AppvkekError> {
+    // approvals are merged first, so that they are kept even if fetching
+    // user operations fails
+    let last_block = collect_approval_logs(explorer, owner_address, start_block, ct_txs)?;
+
+    let mut user_ops: HashMap<String, String> = HashMap::new();
+    explorer::for_each_user_operation_logs_page(explorer, owner_address, start_block, |logs| {
+        for log in logs {
+            if let Some(user_op_hash) = parse_user_operation_log(&log) {
+                user_ops.insert(log.transaction_hash.to_owned(), user_op_hash);
+            }
+        }
+        Ok(())
+    })?;
+
+    for record in ct_txs.values_mut().flat_map(|spenders| spenders.values_mut()) {
+        if let Some(user_op_hash) = user_ops.get(&record.tx_hash) {
+            record.user_op_hash = Some(user_op_hash.to_owned());
+        }
+    }
+
+    Ok(last_block)
+}
+
+/// Return hash of user operation of the specified `UserOperationEvent` event
+/// log if it's emitted by one of canonical EntryPoint contracts, and the user
+/// operation succeeded, otherwise return `None`.
+///
+/// # Arguments
+/// * `log` - event log whose sender topic matches smart account address
+fn parse_user_operation_log(log: &EventLog) -> Option<String> {
+    if log.topics.len() != 4 || log.topics[0] != explorer::USER_OPERATION_EVENT_TOPIC || !explorer::ENTRY_POINT_ADDRESSES.contains(&log.address.as_str()) {
+        return None;
+    }
+
+    // data is (nonce, success, actualGasCost, actualGasUsed); take `success`
+    // from the second word
+    let success = log.data.trim_start_matches("0x").get(64..128)?;
+    if !success.ends_with('1') {
+        return None;
+    }
+    Some(log.topics[1].to_owned())
+}
+
+/// Return whether the specified address is an ERC-4337 smart account i.e.
+/// it has executed user operation through one of canonical EntryPoint
+/// contracts.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `address` - target address; lowercased
+pub fn is_smart_account<E: ExplorerApi + ?Sized>(explorer: &E, address: &str) -> Result<bool, AppvkekError> {
+    let logs = explorer.user_operation_logs_page(address, 0, 1, 100)?;
+    Ok(logs.iter().any(|log| log.topics.first().map(|t| t.as_str()) == Some(explorer::USER_OPERATION_EVENT_TOPIC) && explorer::ENTRY_POINT_ADDRESSES.contains(&log.address.as_str())))
+}
+
+/// Collect all token contracts which the wallet has ever sent or received
+/// via their ERC-20 transfers into `tokens`. This covers tokens the wallet
+/// holds without ever having made an approval on them.
+///
+/// Return the highest block number of transfers seen if any.
+///
+/// # Arguments
+/// * `explorer` - explorer API
+/// * `owner_address` - owner wallet address; lowercased
+/// * `start_block` - starting block number (inclusive) to collect transfers from
+/// * `tokens` - set of token contract addresses (lowercased) to merge into
+pub fn collect_held_tokens<E: ExplorerApi + ?Sized>(explorer: &E, owner_address: &str, start_block: u64, tokens: &mut HashSet<String>) -> Result<Option<u64>, AppvkekError> {
+    let mut last_block: Option<u64> = None;
+
+    explorer::for_each_token_transfers_page(explorer, owner_address, start_block, |transfers| {
+        for t in transfers {
+            last_block = last_block.max(Some(t.block_number));
+            tokens.insert(t.token_address);
+        }
+        Ok(())
+    })?;
+
+    Ok(last_block)
+}
+
+/// Collect approval of the specified `Approval` event log if any into
+/// `ct_txs`. ERC-721's `Approval` event shares the same topic but has token
+/// id indexed as well, so such logs are ignored.
+///
+/// # Arguments
+/// * `log` - event log whose owner topic matches owner wallet address
+/// * `ct_txs` - `HashMap` of token contract address to `HashMap` of spender
+///   addresses to merge collected approval into
+pub fn collect_approval_log(log: &EventLog, ct_txs: &mut ApprovalsMap) {
+    if log.topics.len() != 3 || log.topics[0] != explorer::APPROVAL_EVENT_TOPIC {
+        return;
+    }
+
+    // cleanup topic to get address (66 chars to 40 chars by remove first 26 chars)
+    let spender_topic = &log.topics[2];
+    if spender_topic.len() != 66 {
+        return;
+    }
+    let spender_addr = format!("0x{}", &spender_topic[26..]);
+
+    // logs are in ascending order, so the latest approval wins
+    ct_txs.entry(log.address.to_owned())
+        .or_default()
+        .insert(spender_addr, ApprovalRecord { block_number: log.block_number, timestamp: log.timestamp, tx_hash: log.transaction_hash.to_owned(), issue: None, user_op_hash: None });
+}
+
+/// Query allowance balances of all collected approvals.
+/// All token contracts are queried concurrently, but the number of in-flight
+/// RPC calls is bounded by `semaphore`, and at most `MAX_PENDING_QUERIES`
+/// token contracts are in progress at once to bound memory usage.
+/// If `use_multicall` is `true`, queries are batched into a handful of calls
+/// to Multicall3 contract.
+///
+/// Return vector of result from `query()` for each token contract address.
+/// Each result is also passed to `on_result` as soon as it completes, so
+/// callers can report progress without waiting for all of them.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `ct_txs` - collected approvals from `collect_approvals()`
+/// * `owner_address` - owner wallet address; lowercased
+/// * `use_multicall` - whether to batch queries via Multicall3 contract
+/// * `metadata_cache` - cache of token metadata; metadata of newly queried
+///   token contracts will be added and saved into it
+/// * `failed_contracts` - record of failed token contracts; those which
+///   consistently failed are skipped, and it is updated with the results
+/// * `on_result` - callback to be called for each result
+#[allow(clippy::too_many_arguments)]
+pub async fn query_all<R, F>(rpc: &R, semaphore: &Semaphore, mut ct_txs: ApprovalsMap, owner_address: &str, use_multicall: bool, metadata_cache: &mut MetadataCache, failed_contracts: &mut FailedContracts, mut on_result: F) -> Vec<QueryResult>
+where
+    R: ChainRpc + ?Sized,
+    F: FnMut(&QueryResult)
+{
+    let num_tokens = ct_txs.len();
+    ct_txs.retain(|ct, _| !failed_contracts.should_skip(ct));
+    if ct_txs.len() < num_tokens {
+        tracing::info!("Skipped {} token contracts which failed in {} consecutive scans; use --retry-failed to query them again", num_tokens - ct_txs.len(), failures::FAILURE_THRESHOLD);
+    }
+
+    let mut results: Vec<QueryResult> = Vec::with_capacity(ct_txs.len());
+    if use_multicall {
+        let cached_metadata = metadata_cache.get_all(ct_txs.keys());
+        multicall::query_all_multicall(rpc, semaphore, ct_txs, owner_address, &cached_metadata, |res| {
+            on_result(&res);
+            results.push(res);
+        }).await;
+    }
+    else {
+        let mut outputs = futures::stream::iter(ct_txs)
+            .map(|(ct, spenders)| {
+                let metadata = metadata_cache.get(&ct);
+                query(rpc, semaphore, ct, owner_address.to_owned(), spenders, metadata)
+            })
+            .buffer_unordered(MAX_PENDING_QUERIES);
+        while let Some(res) = outputs.next().await {
+            on_result(&res);
+            results.push(res);
+        }
+    }
+
+    for res in results.iter() {
+        match res {
+            Ok(r) => tracing::debug!("Queried {} with {} spenders", r.address, r.spender_allowances.len()),
+            Err(e) => tracing::debug!("Failed to query {} ({}); err={}", e.token_address, e.error.kind(), e.error),
+        }
+    }
+
+    metadata_cache.update(&results);
+    if let Err(e) = metadata_cache.save() {
+        tracing::warn!("{}", e);
+    }
+    failed_contracts.update(&results);
+    if let Err(e) = failed_contracts.save() {
+        tracing::warn!("{}", e);
+    }
+
+    results
+}
+
+/// Query owner's balance, and total supply of each successfully queried
+/// token contract, then fill in `balance` and `total_supply` fields of the
+/// specified results. Tokens which don't return either of them e.g. revert
+/// on `totalSupply()` just have such field left as `None`.
+/// If `use_multicall` is `true`, queries are batched into calls to
+/// Multicall3 contract.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `results` - results from `query_all()`
+/// * `owner_address` - owner wallet address; lowercased
+/// * `use_multicall` - whether to batch queries via Multicall3 contract
+pub async fn query_balance_and_supply<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, results: &mut [QueryResult], owner_address: &str, use_multicall: bool) -> Result<(), AppvkekError> {
+    let owner = get_address_from_str(owner_address)?;
+    let mut tokens: Vec<&mut TokenContractWithSpenderAllowances> = results.iter_mut()
+        .filter_map(|res| res.as_mut().ok())
+        .collect();
+
+    if use_multicall {
+        multicall::query_balance_and_supply_multicall(rpc, semaphore, &mut tokens, owner).await;
+    }
+    else {
+        query_balance_and_supply_individually(rpc, semaphore, &mut tokens, owner).await;
+    }
+    Ok(())
+}
+
+/// Query owner's balance, and total supply of the specified token contracts
+/// with a pair of calls for each of them.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `tokens` - token contracts to fill in `balance` and `total_supply`
+/// * `owner` - owner wallet address
+pub(crate) async fn query_balance_and_supply_individually<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, tokens: &mut [&mut TokenContractWithSpenderAllowances], owner: Address) {
+    let outputs: Vec<(Option<f64>, Option<f64>)> = futures::stream::iter(tokens.iter())
+        .map(|r| async move {
+            let contract = match get_address_from_str(&r.address) {
+                Ok(res) => res,
+                Err(_) => return (None, None),
+            };
+            let balance_f = with_permit(semaphore, rpc.call(contract, encode_balance_of(owner), None));
+            let total_supply_f = with_permit(semaphore, rpc.call(contract, TOTAL_SUPPLY_METHOD_ID.to_vec(), None));
+            let (balance, total_supply) = futures::join!(balance_f, total_supply_f);
+            (
+                balance.ok().and_then(|b| decode_token_amount(&b, r.decimals)),
+                total_supply.ok().and_then(|b| decode_token_amount(&b, r.decimals)),
+            )
+        })
+        .buffered(MAX_PENDING_QUERIES)
+        .collect()
+        .await;
+
+    for (r, (balance, total_supply)) in tokens.iter_mut().zip(outputs) {
+        r.balance = balance;
+        r.total_supply = total_supply;
+    }
+}
+
+/// Decode `uint256` returned data into amount in token unit.
+///
+/// # Arguments
+/// * `bytes` - returned data
+/// * `decimals` - number of decimals of token
+pub(crate) fn decode_token_amount(bytes: &[u8], decimals: u8) -> Option<f64> {
+    decode_uint(bytes).and_then(|amount| to_token_amount(amount, decimals).ok())
+}
+
+/// Format token name along with its symbol if any e.g. `BUSD Token (BUSD)`.
+///
+/// # Arguments
+/// * `r` - token contract
+pub fn format_token_name(r: &TokenContractWithSpenderAllowances) -> String {
+    // name is empty only if overridden without it
+    if r.name.is_empty() {
+        r.symbol.to_owned()
+    }
+    else if r.symbol.is_empty() || r.symbol == r.name {
+        r.name.to_owned()
+    }
+    else {
+        format!("{} ({})", r.name, r.symbol)
+    }
+}
+
+/// Remove approvals to well-known burn addresses from the specified
+/// results, as they can never be spent; see `util::is_burn_address()`.
+/// Token contracts left without approvals are removed as well.
+/// Return the number of approvals removed.
+///
+/// # Arguments
+/// * `results` - results from `query_all()`
+pub fn remove_burn_approvals(results: &mut Vec<QueryResult>) -> usize {
+    let mut num_removed = 0;
+    results.retain_mut(|res| {
+        let r = match res.as_mut() {
+            Ok(r) => r,
+            Err(_) => return true,
+        };
+        let num_spenders = r.spender_allowances.len();
+        r.spender_allowances.retain(|spender, _| !is_burn_address(spender));
+        num_removed += num_spenders - r.spender_allowances.len();
+        num_spenders == 0 || !r.spender_allowances.is_empty()
+    });
+    num_removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::*;
+    use crate::provider::AtBlock;
+    use web3::types::U256;
+
+    static OWNER: &str = "0x1111111111111111111111111111111111111111";
+    static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+    static SPENDER: &str = "0x3333333333333333333333333333333333333333";
+    static OTHER_SPENDER: &str = "0x4444444444444444444444444444444444444444";
+
+    fn approvals(spenders: &[&str]) -> ApprovalsMap {
+        let spenders = spenders.iter()
+            .map(|s| (s.to_string(), ApprovalRecord { block_number: 1, ..Default::default() }))
+            .collect();
+        HashMap::from([(TOKEN.to_owned(), spenders)])
+    }
+
+    #[test]
+    fn collect_approvals_keeps_latest_approval_of_owner() {
+        let mut failed = make_tx(OWNER, TOKEN, "0x095ea7b3", 12);
+        failed.is_error = true;
+        let explorer = MockExplorer::default()
+            .with_approval(OWNER, TOKEN, SPENDER, U256::from(1), 10)
+            .with_approval(OWNER, TOKEN, SPENDER, U256::from(2), 11)
+            .with_approval(OTHER_SPENDER, TOKEN, OWNER, U256::from(3), 11)
+            .with_tx(failed)
+            .with_tx(make_tx(OWNER, TOKEN, "0xa9059cbb", 13));
+
+        let mut ct_txs: ApprovalsMap = HashMap::new();
+        let last_block = collect_approvals(&explorer, OWNER, 0, &mut ct_txs, None).unwrap();
+
+        assert_eq!(last_block, Some(13));
+        assert_eq!(ct_txs.len(), 1);
+        let spenders = &ct_txs[TOKEN];
+        assert_eq!(spenders.len(), 1);
+        assert_eq!(spenders[SPENDER].block_number, 11);
+        assert_eq!(spenders[SPENDER].tx_hash, format!("0x{:064x}", 11));
+    }
+
+    #[test]
+    fn collect_approvals_skips_malformed_approve_unless_fail_fast() {
+        let explorer = MockExplorer::default()
+            .with_tx(make_tx(OWNER, TOKEN, "0x095ea7b3zz", 10))
+            .with_approval(OWNER, TOKEN, SPENDER, U256::from(1), 11);
+
+        let mut ct_txs: ApprovalsMap = HashMap::new();
+        let mut warnings = Vec::new();
+        let last_block = collect_approvals(&explorer, OWNER, 0, &mut ct_txs, Some(&mut warnings)).unwrap();
+        assert_eq!(last_block, Some(11));
+        assert!(ct_txs[TOKEN].contains_key(SPENDER));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].tx_hash, format!("0x{:064x}", 10));
+
+        assert!(collect_approvals(&explorer, OWNER, 0, &mut HashMap::new(), None).is_err());
+    }
+
+    #[test]
+    fn collect_approval_tolerates_short_and_dirty_calldata() {
+        let mut ct_txs: ApprovalsMap = HashMap::new();
+        // amount truncated to 2 bytes
+        collect_approval(&make_tx(OWNER, TOKEN, &format!("0x095ea7b3{:0>64}ffff", &SPENDER[2..]), 10), OWNER, &mut ct_txs).unwrap();
+        // spender only, with dirty upper bytes
+        collect_approval(&make_tx(OWNER, TOKEN, &format!("0x095ea7b3{:f>64}", &OTHER_SPENDER[2..]), 11), OWNER, &mut ct_txs).unwrap();
+
+        let spenders = &ct_txs[TOKEN];
+        assert_eq!(spenders[SPENDER].issue.as_deref(), Some("calldata is 30 bytes short of 32-byte boundary; padded with zeros"));
+        assert_eq!(spenders[OTHER_SPENDER].issue.as_deref(), Some("amount argument is missing; spender argument has non-zero upper bytes; lower 20 bytes are taken"));
+
+        assert!(collect_approval(&make_tx(OWNER, TOKEN, "0x095ea7b3", 12), OWNER, &mut ct_txs).is_err());
+    }
+
+    #[test]
+    fn collect_approval_logs_skips_erc721_approvals() {
+        let nft_log = EventLog {
+            address: OTHER_SPENDER.to_owned(),
+            topics: vec![explorer::APPROVAL_EVENT_TOPIC.to_owned(), format!("0x{:0>64}", &OWNER[2..]), format!("0x{:0>64}", &SPENDER[2..]), format!("0x{:064x}", 1)],
+            block_number: 14,
+            ..Default::default()
+        };
+        let explorer = MockExplorer::default()
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(1), 10)
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(2), 12)
+            .with_approval_log(OTHER_SPENDER, TOKEN, OWNER, U256::from(3), 13)
+            .with_log(nft_log);
+
+        let mut ct_txs: ApprovalsMap = HashMap::new();
+        let last_block = collect_approval_logs(&explorer, OWNER, 0, &mut ct_txs).unwrap();
+
+        assert_eq!(last_block, Some(14));
+        assert_eq!(ct_txs.len(), 1);
+        assert_eq!(ct_txs[TOKEN][SPENDER].block_number, 12);
+    }
+
+    #[test]
+    fn user_operation_approvals_are_attributed_to_smart_account() {
+        let entry_point = explorer::ENTRY_POINT_ADDRESSES[1];
+        let user_op_hash = format!("0x{:064x}", 0xabc);
+        let explorer = MockExplorer::default()
+            .with_approval_log(OWNER, TOKEN, SPENDER, U256::from(1), 10)
+            .with_approval_log(OWNER, TOKEN, OTHER_SPENDER, U256::from(1), 12)
+            .with_user_operation(entry_point, OWNER, &user_op_hash, true, 12)
+            // spoofed event not emitted by EntryPoint
+            .with_user_operation(TOKEN, OWNER, &user_op_hash, true, 10);
+
+        assert!(is_smart_account(&explorer, OWNER).unwrap());
+        assert!(!is_smart_account(&explorer, SPENDER).unwrap());
+
+        let mut ct_txs: ApprovalsMap = HashMap::new();
+        let last_block = collect_user_operation_approvals(&explorer, OWNER, 0, &mut ct_txs).unwrap();
+
+        assert_eq!(last_block, Some(12));
+        assert_eq!(ct_txs[TOKEN][OTHER_SPENDER].user_op_hash.as_deref(), Some(user_op_hash.as_str()));
+        assert_eq!(ct_txs[TOKEN][SPENDER].user_op_hash, None);
+    }
+
+    #[test]
+    fn collect_held_tokens_covers_sent_and_received_tokens() {
+        let explorer = MockExplorer::default()
+            .with_token_transfer(TOKEN, OTHER_SPENDER, OWNER, 10)
+            .with_token_transfer(SPENDER, OWNER, OTHER_SPENDER, 12)
+            .with_token_transfer(OTHER_SPENDER, SPENDER, OTHER_SPENDER, 13);
+
+        let mut tokens: HashSet<String> = HashSet::new();
+        let last_block = collect_held_tokens(&explorer, OWNER, 0, &mut tokens).unwrap();
+
+        assert_eq!(last_block, Some(12));
+        assert_eq!(tokens, HashSet::from([TOKEN.to_owned(), SPENDER.to_owned()]));
+
+        let mut tokens: HashSet<String> = HashSet::new();
+        assert_eq!(collect_held_tokens(&explorer, OWNER, 11, &mut tokens).unwrap(), Some(12));
+        assert_eq!(tokens, HashSet::from([SPENDER.to_owned()]));
+    }
+
+    #[tokio::test]
+    async fn query_scales_allowance_by_decimals() {
+        let rpc = MockChainRpc::default()
+            .with_erc20(TOKEN, "Token", "TKN", 6)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::from(1_500_000))
+            .with_allowance(TOKEN, OWNER, OTHER_SPENDER, U256::MAX);
+        let semaphore = Semaphore::new(4);
+
+        let r = query(&rpc, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), approvals(&[SPENDER, OTHER_SPENDER]).remove(TOKEN).unwrap(), None).await.unwrap();
+
+        assert_eq!(r.name, "Token");
+        assert_eq!(r.symbol, "TKN");
+        assert_eq!(r.decimals, 6);
+        assert_eq!(r.spender_allowances[SPENDER].allowance, 1.5);
+        assert!(!r.spender_allowances[SPENDER].is_unlimited);
+        assert!(r.spender_allowances[OTHER_SPENDER].is_unlimited);
+    }
+
+    #[tokio::test]
+    async fn query_at_block_reads_historical_allowance() {
+        let rpc = MockChainRpc::default()
+            .with_erc20(TOKEN, "Token", "TKN", 0)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::zero())
+            .with_allowance_at(TOKEN, OWNER, SPENDER, U256::from(100), 10);
+        let semaphore = Semaphore::new(4);
+
+        let at_block = AtBlock { inner: &rpc, block: Some(10) };
+        let r = query(&at_block, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), approvals(&[SPENDER]).remove(TOKEN).unwrap(), None).await.unwrap();
+        assert_eq!(r.spender_allowances[SPENDER].allowance, 100.0);
+
+        let r = query(&rpc, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), approvals(&[SPENDER]).remove(TOKEN).unwrap(), None).await.unwrap();
+        assert!(r.spender_allowances.get(SPENDER).map(|sa| sa.allowance == 0.0).unwrap_or(true));
+    }
+
+    #[tokio::test]
+    async fn query_tells_reverting_allowance_from_network_failure() {
+        let semaphore = Semaphore::new(4);
+        let ct_txs = approvals(&[SPENDER]);
+
+        // allowance() isn't served, so it reverts
+        for use_multicall in [false, true] {
+            let mut rpc = MockChainRpc::default().with_erc20(TOKEN, "Token", "TKN", 18);
+            if use_multicall {
+                rpc = rpc.with_multicall();
+            }
+            let results = query_all(&rpc, &semaphore, ct_txs.clone(), OWNER, use_multicall, &mut MetadataCache::default(), &mut FailedContracts::default(), |_| {}).await;
+            let e = results[0].as_ref().unwrap_err();
+            assert_eq!(e.error.kind(), "reverted");
+            assert!(e.error.to_string().contains("(token reverts on allowance(), likely non-standard)"), "{}", e.error);
+            assert_eq!(failures::classify_error(&e.error), Some(failures::FailureClass::Allowance));
+        }
+
+        let rpc = MockChainRpc::default().with_rpc_error(TOKEN);
+        let e = query(&rpc, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), ct_txs[TOKEN].clone(), None).await.unwrap_err();
+        assert!(e.error.is_transient());
+        assert!(e.error.to_string().contains("(network failure; queried again on next scan)"), "{}", e.error);
+    }
+
+    #[tokio::test]
+    async fn query_fails_permanently_on_non_token() {
+        let rpc = MockChainRpc::default();
+        let semaphore = Semaphore::new(4);
+
+        let e = query(&rpc, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), approvals(&[SPENDER]).remove(TOKEN).unwrap(), None).await.unwrap_err();
+
+        assert_eq!(e.token_address, TOKEN);
+        assert!(!e.error.is_transient());
+        assert_eq!(failures::classify_error(&e.error), Some(failures::FailureClass::Metadata));
+    }
+
+    #[tokio::test]
+    async fn query_all_multicall_matches_individual_queries() {
+        let rpc = MockChainRpc::default()
+            .with_multicall()
+            .with_erc20(TOKEN, "Token", "", 18)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::exp10(18));
+        let semaphore = Semaphore::new(4);
+
+        let mut metadata_cache = MetadataCache::default();
+        let mut failed_contracts = FailedContracts::default();
+        let results = query_all(&rpc, &semaphore, approvals(&[SPENDER]), OWNER, true, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+
+        assert_eq!(rpc.num_calls(), 1);
+        let r = results[0].as_ref().unwrap();
+        assert_eq!(r.symbol, "");
+        assert_eq!(r.spender_allowances[SPENDER].allowance, 1.0);
+    }
+
+    #[tokio::test]
+    async fn query_balance_and_supply_leaves_unreturned_ones_unset() {
+        let semaphore = Semaphore::new(4);
+
+        for use_multicall in [false, true] {
+            // OTHER_SPENDER doubles as a token which doesn't implement totalSupply()
+            let mut rpc = MockChainRpc::default()
+                .with_erc20(TOKEN, "Token", "TKN", 2)
+                .with_allowance(TOKEN, OWNER, SPENDER, U256::from(500))
+                .with_balance(TOKEN, OWNER, U256::from(2_000))
+                .with_total_supply(TOKEN, U256::from(100_000))
+                .with_erc20(OTHER_SPENDER, "Other", "OTH", 0)
+                .with_allowance(OTHER_SPENDER, OWNER, SPENDER, U256::from(1))
+                .with_balance(OTHER_SPENDER, OWNER, U256::zero());
+            if use_multicall {
+                rpc = rpc.with_multicall();
+            }
+            let mut results = vec![
+                query(&rpc, &semaphore, TOKEN.to_owned(), OWNER.to_owned(), approvals(&[SPENDER]).remove(TOKEN).unwrap(), None).await,
+                query(&rpc, &semaphore, OTHER_SPENDER.to_owned(), OWNER.to_owned(), approvals(&[SPENDER]).remove(TOKEN).unwrap(), None).await,
+            ];
+
+            let num_calls = rpc.num_calls();
+            query_balance_and_supply(&rpc, &semaphore, &mut results, OWNER, use_multicall).await.unwrap();
+            assert_eq!(rpc.num_calls() - num_calls, if use_multicall { 1 } else { 4 });
+
+            let r = results[0].as_ref().unwrap();
+            assert_eq!((r.balance, r.total_supply), (Some(20.0), Some(1_000.0)));
+            assert_eq!(r.percent_of_balance(5.0), Some(25.0));
+            assert_eq!(r.percent_of_supply(5.0), Some(0.5));
+
+            let r = results[1].as_ref().unwrap();
+            assert_eq!((r.balance, r.total_supply), (Some(0.0), None));
+            assert_eq!(r.percent_of_balance(1.0), None);
+            assert_eq!(r.percent_of_supply(1.0), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn query_all_multicall_falls_back_on_failed_batch() {
+        let rpc = MockChainRpc::default()
+            .with_erc20(TOKEN, "Token", "TKN", 18)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::zero());
+        let semaphore = Semaphore::new(4);
+
+        let mut metadata_cache = MetadataCache::default();
+        let mut failed_contracts = FailedContracts::default();
+        let results = query_all(&rpc, &semaphore, approvals(&[SPENDER]), OWNER, true, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+
+        // one failed aggregate3, then name, symbol, decimals, and allowance
+        assert_eq!(rpc.num_calls(), 5);
+        assert_eq!(results[0].as_ref().unwrap().spender_allowances[SPENDER].allowance, 0.0);
+    }
+
+    #[tokio::test]
+    async fn remove_burn_approvals_drops_burn_spenders_and_emptied_tokens() {
+        let dead = BURN_ADDRESSES[1];
+        let rpc = MockChainRpc::default()
+            .with_erc20(TOKEN, "Token", "TKN", 18)
+            .with_allowance(TOKEN, OWNER, SPENDER, U256::from(1))
+            .with_allowance(TOKEN, OWNER, dead, U256::from(1))
+            .with_erc20(OTHER_SPENDER, "Other", "OTH", 18)
+            .with_allowance(OTHER_SPENDER, OWNER, BURN_ADDRESSES[0], U256::from(1));
+        let semaphore = Semaphore::new(4);
+
+        let mut ct_txs = approvals(&[SPENDER, dead]);
+        ct_txs.insert(OTHER_SPENDER.to_owned(), HashMap::from([(BURN_ADDRESSES[0].to_owned(), ApprovalRecord::default())]));
+        let mut results = query_all(&rpc, &semaphore, ct_txs, OWNER, false, &mut MetadataCache::default(), &mut FailedContracts::default(), |_| {}).await;
+
+        assert_eq!(remove_burn_approvals(&mut results), 2);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].as_ref().unwrap().spender_allowances.contains_key(SPENDER));
+    }
+}
@@ -0,0 +1,126 @@
+use ::evmscan::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::error::AppvkekError;
+use crate::types::*;
+use crate::util::*;
+
+/// Snapshot of a single token contract from a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenSnapshot {
+    /// Contract name
+    pub name: String,
+
+    /// Spender address to its allowance balance
+    pub spender_allowances: HashMap<String, f64>,
+
+    /// Spender address to risk score of its approval; absent in state files
+    /// of older versions
+    #[serde(default)]
+    pub risk_scores: HashMap<String, u32>,
+}
+
+/// Snapshot of token contract address to its `TokenSnapshot`.
+pub type Snapshot = HashMap<String, TokenSnapshot>;
+
+/// State of watch mode which is persisted to disk in daemon mode.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    /// Highest block number of transactions that has been scanned
+    pub last_scanned_block: Option<u64>,
+
+    /// All approvals collected so far
+    pub approvals: ApprovalsMap,
+
+    /// Baseline snapshot to compare the next scan against
+    pub baseline: Option<Snapshot>,
+}
+
+/// State of a normal (non-watch) scan which is persisted to disk so that
+/// subsequent scans only need to fetch newer transactions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    /// Highest block number of transactions that has been scanned
+    pub last_scanned_block: Option<u64>,
+
+    /// All approvals collected so far
+    pub approvals: ApprovalsMap,
+
+    /// Highest block number of token transfers that has been scanned
+    #[serde(default)]
+    pub last_transfer_block: Option<u64>,
+
+    /// Token contract addresses (lowercased) the wallet has ever sent or
+    /// received
+    #[serde(default)]
+    pub held_tokens: HashSet<String>,
+}
+
+/// Return path of state file for normal scan.
+/// It is `$HOME/.appvkek/scan-<chain>-<owner_address>.json`.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn scan_state_file_path(chain: ChainType, owner_address: &str) -> String {
+    format!("{}/scan-{}-{}.json", appvkek_dir(), chain_name(chain), owner_address)
+}
+
+/// Return default path of state file for daemon mode.
+/// It is `$HOME/.appvkek/state-<chain>-<owner_address>.json`.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn default_state_file_path(chain: ChainType, owner_address: &str) -> String {
+    format!("{}/state-{}-{}.json", appvkek_dir(), chain_name(chain), owner_address)
+}
+
+/// Load state from the specified file.
+/// Return `None` if such file doesn't exist yet.
+///
+/// # Arguments
+/// * `path` - path to state file
+pub fn load_state<T: DeserializeOwned>(path: &str) -> Result<Option<T>, AppvkekError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppvkekError::Io(format!("Error reading state file ({}); err={}", path, e))),
+    };
+
+    match serde_json::from_str::<T>(&content) {
+        Ok(res) => Ok(Some(res)),
+        Err(e) => Err(AppvkekError::Io(format!("Error parsing state file ({}); err={}", path, e))),
+    }
+}
+
+/// Save state into the specified file.
+/// It writes into a temporary file first then renames it, so the state file
+/// won't be left corrupted if the process is killed in the middle.
+///
+/// # Arguments
+/// * `path` - path to state file
+/// * `state` - state to save
+pub fn save_state<T: Serialize>(path: &str, state: &T) -> Result<(), AppvkekError> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            return Err(AppvkekError::Io(format!("Error creating directory for state file ({}); err={}", path, e)));
+        }
+    }
+
+    let content = match serde_json::to_string(state) {
+        Ok(res) => res,
+        Err(e) => return Err(AppvkekError::Io(format!("Error serializing state; err={}", e))),
+    };
+
+    let tmp_path = format!("{}.tmp", path);
+    if let Err(e) = std::fs::write(&tmp_path, content) {
+        return Err(AppvkekError::Io(format!("Error writing state file ({}); err={}", tmp_path, e)));
+    }
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        return Err(AppvkekError::Io(format!("Error renaming state file ({}) to ({}); err={}", tmp_path, path, e)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,649 @@
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use jsonrpc_core::{Call, ErrorCode, Value};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use web3::error::{Error, Result, TransportError};
+use web3::transports::Http;
+use web3::{BatchTransport, RequestId, Transport};
+
+use crate::debugdump;
+use crate::ratelimit::TokenBucket;
+use crate::replay;
+use crate::usage;
+
+/// JSON-RPC error code used by node providers to signal rate limiting
+pub(crate) const RPC_LIMIT_EXCEEDED_CODE: i64 = -32005;
+
+/// Number of consecutive failures after which an endpoint is demoted
+const DEMOTE_THRESHOLD: u32 = 3;
+
+/// How long a demoted endpoint is avoided before it's tried again
+const DEMOTE_DURATION: Duration = Duration::from_secs(60);
+
+/// Weight of the latest round trip in smoothed latency of an endpoint
+const LATENCY_WEIGHT: f64 = 0.3;
+
+/// Policy of retrying requests which failed for transient reasons.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+
+    /// Delay before the first retry; doubled on each subsequent retry
+    pub base_delay: Duration,
+
+    /// Upper bound of delay between retries
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Return delay before the specified retry with up to 50% of random
+    /// jitter added, so that concurrent requests failed at the same time
+    /// don't retry at the same time again.
+    ///
+    /// # Arguments
+    /// * `retry` - retry number starting from 0
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self.base_delay.saturating_mul(2u32.saturating_pow(retry)).min(self.max_delay);
+        let max_jitter_nanos = (delay.as_nanos() / 2) as u64;
+        if max_jitter_nanos == 0 {
+            return delay;
+        }
+
+        // sub-second part of current time is random enough for jitter
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0);
+        delay + Duration::from_nanos(nanos % max_jitter_nanos)
+    }
+}
+
+/// Return whether the specified error is transient so the request is worth
+/// retrying e.g. connection failure, HTTP 429 or 5xx, rate limiting by node
+/// provider, or node failing to process it; see `is_node_failure()`. Reverted
+/// calls, and invalid requests are not transient.
+///
+/// # Arguments
+/// * `err` - error from transport
+pub fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Unreachable => true,
+        Error::Transport(TransportError::Code(code)) => *code == 429 || *code >= 500,
+        // failed to connect, timed out, or connection closed in the middle
+        Error::Transport(TransportError::Message(msg)) => msg.starts_with("failed to send request") || msg.starts_with("failed to read response bytes"),
+        Error::Rpc(e) => e.code == ErrorCode::ServerError(RPC_LIMIT_EXCEEDED_CODE) || is_node_failure(e),
+        _ => false,
+    }
+}
+
+/// Return whether the specified error returned by node is its failure to
+/// process the request e.g. internal error, missing state, or timeout, as
+/// opposed to the call itself reverting. Nodes report both as server error,
+/// so they are told apart by message.
+///
+/// # Arguments
+/// * `err` - JSON-RPC error returned by node
+pub(crate) fn is_node_failure(err: &jsonrpc_core::Error) -> bool {
+    let msg = err.message.to_lowercase();
+    let is_revert = ["revert", "opcode", "out of gas", "stack", "invalid jump", "write protection"].iter().any(|k| msg.contains(k));
+    match err.code {
+        // execution reverted with revert data as of EIP-1474
+        ErrorCode::ServerError(3) => false,
+        ErrorCode::ServerError(_) | ErrorCode::InternalError => !is_revert,
+        _ => false,
+    }
+}
+
+/// Return whether the specified error tells the endpoint itself is failing
+/// rather than the request e.g. transient errors, or HTTP errors such as
+/// invalid API key.
+///
+/// # Arguments
+/// * `err` - error from transport
+fn is_endpoint_failure(err: &Error) -> bool {
+    is_transient(err) || matches!(err, Error::Transport(TransportError::Code(_)))
+}
+
+/// Call `f` until it succeeds, fails with non-transient error, or retries
+/// are exhausted according to `policy`.
+///
+/// # Arguments
+/// * `policy` - retry policy
+/// * `f` - function making the request
+async fn with_retry<T, F, Fut>(policy: RetryPolicy, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: core::future::Future<Output = Result<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match f().await {
+            Err(e) if retry < policy.max_retries && is_transient(&e) => {
+                let delay = policy.delay(retry);
+                tracing::debug!("Retrying RPC request in {:?} ({}/{}); err={}", delay, retry + 1, policy.max_retries, e);
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            },
+            res => return res,
+        }
+    }
+}
+
+/// Counters of requests sent by transport, including retries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransportStats {
+    /// Number of JSON-RPC requests; each request in a batch is counted
+    pub rpc_calls: u64,
+
+    /// Number of HTTP requests
+    pub http_requests: u64,
+}
+
+/// Result of probing an RPC endpoint.
+#[derive(Debug, Clone)]
+pub struct EndpointProbe {
+    /// Name of endpoint; see `endpoint_name()`
+    pub name: String,
+
+    /// Round trip time of `eth_blockNumber`, or error message
+    pub result: std::result::Result<Duration, String>,
+}
+
+/// Return name of the specified RPC endpoint URL to show in logs; only its
+/// host, and port as path or query may contain API key.
+///
+/// # Arguments
+/// * `url` - URL of RPC endpoint
+pub fn endpoint_name(url: &reqwest::Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => url.scheme().to_owned(),
+    }
+}
+
+/// A single RPC endpoint along with its observed health.
+#[derive(Debug)]
+struct Endpoint {
+    /// Name of endpoint; see `endpoint_name()`
+    name: String,
+
+    /// Underlying HTTP transport
+    http: Http,
+
+    /// Smoothed round trip time in microseconds; 0 if not measured yet
+    latency_us: AtomicU64,
+
+    /// Number of consecutive failures
+    failures: AtomicU32,
+
+    /// Until when it's demoted if it is
+    demoted_until: Mutex<Option<Instant>>,
+
+    /// Whether it has rejected a batch request, so requests will be sent
+    /// to it individually from then on
+    is_batch_unsupported: AtomicBool,
+
+    /// Rate limiter of requests to this endpoint if it has its own limit;
+    /// otherwise requests go through shared limiter of `BatchingHttp`
+    limiter: Option<TokenBucket>,
+}
+
+impl Endpoint {
+    /// Return whether it's demoted at the specified time.
+    ///
+    /// # Arguments
+    /// * `now` - current time
+    fn is_demoted(&self, now: Instant) -> bool {
+        self.demoted_until.lock().unwrap().map(|t| t > now).unwrap_or(false)
+    }
+}
+
+/// Pending request waiting to be sent in the next batch.
+type PendingRequest = (RequestId, Call, oneshot::Sender<Result<Value>>);
+
+/// Shared state of `BatchingHttp`.
+#[derive(Debug)]
+struct Inner {
+    /// RPC endpoints in order of preference until their latency is measured
+    endpoints: Vec<Endpoint>,
+
+    /// Requests waiting to be sent
+    pending: Mutex<Vec<PendingRequest>>,
+
+    /// Number of JSON-RPC requests sent
+    rpc_calls: AtomicU64,
+
+    /// Number of HTTP requests sent
+    http_requests: AtomicU64,
+}
+
+impl Inner {
+    /// Return index of endpoint to send the next request to; the lowest
+    /// latency one among those not demoted, or the one whose demotion ends
+    /// the earliest if all are demoted. Endpoints not measured yet are tried
+    /// first in order.
+    fn select_endpoint(&self) -> usize {
+        let now = Instant::now();
+        let healthy = self.endpoints.iter().enumerate()
+            .filter(|(_, e)| !e.is_demoted(now))
+            .min_by_key(|(_, e)| e.latency_us.load(Ordering::Relaxed));
+        if let Some((i, _)) = healthy {
+            return i;
+        }
+        self.endpoints.iter().enumerate()
+            .min_by_key(|(_, e)| *e.demoted_until.lock().unwrap())
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Record how a request to the specified endpoint went. Its latency is
+    /// updated if it responded, otherwise it's demoted after failing
+    /// `DEMOTE_THRESHOLD` times in a row. A single endpoint is never demoted
+    /// as there is no other to turn to.
+    ///
+    /// # Arguments
+    /// * `index` - index of endpoint
+    /// * `elapsed` - round trip time of the request
+    /// * `res` - result of the request
+    fn record_outcome<T>(&self, index: usize, elapsed: Duration, res: &Result<T>) {
+        let endpoint = &self.endpoints[index];
+        match res {
+            Err(e) if is_endpoint_failure(e) => {
+                let failures = endpoint.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if failures >= DEMOTE_THRESHOLD && self.endpoints.len() > 1 {
+                    self.demote(index, &e.to_string());
+                }
+            },
+            _ => {
+                let sample = elapsed.as_micros() as u64;
+                let _ = endpoint.latency_us.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |latency| {
+                    Some(if latency == 0 { sample } else { (latency as f64 * (1.0 - LATENCY_WEIGHT) + sample as f64 * LATENCY_WEIGHT) as u64 })
+                });
+                endpoint.failures.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Demote the specified endpoint for `DEMOTE_DURATION`.
+    ///
+    /// # Arguments
+    /// * `index` - index of endpoint
+    /// * `reason` - why it's demoted
+    fn demote(&self, index: usize, reason: &str) {
+        let endpoint = &self.endpoints[index];
+        endpoint.failures.store(0, Ordering::Relaxed);
+        *endpoint.demoted_until.lock().unwrap() = Some(Instant::now() + DEMOTE_DURATION);
+        tracing::warn!("Demoted RPC endpoint {} for {}s as it's failing; err={}", endpoint.name, DEMOTE_DURATION.as_secs(), reason);
+    }
+}
+
+/// HTTP transport which automatically groups requests made within a short
+/// window into a single JSON-RPC batch request.
+///
+/// Requests are flushed either when `window` has elapsed since the first
+/// pending request, or when `max_batch_size` requests are pending.
+/// If the endpoint rejects a batch request, requests are sent to it
+/// individually from then on.
+///
+/// With multiple endpoints, each request goes to the lowest latency one
+/// which is not demoted; see `probe_endpoints()`. An endpoint failing
+/// `DEMOTE_THRESHOLD` times in a row is avoided for `DEMOTE_DURATION`, so
+/// retries go to the next one.
+///
+/// Requests (and batch requests) failed for transient reasons are retried
+/// with exponential backoff according to `RetryPolicy`.
+///
+/// All requests including retries go through `limiter`, or limiter of the
+/// endpoint they are sent to if it has its own; a batch request takes as many
+/// tokens as the number of requests in it, as node providers count them
+/// individually.
+///
+/// Underlying HTTP client keeps connections alive, so even individual
+/// requests benefit from connection reuse.
+#[derive(Debug, Clone)]
+pub struct BatchingHttp {
+    /// Maximum number of requests in a single batch; 1 disables batching
+    max_batch_size: usize,
+
+    /// How long to wait for more requests before sending a batch
+    window: Duration,
+
+    /// Policy of retrying transient failures
+    retry: RetryPolicy,
+
+    /// Rate limiter of outgoing requests to endpoints without their own limit
+    limiter: Arc<TokenBucket>,
+
+    /// Shared state
+    inner: Arc<Inner>,
+}
+
+impl BatchingHttp {
+    /// Create a new batching transport.
+    ///
+    /// # Arguments
+    /// * `endpoints` - names of RPC endpoints along with their underlying HTTP
+    ///   transport, and their own rate limiter if any in order of preference;
+    ///   there must be at least one
+    /// * `max_batch_size` - maximum number of requests in a single batch; 1 disables batching
+    /// * `window` - how long to wait for more requests before sending a batch
+    /// * `retry` - policy of retrying transient failures
+    /// * `limiter` - rate limiter of outgoing requests to endpoints without their own
+    pub fn new(endpoints: Vec<(String, Http, Option<TokenBucket>)>, max_batch_size: usize, window: Duration, retry: RetryPolicy, limiter: TokenBucket) -> BatchingHttp {
+        assert!(!endpoints.is_empty(), "at least one RPC endpoint is required");
+        let endpoints = endpoints.into_iter()
+            .map(|(name, http, limiter)| Endpoint {
+                name,
+                http,
+                latency_us: AtomicU64::new(0),
+                failures: AtomicU32::new(0),
+                demoted_until: Mutex::new(None),
+                is_batch_unsupported: AtomicBool::new(false),
+                limiter,
+            })
+            .collect();
+        BatchingHttp {
+            max_batch_size: max_batch_size.max(1),
+            window,
+            retry,
+            limiter: Arc::new(limiter),
+            inner: Arc::new(Inner {
+                endpoints,
+                pending: Mutex::new(Vec::new()),
+                rpc_calls: AtomicU64::new(0),
+                http_requests: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Return counters of requests sent so far.
+    pub fn stats(&self) -> TransportStats {
+        TransportStats {
+            rpc_calls: self.inner.rpc_calls.load(Ordering::Relaxed),
+            http_requests: self.inner.http_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Wait until the specified number of requests can be sent to the
+    /// specified endpoint according to its own limiter if any, or the shared
+    /// one.
+    ///
+    /// # Arguments
+    /// * `index` - index of endpoint
+    /// * `count` - number of requests
+    async fn acquire(&self, index: usize, count: usize) {
+        match &self.inner.endpoints[index].limiter {
+            Some(limiter) => limiter.acquire(count).await,
+            None => self.limiter.acquire(count).await,
+        }
+    }
+
+    /// Send `eth_blockNumber` to all endpoints concurrently to measure their
+    /// latency, so that requests go to the fastest one from the start.
+    /// Endpoints failing to respond are demoted right away. Nothing is sent
+    /// if there is only one endpoint as there is nothing to choose from, or
+    /// while replaying.
+    ///
+    /// Return result of each endpoint in order.
+    pub async fn probe_endpoints(&self) -> Vec<EndpointProbe> {
+        if self.inner.endpoints.len() < 2 || replay::is_replaying() {
+            return Vec::new();
+        }
+
+        let futs = self.inner.endpoints.iter().enumerate().map(|(i, endpoint)| async move {
+            let (id, call) = endpoint.http.prepare("eth_blockNumber", vec![]);
+            self.acquire(i, 1).await;
+            self.count_request(1);
+            let start = Instant::now();
+            let res = endpoint.http.send(id, call.clone()).await;
+            let elapsed = start.elapsed();
+            Self::record_result(&call, &res);
+            match res {
+                Ok(_) => {
+                    self.inner.record_outcome(i, elapsed, &res);
+                    EndpointProbe { name: endpoint.name.to_owned(), result: Ok(elapsed) }
+                },
+                Err(e) => {
+                    self.inner.demote(i, &e.to_string());
+                    EndpointProbe { name: endpoint.name.to_owned(), result: Err(e.to_string()) }
+                }
+            }
+        });
+        futures::future::join_all(futs).await
+    }
+
+    /// Return name of endpoint the next request would go to.
+    pub fn selected_endpoint(&self) -> String {
+        self.inner.endpoints[self.inner.select_endpoint()].name.to_owned()
+    }
+
+    /// Count a single HTTP request containing the specified number of
+    /// JSON-RPC requests.
+    ///
+    /// # Arguments
+    /// * `rpc_calls` - number of JSON-RPC requests
+    fn count_request(&self, rpc_calls: usize) {
+        self.inner.rpc_calls.fetch_add(rpc_calls as u64, Ordering::Relaxed);
+        self.inner.http_requests.fetch_add(1, Ordering::Relaxed);
+        usage::record_rpc_request(rpc_calls);
+    }
+
+    /// Count bytes of the specified request along with its result into usage,
+    /// and dump them if enabled.
+    ///
+    /// # Arguments
+    /// * `call` - JSON-RPC request
+    /// * `res` - its result
+    fn record_result(call: &Call, res: &Result<Value>) {
+        let request_bytes = serde_json::to_vec(call).map(|b| b.len()).unwrap_or(0);
+        let response_bytes = res.as_ref().ok().and_then(|v| serde_json::to_vec(v).ok()).map(|b| b.len()).unwrap_or(0);
+        usage::record_rpc_bytes(request_bytes + response_bytes);
+        debugdump::record_rpc(call, res);
+    }
+
+    /// Send all pending requests.
+    async fn flush(self) {
+        let pending: Vec<PendingRequest> = std::mem::take(&mut *self.inner.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+
+        let index = self.inner.select_endpoint();
+        if pending.len() == 1 || self.inner.endpoints[index].is_batch_unsupported.load(Ordering::Relaxed) {
+            self.send_individually(pending).await;
+            return;
+        }
+
+        let requests: Vec<(RequestId, Call)> = pending.iter().map(|(id, call, _)| (*id, call.clone())).collect();
+        tracing::trace!("Sending batch of {} RPC requests", requests.len());
+        let span = tracing::debug_span!("rpc_batch", size = requests.len(), endpoint = tracing::field::Empty);
+        // endpoint is selected again on each retry as the previous one may have been demoted
+        let used_index = AtomicUsize::new(index);
+        let batch_res = with_retry(self.retry, || async {
+            let index = self.inner.select_endpoint();
+            self.acquire(index, requests.len()).await;
+            used_index.store(index, Ordering::Relaxed);
+            self.count_request(requests.len());
+            let start = Instant::now();
+            let res = self.inner.endpoints[index].http.send_batch(requests.clone()).await;
+            self.inner.record_outcome(index, start.elapsed(), &res);
+            res
+        }).instrument(span.clone()).await;
+        span.record("endpoint", self.inner.endpoints[used_index.load(Ordering::Relaxed)].name.as_str());
+        match batch_res {
+            Ok(results) => {
+                let mut retries = Vec::new();
+                for ((id, call, tx), res) in pending.into_iter().zip(results) {
+                    Self::record_result(&call, &res);
+                    match res {
+                        // e.g. rate limited for some requests in batch
+                        Err(e) if self.retry.max_retries > 0 && is_transient(&e) => retries.push((id, call, tx)),
+                        res => {
+                            let _ = tx.send(res);
+                        }
+                    }
+                }
+                self.send_individually(retries).await;
+            },
+            Err(e) if is_transient(&e) => {
+                // endpoints are still down after all retries
+                for (_, call, tx) in pending {
+                    let res = Err(e.clone());
+                    Self::record_result(&call, &res);
+                    let _ = tx.send(res);
+                }
+            },
+            Err(e) => {
+                // endpoint likely doesn't support batch request
+                let endpoint = &self.inner.endpoints[used_index.load(Ordering::Relaxed)];
+                tracing::info!("RPC endpoint {} rejected batch request, sending requests to it individually from now on; err={}", endpoint.name, e);
+                endpoint.is_batch_unsupported.store(true, Ordering::Relaxed);
+                self.send_individually(pending).await;
+            }
+        }
+    }
+
+    /// Send the specified requests individually but concurrently.
+    ///
+    /// # Arguments
+    /// * `pending` - requests to send
+    async fn send_individually(&self, pending: Vec<PendingRequest>) {
+        let futs = pending.into_iter().map(|(id, call, tx)| {
+            let res = self.send_single(id, call);
+            async move {
+                let _ = tx.send(res.await);
+            }
+        });
+        futures::future::join_all(futs).await;
+    }
+
+    /// Send a single request without batching, retrying on transient failure.
+    ///
+    /// # Arguments
+    /// * `id` - request id
+    /// * `call` - request
+    fn send_single(&self, id: RequestId, call: Call) -> BoxFuture<'static, Result<Value>> {
+        let this = self.clone();
+        let method = match &call {
+            Call::MethodCall(m) => m.method.to_owned(),
+            _ => String::new(),
+        };
+        let span = tracing::debug_span!("rpc_call", method = method.as_str(), endpoint = tracing::field::Empty);
+        async move {
+            let res = with_retry(this.retry, || async {
+                let index = this.inner.select_endpoint();
+                this.acquire(index, 1).await;
+                tracing::Span::current().record("endpoint", this.inner.endpoints[index].name.as_str());
+                this.count_request(1);
+                let start = Instant::now();
+                let res = this.inner.endpoints[index].http.send(id, call.clone()).await;
+                this.inner.record_outcome(index, start.elapsed(), &res);
+                res
+            }).await;
+            Self::record_result(&call, &res);
+            res
+        }.instrument(span).boxed()
+    }
+}
+
+impl Transport for BatchingHttp {
+    type Out = BoxFuture<'static, Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        // request ids are taken from the first endpoint so they never collide
+        self.inner.endpoints[0].http.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        if replay::is_replaying() {
+            let res = replay::rpc_response(&request);
+            self.count_request(1);
+            Self::record_result(&request, &res);
+            return futures::future::ready(res).boxed();
+        }
+        if self.max_batch_size == 1 {
+            return self.send_single(id, request);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let num_pending = {
+            let mut pending = self.inner.pending.lock().unwrap();
+            pending.push((id, request, tx));
+            pending.len()
+        };
+
+        // batch is sent in background, so it's traced as part of the caller's span
+        if num_pending >= self.max_batch_size {
+            tokio::spawn(self.clone().flush().in_current_span());
+        }
+        else if num_pending == 1 {
+            let this = self.clone();
+            let window = self.window;
+            tokio::spawn(async move {
+                tokio::time::sleep(window).await;
+                this.flush().await;
+            }.in_current_span());
+        }
+
+        rx.map(|res| match res {
+            Ok(res) => res,
+            Err(_) => Err(Error::Internal),
+        }).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport(num_endpoints: usize) -> BatchingHttp {
+        let endpoints = (0..num_endpoints)
+            .map(|i| (format!("endpoint-{}", i), Http::new(&format!("http://127.0.0.1:{}", 8545 + i)).unwrap(), None))
+            .collect();
+        let retry = RetryPolicy { max_retries: 0, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+        BatchingHttp::new(endpoints, 1, Duration::ZERO, retry, TokenBucket::new(0.0, 0))
+    }
+
+    #[test]
+    fn selects_lowest_latency_endpoint_and_demotes_failing_one() {
+        let transport = transport(2);
+        let inner = &transport.inner;
+        assert_eq!(inner.select_endpoint(), 0);
+
+        inner.record_outcome(0, Duration::from_millis(300), &Ok(()));
+        inner.record_outcome(1, Duration::from_millis(50), &Ok(()));
+        assert_eq!(inner.select_endpoint(), 1);
+
+        // reverted call says nothing about endpoint's health
+        let revert: Result<()> = Err(Error::Rpc(jsonrpc_core::Error { code: ErrorCode::ServerError(3), message: "execution reverted".to_owned(), data: None }));
+        for _ in 0..DEMOTE_THRESHOLD {
+            inner.record_outcome(1, Duration::from_millis(50), &revert);
+        }
+        assert_eq!(inner.select_endpoint(), 1);
+
+        for _ in 0..DEMOTE_THRESHOLD {
+            inner.record_outcome(1, Duration::from_millis(50), &Err::<(), _>(Error::Unreachable));
+        }
+        assert_eq!(inner.select_endpoint(), 0);
+        assert_eq!(transport.selected_endpoint(), "endpoint-0");
+
+        // if all are demoted, the one whose demotion ends the earliest is used
+        inner.demote(0, "unreachable");
+        assert_eq!(inner.select_endpoint(), 1);
+    }
+
+    #[test]
+    fn single_endpoint_is_never_demoted() {
+        let transport = transport(1);
+        for _ in 0..DEMOTE_THRESHOLD {
+            transport.inner.record_outcome(0, Duration::from_millis(50), &Err::<(), _>(Error::Unreachable));
+        }
+        assert!(!transport.inner.endpoints[0].is_demoted(Instant::now()));
+    }
+}
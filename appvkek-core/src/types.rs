@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::QueryError;
+use crate::exploits::ExploitRecord;
+use crate::explorer::{AddressActivity, ContractCreation};
+use crate::proxy::{ControlInfo, ProxyInfo};
+use crate::risk::RiskAssessment;
+
+/// Top-level meta information.
+#[derive(Debug, Clone)]
+pub struct TokenContractWithSpenderAllowances {
+    /// Contract name
+    pub name: String,
+
+    /// Token symbol; empty if token doesn't implement it
+    pub symbol: String,
+
+    /// Contract address
+    pub address: String,
+
+    /// Number of decimals to token
+    pub decimals: u8,
+
+    /// Balance of owner in token unit; `None` if not queried, or token
+    /// doesn't return it
+    pub balance: Option<f64>,
+
+    /// Total supply in token unit; `None` if not queried, or token doesn't
+    /// return it
+    pub total_supply: Option<f64>,
+
+    /// Hash map of spender with its associated allowance
+    pub spender_allowances: HashMap<String, SpenderAllowance>,
+}
+
+impl TokenContractWithSpenderAllowances {
+    /// Return the specified amount as percentage of owner's balance; `None`
+    /// if balance isn't known or is zero.
+    ///
+    /// # Arguments
+    /// * `amount` - amount in token unit
+    pub fn percent_of_balance(&self, amount: f64) -> Option<f64> {
+        percent_of(amount, self.balance)
+    }
+
+    /// Return the specified amount as percentage of total supply; `None` if
+    /// total supply isn't known or is zero.
+    ///
+    /// # Arguments
+    /// * `amount` - amount in token unit
+    pub fn percent_of_supply(&self, amount: f64) -> Option<f64> {
+        percent_of(amount, self.total_supply)
+    }
+}
+
+/// Return `amount` as percentage of `total` if it's known and non-zero.
+///
+/// # Arguments
+/// * `amount` - amount
+/// * `total` - total amount if known
+fn percent_of(amount: f64, total: Option<f64>) -> Option<f64> {
+    total.filter(|t| *t > 0.0).map(|t| amount / t * 100.0)
+}
+
+/// Allowance of a single spender.
+#[derive(Debug, Clone)]
+pub struct SpenderAllowance {
+    /// Allowance balance
+    /// It would be possible to hold maximum allowance value as maximum value of
+    /// `f64` is `1.7976931348623157e+308_f64`.
+    pub allowance: f64,
+
+    /// Whether the allowance is effectively unlimited
+    pub is_unlimited: bool,
+
+    /// Risk assessment of this approval
+    pub risk: RiskAssessment,
+
+    /// Proxy information if spender is a proxy contract
+    pub proxy: Option<ProxyInfo>,
+
+    /// Record of the latest approve transaction
+    pub approval: ApprovalRecord,
+
+    /// Exploit record if spender has been exploited or compromised
+    pub exploit: Option<ExploitRecord>,
+
+    /// Recent activity of spender; only for unknown spender contracts
+    pub activity: Option<AddressActivity>,
+
+    /// Whether spender contract has verified source code; that of its
+    /// implementation if it's a proxy. `None` if not checked, or spender is
+    /// not a contract.
+    pub is_verified: Option<bool>,
+
+    /// Creation record of spender contract; only for unknown spender
+    /// contracts
+    pub creation: Option<ContractCreation>,
+
+    /// Owner and proxy admin of spender contract if any of them is found
+    pub control: Option<ControlInfo>,
+}
+
+/// Record of the latest approve transaction made to a spender.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    /// Block number of the transaction
+    pub block_number: u64,
+
+    /// Unix timestamp (in seconds) of the block containing the transaction
+    pub timestamp: u64,
+
+    /// Hash of the transaction; empty if unknown e.g. collected by older
+    /// versions
+    #[serde(default)]
+    pub tx_hash: String,
+
+    /// Issue in calldata of the transaction which was tolerated e.g.
+    /// truncated arguments; `None` if it's well-formed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+
+    /// Hash of ERC-4337 user operation of smart account which made the
+    /// approval; `None` if it's not made through user operation, in which
+    /// case `tx_hash` is sent by owner itself rather than bundler
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_op_hash: Option<String>,
+}
+
+/// `HashMap` of token contract address to `HashMap` of spender addresses to
+/// their latest approval record
+pub type ApprovalsMap = HashMap<String, HashMap<String, ApprovalRecord>>;
+
+/// Result of querying a single token contract; error carries token contract
+/// address along with the cause.
+pub type QueryResult = Result<TokenContractWithSpenderAllowances, QueryError>;
+
+/// Event log emitted by a contract.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    /// Address of contract which emitted the log; lowercased
+    pub address: String,
+
+    /// Indexed topics in hex string prefixed with '0x'; lowercased
+    pub topics: Vec<String>,
+
+    /// Non-indexed data in hex string prefixed with '0x'
+    pub data: String,
+
+    /// Block number of the transaction
+    pub block_number: u64,
+
+    /// Unix timestamp (in seconds) of the block containing the transaction
+    pub timestamp: u64,
+
+    /// Hash of the transaction which emitted the log
+    pub transaction_hash: String,
+
+    /// Index of the log in the block
+    pub log_index: u64,
+}
+
+/// ERC-20 token transfer to or from a wallet.
+#[derive(Debug, Clone, Default)]
+pub struct TokenTransfer {
+    /// Token contract address; lowercased
+    pub token_address: String,
+
+    /// Sender address; lowercased
+    pub from: String,
+
+    /// Recipient address; lowercased
+    pub to: String,
+
+    /// Block number of the transaction
+    pub block_number: u64,
+}
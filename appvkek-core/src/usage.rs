@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of explorer API calls
+static EXPLORER_CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes of explorer API responses
+static EXPLORER_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Number of JSON-RPC requests
+static RPC_CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of HTTP requests to RPC endpoints
+static RPC_HTTP_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes of JSON-RPC requests, and their responses
+static RPC_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Number of lookups found in cache
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of lookups not found in cache
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Usage of explorer API, and RPC endpoints made by this process so far, for
+/// knowing what a scan costs on pay-per-request providers. Requests served
+/// from replay fixture are counted as if they were sent, so that cost can be
+/// measured offline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Number of explorer API calls
+    pub explorer_calls: u64,
+
+    /// Bytes of explorer API responses
+    pub explorer_bytes: u64,
+
+    /// Number of JSON-RPC requests including retries; each request in a
+    /// batch is counted as node providers count them individually
+    pub rpc_calls: u64,
+
+    /// Number of HTTP requests to RPC endpoints
+    pub rpc_http_requests: u64,
+
+    /// Bytes of JSON-RPC requests, and their responses as serialized; HTTP
+    /// headers are not included
+    pub rpc_bytes: u64,
+
+    /// Number of lookups of token metadata, and spender code found in cache
+    pub cache_hits: u64,
+
+    /// Number of lookups of token metadata, and spender code not found in
+    /// cache
+    pub cache_misses: u64,
+}
+
+impl Usage {
+    /// Return ratio of cache lookups found in cache from 0.0 to 1.0; `None`
+    /// if there was no lookup.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            return None;
+        }
+        Some(self.cache_hits as f64 / lookups as f64)
+    }
+
+    /// Return whether any request, or cache lookup was made.
+    pub fn is_empty(&self) -> bool {
+        *self == Usage::default()
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} explorer calls ({}), {} RPC calls in {} HTTP requests ({})", self.explorer_calls, format_bytes(self.explorer_bytes), self.rpc_calls, self.rpc_http_requests, format_bytes(self.rpc_bytes))?;
+        match self.cache_hit_rate() {
+            Some(rate) => write!(f, ", cache hit rate {:.0}% ({} of {})", rate * 100.0, self.cache_hits, self.cache_hits + self.cache_misses),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Return the specified number of bytes in human-readable unit e.g. `1.5 MB`.
+///
+/// # Arguments
+/// * `bytes` - number of bytes
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} B", bytes),
+        1_000..=999_999 => format!("{:.1} KB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+/// Return usage made so far.
+pub fn current() -> Usage {
+    Usage {
+        explorer_calls: EXPLORER_CALLS.load(Ordering::Relaxed),
+        explorer_bytes: EXPLORER_BYTES.load(Ordering::Relaxed),
+        rpc_calls: RPC_CALLS.load(Ordering::Relaxed),
+        rpc_http_requests: RPC_HTTP_REQUESTS.load(Ordering::Relaxed),
+        rpc_bytes: RPC_BYTES.load(Ordering::Relaxed),
+        cache_hits: CACHE_HITS.load(Ordering::Relaxed),
+        cache_misses: CACHE_MISSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Count a single explorer API call.
+///
+/// # Arguments
+/// * `bytes` - bytes of its response
+pub(crate) fn record_explorer_call(bytes: usize) {
+    EXPLORER_CALLS.fetch_add(1, Ordering::Relaxed);
+    EXPLORER_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Count a single HTTP request containing the specified number of JSON-RPC
+/// requests.
+///
+/// # Arguments
+/// * `rpc_calls` - number of JSON-RPC requests
+pub(crate) fn record_rpc_request(rpc_calls: usize) {
+    RPC_CALLS.fetch_add(rpc_calls as u64, Ordering::Relaxed);
+    RPC_HTTP_REQUESTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count bytes of JSON-RPC request, and its response.
+///
+/// # Arguments
+/// * `bytes` - bytes of both as serialized
+pub(crate) fn record_rpc_bytes(bytes: usize) {
+    RPC_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Count a single cache lookup.
+///
+/// # Arguments
+/// * `is_hit` - whether it was found in cache
+pub(crate) fn record_cache_lookup(is_hit: bool) {
+    if is_hit {
+        CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+    }
+    else {
+        CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_hit_rate_is_ratio_of_hits() {
+        let usage = Usage { cache_hits: 3, cache_misses: 1, ..Default::default() };
+        assert_eq!(usage.cache_hit_rate(), Some(0.75));
+        assert_eq!(Usage::default().cache_hit_rate(), None);
+        assert!(Usage::default().is_empty());
+
+        let usage = Usage { explorer_calls: 2, explorer_bytes: 1_500, rpc_calls: 30, rpc_http_requests: 3, rpc_bytes: 2_500_000, ..usage };
+        assert_eq!(usage.to_string(), "2 explorer calls (1.5 KB), 30 RPC calls in 3 HTTP requests (2.5 MB), cache hit rate 75% (3 of 4)");
+    }
+}
@@ -0,0 +1,788 @@
+use web3::{
+    Web3,
+    types::{Address, BlockId, BlockNumber, U256},
+    transports::http::Http,
+};
+use regex::Regex;
+use std::collections::HashMap;
+use ::evmscan::prelude::*;
+
+use crate::error::AppvkekError;
+use crate::provider::ChainRpc;
+use crate::ratelimit::TokenBucket;
+use crate::transport::{endpoint_name, BatchingHttp, RetryPolicy};
+
+/// Transport used by all web3 instances
+pub type TransportType = BatchingHttp;
+
+pub type Web3Type = web3::Web3<TransportType>;
+
+/// Maximum number of JSON-RPC requests grouped into a single batch request
+pub const RPC_BATCH_SIZE: usize = 100;
+
+/// How long to wait for more JSON-RPC requests before sending a batch request
+pub const RPC_BATCH_WINDOW: std::time::Duration = std::time::Duration::from_millis(5);
+
+/// Maximum number of token contracts (or Multicall3 batches) being queried at
+/// once; the rest wait without allocating their futures yet
+pub const MAX_PENDING_QUERIES: usize = 256;
+
+/// Upper bound of delay between retries of JSON-RPC requests
+pub const RPC_MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// RPC endpoint of BSC chain
+pub(crate) static BSC_RPC_ENDPOINT: &str = "https://bsc-dataseed.binance.org/";
+/// RPC endpoint of Ethereum chain
+pub(crate) static ETHEREUM_RPC_ENDPOINT: &str = "https://rpc.ankr.com/eth";
+/// RPC endpoint of Polygon chain
+pub(crate) static POLYGON_RPC_ENDPOINT: &str = "https://polygon-rpc.com/";
+
+/// Return default rate limit of RPC endpoint of the specified chain as tuple
+/// of `(requests_per_second, burst)`. These are slightly below what public
+/// endpoints document or were observed to tolerate.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn default_rpc_rate_limit(chain: ChainType) -> (f64, u32) {
+    match chain {
+        // 10,000 requests per 5 minutes
+        ChainType::BSC => (30.0, 60),
+        ChainType::Ethereum => (25.0, 50),
+        ChainType::Polygon => (20.0, 40),
+    }
+}
+
+/// Validate whether the specified address is in correct format.
+/// Return true if the format is correct, otherwise return false.
+///
+/// # Arguments
+/// * `address` - address to check its format correctness
+pub fn validate_address_format(address: &str) -> bool {
+    let lowercase_address = address.to_lowercase();
+    let regex: Regex = Regex::new(r#"^(0x)?[0-9a-f]{40}$"#).unwrap();
+
+    regex.is_match(&lowercase_address)
+}
+
+/// Return the specified address in EIP-55 mixed-case checksum encoding
+/// prefixed with '0x'. Input of any case is accepted; it is returned as is if
+/// it's not in correct format.
+///
+/// # Arguments
+/// * `address` - address with or without '0x' prefix
+pub fn to_checksum_address(address: &str) -> String {
+    if !validate_address_format(address) {
+        return address.to_owned();
+    }
+
+    let lowercase_address = address.trim_start_matches("0x").to_lowercase();
+    let hash = web3::signing::keccak256(lowercase_address.as_bytes());
+    let mut ret = String::with_capacity(42);
+    ret.push_str("0x");
+    for (i, c) in lowercase_address.chars().enumerate() {
+        // uppercase letter if the corresponding nibble of hash is at least 8
+        let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+        if nibble >= 8 {
+            ret.push(c.to_ascii_uppercase());
+        }
+        else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+/// Validate EIP-55 checksum of the specified address.
+/// Return true if the address is either all lowercase, all uppercase, or
+/// mixed case with valid checksum, otherwise return false.
+///
+/// # Arguments
+/// * `address` - address in correct format; with or without '0x' prefix
+pub fn validate_address_checksum(address: &str) -> bool {
+    let digits = address.trim_start_matches("0x");
+    if digits == digits.to_lowercase() || digits == digits.to_uppercase() {
+        return true;
+    }
+    to_checksum_address(digits)[2..] == *digits
+}
+
+/// Perform check whether the specified address is an EOA.
+/// Return true if it is, otherwise return false.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - address to check; in format `0x...`.
+pub async fn perform_check_is_eoa<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<bool, AppvkekError> {
+    let addr = get_address_from_str(address)?;
+
+    // query for code
+    let code_bytes = rpc.code(addr, None).await?;
+
+    // encode hex bytes into hex string
+    let code_str = hex::encode(code_bytes.as_slice());
+
+    if !code_str.is_empty() {
+        // it is a contract address
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Well-known burn addresses which nobody holds key of; lowercased. Tokens
+/// approved to them can never be spent.
+pub static BURN_ADDRESSES: [&str; 3] = [
+    "0x0000000000000000000000000000000000000000",
+    "0x000000000000000000000000000000000000dead",
+    "0xdead000000000000000042069420694206942069",
+];
+
+/// Return whether the specified address is a well-known burn address.
+///
+/// # Arguments
+/// * `address` - address prefixed with '0x'
+pub fn is_burn_address(address: &str) -> bool {
+    BURN_ADDRESSES.contains(&address.to_lowercase().as_str())
+}
+
+/// Prefix of code of an EOA which has delegated to contract code via
+/// EIP-7702; followed by 20-byte address of the delegate
+pub const EIP7702_DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Return address of the delegate if the specified code is an EIP-7702
+/// delegation designator, otherwise return `None`.
+///
+/// # Arguments
+/// * `code` - code of an address
+pub fn parse_delegation(code: &[u8]) -> Option<String> {
+    if code.len() != EIP7702_DELEGATION_PREFIX.len() + 20 || !code.starts_with(&EIP7702_DELEGATION_PREFIX) {
+        return None;
+    }
+    Some(format!("0x{}", hex::encode(&code[EIP7702_DELEGATION_PREFIX.len()..])))
+}
+
+/// Return address of the contract which the specified EOA has delegated to
+/// via EIP-7702 if any. As such EOA has code, `perform_check_is_eoa()`
+/// tells it's not an EOA.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - address to check; in format `0x...`.
+pub async fn get_delegation<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<Option<String>, AppvkekError> {
+    let addr = get_address_from_str(address)?;
+    let code_bytes = rpc.code(addr, None).await?;
+    Ok(parse_delegation(&code_bytes))
+}
+
+/// Chain id, and the latest block as reported by RPC endpoint.
+#[derive(Debug, Clone)]
+pub struct ChainHead {
+    /// EIP-155 chain id
+    pub chain_id: u64,
+
+    /// Number of the latest block
+    pub block_number: u64,
+
+    /// Unix timestamp (in seconds) of the latest block
+    pub timestamp: u64,
+}
+
+/// Query chain id, and the latest block from RPC endpoint.
+///
+/// # Arguments
+/// * `web3` - web3 instance
+pub async fn fetch_chain_head(web3: &Web3Type) -> Result<ChainHead, AppvkekError> {
+    let chain_id = match web3.eth().chain_id().await {
+        Ok(res) => res.as_u64(),
+        Err(e) => return Err(AppvkekError::from_web3("Error querying chain id", &e)),
+    };
+    let block = match web3.eth().block(BlockId::Number(BlockNumber::Latest)).await {
+        Ok(Some(res)) => res,
+        Ok(None) => return Err(AppvkekError::Rpc("Error querying the latest block; err=no block returned".to_owned())),
+        Err(e) => return Err(AppvkekError::from_web3("Error querying the latest block", &e)),
+    };
+    Ok(ChainHead {
+        chain_id,
+        block_number: block.number.map(|n| n.as_u64()).unwrap_or_default(),
+        timestamp: block.timestamp.as_u64(),
+    })
+}
+
+/// Return Keccak-256 hash of code at the specified address in hex string, or
+/// `None` if it has no code i.e. it is an EOA.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - address to check; in format `0x...`
+pub async fn get_code_hash<R: ChainRpc + ?Sized>(rpc: &R, address: &str) -> Result<Option<String>, AppvkekError> {
+    let addr = get_address_from_str(address)?;
+
+    match rpc.code(addr, None).await {
+        Ok(res) if res.is_empty() => Ok(None),
+        Ok(res) => Ok(Some(format!("0x{}", hex::encode(web3::signing::keccak256(&res))))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Return whether the specified address had code at the specified block.
+/// This requires RPC endpoint to serve historical state (archive node).
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `address` - address to check; in format `0x...`
+/// * `block` - block number
+pub async fn has_code_at_block<R: ChainRpc + ?Sized>(rpc: &R, address: &str, block: u64) -> Result<bool, AppvkekError> {
+    let addr = get_address_from_str(address)?;
+
+    match rpc.code(addr, Some(block)).await {
+        Ok(res) => Ok(!res.is_empty()),
+        Err(e) => Err(e.with_context(&format!("Error getting code at block {}", block))),
+    }
+}
+
+/// Get `Address` from string literal.
+///
+/// # Arguments
+/// * `address` - address string literal prefixed with '0x'
+pub fn get_address_from_str(address: &str) -> Result<Address, AppvkekError> {
+    if !validate_address_format(address) || !address.starts_with("0x") {
+        return Err(AppvkekError::AddressFormat(address.to_owned()));
+    }
+    
+    Ok(Address::from_slice(hex::decode(&address[2..]).unwrap().as_slice()))
+}
+
+/// Environment variables to take proxy URL from, in order of precedence
+const PROXY_ENV_VARS: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// Return URL of proxy to send all HTTP requests through; the specified one
+/// if any, otherwise from `HTTPS_PROXY`, or `ALL_PROXY` environment variable.
+/// Both HTTP, and SOCKS5 proxies are supported e.g. `socks5h://127.0.0.1:9050`
+/// for Tor.
+///
+/// # Arguments
+/// * `proxy` - proxy URL as specified by user
+pub fn resolve_proxy(proxy: Option<&str>) -> Option<String> {
+    if let Some(proxy) = proxy {
+        return Some(proxy.to_owned());
+    }
+    PROXY_ENV_VARS.iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Build HTTP client from the specified builder with timeout, and proxy
+/// applied.
+///
+/// # Arguments
+/// * `builder` - builder of HTTP client
+/// * `timeout` - timeout of each HTTP request
+/// * `proxy` - URL of proxy to send requests through; see `resolve_proxy()`
+fn build_http_client(builder: reqwest::ClientBuilder, timeout: std::time::Duration, proxy: Option<&str>) -> Result<reqwest::Client, AppvkekError> {
+    let mut builder = builder.timeout(timeout);
+    if let Some(proxy) = proxy {
+        builder = match reqwest::Proxy::all(proxy) {
+            Ok(res) => builder.proxy(res),
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid proxy URL ({}); err={}", proxy, e))),
+        };
+    }
+    match builder.build() {
+        Ok(res) => Ok(res),
+        Err(e) => Err(AppvkekError::Config(format!("Error creating HTTP client; err={}", e))),
+    }
+}
+
+/// Create HTTP client for requests other than those to RPC endpoints e.g.
+/// price queries, and webhooks.
+///
+/// # Arguments
+/// * `timeout` - timeout of each HTTP request
+/// * `proxy` - URL of proxy to send requests through; see `resolve_proxy()`
+pub fn create_http_client(timeout: std::time::Duration, proxy: Option<&str>) -> Result<reqwest::Client, AppvkekError> {
+    build_http_client(reqwest::Client::builder(), timeout, proxy)
+}
+
+/// Create a web3 instance.
+/// JSON-RPC requests made close together are sent as a single batch request
+/// unless `use_rpc_batch` is false. With multiple RPC endpoints, requests go
+/// to the lowest latency one which is healthy; see `BatchingHttp`.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `rpc_urls` - URLs of RPC endpoints in order of preference; default
+///   public endpoint of the chain if empty
+/// * `use_rpc_batch` - whether to batch JSON-RPC requests
+/// * `retry` - policy of retrying transient failures of JSON-RPC requests
+/// * `timeout` - timeout of each HTTP request to RPC endpoint
+/// * `limiter` - rate limiter of requests to RPC endpoints without their own
+///   limit in `endpoint_limits`
+/// * `endpoint_limits` - URL of RPC endpoint to its own rate limit in form of
+///   `(requests_per_second, burst)`; trailing `/` of URL is ignored
+/// * `proxy` - URL of proxy to send requests through; see `resolve_proxy()`
+#[allow(clippy::too_many_arguments)]
+pub fn create_web3(chain: ChainType, rpc_urls: &[String], use_rpc_batch: bool, retry: RetryPolicy, timeout: std::time::Duration, limiter: TokenBucket, endpoint_limits: &HashMap<String, (f64, u32)>, proxy: Option<&str>) -> Result<Web3Type, AppvkekError> {
+    let default_endpoint = match chain {
+        ChainType::BSC => BSC_RPC_ENDPOINT,
+        ChainType::Ethereum => ETHEREUM_RPC_ENDPOINT,
+        ChainType::Polygon => POLYGON_RPC_ENDPOINT,
+    };
+    let rpc_urls: Vec<&str> = if rpc_urls.is_empty() { vec![default_endpoint] } else { rpc_urls.iter().map(|u| u.as_str()).collect() };
+
+    let client = build_http_client(reqwest::Client::builder().user_agent("web3.rs"), timeout, proxy)?;
+
+    let mut endpoints = Vec::with_capacity(rpc_urls.len());
+    for rpc_url in rpc_urls {
+        let rpc_endpoint: reqwest::Url = match rpc_url.parse() {
+            Ok(res) => res,
+            Err(e) => return Err(AppvkekError::Config(format!("Error invalid RPC URL ({}); err={}", rpc_url, e))),
+        };
+        let endpoint_limiter = endpoint_limits.iter()
+            .find(|(url, _)| url.trim_end_matches('/') == rpc_url.trim_end_matches('/'))
+            .map(|(_, (rps, burst))| TokenBucket::new(*rps, *burst));
+        // client is cloned cheaply, sharing its connection pool
+        endpoints.push((endpoint_name(&rpc_endpoint), Http::with_client(client.clone(), rpc_endpoint), endpoint_limiter));
+    }
+    let max_batch_size = if use_rpc_batch { RPC_BATCH_SIZE } else { 1 };
+    Ok(Web3::new(BatchingHttp::new(endpoints, max_batch_size, RPC_BATCH_WINDOW, retry, limiter)))
+}
+
+/// Return whether the specified raw allowance is effectively unlimited.
+/// Wallets and dapps commonly use `type(uint256).max` for infinite approval,
+/// but some tokens cap allowance at `type(uint96).max`, so anything at or
+/// above the latter is treated as unlimited.
+///
+/// # Arguments
+/// * `allowance` - raw allowance balance
+pub fn is_unlimited_allowance(allowance: U256) -> bool {
+    allowance >= U256::from(u128::MAX >> 32)
+}
+
+/// Return name of the chain as used in `--chain` flag.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn chain_name(chain: ChainType) -> &'static str {
+    match chain {
+        ChainType::BSC => "bsc",
+        ChainType::Ethereum => "ethereum",
+        ChainType::Polygon => "polygon",
+    }
+}
+
+/// Return EIP-155 chain id of the chain e.g. `56` for BSC.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn chain_id(chain: ChainType) -> u64 {
+    match chain {
+        ChainType::BSC => 56,
+        ChainType::Ethereum => 1,
+        ChainType::Polygon => 137,
+    }
+}
+
+/// Return symbol of native token of the chain which gas is paid in e.g.
+/// `BNB` for BSC.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn native_symbol(chain: ChainType) -> &'static str {
+    match chain {
+        ChainType::BSC => "BNB",
+        ChainType::Ethereum => "ETH",
+        ChainType::Polygon => "POL",
+    }
+}
+
+/// Return EIP-3770 short name of the chain e.g. `bnb` for BSC, as used to
+/// prefix addresses e.g. `bnb:0x...`.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn chain_short_name(chain: ChainType) -> &'static str {
+    match chain {
+        ChainType::BSC => "bnb",
+        ChainType::Ethereum => "eth",
+        ChainType::Polygon => "matic",
+    }
+}
+
+/// Split EIP-3770 chain prefix off the specified address e.g. `eth:0x...`,
+/// and return chain of the prefix if any, along with the rest. Besides short
+/// names of chains, `bsc` and `pol` are accepted too.
+///
+/// # Arguments
+/// * `address` - address, or ENS name optionally prefixed with short name of chain
+pub fn split_chain_prefix(address: &str) -> Result<(Option<ChainType>, &str), AppvkekError> {
+    let (prefix, rest) = match address.split_once(':') {
+        Some(res) => res,
+        None => return Ok((None, address)),
+    };
+    let chain = match prefix.to_lowercase().as_str() {
+        "bnb" | "bsc" => ChainType::BSC,
+        "eth" => ChainType::Ethereum,
+        "matic" | "pol" => ChainType::Polygon,
+        // unknown chain
+        _ => return Err(AppvkekError::AddressFormat(address.to_owned())),
+    };
+    Ok((Some(chain), rest))
+}
+
+/// Return base URL of explorer website of the chain e.g. `https://bscscan.com`.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn explorer_url(chain: ChainType) -> &'static str {
+    match chain {
+        ChainType::BSC => "https://bscscan.com",
+        ChainType::Ethereum => "https://etherscan.io",
+        ChainType::Polygon => "https://polygonscan.com",
+    }
+}
+
+/// Return URL of the specified transaction on explorer website of the chain.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `tx_hash` - transaction hash prefixed with '0x'
+pub fn explorer_tx_url(chain: ChainType, tx_hash: &str) -> String {
+    format!("{}/tx/{}", explorer_url(chain), tx_hash)
+}
+
+/// Return URL of the specified address e.g. spender on explorer website of
+/// the chain.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `address` - address prefixed with '0x'
+pub fn explorer_address_url(chain: ChainType, address: &str) -> String {
+    format!("{}/address/{}", explorer_url(chain), address)
+}
+
+/// Return URL of the specified token contract on explorer website of the
+/// chain; its token page rather than its address page.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `token_address` - token contract address prefixed with '0x'
+pub fn explorer_token_url(chain: ChainType, token_address: &str) -> String {
+    format!("{}/token/{}", explorer_url(chain), token_address)
+}
+
+/// Return URL of page on revoke.cash listing approvals of the owner on the
+/// chain. revoke.cash has no link to a single approval, so this is the
+/// closest to it.
+///
+/// # Arguments
+/// * `chain_id` - EIP-155 chain id
+/// * `owner_address` - owner wallet address prefixed with '0x'
+pub fn revokecash_url(chain_id: u64, owner_address: &str) -> String {
+    format!("https://revoke.cash/address/{}?chainId={}", owner_address, chain_id)
+}
+
+/// Return current unix timestamp in seconds.
+pub fn unix_timestamp() -> u64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(res) => res.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+/// Return path to application's data directory; `$HOME/.appvkek`.
+pub fn appvkek_dir() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_owned());
+    format!("{}/.appvkek", home)
+}
+
+/// Return path to cache directory; `$HOME/.appvkek/cache`.
+pub fn cache_dir() -> String {
+    format!("{}/cache", appvkek_dir())
+}
+
+/// Replace all characters that are not safe to use in file name with `_`.
+///
+/// # Arguments
+/// * `name` - input name
+pub fn sanitize_filename(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' }).collect()
+}
+
+/// Read content of cache file if it exists, and is not older than `ttl`.
+///
+/// # Arguments
+/// * `path` - path to cache file
+/// * `ttl` - time-to-live of cache file
+pub fn read_cache(path: &str, ttl: std::time::Duration) -> Option<String> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    if modified.elapsed().ok()? > ttl {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Write content into file, creating its parent directory if needed.
+///
+/// # Arguments
+/// * `path` - path to file
+/// * `content` - content to write
+pub fn write_file(path: &str, content: &str) -> Result<(), AppvkekError> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            return Err(AppvkekError::Io(format!("Error creating directory ({}); err={}", parent.display(), e)));
+        }
+    }
+    std::fs::write(path, content).map_err(|e| AppvkekError::Io(format!("Error writing file ({}); err={}", path, e)))
+}
+
+/// Return number of whole days elapsed since the specified unix timestamp.
+///
+/// # Arguments
+/// * `timestamp` - unix timestamp in seconds
+pub fn days_since(timestamp: u64) -> u64 {
+    unix_timestamp().saturating_sub(timestamp) / (24 * 60 * 60)
+}
+
+/// Format the specified amount abbreviated for reading at a glance e.g.
+/// `45.3K`, `1.2M`, or `12.35`. Amounts too small to show in 2 decimals are
+/// `<0.01`, and those of a thousand trillion or more are `>1000T`.
+///
+/// # Arguments
+/// * `amount` - non-negative amount in token unit
+pub fn format_abbreviated(amount: f64) -> String {
+    let trim = |s: String| if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.').to_owned() } else { s };
+
+    if amount == 0.0 {
+        return "0".to_owned();
+    }
+    if amount < 0.005 {
+        return "<0.01".to_owned();
+    }
+    if amount < 999.995 {
+        return trim(format!("{:.2}", amount));
+    }
+    for (unit, suffix) in [(1e3, "K"), (1e6, "M"), (1e9, "B"), (1e12, "T")] {
+        let scaled = amount / unit;
+        if scaled < 999.95 {
+            return format!("{}{}", trim(format!("{:.1}", scaled)), suffix);
+        }
+    }
+    ">1000T".to_owned()
+}
+
+/// Format age of the specified unix timestamp relative to now e.g.
+/// `just now`, `5 minutes ago`, `1 day ago`, or `2 years ago`.
+///
+/// # Arguments
+/// * `timestamp` - unix timestamp in seconds
+pub fn format_age(timestamp: u64) -> String {
+    format_elapsed(unix_timestamp().saturating_sub(timestamp))
+}
+
+/// Format the specified elapsed time in its largest whole unit e.g.
+/// `3 hours ago`. Months are of 30 days, and years are of 365 days.
+///
+/// # Arguments
+/// * `secs` - elapsed time in seconds
+fn format_elapsed(secs: u64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_owned(),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < 30 * DAY => (s / DAY, "day"),
+        s if s < 365 * DAY => (s / (30 * DAY), "month"),
+        s => (s / (365 * DAY), "year"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Arguments parsed from calldata of method call by `parse_256_method_arguments()`.
+#[derive(Debug, Default)]
+pub struct MethodArguments {
+    /// Hex strings of 64 characters in length (256 bit) without prefix of 0x
+    pub arguments: Vec<String>,
+
+    /// Issue tolerated while parsing e.g. truncated calldata padded with zeros
+    pub issue: Option<String>,
+}
+
+/// Parse a long hex string into vector of hex string of 64 characters in length (256 bit)
+/// excluding the prefixed method-id which has 8 characters in length (32 bit).
+///
+/// Calldata not ending at 32-byte boundary e.g. truncated by a wallet is padded
+/// with zeros as EVM does when reading beyond its end, and it's noted as issue.
+/// Return error only if it's not hex string at all.
+///
+/// # Arguments
+/// * `long_hex_str` - input long hex string to parse; included a prefix of `0x`
+pub fn parse_256_method_arguments(long_hex_str: &str) -> Result<MethodArguments, AppvkekError> {
+    if long_hex_str.is_empty() {
+        return Ok(MethodArguments::default());
+    }
+
+    // get slice excluding prefix of method-id
+    let arguments_hex_str = match long_hex_str.get(10..) {
+        Some(res) if long_hex_str.starts_with("0x") => res,
+        _ => return Err(AppvkekError::Decode(format!("Error input hex string is too short to contain method-id; input={}", long_hex_str))),
+    };
+    if !arguments_hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppvkekError::Decode("Error input contains non-hex characters".to_owned()));
+    }
+    if arguments_hex_str.len() % 2 != 0 {
+        return Err(AppvkekError::Decode(format!("Error input hex string has odd length of {}", long_hex_str.len())));
+    }
+
+    let mut res = MethodArguments::default();
+    let num_missing = (64 - arguments_hex_str.len() % 64) % 64;
+    if num_missing > 0 {
+        res.issue = Some(format!("calldata is {} bytes short of 32-byte boundary; padded with zeros", num_missing / 2));
+    }
+    let padded = format!("{}{}", arguments_hex_str, "0".repeat(num_missing));
+
+    let mut offset_i: usize = 0;
+    while offset_i + 64 <= padded.len() {
+        res.arguments.push(padded[offset_i..offset_i+64].to_owned());
+        offset_i += 64;
+    }
+
+    Ok(res)
+}
+
+/// Run the specified future once a permit is acquired from the semaphore,
+/// and release the permit once it completes. This is used to bound the number
+/// of in-flight RPC calls.
+///
+/// # Arguments
+/// * `semaphore` - semaphore bounding the concurrency
+/// * `fut` - future to run
+pub async fn with_permit<F: core::future::Future>(semaphore: &tokio::sync::Semaphore, fut: F) -> F::Output {
+    // semaphore is never closed, so acquiring always succeeds
+    let _permit = semaphore.acquire().await;
+    fut.await
+}
+
+/// Run the specified blocking call e.g. explorer API call from async context
+/// without stalling other tasks scheduled on the same worker thread; the
+/// worker hands its tasks over to another thread meanwhile. Outside of
+/// runtime, it's just called as is.
+///
+/// Runtime if any has to be multi-threaded one, as is the case for both CLI,
+/// and Python binding.
+///
+/// # Arguments
+/// * `f` - blocking call
+pub fn run_blocking<T>(f: impl FnOnce() -> T) -> T {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        tokio::task::block_in_place(f)
+    }
+    else {
+        f()
+    }
+}
+
+/// Start measuring time. Suitable for wall-clock time measurement.
+/// This is mainly used to measure time of placing a limit order onto Bybit.
+pub fn measure_start(start: &mut std::time::Instant) {
+    *start = std::time::Instant::now();
+}
+
+/// Mark the end of the measurement of time performance.
+/// Return result in seconds, along with printing the elapsed time if `also_print`
+/// is `true`.
+pub fn measure_end(start: &std::time::Instant, also_print: bool) -> f64 {
+    let elapsed = start.elapsed().as_secs_f64();
+    if also_print {
+        println!("(elapsed = {:.2} secs)", elapsed);
+    }
+    elapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_method_arguments_pads_truncated_calldata() {
+        let spender = format!("{:0>64}", "33");
+        let res = parse_256_method_arguments(&format!("0x095ea7b3{}{:f>64}", spender, "")).unwrap();
+        assert_eq!(res.arguments, vec![spender.to_owned(), "f".repeat(64)]);
+        assert!(res.issue.is_none());
+
+        let res = parse_256_method_arguments(&format!("0x095ea7b3{}ffff", spender)).unwrap();
+        assert_eq!(res.arguments, vec![spender, format!("ffff{}", "0".repeat(60))]);
+        assert_eq!(res.issue.as_deref(), Some("calldata is 30 bytes short of 32-byte boundary; padded with zeros"));
+
+        assert!(parse_256_method_arguments("0x095ea7b3").unwrap().arguments.is_empty());
+        assert!(parse_256_method_arguments("0x095ea7").is_err());
+        assert!(parse_256_method_arguments("0x095ea7b3zz").is_err());
+        assert!(parse_256_method_arguments("0x095ea7b3abc").is_err());
+    }
+
+    #[test]
+    fn delegation_designator_is_parsed() {
+        let delegate = "0x63c0c19a282a1b52b07dd5a65b58948a07dae32b";
+        let mut code = EIP7702_DELEGATION_PREFIX.to_vec();
+        code.extend(hex::decode(&delegate[2..]).unwrap());
+        assert_eq!(parse_delegation(&code).as_deref(), Some(delegate));
+
+        // regular contract code, and truncated designator
+        assert!(parse_delegation(&[0x60, 0x80, 0x60, 0x40]).is_none());
+        assert!(parse_delegation(&code[..22]).is_none());
+        assert!(parse_delegation(&[]).is_none());
+    }
+
+    #[test]
+    fn checksum_address_matches_eip55() {
+        for address in ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359", "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb"] {
+            assert_eq!(to_checksum_address(&address.to_lowercase()), address);
+            assert!(validate_address_checksum(address));
+        }
+        assert!(validate_address_checksum("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+        assert!(!validate_address_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"));
+    }
+
+    #[test]
+    fn chain_prefix_is_split_off_address() {
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+        let prefixed = format!("matic:{}", address);
+        let (chain, rest) = split_chain_prefix(&prefixed).unwrap();
+        assert_eq!(chain.map(chain_id), Some(137));
+        assert_eq!(rest, address);
+
+        let (chain, rest) = split_chain_prefix("ETH:vitalik.eth").unwrap();
+        assert_eq!(chain.map(chain_short_name), Some("eth"));
+        assert_eq!(rest, "vitalik.eth");
+
+        let (chain, rest) = split_chain_prefix(address).unwrap();
+        assert!(chain.is_none());
+        assert_eq!(rest, address);
+
+        assert!(split_chain_prefix(&format!("arb1:{}", address)).is_err());
+    }
+
+    #[test]
+    fn amount_is_abbreviated() {
+        assert_eq!(format_abbreviated(0.0), "0");
+        assert_eq!(format_abbreviated(0.001), "<0.01");
+        assert_eq!(format_abbreviated(5.0), "5");
+        assert_eq!(format_abbreviated(12.3456), "12.35");
+        assert_eq!(format_abbreviated(45_300.0), "45.3K");
+        assert_eq!(format_abbreviated(999_999.0), "1M");
+        assert_eq!(format_abbreviated(1_234_567.0), "1.2M");
+        assert_eq!(format_abbreviated(7e12), "7T");
+        assert_eq!(format_abbreviated(1.157920892373162e59), ">1000T");
+    }
+
+    #[test]
+    fn elapsed_time_is_formatted_in_largest_unit() {
+        assert_eq!(format_elapsed(30), "just now");
+        assert_eq!(format_elapsed(60), "1 minute ago");
+        assert_eq!(format_elapsed(3 * 60 * 60 + 59), "3 hours ago");
+        assert_eq!(format_elapsed(24 * 60 * 60), "1 day ago");
+        assert_eq!(format_elapsed(45 * 24 * 60 * 60), "1 month ago");
+        assert_eq!(format_elapsed(800 * 24 * 60 * 60), "2 years ago");
+    }
+}
@@ -0,0 +1,196 @@
+//! End-to-end tests against a local chain run by anvil of Foundry, verifying
+//! scanner, and revoker against real EVM semantics.
+//!
+//! They are ignored by default as they need `anvil` installed; run with
+//! `cargo test -p appvkek-core --test anvil -- --ignored`. Set `ANVIL` to path
+//! of anvil binary if it's not on `PATH`, and `ANVIL_FORK_URL` to RPC endpoint
+//! to fork from instead of starting an empty chain.
+
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use appvkek_core::evmscan::prelude::ChainType;
+use appvkek_core::explorer::APPROVAL_EVENT_TOPIC;
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::mock::MockExplorer;
+use appvkek_core::provider::AtBlock;
+use appvkek_core::ratelimit::TokenBucket;
+use appvkek_core::revoke::build_revoke_tx;
+use appvkek_core::scanner;
+use appvkek_core::transport::RetryPolicy;
+use appvkek_core::types::{ApprovalsMap, EventLog, QueryResult};
+use appvkek_core::util::*;
+use appvkek_core::web3::types::{Address, BlockNumber, Bytes, FilterBuilder, TransactionRequest, H256, U256};
+
+/// Creation code of test token; a minimal ERC-20 implementing only `name()`
+/// ("Test Token"), `symbol()` ("TST"), `decimals()` (18), `allowance()`, and
+/// `approve()` which emits `Approval` event. Hand-assembled as follows, then
+/// prefixed with code copying it as runtime code.
+///
+/// ```text
+/// selector = calldataload(0) >> 224; jump to matching function, else revert
+/// name, symbol: return abi-encoded string
+/// decimals:     return 18
+/// allowance:    return sload(keccak256(owner . spender))
+/// approve:      sstore(keccak256(caller . spender), amount)
+///               log3(amount, Approval, caller, spender); return true
+/// ```
+static TEST_TOKEN_CODE: &str = "61011d80600c6000396000f360003560e01c806306fdde031461004157806395d89b4114610075578063313ce567146100a9578063dd62ed3e146100b4578063095ea7b3146100cf57600080fd5b6020600052600a6020527f5465737420546f6b656e0000000000000000000000000000000000000000000060405260606000f35b602060005260036020527f545354000000000000000000000000000000000000000000000000000000000060405260606000f35b601260005260206000f35b60043560005260243560205260406000205460005260206000f35b33600052600435602052602435604060002055602435600052600435337f8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b92560206000a3600160005260206000f3";
+
+/// The first default account of anvil; unlocked
+static OWNER: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+/// The second default account of anvil
+static SPENDER: &str = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+
+/// Running anvil; killed on drop.
+struct Anvil {
+    /// Process of anvil
+    child: Child,
+
+    /// URL of its RPC endpoint
+    url: String,
+}
+
+impl Anvil {
+    /// Start anvil on a free port, and wait until it accepts connections.
+    fn start() -> Anvil {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut cmd = Command::new(std::env::var("ANVIL").unwrap_or_else(|_| "anvil".to_owned()));
+        cmd.args(["--port", &port.to_string(), "--silent"]).stdout(Stdio::null());
+        if let Ok(fork_url) = std::env::var("ANVIL_FORK_URL") {
+            cmd.args(["--fork-url", &fork_url]);
+        }
+        // killed on drop even if it doesn't start
+        let anvil = Anvil {
+            child: cmd.spawn().expect("anvil is required; see doc of this file"),
+            url: format!("http://127.0.0.1:{}", port),
+        };
+
+        for _ in 0..100 {
+            if std::net::TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                return anvil;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        panic!("anvil didn't start listening on port {}", port);
+    }
+}
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Send transaction from the specified unlocked account, and return block
+/// number it was mined in, along with contract address if it created one.
+/// anvil mines each transaction immediately.
+///
+/// # Arguments
+/// * `web3` - web3 instance connected to anvil
+/// * `from` - sender address
+/// * `to` - recipient address; `None` to create contract
+/// * `data` - calldata, or creation code
+async fn send(web3: &Web3Type, from: &str, to: Option<&str>, data: Vec<u8>) -> (u64, Option<Address>) {
+    let tx = TransactionRequest {
+        from: get_address_from_str(from).unwrap(),
+        to: to.map(|to| get_address_from_str(to).unwrap()),
+        gas: Some(U256::from(1_000_000)),
+        data: Some(Bytes(data)),
+        ..Default::default()
+    };
+    let hash = web3.eth().send_transaction(tx).await.unwrap();
+    let receipt = web3.eth().transaction_receipt(hash).await.unwrap().expect("transaction is mined");
+    assert_eq!(receipt.status, Some(1.into()), "transaction failed");
+    (receipt.block_number.unwrap().as_u64(), receipt.contract_address)
+}
+
+/// Return explorer serving `Approval` event logs of owner from chain, as
+/// anvil has no explorer.
+///
+/// # Arguments
+/// * `web3` - web3 instance connected to anvil
+/// * `owner_address` - owner wallet address; lowercased
+async fn approval_logs_explorer(web3: &Web3Type, owner_address: &str) -> MockExplorer {
+    let owner_topic: H256 = format!("0x{:0>64}", &owner_address[2..]).parse().unwrap();
+    let filter = FilterBuilder::default()
+        .from_block(BlockNumber::Earliest)
+        .topics(Some(vec![APPROVAL_EVENT_TOPIC.parse().unwrap()]), Some(vec![owner_topic]), None, None)
+        .build();
+
+    let mut explorer = MockExplorer::default();
+    for log in web3.eth().logs(filter).await.unwrap() {
+        explorer = explorer.with_log(EventLog {
+            address: format!("{:?}", log.address),
+            topics: log.topics.iter().map(|t| format!("{:?}", t)).collect(),
+            data: format!("0x{}", hex::encode(&log.data.0)),
+            block_number: log.block_number.unwrap().as_u64(),
+            timestamp: 0,
+            transaction_hash: format!("{:?}", log.transaction_hash.unwrap()),
+            log_index: log.log_index.unwrap().as_u64(),
+        });
+    }
+    explorer
+}
+
+/// Collect approvals of owner, then query their allowances.
+///
+/// # Arguments
+/// * `web3` - web3 instance connected to anvil
+/// * `at_block` - block number to query allowances at; latest if `None`
+async fn scan(web3: &Web3Type, at_block: Option<u64>) -> Vec<QueryResult> {
+    let explorer = approval_logs_explorer(web3, OWNER).await;
+    let mut approvals = ApprovalsMap::new();
+    scanner::collect_approval_logs(&explorer, OWNER, 0, &mut approvals).unwrap();
+
+    let semaphore = tokio::sync::Semaphore::new(4);
+    let mut metadata_cache = MetadataCache::default();
+    let mut failed_contracts = FailedContracts::default();
+    let rpc = AtBlock { inner: web3, block: at_block };
+    scanner::query_all(&rpc, &semaphore, approvals, OWNER, false, &mut metadata_cache, &mut failed_contracts, |_| {}).await
+}
+
+#[tokio::test]
+#[ignore]
+async fn scan_and_revoke_approval_of_test_token() {
+    let anvil = Anvil::start();
+    let retry = RetryPolicy { max_retries: 0, base_delay: Duration::from_millis(1), max_delay: RPC_MAX_RETRY_DELAY };
+    let web3 = create_web3(ChainType::Ethereum, std::slice::from_ref(&anvil.url), false, retry, Duration::from_secs(10), TokenBucket::new(0.0, 0), &Default::default(), None).unwrap();
+
+    let (_, token) = send(&web3, OWNER, None, hex::decode(TEST_TOKEN_CODE).unwrap()).await;
+    let token = format!("{:?}", token.expect("token is deployed"));
+
+    // unlimited approval
+    let mut approve = hex::decode("095ea7b3").unwrap();
+    approve.extend(appvkek_core::web3::ethabi::encode(&[
+        appvkek_core::web3::ethabi::Token::Address(get_address_from_str(SPENDER).unwrap()),
+        appvkek_core::web3::ethabi::Token::Uint(U256::MAX),
+    ]));
+    let (approve_block, _) = send(&web3, OWNER, Some(&token), approve).await;
+
+    let results = scan(&web3, None).await;
+    assert_eq!(results.len(), 1);
+    let r = results[0].as_ref().unwrap();
+    assert_eq!(r.name, "Test Token");
+    assert_eq!(r.symbol, "TST");
+    assert_eq!(r.decimals, 18);
+    assert!(r.spender_allowances[SPENDER].is_unlimited);
+    assert_eq!(r.spender_allowances[SPENDER].approval.block_number, approve_block);
+
+    // revoke as printed by `revoke` subcommand
+    let revoke_tx = build_revoke_tx(&token, SPENDER).unwrap();
+    let (revoke_block, _) = send(&web3, OWNER, Some(&revoke_tx.token_address), hex::decode(&revoke_tx.data[2..]).unwrap()).await;
+
+    let results = scan(&web3, None).await;
+    let sa = &results[0].as_ref().unwrap().spender_allowances[SPENDER];
+    assert_eq!(sa.allowance, 0.0);
+    assert_eq!(sa.approval.block_number, revoke_block);
+
+    // allowance before revoke is still there at historical block
+    let results = scan(&web3, Some(approve_block)).await;
+    assert!(results[0].as_ref().unwrap().spender_allowances[SPENDER].is_unlimited);
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use appvkek_core::evmscan::environ::Context;
+use appvkek_core::evmscan::prelude::ChainType;
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::ratelimit::TokenBucket;
+use appvkek_core::transport::RetryPolicy;
+use appvkek_core::types::ApprovalsMap;
+use appvkek_core::util::*;
+use appvkek_core::{replay, scanner};
+
+static OWNER: &str = "0x1111111111111111111111111111111111111111";
+static TOKEN: &str = "0x2222222222222222222222222222222222222222";
+static SPENDER: &str = "0x3333333333333333333333333333333333333333";
+
+#[tokio::test]
+async fn scan_replays_recorded_responses() {
+    replay::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/scan-bsc.jsonl")).unwrap();
+
+    let chain = ChainType::BSC;
+    let retry = RetryPolicy { max_retries: 0, base_delay: std::time::Duration::from_millis(1), max_delay: RPC_MAX_RETRY_DELAY };
+    let web3 = create_web3(chain, &[], true, retry, std::time::Duration::from_secs(1), TokenBucket::new(0.0, 0), &Default::default(), None).unwrap();
+    let semaphore = tokio::sync::Semaphore::new(4);
+    let ctx = Context::create(chain, String::new());
+
+    assert!(perform_check_is_eoa(&web3, OWNER).await.unwrap());
+
+    let mut approvals: ApprovalsMap = HashMap::new();
+    assert_eq!(scanner::collect_approvals(&ctx, OWNER, 0, &mut approvals, None).unwrap(), Some(100));
+    assert_eq!(approvals[TOKEN][SPENDER].block_number, 100);
+
+    let mut metadata_cache = MetadataCache::default();
+    let mut failed_contracts = FailedContracts::default();
+    let results = scanner::query_all(&web3, &semaphore, approvals, OWNER, false, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+
+    let r = results[0].as_ref().unwrap();
+    assert_eq!(r.name, "Token");
+    assert_eq!(r.symbol, "TKN");
+    assert_eq!(r.spender_allowances[SPENDER].allowance, 5.0);
+}
@@ -0,0 +1,271 @@
+//! Python bindings of appvkek-core; scan approvals of a wallet, and build
+//! revoke transactions from Python without parsing output of the cli.
+//!
+//! ```python
+//! import appvkek
+//!
+//! for a in appvkek.scan("bsc", "0x...", api_key="<explorer-api-key>"):
+//!     if a.is_unlimited:
+//!         tx = appvkek.build_revoke_tx(a.token_address, a.spender)
+//!         print(a.token_symbol, a.spender, tx.data)
+//! ```
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use appvkek_core::evmscan::environ::Context;
+use appvkek_core::evmscan::prelude::ChainType;
+use appvkek_core::exploits::ExploitDb;
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::labels::Labels;
+use appvkek_core::malicious::{self, MaliciousDb};
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::ratelimit::TokenBucket;
+use appvkek_core::report::{self, ApprovalEntry};
+use appvkek_core::transport::RetryPolicy;
+use appvkek_core::types::ApprovalsMap;
+use appvkek_core::util::*;
+use appvkek_core::{codecache, creationcache, revoke, risk, scanner};
+
+/// Maximum number of concurrent RPC requests; as of default of `--concurrency`
+static CONCURRENCY: usize = 16;
+
+/// A single approval of a spender on a token contract; as of an entry of
+/// JSON output of `export`.
+#[pyclass(get_all, frozen, module = "appvkek")]
+#[derive(Debug, Clone)]
+pub struct Approval {
+    /// Chain name e.g. `bsc`
+    chain: String,
+
+    /// Owner wallet address; EIP-55 checksummed
+    owner: String,
+
+    /// Token contract address; EIP-55 checksummed
+    token_address: String,
+
+    /// Token name
+    token_name: String,
+
+    /// Token symbol; empty if token doesn't implement it
+    token_symbol: String,
+
+    /// Spender address; EIP-55 checksummed
+    spender: String,
+
+    /// Label of spender if it's a well-known address
+    spender_label: Option<String>,
+
+    /// Current allowance in token unit i.e. divided by `10^decimals`
+    allowance: f64,
+
+    /// Whether the allowance is effectively unlimited
+    is_unlimited: bool,
+
+    /// Risk score; higher is riskier
+    risk_score: u32,
+
+    /// Factors contributing to risk score e.g. `unlimited`
+    risk_reasons: Vec<String>,
+
+    /// Block number of the latest approve transaction
+    approval_block: u64,
+
+    /// Unix timestamp (in seconds) of the latest approve transaction
+    approval_timestamp: u64,
+
+    /// Implementation address if spender is a proxy contract
+    implementation: Option<String>,
+
+    /// Hash of the latest approve transaction
+    approval_tx_hash: Option<String>,
+
+    /// URL of the latest approve transaction on explorer website
+    approval_tx_url: Option<String>,
+
+    /// Hash of ERC-4337 user operation which made the latest approval if
+    /// owner is a smart account
+    approval_user_op_hash: Option<String>,
+}
+
+#[pymethods]
+impl Approval {
+    fn __repr__(&self) -> String {
+        format!("Approval(token={}, symbol={}, spender={}, allowance={}, risk_score={})", self.token_address, self.token_symbol, self.spender, self.allowance, self.risk_score)
+    }
+}
+
+impl From<ApprovalEntry> for Approval {
+    fn from(e: ApprovalEntry) -> Self {
+        Approval {
+            chain: e.chain,
+            owner: e.owner,
+            token_address: e.token_address,
+            token_name: e.token_name,
+            token_symbol: e.token_symbol,
+            spender: e.spender,
+            spender_label: e.spender_label,
+            allowance: e.allowance,
+            is_unlimited: e.is_unlimited,
+            risk_score: e.risk_score,
+            risk_reasons: e.risk_reasons,
+            approval_block: e.approval_block,
+            approval_timestamp: e.approval_timestamp,
+            implementation: e.implementation,
+            approval_tx_hash: e.approval_tx_hash,
+            approval_tx_url: e.approval_tx_url,
+            approval_user_op_hash: e.approval_user_op_hash,
+        }
+    }
+}
+
+/// Unsigned transaction which revokes an approval by setting its allowance
+/// to zero.
+#[pyclass(get_all, frozen, module = "appvkek")]
+#[derive(Debug, Clone)]
+pub struct RevokeTx {
+    /// Token contract address; destination of transaction
+    to: String,
+
+    /// Spender address whose allowance is revoked
+    spender: String,
+
+    /// Calldata of `approve(spender, 0)`; hex string prefixed with '0x'
+    data: String,
+}
+
+#[pymethods]
+impl RevokeTx {
+    fn __repr__(&self) -> String {
+        format!("RevokeTx(to={}, spender={}, data={})", self.to, self.spender, self.data)
+    }
+}
+
+/// Parse the specified chain name into chain type.
+///
+/// # Arguments
+/// * `chain` - chain name; `bsc`, `ethereum`, or `polygon`
+fn parse_chain(chain: &str) -> PyResult<ChainType> {
+    match chain.to_lowercase().as_str() {
+        "bsc" => Ok(ChainType::BSC),
+        "ethereum" => Ok(ChainType::Ethereum),
+        "polygon" => Ok(ChainType::Polygon),
+        _ => Err(PyValueError::new_err(format!("Error invalid chain ({}); possible values are 'bsc', 'ethereum', or 'polygon'", chain))),
+    }
+}
+
+/// Scan approvals made by the specified wallet, query their current
+/// allowances, and assess risk of their spenders.
+///
+/// Approvals whose allowance is zero are included; filter on `allowance` to
+/// get only active ones. Raise `RuntimeError` if collecting approvals fails,
+/// or if `strict` is set and any token contract fails to be queried;
+/// otherwise such token contracts are left out.
+///
+/// # Arguments
+/// * `chain` - chain name; `bsc`, `ethereum`, or `polygon`
+/// * `address` - wallet address; EIP-3770 chain prefix is accepted
+/// * `api_key` - explorer API key
+/// * `rpc_urls` - RPC endpoints to use instead of the default ones
+/// * `assess_risk` - whether to assess risk of spenders; `risk_score` is 0 otherwise
+/// * `strict` - whether to raise if any token contract fails to be queried
+#[pyfunction]
+#[pyo3(signature = (chain, address, api_key, rpc_urls=Vec::new(), assess_risk=true, strict=false))]
+fn scan(py: Python<'_>, chain: &str, address: &str, api_key: String, rpc_urls: Vec<String>, assess_risk: bool, strict: bool) -> PyResult<Vec<Approval>> {
+    let chain = parse_chain(chain)?;
+    let owner_address = match split_chain_prefix(address) {
+        Ok((prefix, rest)) if prefix.map(|c| chain_id(c) == chain_id(chain)).unwrap_or(true) => rest.to_lowercase(),
+        Ok(_) => return Err(PyValueError::new_err(format!("Error chain prefix of address doesn't match chain ({})", address))),
+        Err(e) => return Err(PyValueError::new_err(e.to_string())),
+    };
+    if !validate_address_format(&owner_address) {
+        return Err(PyValueError::new_err(format!("Error invalid address format ({})", address)));
+    }
+
+    // release GIL as scan takes a while, and doesn't touch Python objects
+    let report = py.allow_threads(move || -> Result<report::Report, String> {
+        let retry = RetryPolicy { max_retries: 3, base_delay: std::time::Duration::from_millis(500), max_delay: RPC_MAX_RETRY_DELAY };
+        let (rps, burst) = default_rpc_rate_limit(chain);
+        let web3 = create_web3(chain, &rpc_urls, true, retry, std::time::Duration::from_secs(30), TokenBucket::new(rps, burst), &HashMap::new(), None).map_err(|e| e.to_string())?;
+        let semaphore = tokio::sync::Semaphore::new(CONCURRENCY);
+        let ctx = Context::create(chain, api_key);
+        let labels = Labels::load(chain, None)?;
+
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error creating async runtime; err={}", e)),
+        };
+
+        // contract wallets e.g. Safe, or ERC-4337 smart accounts approve via
+        // internal calls, so their approvals are only found in event logs
+        let mut approvals: ApprovalsMap = HashMap::new();
+        let collected = match runtime.block_on(perform_check_is_eoa(&web3, &owner_address)) {
+            Ok(true) => scanner::collect_approvals(&ctx, &owner_address, 0, &mut approvals, None),
+            Ok(false) => scanner::collect_user_operation_approvals(&ctx, &owner_address, 0, &mut approvals),
+            Err(e) => Err(e),
+        };
+        collected.map_err(|e| e.to_string())?;
+        runtime.block_on(async {
+            let mut metadata_cache = MetadataCache::load(chain, true);
+            let mut failed_contracts = FailedContracts::default();
+            let mut results = scanner::query_all(&web3, &semaphore, approvals, &owner_address, true, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+            // cache only spares requests of later scans; failing to save it isn't fatal
+            let _ = metadata_cache.save();
+
+            if assess_risk {
+                let malicious_db = MaliciousDb::load(&[malicious::SCAMSNIFFER_ADDRESS_LIST_URL.to_owned()]);
+                let exploit_db = ExploitDb::load(chain, None)?;
+                let sources = risk::RiskSources {
+                    malicious_db: &malicious_db,
+                    exploit_db: &exploit_db,
+                    labels: &labels,
+                    prices: None,
+                };
+                // as of defaults of risk flags of the cli
+                let opts = risk::RiskOptions {
+                    check_verification: true,
+                    stale_days: 365,
+                    check_activity: true,
+                    dormant_days: 180,
+                    check_creation: true,
+                };
+                let mut code_cache = codecache::CodeCache::load(chain, std::time::Duration::from_secs(24 * 60 * 60));
+                let mut creation_cache = creationcache::CreationCache::load(chain, true);
+                risk::assess_all(&web3, &semaphore, &ctx, &mut results, &sources, &opts, &mut code_cache, &mut creation_cache).await;
+            }
+            Ok(report::build_report(chain, &owner_address, &results, &labels))
+        })
+    }).map_err(PyRuntimeError::new_err)?;
+
+    if strict && !report.errors.is_empty() {
+        let addresses: Vec<&str> = report.errors.iter().map(|e| e.token_address.as_str()).collect();
+        return Err(PyRuntimeError::new_err(format!("Error {} token contracts failed to be queried ({})", addresses.len(), addresses.join(", "))));
+    }
+    Ok(report.approvals.into_iter().map(Approval::from).collect())
+}
+
+/// Build unsigned transaction which revokes approval of the specified
+/// spender on the specified token contract. Raise `ValueError` if either
+/// address is in invalid format.
+///
+/// # Arguments
+/// * `token_address` - token contract address
+/// * `spender` - spender address to revoke approval of
+#[pyfunction]
+fn build_revoke_tx(token_address: &str, spender: &str) -> PyResult<RevokeTx> {
+    match revoke::build_revoke_tx(token_address, spender) {
+        Ok(tx) => Ok(RevokeTx { to: tx.token_address, spender: tx.spender, data: tx.data }),
+        Err(e) => Err(PyValueError::new_err(e.to_string())),
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "appvkek")]
+fn appvkek_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Approval>()?;
+    m.add_class::<RevokeTx>()?;
+    m.add_function(wrap_pyfunction!(scan, m)?)?;
+    m.add_function(wrap_pyfunction!(build_revoke_tx, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use clap::CommandFactory;
+
+#[allow(dead_code)]
+#[path = "src/args.rs"]
+mod args;
+
+/// Generate man pages of `appvkek`, and each of its subcommands into
+/// `$APPVKEK_MAN_DIR` if set, otherwise into `$OUT_DIR/man`.
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=src/args.rs");
+    println!("cargo:rerun-if-env-changed=APPVKEK_MAN_DIR");
+
+    let out_dir = match std::env::var_os("APPVKEK_MAN_DIR") {
+        Some(res) => std::path::PathBuf::from(res),
+        None => std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("man"),
+    };
+    std::fs::create_dir_all(&out_dir)?;
+
+    let cmd = args::CommandlineArgs::command();
+    let mut buffer: Vec<u8> = Vec::new();
+    clap_mangen::Man::new(cmd.clone()).render(&mut buffer)?;
+    std::fs::write(out_dir.join("appvkek.1"), buffer)?;
+
+    for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+        let name = format!("appvkek-{}", sub.get_name());
+        let mut buffer: Vec<u8> = Vec::new();
+        clap_mangen::Man::new(sub.clone().name(name.as_str())).render(&mut buffer)?;
+        std::fs::write(out_dir.join(format!("{}.1", name)), buffer)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use appvkek_core::types::{SpenderAllowance, TokenContractWithSpenderAllowances};
+use appvkek_core::util::format_abbreviated;
+
+/// Whether to show amounts abbreviated instead of exact
+static HUMAN: AtomicBool = AtomicBool::new(false);
+
+/// Show amounts abbreviated along with token symbol from now on.
+///
+/// # Arguments
+/// * `human` - whether to show amounts abbreviated
+pub fn set_human(human: bool) {
+    HUMAN.store(human, Ordering::Relaxed);
+}
+
+/// Return whether amounts are shown abbreviated.
+pub fn is_human() -> bool {
+    HUMAN.load(Ordering::Relaxed)
+}
+
+/// Format the specified amount for display; abbreviated along with token
+/// symbol e.g. `1.2M USDC` if set by `set_human()`, otherwise exact.
+///
+/// # Arguments
+/// * `amount` - amount in token unit
+/// * `symbol` - token symbol; may be empty
+pub fn format_amount(amount: f64, symbol: &str) -> String {
+    if !is_human() {
+        return amount.to_string();
+    }
+    with_symbol(format_abbreviated(amount), symbol)
+}
+
+/// Format the specified allowance for display as of `format_amount()`, but
+/// `unlimited` if abbreviated, and it's effectively unlimited.
+///
+/// # Arguments
+/// * `allowance` - allowance in token unit
+/// * `is_unlimited` - whether it's effectively unlimited
+/// * `symbol` - token symbol; may be empty
+pub fn format_allowance(allowance: f64, is_unlimited: bool, symbol: &str) -> String {
+    if is_human() && is_unlimited {
+        return with_symbol("unlimited".to_owned(), symbol);
+    }
+    format_amount(allowance, symbol)
+}
+
+/// Format the specified allowance as share of owner's balance, and of total
+/// supply e.g. `25% of balance (20 TKN), 0.5% of supply`. Unlimited
+/// allowance covers all of balance, but says nothing about supply.
+///
+/// Return `None` if neither of them is known, or allowance is zero.
+///
+/// # Arguments
+/// * `r` - token contract along with its balance, and total supply
+/// * `sa` - allowance of spender
+pub fn format_share(r: &TokenContractWithSpenderAllowances, sa: &SpenderAllowance) -> Option<String> {
+    if sa.allowance <= 0.0 {
+        return None;
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(balance) = r.balance {
+        // balance is known, so there is no percentage only if it's zero
+        match r.percent_of_balance(sa.allowance) {
+            None => parts.push("no balance".to_owned()),
+            Some(_) if sa.is_unlimited => parts.push(format!("all of balance ({})", format_amount(balance, &r.symbol))),
+            Some(p) => parts.push(format!("{}% of balance ({})", format_abbreviated(p), format_amount(balance, &r.symbol))),
+        }
+    }
+    if let Some(p) = r.percent_of_supply(sa.allowance).filter(|_| !sa.is_unlimited) {
+        parts.push(format!("{}% of supply", format_abbreviated(p)));
+    }
+
+    if parts.is_empty() { None } else { Some(parts.join(", ")) }
+}
+
+/// Append token symbol to the specified amount if any.
+///
+/// # Arguments
+/// * `amount` - formatted amount
+/// * `symbol` - token symbol; may be empty
+fn with_symbol(amount: String, symbol: &str) -> String {
+    if symbol.is_empty() { amount } else { format!("{} {}", amount, symbol) }
+}
@@ -0,0 +1,134 @@
+use ::evmscan::prelude::*;
+
+use appvkek_core::util::chain_name;
+
+/// Service name under which API keys are stored in OS keyring
+pub static KEYRING_SERVICE: &str = "appvkek";
+
+/// Return name of environment variable holding explorer API key of the
+/// specified chain. It is also used as username of keyring entry.
+///
+/// # Arguments
+/// * `chain` - chain type
+pub fn apikey_env_var(chain: ChainType) -> &'static str {
+    match chain {
+        ChainType::BSC => "APPVKEK_BSCSCAN_APIKEY",
+        ChainType::Ethereum => "APPVKEK_ETHERSCAN_APIKEY",
+        ChainType::Polygon => "APPVKEK_POLYGONSCAN_APIKEY",
+    }
+}
+
+/// Name of environment variable holding sponsor API key of Gelato Relay
+pub static RELAY_APIKEY_ENV_VAR: &str = "APPVKEK_GELATO_APIKEY";
+
+/// Return sponsor API key of Gelato Relay from command line if specified,
+/// otherwise from environment variable.
+///
+/// # Arguments
+/// * `cli_apikey` - API key from `--relay-api-key`
+pub fn select_relay_apikey(cli_apikey: Option<&str>) -> Result<String, String> {
+    if let Some(key) = cli_apikey {
+        return Ok(key.to_owned());
+    }
+    match std::env::var(RELAY_APIKEY_ENV_VAR) {
+        Ok(key) if !key.is_empty() => Ok(key),
+        _ => Err(format!("Error Gelato Relay API key is required; use --relay-api-key, or set {} environment variable", RELAY_APIKEY_ENV_VAR)),
+    }
+}
+
+/// Load environment variables from `.env` file in current directory or any
+/// of its parents if exists. Variables already set in environment take
+/// precedence.
+pub fn load_dotenv() {
+    match dotenvy::dotenv() {
+        Ok(path) => tracing::debug!("Loaded environment variables from {}", path.display()),
+        Err(e) if e.not_found() => (),
+        Err(e) => tracing::warn!("Error loading .env file; err={}", e),
+    }
+}
+
+/// Return API key of the specified chain stored in OS keyring if any.
+///
+/// # Arguments
+/// * `chain` - chain type
+fn get_keyring_apikey(chain: ChainType) -> Option<String> {
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, apikey_env_var(chain)) {
+        Ok(res) => res,
+        Err(e) => {
+            tracing::debug!("Error accessing OS keyring; err={}", e);
+            return None;
+        }
+    };
+    match entry.get_password() {
+        Ok(res) => Some(res),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            tracing::debug!("Error reading API key from OS keyring; err={}", e);
+            None
+        }
+    }
+}
+
+/// Store API key of the specified chain into OS keyring.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `api_key` - API key to store
+pub fn set_keyring_apikey(chain: ChainType, api_key: &str) -> Result<(), String> {
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, apikey_env_var(chain)) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error accessing OS keyring; err={}", e)),
+    };
+    match entry.set_password(api_key) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error storing API key into OS keyring; err={}", e)),
+    }
+}
+
+/// Find explorer API key for the specified chain in the same order as
+/// `select_apikey()`, and return it along with description of where it's
+/// found; `None` if not found anywhere.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `api_key` - API key specified explicitly if any
+pub fn find_apikey(chain: ChainType, api_key: Option<&str>) -> Option<(String, String)> {
+    if let Some(api_key) = api_key {
+        return Some((api_key.to_owned(), "--api-key, or profile".to_owned()));
+    }
+
+    let env_var = apikey_env_var(chain);
+    if let Ok(api_key) = std::env::var(env_var) {
+        if !api_key.is_empty() {
+            return Some((api_key, format!("{} environment variable", env_var)));
+        }
+    }
+
+    get_keyring_apikey(chain).map(|api_key| (api_key, "OS keyring".to_owned()))
+}
+
+/// Select and return explorer API key for the specified chain from the
+/// following in order
+///
+/// 1. `api_key` i.e. `--api-key` flag, or `api_key` of profile
+/// 2. environment variable e.g. `APPVKEK_BSCSCAN_APIKEY`, which can also be
+///    defined in `.env` file
+/// 3. OS keyring; service `appvkek`, and username as of environment variable
+///
+/// Return error explaining all of them if none is found.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `api_key` - API key specified explicitly if any
+pub fn select_apikey(chain: ChainType, api_key: Option<&str>) -> Result<String, String> {
+    if let Some((api_key, _)) = find_apikey(chain, api_key) {
+        return Ok(api_key);
+    }
+
+    let env_var = apikey_env_var(chain);
+    Err(format!("Error explorer API key for {} is required. Supply it via any of the following
+  * --api-key <key> flag
+  * `api_key` in profile of config file
+  * {} environment variable, or the same in .env file
+  * OS keyring, stored via `appvkek set-api-key -c {}`", chain_name(chain), env_var, chain_name(chain)))
+}
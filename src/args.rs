@@ -0,0 +1,817 @@
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[clap(author="Wasin Thonkaew (wasin@wasin.io)")]
+#[clap(name="appvkek")]
+#[clap(about="cli tool to check your approval and allowance associated with token contract addresses out there")]
+pub struct CommandlineArgs {
+    #[clap(subcommand)]
+    pub command: Command,
+
+    /// Increase verbosity of diagnostics written to stderr; `-v` for info,
+    /// `-vv` for debug, and `-vvv` for trace.
+    #[clap(long="verbose", short='v', global=true, parse(from_occurrences), conflicts_with="quiet")]
+    pub verbose: u64,
+
+    /// Only write errors to stderr, and don't show progress bar.
+    #[clap(long="quiet", short='q', global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub quiet: bool,
+
+    /// Show amounts abbreviated along with token symbol e.g. `1.2M USDC`,
+    /// and unlimited allowances as `unlimited`, instead of exact values.
+    #[clap(long="human", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub human: bool,
+
+    /// Show timestamps in UTC instead of local time.
+    #[clap(long="utc", global=true, multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub utc: bool,
+
+    /// Also write diagnostics into the specified file, at the same verbosity.
+    #[clap(long="log-file", global=true, multiple_values=false)]
+    pub log_file: Option<String>,
+
+    /// Export traces of scan phases, and RPC calls via OTLP over HTTP to this
+    /// collector endpoint e.g. `http://localhost:4318/v1/traces`.
+    #[clap(long="otel-endpoint", global=true, multiple_values=false)]
+    pub otel_endpoint: Option<String>,
+
+    /// Name of profile in config file to take default values of options from.
+    /// Options specified on command line take precedence.
+    #[clap(long="profile", global=true, multiple_values=false)]
+    pub profile: Option<String>,
+
+    /// Path to config file.
+    /// Default is `$XDG_CONFIG_HOME/appvkek/config.toml`, or
+    /// `$HOME/.config/appvkek/config.toml` if `XDG_CONFIG_HOME` is not set.
+    #[clap(long="config", global=true, multiple_values=false)]
+    pub config: Option<String>,
+}
+
+/// Subcommands.
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Scan a wallet for approvals, and report them prioritized by risk score.
+    Scan(ScanArgs),
+
+    /// Keep running, and re-scan a wallet periodically reporting newly
+    /// detected approvals as they are found.
+    Watch(WatchArgs),
+
+    /// Print unsigned transactions which revoke approvals of a wallet, to be
+    /// submitted via wallet of your choice.
+    Revoke(RevokeArgs),
+
+    /// Submit revoke of an approval signed as EIP-2612 permit via `revoke
+    /// --gasless` to Gelato Relay, so that it's sent without native token of
+    /// wallet for gas.
+    RelayRevoke(RelayRevokeArgs),
+
+    /// Export approvals of a wallet along with their risk assessment as JSON
+    /// or CSV.
+    Export(ExportArgs),
+
+    /// Report approvals added, removed, and changed between two snapshots
+    /// taken via `scan --snapshot`, or `export`.
+    Diff(DiffArgs),
+
+    /// Aggregate approvals of snapshots of a wallet taken on different
+    /// chains by spender, to see spenders approved on several of them.
+    Aggregate(AggregateArgs),
+
+    /// Show every change of allowance of a spender on a token contract
+    /// chronologically i.e. approve, increase, decrease, and revoke.
+    History(HistoryArgs),
+
+    /// Show all token contracts on which a spender has been approved, and
+    /// how much, querying allowance directly on each of them.
+    Spender(SpenderArgs),
+
+    /// Scan a wallet, and report per-phase timings and RPC call counts instead
+    /// of approvals. Scans a well-known fixture wallet unless `-a` is given.
+    Bench(BenchArgs),
+
+    /// Run scans of many wallets on many chains listed in a jobs file, each
+    /// notifying its own webhook; for scheduled scans of a fleet of wallets.
+    RunJobs(RunJobsArgs),
+
+    /// Store explorer API key of a chain into OS keyring. The key is read
+    /// from stdin.
+    SetApiKey(SetApiKeyArgs),
+
+    /// Check environment, explorer API key, RPC endpoint, clock, and data
+    /// directory for misconfiguration, and print pass/fail of each.
+    Doctor(DoctorArgs),
+
+    /// Print shell completion script to stdout.
+    #[clap(hide=true)]
+    Completions(CompletionsArgs),
+}
+
+/// Options shared by all subcommands; chain, and how to query it.
+#[derive(Debug, clap::Args)]
+pub struct CommonArgs {
+    /// Which chain to work with; required unless set in profile.
+    /// Possible values are 'bsc', 'ethereum', and 'polygon'.
+    #[clap(long="chain", short='c', multiple_values=false)]
+    pub chain: Option<String>,
+
+    /// URL of RPC endpoint to use instead of the default public one of the chain.
+    /// It can be specified multiple times, or comma-separated; endpoints are
+    /// probed at startup, and requests go to the lowest latency one which is
+    /// healthy.
+    #[clap(long="rpc-url", multiple_occurrences=true, use_value_delimiter=true)]
+    pub rpc_urls: Vec<String>,
+
+    /// Explorer API key of the chain. Default is taken from profile,
+    /// environment variable e.g. `APPVKEK_BSCSCAN_APIKEY` (or `.env` file),
+    /// then OS keyring in order.
+    #[clap(long="api-key", multiple_values=false)]
+    pub api_key: Option<String>,
+
+    /// Whether to include execution time statistics at the end of procesing
+    #[clap(long="execution-time", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub execution_time: bool,
+
+    /// Maximum number of in-flight RPC calls at any time.
+    /// Increase for fast private RPC endpoints, decrease for slow public ones.
+    #[clap(long="concurrency", multiple_values=false, default_value="16")]
+    pub concurrency: usize,
+
+    /// Don't batch queries via Multicall3 contract, but query each token
+    /// contract individually instead.
+    #[clap(long="no-multicall", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_multicall: bool,
+
+    /// Don't group JSON-RPC requests into batch requests, but send each
+    /// request individually instead. Useful for endpoints which don't
+    /// support batch requests well.
+    #[clap(long="no-rpc-batch", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_rpc_batch: bool,
+
+    /// Maximum number of retries of RPC requests which failed for transient
+    /// reasons e.g. connection failure, HTTP 429 or 5xx. Reverted calls are
+    /// never retried.
+    #[clap(long="rpc-retries", multiple_values=false, default_value="3")]
+    pub rpc_retries: u32,
+
+    /// Delay in milliseconds before the first retry of RPC request; doubled on
+    /// each subsequent retry, with random jitter added.
+    #[clap(long="rpc-retry-backoff", multiple_values=false, default_value="500")]
+    pub rpc_retry_backoff: u64,
+
+    /// Maximum number of requests per second sent to RPC endpoint, including
+    /// each request in a batch request; 0 means unlimited. Default depends on
+    /// RPC endpoint of the chain.
+    #[clap(long="rpc-rate-limit", multiple_values=false)]
+    pub rpc_rate_limit: Option<f64>,
+
+    /// Maximum number of requests sent to RPC endpoint at once before rate
+    /// limit kicks in. Default depends on RPC endpoint of the chain.
+    #[clap(long="rpc-burst", multiple_values=false)]
+    pub rpc_burst: Option<u32>,
+
+    /// URL of RPC endpoint to its own rate limit in form of
+    /// `(requests_per_second, burst)`; from `rpc_rate_limits` of config file.
+    #[clap(skip)]
+    pub rpc_rate_limits: std::collections::HashMap<String, (f64, u32)>,
+
+    /// Chain name to rate limit of explorer API calls in form of
+    /// `(requests_per_second, burst)`; from `explorer_rate_limits` of config
+    /// file.
+    #[clap(skip)]
+    pub explorer_rate_limits: std::collections::HashMap<String, (f64, u32)>,
+
+    /// Timeout in seconds of each outgoing HTTP request e.g. to RPC endpoint,
+    /// explorer API, DefiLlama for token prices, and webhook.
+    #[clap(long="timeout", multiple_values=false, default_value="30")]
+    pub timeout: u64,
+
+    /// URL of HTTP, or SOCKS5 proxy to send every outgoing HTTP request
+    /// through e.g. `socks5h://127.0.0.1:9050` for Tor. Default is taken from
+    /// profile, then `HTTPS_PROXY`, or `ALL_PROXY` environment variable.
+    #[clap(long="proxy", multiple_values=false)]
+    pub proxy: Option<String>,
+
+    /// Dump every JSON-RPC, and explorer API request along with its response
+    /// into the specified file as JSON lines for troubleshooting. API keys
+    /// are redacted. The file can be replayed via `--replay`.
+    #[clap(long="debug-rpc", alias="record", multiple_values=false)]
+    pub debug_rpc: Option<String>,
+
+    /// Serve every JSON-RPC, and explorer API response from the specified
+    /// file recorded via `--debug-rpc` instead of network, to re-run a scan
+    /// offline. Explorer API key is not required.
+    #[clap(long="replay", alias="offline", multiple_values=false)]
+    pub replay: Option<String>,
+
+    /// Timeout in seconds of each request to RPC endpoint; overrides `--timeout`.
+    #[clap(long="rpc-timeout", multiple_values=false)]
+    pub rpc_timeout: Option<u64>,
+
+    /// Timeout in seconds of each request to explorer API, and of label, and
+    /// malicious list updates; overrides `--timeout`.
+    #[clap(long="explorer-timeout", multiple_values=false)]
+    pub explorer_timeout: Option<u64>,
+
+    /// Don't use persistent cache of token metadata (name, symbol, and
+    /// decimals), but always query them from chain.
+    #[clap(long="no-metadata-cache", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_metadata_cache: bool,
+
+    /// Query token contracts again even if they have consistently failed in
+    /// previous scans.
+    #[clap(long="retry-failed", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub retry_failed: bool,
+
+    /// Additional labels file mapping addresses to their labels; in the same
+    /// format as the bundled `data/labels.json`.
+    #[clap(long="labels-file", multiple_values=false)]
+    pub labels_file: Option<String>,
+
+    /// Address book mapping addresses to your own labels e.g. "my hot
+    /// wallet"; JSON object of address to label. Default is
+    /// `$HOME/.appvkek/addressbook.json` if exists.
+    #[clap(long="address-book", multiple_values=false)]
+    pub address_book: Option<String>,
+
+    /// Fetch the latest label database of well-known spender addresses into
+    /// `$HOME/.appvkek/labels.json` before scanning.
+    #[clap(long="update-labels", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub update_labels: bool,
+
+    /// Allow wallet address to be a contract wallet e.g. Safe, or smart
+    /// account. Approvals of contract wallets are made via internal calls, so
+    /// they are collected from `Approval` event logs instead of transactions.
+    #[clap(long="allow-contract-wallet", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub allow_contract_wallet: bool,
+
+    /// Abort on the first transaction which can't be parsed e.g. malformed
+    /// approve calldata, instead of skipping it, and reporting it as warning
+    /// at the end.
+    #[clap(long="fail-fast", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub fail_fast: bool,
+}
+
+/// Options of risk assessment of approvals.
+#[derive(Debug, clap::Args)]
+pub struct RiskArgs {
+    /// Number of hours to cache whether a spender address has code; 0
+    /// disables cache. Code can be deployed later at an address, or destroyed,
+    /// so it shouldn't be too long.
+    #[clap(long="code-cache-ttl", multiple_values=false, default_value="24")]
+    pub code_cache_ttl: u64,
+
+    /// Skip checking recent activity of unknown spender contracts via explorer.
+    #[clap(long="skip-activity-check", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub skip_activity_check: bool,
+
+    /// Skip fetching creation date and creator of unknown spender contracts via
+    /// explorer.
+    #[clap(long="skip-creation-check", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub skip_creation_check: bool,
+
+    /// Number of days without activity after which an unknown spender contract
+    /// is considered dormant.
+    #[clap(long="dormant-days", multiple_values=false, default_value="180")]
+    pub dormant_days: u64,
+
+    /// Additional known-malicious address list to check spenders against.
+    /// It can be either URL or local file path, and can be specified multiple times.
+    /// ScamSniffer's list is always included unless `--no-default-malicious-list`.
+    #[clap(long="malicious-list", multiple_occurrences=true)]
+    pub malicious_lists: Vec<String>,
+
+    /// Don't include ScamSniffer's list by default.
+    #[clap(long="no-default-malicious-list", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub no_default_malicious_list: bool,
+
+    /// Skip checking verification status of spender contracts via explorer.
+    #[clap(long="skip-verification-check", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub skip_verification_check: bool,
+
+    /// Skip valuing what each approval puts at risk i.e. querying balance of
+    /// each token, and its current price from DefiLlama.
+    #[clap(long="skip-value-check", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub skip_value_check: bool,
+
+    /// Number of days after which an approval is considered stale.
+    #[clap(long="stale-days", multiple_values=false, default_value="365")]
+    pub stale_days: u64,
+
+    /// Additional dataset of exploited or compromised contracts; in the same
+    /// format as the bundled `data/exploits.json`.
+    #[clap(long="exploits-file", multiple_values=false)]
+    pub exploits_file: Option<String>,
+
+    /// External analyzer to run after risk assessment; can be specified
+    /// multiple times. It receives report in JSON via stdin, and prints JSON
+    /// array of findings to stdout.
+    #[clap(long="analyzer", multiple_occurrences=true)]
+    pub analyzers: Vec<String>,
+
+    /// Skip looking up primary ENS names of spenders; only done on Ethereum.
+    #[clap(long="skip-ens-lookup", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub skip_ens_lookup: bool,
+
+    /// Show approvals to well-known burn addresses e.g. `0x...dEaD`, which
+    /// are hidden by default as nobody can ever spend them.
+    #[clap(long="show-burn", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub show_burn: bool,
+}
+
+/// Options of `scan` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct ScanArgs {
+    /// User's wallet address to check against; required unless set in profile.
+    /// It can be specified multiple times, or comma-separated to scan multiple
+    /// wallets at once.
+    #[clap(long="wallet-address", short='a', multiple_occurrences=true, use_value_delimiter=true)]
+    pub addresses: Vec<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(flatten)]
+    pub risk: RiskArgs,
+
+    /// Extended public key (xpub) to derive wallets from, and scan along
+    /// with `--wallet-address`; e.g. exported by hardware wallet at account
+    /// level.
+    #[clap(long="xpub", multiple_values=false, conflicts_with="mnemonic")]
+    pub xpub: Option<String>,
+
+    /// Read BIP-39 mnemonic from stdin to derive wallets from, and scan along
+    /// with `--wallet-address`. It's prompted without echo on terminal, and
+    /// never stored.
+    #[clap(long="mnemonic", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub mnemonic: bool,
+
+    /// Also read BIP-39 passphrase from stdin after mnemonic.
+    #[clap(long="mnemonic-passphrase", multiple_values=false, default_missing_value="true", takes_value=false, requires="mnemonic")]
+    pub mnemonic_passphrase: bool,
+
+    /// Derivation path pattern of wallets with `{i}` substituted with index
+    /// of each. Default is `m/44'/60'/0'/0/{i}` for `--mnemonic`, and `0/{i}`
+    /// relative to `--xpub`.
+    #[clap(long="derivation-path", multiple_values=false)]
+    pub derivation_path: Option<String>,
+
+    /// Number of wallets to derive from `--xpub`, or `--mnemonic`.
+    #[clap(long="derive-count", multiple_values=false, default_value="5")]
+    pub derive_count: u32,
+
+    /// Ignore approvals collected by previous scans, and fetch the full
+    /// transaction history of wallet again.
+    #[clap(long="full-rescan", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub full_rescan: bool,
+
+    /// Continue from checkpoint of the previous scan which didn't complete
+    /// e.g. interrupted, or failed on transient errors, instead of starting
+    /// over. Token contracts queried by it are not queried again.
+    #[clap(long="resume", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub resume: bool,
+
+    /// Collect approvals first, then print estimated number of RPC calls, and
+    /// time querying them takes before asking whether to proceed. If stdin is
+    /// not a terminal, it only prints the estimate.
+    #[clap(long="estimate", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub estimate: bool,
+
+    /// Query allowances of approvals imported from this file instead of
+    /// collecting them from transaction history of wallet e.g. collected on
+    /// another machine. It's JSON output of `export`, array of them, or array
+    /// of approvals with at least `token_address`, and `spender`.
+    #[clap(long="import", multiple_values=false, conflicts_with="full-rescan")]
+    pub import: Option<String>,
+
+    /// Query token metadata and allowances as of this block number instead
+    /// of the latest block e.g. right before an exploit.
+    #[clap(long="at-block", multiple_values=false)]
+    pub at_block: Option<u64>,
+
+    /// Only show approvals whose risk score is at least this value (0-100).
+    #[clap(long="min-risk", multiple_values=false, default_value="0")]
+    pub min_risk: u32,
+
+    /// Only show approvals whose spender is unknown i.e. neither labeled as
+    /// well-known, nor in address book, nor a contract with verified source
+    /// code. Spenders aren't checked for verification with
+    /// --skip-verification-check, so they are regarded as unverified.
+    #[clap(long="unknown-only")]
+    pub unknown_only: bool,
+
+    /// Append links to explorer website for each token, and spender.
+    /// Possible values are 'explorer' (default), and 'revoke' which also
+    /// appends link to revoke.cash for each approval.
+    #[clap(long="links", min_values=0, max_values=1, require_equals=true, default_missing_value="explorer")]
+    pub links: Option<String>,
+
+    /// Also query wallet's balance, and total supply of each token, then show
+    /// allowances as percentage of them. It takes 2 more calls per token.
+    #[clap(long="percent")]
+    pub percent: bool,
+
+    /// Group approvals by protocol of well-known spenders e.g. all contracts
+    /// of Uniswap together, with exposure of each protocol, instead of by
+    /// token. Approvals with zero allowance are left out.
+    #[clap(long="by-protocol")]
+    pub by_protocol: bool,
+
+    /// List each spender once with the number of tokens approved to it, and
+    /// total USD value it can take i.e. allowance capped at balance, most
+    /// exposed first. It queries balance of each token as of `--percent`, and
+    /// current prices from DefiLlama.
+    #[clap(long="by-spender", conflicts_with="by-protocol")]
+    pub by_spender: bool,
+
+    /// Exit with code 2 if matching approvals with non-zero allowance remain,
+    /// or code 3 if none matches but some token contracts failed to be
+    /// queried, or scan was interrupted. Note that invalid command-line usage
+    /// also exits with code 2.
+    /// Possible values are 'any', 'unlimited', or 'risk>=N' e.g. 'risk>=50'.
+    #[clap(long="fail-on", multiple_values=false)]
+    pub fail_on: Option<String>,
+
+    /// Save approvals as snapshot into this file; in JSON as of `export`, or
+    /// array of them for multiple wallets.
+    #[clap(long="snapshot", multiple_values=false)]
+    pub snapshot: Option<String>,
+
+    /// Report approvals added, removed, and changed since the specified
+    /// snapshot instead of all approvals.
+    #[clap(long="diff-with", multiple_values=false)]
+    pub diff_with: Option<String>,
+
+    /// Record results into this SQLite database along with all previous
+    /// scans; created if not exist.
+    #[clap(long="db", multiple_values=false)]
+    pub db: Option<String>,
+}
+
+/// Options of `watch` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct WatchArgs {
+    /// User's wallet address to check against; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(flatten)]
+    pub risk: RiskArgs,
+
+    /// Re-scan for approvals every specified number of seconds.
+    #[clap(long="interval", multiple_values=false, default_value="300")]
+    pub interval: u64,
+
+    /// Webhook URL to POST a JSON payload to whenever a new approval is detected,
+    /// or an existing approval crosses the threshold, or minimum risk score.
+    #[clap(long="webhook-url", multiple_values=false)]
+    pub webhook_url: Option<String>,
+
+    /// Allowance threshold (in token unit) for an existing approval to be
+    /// reported once its allowance crosses from below to at or above it.
+    #[clap(long="webhook-threshold", multiple_values=false)]
+    pub webhook_threshold: Option<f64>,
+
+    /// Minimum risk score for an existing approval to be reported once its
+    /// score crosses from below to at or above it e.g. its spender has been
+    /// listed as malicious since.
+    #[clap(long="webhook-min-risk", multiple_values=false)]
+    pub webhook_min_risk: Option<u32>,
+
+    /// Run as a daemon. Its baseline and last scanned block are persisted to
+    /// the state file so restarts continue from where it left off.
+    #[clap(long="daemon", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub daemon: bool,
+
+    /// Path to state file used in daemon mode.
+    /// Default is `$HOME/.appvkek/state-<chain>-<wallet-address>.json`.
+    #[clap(long="state-file", multiple_values=false)]
+    pub state_file: Option<String>,
+
+    /// Record results of every round into this SQLite database.
+    #[clap(long="db", multiple_values=false)]
+    pub db: Option<String>,
+
+    /// WebSocket URL of RPC endpoint to monitor pending transactions of
+    /// wallet via. Approvals are alerted as soon as they are seen in mempool,
+    /// before they are confirmed.
+    #[clap(long="ws-url", multiple_values=false)]
+    pub ws_url: Option<String>,
+
+    /// Along with alert of each pending approval, print unsigned
+    /// `approve(spender, 0)` transaction which replaces it by taking its nonce
+    /// with higher fees.
+    #[clap(long="replacement-tx", multiple_values=false, default_missing_value="true", takes_value=false, requires="ws-url")]
+    pub replacement_tx: bool,
+}
+
+/// Options of `revoke` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct RevokeArgs {
+    /// User's wallet address to revoke approvals of; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(flatten)]
+    pub risk: RiskArgs,
+
+    /// Ignore approvals collected by previous scans, and fetch the full
+    /// transaction history of wallet again.
+    #[clap(long="full-rescan", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub full_rescan: bool,
+
+    /// Continue from checkpoint of the previous scan which didn't complete
+    /// e.g. interrupted, or failed on transient errors, instead of starting
+    /// over. Token contracts queried by it are not queried again.
+    #[clap(long="resume", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub resume: bool,
+
+    /// Collect approvals first, then print estimated number of RPC calls, and
+    /// time querying them takes before asking whether to proceed. If stdin is
+    /// not a terminal, it only prints the estimate.
+    #[clap(long="estimate", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub estimate: bool,
+
+    /// Only revoke approvals of the specified token contract; can be specified
+    /// multiple times.
+    #[clap(long="token", multiple_occurrences=true)]
+    pub tokens: Vec<String>,
+
+    /// Only revoke approvals to the specified spender; can be specified
+    /// multiple times.
+    #[clap(long="spender", multiple_occurrences=true)]
+    pub spenders: Vec<String>,
+
+    /// Only revoke approvals whose risk score is at least this value (0-100).
+    #[clap(long="min-risk", multiple_values=false, default_value="0")]
+    pub min_risk: u32,
+
+    /// Don't ask for confirmation; required when stdin is not a terminal
+    /// e.g. in automated pipelines.
+    #[clap(long="yes", short='y', alias="non-interactive", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub yes: bool,
+
+    /// Print EIP-2612 permits to sign instead of transactions for token
+    /// contracts supporting it, so that revokes can be submitted via
+    /// `relay-revoke` without native token for gas. Other token contracts
+    /// still need transactions.
+    #[clap(long="gasless", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub gasless: bool,
+
+    /// Minutes from now until which permits printed via `--gasless` are
+    /// valid.
+    #[clap(long="permit-ttl", multiple_values=false, default_value="60")]
+    pub permit_ttl: u64,
+
+    /// Save matching approvals into the specified queue file instead of
+    /// printing transactions, so that they can be executed later via
+    /// `--execute`. Approvals already in queue are kept along with their
+    /// status.
+    #[clap(long="queue", multiple_values=false)]
+    pub queue: Option<String>,
+
+    /// Execute queue file of `--queue` instead of scanning; current allowance
+    /// of each pending revoke is checked, those found zero are marked as
+    /// revoked, and transactions of the rest are printed. Re-run it after
+    /// sending them to track which went through.
+    #[clap(long="execute", requires="queue", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub execute: bool,
+
+    /// Print each transaction as EIP-681 URI along with its QR code, so that
+    /// it can be scanned, and signed in a mobile wallet.
+    #[clap(long="qr", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub qr: bool,
+
+    /// Copy calldata of each transaction onto system clipboard in turn,
+    /// pressing Enter to move on to the next one. Requires stdin to be a
+    /// terminal.
+    #[clap(long="copy", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub copy: bool,
+
+    /// Print explicit nonce for each transaction, consecutive from the next
+    /// nonce of wallet, so that they are sent one after another without
+    /// colliding. Transactions of wallet pending in mempool are warned as
+    /// they hold back revokes.
+    #[clap(long="nonces", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub nonces: bool,
+
+    /// Along with `--nonces`, replace transactions of wallet pending in
+    /// mempool e.g. stuck as underpriced; the first revokes take their
+    /// nonces, and are printed with gas price 25% higher than the current
+    /// one.
+    #[clap(long="replace", requires="nonces", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub replace: bool,
+
+    /// Print EIP-1559 fees for each transaction derived from priority fees
+    /// paid in the latest blocks, and estimate cost with them.
+    /// Possible values are 'slow', 'normal', 'fast', and 'urgent' which is
+    /// meant for incident response e.g. racing a drainer.
+    #[clap(long="fee-preset", multiple_values=false)]
+    pub fee_preset: Option<String>,
+}
+
+/// Options of `relay-revoke` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct RelayRevokeArgs {
+    /// User's wallet address which signed the permit; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    /// Token contract address of the permit.
+    #[clap(long="token", required=true, multiple_values=false)]
+    pub token: String,
+
+    /// Spender address whose approval is revoked by the permit.
+    #[clap(long="spender", required=true, multiple_values=false)]
+    pub spender: String,
+
+    /// Deadline of the permit as printed via `revoke --gasless`.
+    #[clap(long="deadline", required=true, multiple_values=false)]
+    pub deadline: u64,
+
+    /// Signature of the permit as returned by `eth_signTypedData_v4`; hex
+    /// string of 65 bytes.
+    #[clap(long="signature", required=true, multiple_values=false)]
+    pub signature: String,
+
+    /// Sponsor API key of Gelato Relay. Default is taken from environment
+    /// variable `APPVKEK_GELATO_APIKEY` (or `.env` file).
+    #[clap(long="relay-api-key", multiple_values=false)]
+    pub relay_api_key: Option<String>,
+}
+
+/// Options of `diff` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Older snapshot file.
+    pub old: String,
+
+    /// Newer snapshot file.
+    pub new: String,
+
+    /// Prefix addresses with EIP-3770 short name of their chain e.g.
+    /// `bnb:0x...`.
+    #[clap(long="chain-prefix")]
+    pub chain_prefix: bool,
+}
+
+/// Options of `aggregate` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct AggregateArgs {
+    /// Snapshot files e.g. one per chain.
+    #[clap(required=true)]
+    pub snapshots: Vec<String>,
+
+    /// Aggregate by protocol of well-known spenders e.g. all contracts of
+    /// Uniswap together instead of by spender address.
+    #[clap(long="by-protocol")]
+    pub by_protocol: bool,
+
+    /// Prefix addresses with EIP-3770 short name of their chain e.g.
+    /// `bnb:0x...`.
+    #[clap(long="chain-prefix")]
+    pub chain_prefix: bool,
+}
+
+/// Options of `history` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct HistoryArgs {
+    /// User's wallet address; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    /// Token contract address.
+    #[clap(long="token", required=true, multiple_values=false)]
+    pub token: String,
+
+    /// Spender address.
+    #[clap(long="spender", required=true, multiple_values=false)]
+    pub spender: String,
+}
+
+/// Options of `spender` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct SpenderArgs {
+    /// User's wallet address; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    /// Spender address.
+    #[clap(long="address", required=true, multiple_values=false)]
+    pub spender: String,
+
+    /// Append links to explorer website for each token, and spender.
+    /// Possible values are 'explorer' (default), and 'revoke' which also
+    /// appends link to revoke.cash for each approval.
+    #[clap(long="links", min_values=0, max_values=1, require_equals=true, default_missing_value="explorer")]
+    pub links: Option<String>,
+}
+
+/// Options of `export` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// User's wallet address to export approvals of; required unless set in profile.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(flatten)]
+    pub risk: RiskArgs,
+
+    /// Ignore approvals collected by previous scans, and fetch the full
+    /// transaction history of wallet again.
+    #[clap(long="full-rescan", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub full_rescan: bool,
+
+    /// Continue from checkpoint of the previous scan which didn't complete
+    /// e.g. interrupted, or failed on transient errors, instead of starting
+    /// over. Token contracts queried by it are not queried again.
+    #[clap(long="resume", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub resume: bool,
+
+    /// Collect approvals first, then print estimated number of RPC calls, and
+    /// time querying them takes before asking whether to proceed. If stdin is
+    /// not a terminal, it only prints the estimate.
+    #[clap(long="estimate", multiple_values=false, default_missing_value="true", takes_value=false)]
+    pub estimate: bool,
+
+    /// Query token metadata and allowances as of this block number instead
+    /// of the latest block e.g. right before an exploit.
+    #[clap(long="at-block", multiple_values=false)]
+    pub at_block: Option<u64>,
+
+    /// Format to export as; default is 'json'.
+    /// Possible values are 'json', 'csv', 'revokecash', 'dot', and 'mermaid'.
+    #[clap(long="format", multiple_values=false)]
+    pub format: Option<String>,
+
+    /// Path to file to write into; default is stdout.
+    #[clap(long="output", short='o', multiple_values=false)]
+    pub output: Option<String>,
+}
+
+/// Options of `doctor` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct DoctorArgs {
+    #[clap(flatten)]
+    pub common: CommonArgs,
+}
+
+/// Options of `bench` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    /// Wallet address to benchmark against; default is a well-known fixture
+    /// wallet with a long history of approvals.
+    #[clap(long="wallet-address", short='a')]
+    pub address: Option<String>,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+}
+
+/// Options of `run-jobs` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct RunJobsArgs {
+    /// Path to jobs file in TOML.
+    pub jobs_file: String,
+
+    #[clap(flatten)]
+    pub common: CommonArgs,
+
+    #[clap(flatten)]
+    pub risk: RiskArgs,
+}
+
+/// Options of `set-api-key` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct SetApiKeyArgs {
+    /// Which chain the API key is for.
+    /// Possible values are 'bsc', 'ethereum', and 'polygon'.
+    #[clap(long="chain", short='c', required=true, multiple_values=false)]
+    pub chain: String,
+}
+
+/// Options of `completions` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct CompletionsArgs {
+    /// Which shell to generate completion script for.
+    #[clap(arg_enum)]
+    pub shell: clap_complete::Shell,
+}
@@ -0,0 +1,127 @@
+use ::evmscan::environ::Context;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::transport::TransportStats;
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+use appvkek_core::scanner;
+
+/// Default wallet to benchmark against; vitalik.eth which has a long history
+/// of approvals, and exists as an EOA on all supported chains
+pub static FIXTURE_WALLET_ADDRESS: &str = "0xd8da6bf26964af9d7eed9e03e53415d37aa96045";
+
+/// Timing and request counts of a single phase.
+struct Phase {
+    /// Name of phase
+    name: &'static str,
+
+    /// Wall-clock time spent
+    elapsed: Duration,
+
+    /// Requests made to RPC endpoint during phase
+    rpc: Option<TransportStats>,
+
+    /// Number of HTTP requests made to explorer during phase
+    explorer_requests: Option<usize>,
+}
+
+/// Return difference of transport counters between two points in time.
+///
+/// # Arguments
+/// * `before` - counters before
+/// * `after` - counters after
+fn stats_diff(before: TransportStats, after: TransportStats) -> TransportStats {
+    TransportStats {
+        rpc_calls: after.rpc_calls - before.rpc_calls,
+        http_requests: after.http_requests - before.http_requests,
+    }
+}
+
+/// Run a scan against the specified wallet, and print timings and request
+/// counts of each phase to stdout.
+///
+/// Token contracts are queried twice; first without any cached metadata,
+/// then with metadata cached from the first pass in memory, so that cost of
+/// metadata queries can be told apart from allowance queries. Persistent
+/// caches are neither read nor written, so results are repeatable.
+///
+/// # Arguments
+/// * `web3` - web3 instance
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `ctx` - evmscan's context
+/// * `owner_address` - wallet address to scan; lowercased
+/// * `use_multicall` - whether to batch queries via Multicall3 contract
+pub async fn run(web3: &Web3Type, semaphore: &Semaphore, ctx: &Context, owner_address: &str, use_multicall: bool) {
+    let mut phases: Vec<Phase> = Vec::new();
+
+    // 1. explorer fetch, and parsing of transactions
+    let mut ct_txs: ApprovalsMap = HashMap::new();
+    let mut num_pages = 0;
+    let mut num_txs = 0;
+    let mut parse_elapsed = Duration::ZERO;
+    let fetch_start = Instant::now();
+    let fetch_res = appvkek_core::explorer::for_each_normal_transactions_page(ctx, owner_address, 0, |txs| {
+        let parse_start = Instant::now();
+        num_pages += 1;
+        num_txs += txs.len();
+        for tx in txs {
+            if let Err(e) = scanner::collect_approval(&tx, owner_address, &mut ct_txs) {
+                tracing::warn!("Transaction {} was skipped; {}", tx.hash, e);
+            }
+        }
+        parse_elapsed += parse_start.elapsed();
+        Ok(())
+    });
+    if let Err(e) = fetch_res {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    phases.push(Phase { name: "explorer fetch", elapsed: fetch_start.elapsed() - parse_elapsed, rpc: None, explorer_requests: Some(num_pages) });
+    phases.push(Phase { name: "parsing", elapsed: parse_elapsed, rpc: None, explorer_requests: None });
+
+    let num_tokens = ct_txs.len();
+    let num_approvals: usize = ct_txs.values().map(|spenders| spenders.len()).sum();
+
+    // 2. metadata and allowance queries without cache
+    let mut metadata_cache = MetadataCache::load(ctx.chain, false);
+    let mut failed_contracts = FailedContracts::default();
+    let before = web3.transport().stats();
+    let start = Instant::now();
+    let results = scanner::query_all(web3, semaphore, ct_txs.clone(), owner_address, use_multicall, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+    let cold_elapsed = start.elapsed();
+    let cold_stats = stats_diff(before, web3.transport().stats());
+    let num_errors = results.iter().filter(|res| res.is_err()).count();
+
+    // 3. allowance queries only, with metadata cached in memory
+    let before = web3.transport().stats();
+    let start = Instant::now();
+    scanner::query_all(web3, semaphore, ct_txs, owner_address, use_multicall, &mut metadata_cache, &mut failed_contracts, |_| {}).await;
+    let warm_elapsed = start.elapsed();
+    let warm_stats = stats_diff(before, web3.transport().stats());
+
+    phases.push(Phase { name: "metadata queries", elapsed: cold_elapsed.saturating_sub(warm_elapsed), rpc: Some(TransportStats {
+        rpc_calls: cold_stats.rpc_calls.saturating_sub(warm_stats.rpc_calls),
+        http_requests: cold_stats.http_requests.saturating_sub(warm_stats.http_requests),
+    }), explorer_requests: None });
+    phases.push(Phase { name: "allowance queries", elapsed: warm_elapsed, rpc: Some(warm_stats), explorer_requests: None });
+
+    println!("Benchmark of {} on {}", to_checksum_address(owner_address), chain_name(ctx.chain));
+    println!("  {} transactions in {} pages, {} approvals across {} token contracts ({} failed)", num_txs, num_pages, num_approvals, num_tokens, num_errors);
+    println!("  multicall: {}", if use_multicall { "on" } else { "off" });
+    println!();
+    println!("{:<20} {:>10} {:>10} {:>14} {:>18}", "phase", "time (s)", "RPC calls", "RPC HTTP reqs", "explorer HTTP reqs");
+    for phase in phases.iter() {
+        println!("{:<20} {:>10.3} {:>10} {:>14} {:>18}",
+            phase.name,
+            phase.elapsed.as_secs_f64(),
+            phase.rpc.map(|s| s.rpc_calls.to_string()).unwrap_or_else(|| "-".to_owned()),
+            phase.rpc.map(|s| s.http_requests.to_string()).unwrap_or_else(|| "-".to_owned()),
+            phase.explorer_requests.map(|n| n.to_string()).unwrap_or_else(|| "-".to_owned()));
+    }
+    println!();
+    println!("Metadata queries are derived as the difference between querying without, and with cached metadata.");
+}
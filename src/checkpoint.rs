@@ -0,0 +1,190 @@
+use ::evmscan::prelude::ChainType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use appvkek_core::state::{load_state, save_state};
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+
+/// Number of token contracts queried between each save of checkpoint
+pub const CHECKPOINT_INTERVAL: usize = 20;
+
+/// Allowance of a single spender as queried; before risk assessment.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckpointAllowance {
+    /// Allowance balance in token unit
+    pub allowance: f64,
+
+    /// Whether the allowance is effectively unlimited
+    pub is_unlimited: bool,
+
+    /// Record of the latest approve transaction
+    pub approval: ApprovalRecord,
+}
+
+/// Token contract which has been queried successfully.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckpointToken {
+    /// Contract name
+    pub name: String,
+
+    /// Token symbol
+    pub symbol: String,
+
+    /// Number of decimals to token
+    pub decimals: u8,
+
+    /// Spender address to its allowance
+    pub spender_allowances: HashMap<String, CheckpointAllowance>,
+}
+
+/// Progress of collecting approvals which didn't complete.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectProgress {
+    /// Block number (inclusive) to continue fetching from
+    pub next_block: u64,
+
+    /// Approvals collected so far, including those of previous scans
+    pub approvals: ApprovalsMap,
+}
+
+/// Progress of an incomplete scan of a wallet.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Block number token contracts were queried at; latest block if `None`
+    #[serde(default)]
+    pub at_block: Option<u64>,
+
+    /// Progress of collecting approvals; `None` if it completed
+    #[serde(default)]
+    pub collected: Option<CollectProgress>,
+
+    /// Token contract address (lowercased) to its query result
+    pub tokens: HashMap<String, CheckpointToken>,
+}
+
+impl Checkpoint {
+    /// Create checkpoint of token contracts queried successfully.
+    ///
+    /// # Arguments
+    /// * `at_block` - block number token contracts were queried at
+    /// * `results` - results completed so far
+    pub fn from_results(at_block: Option<u64>, results: &[QueryResult]) -> Checkpoint {
+        let tokens = results.iter()
+            .filter_map(|res| res.as_ref().ok())
+            .map(|r| (r.address.to_lowercase(), CheckpointToken {
+                name: r.name.to_owned(),
+                symbol: r.symbol.to_owned(),
+                decimals: r.decimals,
+                spender_allowances: r.spender_allowances.iter()
+                    .map(|(spender, sa)| (spender.to_owned(), CheckpointAllowance {
+                        allowance: sa.allowance,
+                        is_unlimited: sa.is_unlimited,
+                        approval: sa.approval.clone(),
+                    }))
+                    .collect(),
+            }))
+            .collect();
+
+        Checkpoint {
+            at_block,
+            collected: None,
+            tokens,
+        }
+    }
+
+    /// Take token contracts of `ct_txs` which have been queried already out
+    /// of it, and return their results. Those with spender, or approval not
+    /// in checkpoint e.g. approved again since are left to be queried.
+    ///
+    /// # Arguments
+    /// * `ct_txs` - approvals to query
+    pub fn take_results(&self, ct_txs: &mut ApprovalsMap) -> Vec<QueryResult> {
+        let mut results: Vec<QueryResult> = Vec::new();
+        ct_txs.retain(|ct, spenders| {
+            let token = match self.tokens.get(&ct.to_lowercase()) {
+                Some(res) => res,
+                None => return true,
+            };
+            let is_up_to_date = spenders.iter().all(|(spender, approval)| {
+                token.spender_allowances.get(spender).map(|a| a.approval.block_number == approval.block_number).unwrap_or(false)
+            });
+            if !is_up_to_date {
+                return true;
+            }
+
+            results.push(Ok(TokenContractWithSpenderAllowances {
+                name: token.name.to_owned(),
+                symbol: token.symbol.to_owned(),
+                address: ct.to_owned(),
+                decimals: token.decimals,
+                balance: None,
+                total_supply: None,
+                spender_allowances: spenders.iter()
+                    .map(|(spender, approval)| {
+                        let a = &token.spender_allowances[spender];
+                        (spender.to_owned(), SpenderAllowance {
+                            allowance: a.allowance,
+                            is_unlimited: a.is_unlimited,
+                            risk: Default::default(),
+                            proxy: None,
+                            approval: approval.clone(),
+                            exploit: None,
+                            activity: None,
+                            is_verified: None,
+                            creation: None,
+                            control: None,
+                        })
+                    })
+                    .collect(),
+            }));
+            false
+        });
+        results
+    }
+}
+
+/// Return path of checkpoint file of the specified wallet.
+/// It is `$HOME/.appvkek/checkpoint-<chain>-<owner_address>.json`.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn checkpoint_file_path(chain: ChainType, owner_address: &str) -> String {
+    format!("{}/checkpoint-{}-{}.json", appvkek_dir(), chain_name(chain), owner_address)
+}
+
+/// Save checkpoint of the specified wallet, and return its path.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+/// * `checkpoint` - checkpoint to save
+pub fn save_checkpoint(chain: ChainType, owner_address: &str, checkpoint: &Checkpoint) -> Result<String, String> {
+    let path = checkpoint_file_path(chain, owner_address);
+    save_state(&path, checkpoint).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Load checkpoint of the specified wallet; `None` if there is none.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn load_checkpoint(chain: ChainType, owner_address: &str) -> Result<Option<Checkpoint>, String> {
+    load_state::<Checkpoint>(&checkpoint_file_path(chain, owner_address)).map_err(|e| e.to_string())
+}
+
+/// Remove checkpoint of the specified wallet if any, as its scan completed.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn remove_checkpoint(chain: ChainType, owner_address: &str) {
+    let path = checkpoint_file_path(chain, owner_address);
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Error removing checkpoint ({}); err={}", path, e);
+        }
+    }
+}
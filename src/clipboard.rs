@@ -0,0 +1,39 @@
+use std::io::Write;
+
+use appvkek_core::labels::Labels;
+use appvkek_core::revoke::RevokeTx;
+use appvkek_core::util::to_checksum_address;
+
+/// Copy calldata of the specified transactions onto system clipboard one at
+/// a time, waiting for Enter on the terminal before moving on to the next,
+/// so that each can be pasted into wallet UI in turn.
+/// Clipboard is owned by this process on some platforms e.g. X11 without
+/// clipboard manager, so the last one is also kept until Enter is pressed.
+///
+/// # Arguments
+/// * `txs` - transactions as returned by `print_revoke_txs()`
+/// * `labels` - labels of well-known addresses
+pub fn copy_revoke_txs(txs: &[RevokeTx], labels: &Labels) -> Result<(), String> {
+    if txs.is_empty() {
+        return Ok(());
+    }
+    let mut clipboard = match arboard::Clipboard::new() {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error accessing clipboard; err={}", e)),
+    };
+
+    for (i, tx) in txs.iter().enumerate() {
+        if let Err(e) = clipboard.set_text(tx.data.to_owned()) {
+            return Err(format!("Error copying calldata onto clipboard; err={}", e));
+        }
+        let next = if i + 1 < txs.len() { "copy the next one" } else { "finish" };
+        eprint!("Copied calldata of revoke {} on {} to {} ({}/{}); press Enter to {} ", labels.format(&tx.spender), labels.format(&tx.token_address), to_checksum_address(&tx.token_address), i + 1, txs.len(), next);
+        let _ = std::io::stderr().flush();
+
+        let mut line = String::new();
+        if let Err(e) = std::io::stdin().read_line(&mut line) {
+            return Err(format!("Error reading from stdin; err={}", e));
+        }
+    }
+    Ok(())
+}
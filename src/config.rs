@@ -0,0 +1,254 @@
+use appvkek_core::metadata::MetadataOverride;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::args::{CommonArgs, RiskArgs};
+
+/// Chain name to its token contract address to metadata override.
+pub type TokenOverrides = HashMap<String, HashMap<String, MetadataOverride>>;
+
+/// Rate limit of an RPC endpoint, or explorer API.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Number of requests allowed per second; 0 means unlimited
+    pub requests_per_second: f64,
+
+    /// Maximum number of requests allowed at once; default is
+    /// `requests_per_second` rounded up
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Return it in form of `(requests_per_second, burst)`.
+    pub fn to_pair(self) -> (f64, u32) {
+        (self.requests_per_second, self.burst.unwrap_or(self.requests_per_second.ceil().max(1.0) as u32))
+    }
+}
+
+/// Named set of default values of options.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Wallet address to work with
+    pub wallet_address: Option<String>,
+
+    /// Chain to work with; same values as of `--chain`
+    pub chain: Option<String>,
+
+    /// URL of RPC endpoint; can be comma-separated for multiple endpoints
+    pub rpc_url: Option<String>,
+
+    /// Explorer API key of the chain
+    pub api_key: Option<String>,
+
+    /// Format to export as; same values as of `--format`
+    pub format: Option<String>,
+
+    /// Additional known-malicious address lists; in addition to those on
+    /// command line
+    #[serde(default)]
+    pub malicious_lists: Vec<String>,
+
+    /// Additional labels file
+    pub labels_file: Option<String>,
+
+    /// Address book of user-provided labels
+    pub address_book: Option<String>,
+
+    /// URL of proxy to send requests through
+    pub proxy: Option<String>,
+
+    /// Additional dataset of exploited or compromised contracts
+    pub exploits_file: Option<String>,
+
+    /// External analyzers; appended to those specified on command line
+    #[serde(default)]
+    pub analyzers: Vec<String>,
+
+    /// Chain name to its token contract address to metadata override; from
+    /// top-level `token_overrides` of config file as it's shared by all
+    /// profiles
+    #[serde(skip)]
+    pub token_overrides: TokenOverrides,
+
+    /// URL of RPC endpoint to its rate limit; from top-level
+    /// `rpc_rate_limits` of config file
+    #[serde(skip)]
+    pub rpc_rate_limits: HashMap<String, RateLimit>,
+
+    /// Chain name to rate limit of its explorer API key; from top-level
+    /// `explorer_rate_limits` of config file
+    #[serde(skip)]
+    pub explorer_rate_limits: HashMap<String, RateLimit>,
+}
+
+/// Content of config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// Name of profile to use when `--profile` is not specified
+    default_profile: Option<String>,
+
+    /// Profile name to its profile
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+
+    /// Chain name to its token contract address to metadata override
+    #[serde(default)]
+    token_overrides: TokenOverrides,
+
+    /// URL of RPC endpoint to its rate limit
+    #[serde(default)]
+    rpc_rate_limits: HashMap<String, RateLimit>,
+
+    /// Chain name to rate limit of its explorer API key
+    #[serde(default)]
+    explorer_rate_limits: HashMap<String, RateLimit>,
+}
+
+/// Return default path of config file.
+/// It is `$XDG_CONFIG_HOME/appvkek/config.toml`, or
+/// `$HOME/.config/appvkek/config.toml` if `XDG_CONFIG_HOME` is not set.
+pub fn default_config_path() -> String {
+    match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => format!("{}/appvkek/config.toml", dir),
+        _ => format!("{}/.config/appvkek/config.toml", std::env::var("HOME").unwrap_or_else(|_| ".".to_owned())),
+    }
+}
+
+/// Load the specified profile from config file.
+/// If `name` is `None`, the profile named by `default_profile` is loaded if
+/// any, otherwise an empty profile is returned. Missing config file at the
+/// default path is not an error unless a profile is asked for.
+///
+/// # Arguments
+/// * `path` - path to config file; default path if `None`
+/// * `name` - name of profile
+pub fn load_profile(path: Option<&str>, name: Option<&str>) -> Result<Profile, String> {
+    let config_path = path.map(|p| p.to_owned()).unwrap_or_else(default_config_path);
+    let content = match std::fs::read_to_string(&config_path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && path.is_none() && name.is_none() => return Ok(Profile::default()),
+        Err(e) => return Err(format!("Error reading config file ({}); err={}", config_path, e)),
+    };
+
+    let mut config = match toml::from_str::<ConfigFile>(&content) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error parsing config file ({}); err={}", config_path, e)),
+    };
+
+    for chain in config.explorer_rate_limits.keys() {
+        if crate::parse_chain(chain).is_err() {
+            return Err(format!("Error invalid chain '{}' in explorer_rate_limits of config file ({}); possible values are 'bsc', 'ethereum', or 'polygon'", chain, config_path));
+        }
+    }
+    let shared = Profile {
+        token_overrides: config.token_overrides,
+        rpc_rate_limits: config.rpc_rate_limits,
+        explorer_rate_limits: config.explorer_rate_limits.into_iter().map(|(chain, limit)| (chain.to_lowercase(), limit)).collect(),
+        ..Default::default()
+    };
+
+    let name = match name.map(|n| n.to_owned()).or(config.default_profile) {
+        Some(res) => res,
+        None => return Ok(shared),
+    };
+    match config.profiles.remove(&name) {
+        Some(res) => Ok(Profile {
+            token_overrides: shared.token_overrides,
+            rpc_rate_limits: shared.rpc_rate_limits,
+            explorer_rate_limits: shared.explorer_rate_limits,
+            ..res
+        }),
+        None => {
+            let mut names: Vec<&String> = config.profiles.keys().collect();
+            names.sort();
+            Err(format!("Error profile '{}' not found in config file ({}); available profiles are {:?}", name, config_path, names))
+        }
+    }
+}
+
+impl Profile {
+    /// Fill common options not specified on command line from this profile.
+    ///
+    /// # Arguments
+    /// * `common` - common options from command line
+    pub fn apply_common(&self, common: &mut CommonArgs) {
+        if common.chain.is_none() {
+            common.chain = self.chain.to_owned();
+        }
+        if common.rpc_urls.is_empty() {
+            common.rpc_urls = self.rpc_url.iter()
+                .flat_map(|u| u.split(','))
+                .map(|u| u.trim().to_owned())
+                .filter(|u| !u.is_empty())
+                .collect();
+        }
+        if common.api_key.is_none() {
+            common.api_key = self.api_key.to_owned();
+        }
+        if common.labels_file.is_none() {
+            common.labels_file = self.labels_file.to_owned();
+        }
+        if common.address_book.is_none() {
+            common.address_book = self.address_book.to_owned();
+        }
+        if common.proxy.is_none() {
+            common.proxy = self.proxy.to_owned();
+        }
+        for (url, limit) in self.rpc_rate_limits.iter() {
+            common.rpc_rate_limits.entry(url.to_owned()).or_insert(limit.to_pair());
+        }
+        for (chain, limit) in self.explorer_rate_limits.iter() {
+            common.explorer_rate_limits.entry(chain.to_owned()).or_insert(limit.to_pair());
+        }
+    }
+
+    /// Fill options of risk assessment not specified on command line from
+    /// this profile. Malicious address lists of both are used.
+    ///
+    /// # Arguments
+    /// * `risk` - options of risk assessment from command line
+    pub fn apply_risk(&self, risk: &mut RiskArgs) {
+        for list in self.malicious_lists.iter() {
+            if !risk.malicious_lists.contains(list) {
+                risk.malicious_lists.push(list.to_owned());
+            }
+        }
+        if risk.exploits_file.is_none() {
+            risk.exploits_file = self.exploits_file.to_owned();
+        }
+        for analyzer in self.analyzers.iter() {
+            if !risk.analyzers.contains(analyzer) {
+                risk.analyzers.push(analyzer.to_owned());
+            }
+        }
+    }
+
+    /// Return wallet address specified on command line if any, otherwise
+    /// the one of this profile.
+    ///
+    /// # Arguments
+    /// * `address` - wallet address from command line
+    pub fn wallet_address(&self, address: Option<String>) -> Option<String> {
+        address.or_else(|| self.wallet_address.to_owned())
+    }
+
+    /// Return wallet addresses specified on command line if any, otherwise
+    /// those of this profile; `wallet_address` of profile can be
+    /// comma-separated.
+    ///
+    /// # Arguments
+    /// * `addresses` - wallet addresses from command line
+    pub fn wallet_addresses(&self, addresses: Vec<String>) -> Vec<String> {
+        if !addresses.is_empty() {
+            return addresses;
+        }
+        self.wallet_address.iter()
+            .flat_map(|a| a.split(','))
+            .map(|a| a.trim().to_owned())
+            .filter(|a| !a.is_empty())
+            .collect()
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::{IsTerminal, Write};
+
+/// Print summary of a destructive action to stderr, then ask for
+/// confirmation on the terminal unless `assume_yes`.
+/// Return whether to proceed. It fails rather than proceeds if confirmation
+/// is needed but stdin is not a terminal.
+///
+/// # Arguments
+/// * `summary` - summary of the action
+/// * `assume_yes` - whether `--yes` is specified
+pub fn confirm(summary: &str, assume_yes: bool) -> Result<bool, String> {
+    eprintln!("{}", summary);
+    if assume_yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("Error confirmation is required but stdin is not a terminal; use --yes to proceed non-interactively".to_owned());
+    }
+
+    eprint!("Proceed? [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut answer) {
+        return Err(format!("Error reading confirmation from stdin; err={}", e));
+    }
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Set whether the terminal echoes input back via `stty`.
+/// Return whether it succeeded.
+///
+/// # Arguments
+/// * `echo` - whether to echo
+fn set_echo(echo: bool) -> bool {
+    std::process::Command::new("stty")
+        .arg(if echo { "echo" } else { "-echo" })
+        .stdin(std::process::Stdio::inherit())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Read a secret line from stdin. On terminal, `prompt` is printed to stderr
+/// first, and input is not echoed back; it fails rather than shows the secret
+/// if echo can't be turned off.
+///
+/// # Arguments
+/// * `prompt` - prompt to print on terminal
+pub fn read_secret(prompt: &str) -> Result<String, String> {
+    let is_terminal = std::io::stdin().is_terminal();
+    if is_terminal {
+        if !set_echo(false) {
+            return Err("Error can't turn off echo of terminal to read secret; pipe it via stdin instead".to_owned());
+        }
+        eprint!("{}", prompt);
+        let _ = std::io::stderr().flush();
+    }
+
+    let mut secret = String::new();
+    let res = std::io::stdin().read_line(&mut secret);
+    if is_terminal {
+        set_echo(true);
+        eprintln!();
+    }
+    if let Err(e) = res {
+        return Err(format!("Error reading secret from stdin; err={}", e));
+    }
+    Ok(secret.trim().to_owned())
+}
@@ -0,0 +1,98 @@
+use rusqlite::{params, Connection};
+
+use appvkek_core::report::Report;
+
+/// Tables of the database; created if not exist.
+/// Addresses are stored EIP-55 checksummed as of `Report`.
+static SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schema_version INTEGER NOT NULL,
+    chain TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    generated_at INTEGER NOT NULL,
+    at_block INTEGER
+);
+CREATE TABLE IF NOT EXISTS approvals (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    token_address TEXT NOT NULL,
+    token_name TEXT NOT NULL,
+    token_symbol TEXT NOT NULL,
+    spender TEXT NOT NULL,
+    spender_label TEXT,
+    allowance REAL NOT NULL,
+    is_unlimited INTEGER NOT NULL,
+    risk_score INTEGER NOT NULL,
+    risk_reasons TEXT NOT NULL,
+    approval_block INTEGER NOT NULL,
+    approval_timestamp INTEGER NOT NULL,
+    approval_tx_hash TEXT
+);
+CREATE TABLE IF NOT EXISTS errors (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    token_address TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS scans_owner ON scans(chain, owner, generated_at);
+CREATE INDEX IF NOT EXISTS approvals_scan ON approvals(scan_id);
+";
+
+/// SQLite database recording results of every scan, for querying how
+/// approvals evolve over time.
+pub struct ScanDb {
+    /// Connection to database
+    conn: Connection,
+}
+
+impl ScanDb {
+    /// Open database at the specified path, creating it and its tables if
+    /// not exist.
+    ///
+    /// # Arguments
+    /// * `path` - path to database file
+    pub fn open(path: &str) -> Result<ScanDb, String> {
+        let conn = match Connection::open(path) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error opening database ({}); err={}", path, e)),
+        };
+        if let Err(e) = conn.execute_batch(SCHEMA) {
+            return Err(format!("Error creating tables in database ({}); err={}", path, e));
+        }
+        Ok(ScanDb { conn })
+    }
+
+    /// Record report of a scan as a single transaction, and return its scan id.
+    ///
+    /// # Arguments
+    /// * `report` - report of a single wallet from `build_report()`
+    pub fn record(&mut self, report: &Report) -> Result<i64, String> {
+        self.insert(report).map_err(|e| format!("Error recording scan into database; err={}", e))
+    }
+
+    /// Insert report within a transaction.
+    ///
+    /// # Arguments
+    /// * `report` - report of a single wallet
+    fn insert(&mut self, report: &Report) -> Result<i64, rusqlite::Error> {
+        let tx = self.conn.transaction()?;
+        tx.execute("INSERT INTO scans (schema_version, chain, owner, generated_at, at_block) VALUES (?1, ?2, ?3, ?4, ?5)",
+                   params![report.schema_version, report.chain, report.owner, report.generated_at, report.at_block])?;
+        let scan_id = tx.last_insert_rowid();
+
+        {
+            let mut stmt = tx.prepare("INSERT INTO approvals (scan_id, token_address, token_name, token_symbol, spender, spender_label, allowance, is_unlimited, risk_score, risk_reasons, approval_block, approval_timestamp, approval_tx_hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)")?;
+            for a in report.approvals.iter() {
+                stmt.execute(params![scan_id, a.token_address, a.token_name, a.token_symbol, a.spender, a.spender_label, a.allowance, a.is_unlimited, a.risk_score, a.risk_reasons.join(";"), a.approval_block, a.approval_timestamp, a.approval_tx_hash])?;
+            }
+
+            let mut stmt = tx.prepare("INSERT INTO errors (scan_id, token_address, kind, message) VALUES (?1, ?2, ?3, ?4)")?;
+            for e in report.errors.iter() {
+                stmt.execute(params![scan_id, e.token_address, e.kind, e.message])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(scan_id)
+    }
+}
@@ -0,0 +1,173 @@
+use ::evmscan::environ::Context;
+use ::evmscan::prelude::ChainType;
+
+use appvkek_core::error::AppvkekError;
+use appvkek_core::explorer;
+use appvkek_core::util::*;
+
+use crate::apikey;
+
+/// Maximum difference in seconds between local clock, and timestamp of the
+/// latest block before clock is considered skewed. Blocks are produced every
+/// few seconds on all supported chains, so it's well above normal lag.
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// Result of a single diagnostic check.
+pub struct Check {
+    /// What was checked e.g. `RPC endpoint`
+    pub name: &'static str,
+
+    /// Detail of what was found if passed, otherwise reason it failed
+    pub result: Result<String, String>,
+}
+
+/// Check environment variables which affect how the tool runs.
+///
+/// # Arguments
+/// * `chain` - chain type
+fn check_env(chain: ChainType) -> Check {
+    if std::env::var("HOME").map(|home| home.is_empty()).unwrap_or(true) {
+        return Check { name: "environment", result: Err("HOME is not set; data is stored under current directory instead".to_owned()) };
+    }
+
+    let vars: Vec<String> = [apikey::apikey_env_var(chain), "HTTPS_PROXY", "ALL_PROXY"].iter()
+        .map(|name| format!("{} is {}", name, if std::env::var(name).map(|v| !v.is_empty()).unwrap_or(false) { "set" } else { "not set" }))
+        .collect();
+    Check { name: "environment", result: Ok(vars.join(", ")) }
+}
+
+/// Check that explorer API key is found, and accepted by explorer.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `api_key` - API key specified explicitly if any
+fn check_api_key(chain: ChainType, api_key: Option<&str>) -> Check {
+    let name = "explorer API key";
+    let (api_key, source) = match apikey::find_apikey(chain, api_key) {
+        Some(res) => res,
+        None => return Check { name, result: Err(format!("not found; set it via --api-key, {} environment variable, or `appvkek set-api-key -c {}`", apikey::apikey_env_var(chain), chain_name(chain))) },
+    };
+    let result = match explorer::check_api_key(&Context::create(chain, api_key)) {
+        Ok(_) => Ok(format!("valid; from {}", source)),
+        Err(e) => Err(format!("check failed; from {}; {}", source, e)),
+    };
+    Check { name, result }
+}
+
+/// Check latency of each RPC endpoint if multiple are specified. It passes
+/// if any of them responds as requests go to healthy ones.
+///
+/// # Arguments
+/// * `web3` - web3 instance
+async fn check_endpoints(web3: &Web3Type) -> Option<Check> {
+    let probes = web3.transport().probe_endpoints().await;
+    if probes.is_empty() {
+        return None;
+    }
+
+    let details: Vec<String> = probes.iter()
+        .map(|p| match &p.result {
+            Ok(latency) => format!("{} ({} ms)", p.name, latency.as_millis()),
+            Err(e) => format!("{} (failed; {})", p.name, e),
+        })
+        .collect();
+    let result = if probes.iter().any(|p| p.result.is_ok()) {
+        Ok(format!("using {}; {}", web3.transport().selected_endpoint(), details.join(", ")))
+    }
+    else {
+        Err(format!("none responded; {}", details.join(", ")))
+    };
+    Some(Check { name: "RPC endpoints", result })
+}
+
+/// Check RPC endpoint is reachable, and is of the chain, then check local
+/// clock against its latest block. With multiple endpoints, latency of each
+/// is checked first, then the rest is checked with the selected one.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `web3` - web3 instance, or error creating it
+async fn check_rpc(chain: ChainType, web3: Result<Web3Type, AppvkekError>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let head = match web3 {
+        Ok(web3) => {
+            checks.extend(check_endpoints(&web3).await);
+            fetch_chain_head(&web3).await
+        },
+        Err(e) => Err(e),
+    };
+    let head = match head {
+        Ok(res) => res,
+        Err(e) => {
+            checks.push(Check { name: "RPC endpoint", result: Err(e.to_string()) });
+            checks.push(Check { name: "clock", result: Err("not checked as RPC endpoint is unreachable".to_owned()) });
+            return checks;
+        }
+    };
+
+    let rpc = if head.chain_id == chain_id(chain) {
+        Ok(format!("reachable; chain id {}, latest block {}", head.chain_id, head.block_number))
+    }
+    else {
+        Err(format!("chain id is {} but {} is {}; check --rpc-url", head.chain_id, chain_name(chain), chain_id(chain)))
+    };
+
+    let now = unix_timestamp();
+    let skew = now.abs_diff(head.timestamp);
+    let clock = if skew <= MAX_CLOCK_SKEW_SECS {
+        Ok(format!("{} seconds from the latest block", skew))
+    }
+    else if now > head.timestamp {
+        Err(format!("{} seconds ahead of the latest block; local clock is ahead, or RPC endpoint is not synced", skew))
+    }
+    else {
+        Err(format!("{} seconds behind the latest block; local clock is behind", skew))
+    };
+
+    checks.push(Check { name: "RPC endpoint", result: rpc });
+    checks.push(Check { name: "clock", result: clock });
+    checks
+}
+
+/// Check that data, and cache directory can be written to.
+fn check_data_dir() -> Check {
+    let name = "data directory";
+    let dir = cache_dir();
+    let path = format!("{}/.doctor", dir);
+    let result = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, b""))
+        .and_then(|_| std::fs::remove_file(&path));
+    match result {
+        Ok(_) => Check { name, result: Ok(format!("{} is writable", appvkek_dir())) },
+        Err(e) => Check { name, result: Err(format!("{} is not writable; err={}", dir, e)) },
+    }
+}
+
+/// Run all checks, and return their results.
+///
+/// # Arguments
+/// * `chain` - chain type
+/// * `api_key` - API key specified explicitly if any
+/// * `web3` - web3 instance, or error creating it
+pub async fn run(chain: ChainType, api_key: Option<&str>, web3: Result<Web3Type, AppvkekError>) -> Vec<Check> {
+    let mut checks = vec![check_env(chain), check_api_key(chain, api_key)];
+    checks.extend(check_rpc(chain, web3).await);
+    checks.push(check_data_dir());
+    checks
+}
+
+/// Print results of checks to stdout, and return whether all passed.
+///
+/// # Arguments
+/// * `checks` - results from `run()`
+pub fn print_checks(checks: &[Check]) -> bool {
+    for check in checks.iter() {
+        match &check.result {
+            Ok(detail) => println!("[PASS] {} - {}", check.name, detail),
+            Err(reason) => println!("[FAIL] {} - {}", check.name, reason),
+        }
+    }
+    let num_failed = checks.iter().filter(|c| c.result.is_err()).count();
+    println!("{} of {} checks passed", checks.len() - num_failed, checks.len());
+    num_failed == 0
+}
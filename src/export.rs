@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use appvkek_core::report::*;
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+
+use crate::Session;
+
+/// Format of exported approvals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// JSON array of approvals
+    Json,
+
+    /// CSV with a header row
+    Csv,
+
+    /// CSV with a link to revoke.cash on each row; for revoking via its UI
+    RevokeCash,
+
+    /// Graphviz DOT graph of wallet to tokens to spenders
+    Dot,
+
+    /// Mermaid flowchart of wallet to tokens to spenders
+    Mermaid,
+}
+
+impl ExportFormat {
+    /// Parse from value of `--format` flag.
+    ///
+    /// # Arguments
+    /// * `value` - either 'json', 'csv', 'revokecash', 'dot', or 'mermaid'
+    pub fn parse(value: &str) -> Result<ExportFormat, String> {
+        match value.trim().to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "revokecash" => Ok(ExportFormat::RevokeCash),
+            "dot" => Ok(ExportFormat::Dot),
+            "mermaid" => Ok(ExportFormat::Mermaid),
+            _ => Err("Error invalid value for --format.
+Possible values are 'json', 'csv', 'revokecash', 'dot', or 'mermaid'.".to_owned()),
+        }
+    }
+
+    /// Return whether it's a graph, whose edges are weighted by value at
+    /// risk so it needs balances, and prices of tokens.
+    pub fn is_graph(&self) -> bool {
+        matches!(self, ExportFormat::Dot | ExportFormat::Mermaid)
+    }
+}
+
+/// Escape a field of CSV if needed.
+///
+/// # Arguments
+/// * `field` - field to escape
+fn escape_csv(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+    else {
+        field.to_owned()
+    }
+}
+
+/// Format approvals as CSV with a header row.
+///
+/// # Arguments
+/// * `rows` - approvals to format
+fn format_csv(rows: &[ApprovalEntry]) -> String {
+    let mut content = format!("{}\n", CSV_HEADER);
+    for row in rows {
+        let fields = [
+            row.schema_version.to_string(),
+            row.chain.to_owned(),
+            row.owner.to_owned(),
+            row.token_address.to_owned(),
+            row.token_name.to_owned(),
+            row.token_symbol.to_owned(),
+            row.spender.to_owned(),
+            row.spender_label.to_owned().unwrap_or_default(),
+            row.allowance.to_string(),
+            row.is_unlimited.to_string(),
+            row.risk_score.to_string(),
+            row.risk_reasons.join(";"),
+            row.approval_block.to_string(),
+            row.approval_timestamp.to_string(),
+            row.implementation.to_owned().unwrap_or_default(),
+            row.spender_ens.to_owned().unwrap_or_default(),
+            row.approval_tx_hash.to_owned().unwrap_or_default(),
+            row.approval_tx_url.to_owned().unwrap_or_default(),
+            row.approval_issue.to_owned().unwrap_or_default(),
+            row.approval_user_op_hash.to_owned().unwrap_or_default(),
+        ];
+        content.push_str(&fields.iter().map(|f| escape_csv(f)).collect::<Vec<String>>().join(","));
+        content.push('\n');
+    }
+    content
+}
+
+/// Header row of revoke.cash output
+static REVOKECASH_HEADER: &str = "chain_id,owner,token_address,token_name,token_symbol,spender,spender_label,allowance,is_unlimited,risk_score,revoke_url";
+
+/// Format approvals as CSV with a header row, and a link to revoke.cash on
+/// each row.
+///
+/// # Arguments
+/// * `rows` - approvals to format
+/// * `chain_id` - EIP-155 chain id
+fn format_revokecash(rows: &[ApprovalEntry], chain_id: u64) -> String {
+    let mut content = format!("{}\n", REVOKECASH_HEADER);
+    for row in rows.iter().filter(|r| r.allowance > 0.0) {
+        let fields = [
+            chain_id.to_string(),
+            row.owner.to_owned(),
+            row.token_address.to_owned(),
+            row.token_name.to_owned(),
+            row.token_symbol.to_owned(),
+            row.spender.to_owned(),
+            row.spender_label.to_owned().unwrap_or_default(),
+            row.allowance.to_string(),
+            row.is_unlimited.to_string(),
+            row.risk_score.to_string(),
+            revokecash_url(chain_id, &row.owner),
+        ];
+        content.push_str(&fields.iter().map(|f| escape_csv(f)).collect::<Vec<String>>().join(","));
+        content.push('\n');
+    }
+    content
+}
+
+/// Export report of the specified results.
+/// JSON output is a single `Report` object along with errors, and skipped
+/// transactions as structured entries, while CSV output only has its
+/// approvals; token contracts which failed to be queried are not in it.
+/// revoke.cash output only has approvals with non-zero allowance which are
+/// left to revoke, and so do graph outputs.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `format` - format to export as
+/// * `output` - path to file to write into; stdout if `None`
+/// * `session` - session the results were scanned with
+/// * `owner_address` - owner wallet address; lowercased
+/// * `prices` - USD price keyed by lowercased token address; only for graph outputs
+pub fn export(results: &[QueryResult], format: ExportFormat, output: Option<&str>, session: &Session, owner_address: &str, prices: &HashMap<String, f64>) -> Result<(), String> {
+    let mut report = build_report(session.chain, owner_address, results, &session.labels);
+    report.at_block = session.at_block;
+    report.delegation = session.delegations.get(owner_address).map(|d| to_checksum_address(d));
+    report.usage = Some(appvkek_core::usage::current());
+    if let Ok(warnings) = session.warnings.lock() {
+        report.warnings = warnings.iter()
+            .filter(|(address, _)| address == owner_address)
+            .map(|(_, w)| WarningEntry::from(w))
+            .collect();
+    }
+
+    let content = match format {
+        ExportFormat::Json => match serde_json::to_string_pretty(&report) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Error serializing report; err={}", e)),
+        },
+        ExportFormat::Csv => {
+            if !report.errors.is_empty() {
+                tracing::warn!("{} token contracts failed to be queried, and are not exported", report.errors.len());
+            }
+            format_csv(&report.approvals)
+        },
+        ExportFormat::RevokeCash => format_revokecash(&report.approvals, chain_id(session.chain)),
+        ExportFormat::Dot | ExportFormat::Mermaid => {
+            let balances: HashMap<String, f64> = results.iter()
+                .filter_map(|res| res.as_ref().ok())
+                .filter_map(|r| r.balance.map(|balance| (r.address.to_lowercase(), balance)))
+                .collect();
+            if format == ExportFormat::Dot {
+                crate::graph::format_dot(&report, &balances, prices)
+            }
+            else {
+                crate::graph::format_mermaid(&report, &balances, prices)
+            }
+        },
+    };
+
+    match output {
+        Some(path) => write_file(path, &content).map_err(|e| e.to_string()),
+        None => {
+            println!("{}", content);
+            Ok(())
+        }
+    }
+}
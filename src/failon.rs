@@ -0,0 +1,134 @@
+use appvkek_core::types::SpenderAllowance;
+
+/// Exit code when approvals matching `--fail-on` remain. Note that clap also
+/// exits with it on invalid command-line usage.
+pub const EXIT_MATCHED: i32 = 2;
+
+/// Exit code when no approval matches `--fail-on`, but results are incomplete
+/// so that a matching one may have been missed
+pub const EXIT_INCOMPLETE: i32 = 3;
+
+/// Condition of approvals to make the process exit with non-zero code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailOn {
+    /// Any approval with non-zero allowance
+    Any,
+
+    /// Any approval with unlimited allowance
+    Unlimited,
+
+    /// Any approval with non-zero allowance whose risk score is at least the value
+    RiskAtLeast(u32),
+}
+
+impl FailOn {
+    /// Parse from value of `--fail-on` flag.
+    ///
+    /// # Arguments
+    /// * `value` - one of 'any', 'unlimited', or 'risk>=N'
+    pub fn parse(value: &str) -> Result<FailOn, String> {
+        let value = value.trim().to_lowercase();
+        if value == "any" {
+            return Ok(FailOn::Any);
+        }
+        if value == "unlimited" {
+            return Ok(FailOn::Unlimited);
+        }
+        if let Some(score) = value.strip_prefix("risk>=") {
+            return match score.trim().parse::<u32>() {
+                Ok(res) => Ok(FailOn::RiskAtLeast(res)),
+                Err(e) => Err(format!("Error invalid risk score for --fail-on ({}); err={}", score, e)),
+            };
+        }
+        Err("Error invalid value for --fail-on.
+Possible values are 'any', 'unlimited', or 'risk>=N'.".to_owned())
+    }
+
+    /// Return whether the specified spender's allowance matches this condition.
+    ///
+    /// # Arguments
+    /// * `sa` - spender's allowance
+    pub fn matches(&self, sa: &SpenderAllowance) -> bool {
+        if sa.allowance <= 0.0 {
+            return false;
+        }
+        match self {
+            FailOn::Any => true,
+            FailOn::Unlimited => sa.is_unlimited,
+            FailOn::RiskAtLeast(score) => sa.risk.score >= *score,
+        }
+    }
+}
+
+/// Return exit code of a scan with `--fail-on`; `None` to exit normally.
+///
+/// # Arguments
+/// * `is_matched` - whether any approval matches the condition
+/// * `is_complete` - whether all token contracts were queried i.e. no query error, nor interrupt
+pub fn exit_code(is_matched: bool, is_complete: bool) -> Option<i32> {
+    if is_matched {
+        Some(EXIT_MATCHED)
+    }
+    else if !is_complete {
+        Some(EXIT_INCOMPLETE)
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use appvkek_core::risk::RiskAssessment;
+    use appvkek_core::types::ApprovalRecord;
+
+    fn spender_allowance(allowance: f64, is_unlimited: bool, score: u32) -> SpenderAllowance {
+        SpenderAllowance {
+            allowance,
+            is_unlimited,
+            risk: RiskAssessment { score, reasons: Vec::new() },
+            proxy: None,
+            approval: ApprovalRecord::default(),
+            exploit: None,
+            activity: None,
+            is_verified: None,
+            creation: None,
+            control: None,
+        }
+    }
+
+    #[test]
+    fn parse_accepts_possible_values() {
+        assert_eq!(FailOn::parse("any"), Ok(FailOn::Any));
+        assert_eq!(FailOn::parse(" Unlimited "), Ok(FailOn::Unlimited));
+        assert_eq!(FailOn::parse("risk>=50"), Ok(FailOn::RiskAtLeast(50)));
+        assert_eq!(FailOn::parse("RISK>= 7"), Ok(FailOn::RiskAtLeast(7)));
+
+        assert!(FailOn::parse("risk>=high").is_err());
+        assert!(FailOn::parse("risk>-1").is_err());
+        assert!(FailOn::parse("").is_err());
+    }
+
+    #[test]
+    fn matches_only_non_zero_allowance() {
+        assert!(FailOn::Any.matches(&spender_allowance(1.0, false, 0)));
+        assert!(!FailOn::Any.matches(&spender_allowance(0.0, false, 100)));
+
+        assert!(FailOn::Unlimited.matches(&spender_allowance(f64::MAX, true, 0)));
+        assert!(!FailOn::Unlimited.matches(&spender_allowance(1.0, false, 0)));
+        assert!(!FailOn::Unlimited.matches(&spender_allowance(0.0, true, 0)));
+
+        assert!(FailOn::RiskAtLeast(50).matches(&spender_allowance(1.0, false, 50)));
+        assert!(!FailOn::RiskAtLeast(50).matches(&spender_allowance(1.0, false, 49)));
+        assert!(!FailOn::RiskAtLeast(50).matches(&spender_allowance(0.0, false, 100)));
+    }
+
+    #[test]
+    fn exit_code_flags_incomplete_results_without_match() {
+        assert_eq!(exit_code(true, true), Some(EXIT_MATCHED));
+        assert_eq!(exit_code(true, false), Some(EXIT_MATCHED));
+        assert_eq!(exit_code(false, false), Some(EXIT_INCOMPLETE));
+        assert_eq!(exit_code(false, true), None);
+    }
+}
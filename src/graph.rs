@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use appvkek_core::prices::exposure_usd;
+use appvkek_core::report::*;
+
+/// Width of edges whose value is the lowest, or unknown
+const MIN_EDGE_WIDTH: f64 = 1.0;
+
+/// Width of edges whose value is the highest
+const MAX_EDGE_WIDTH: f64 = 8.0;
+
+/// Node of approval graph i.e. wallet, token, or spender.
+struct Node {
+    /// Identifier of node in graph source; prefixed by its kind as the same
+    /// address can be both token, and spender
+    id: String,
+
+    /// Lines of label to display
+    lines: Vec<String>,
+
+    /// Whether it's the wallet itself
+    is_wallet: bool,
+}
+
+/// Edge of approval graph; wallet to token it holds, or token to spender
+/// approved to take it.
+struct Edge {
+    /// Identifier of source node
+    from: String,
+
+    /// Identifier of target node
+    to: String,
+
+    /// Label to display
+    label: String,
+
+    /// USD value at risk; `None` if unknown
+    value: Option<f64>,
+}
+
+/// Graph of wallet to tokens it has approved, to spenders of each of them.
+struct ApprovalGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+
+    /// Highest value among edges; 0 if none is known
+    max_value: f64,
+}
+
+impl ApprovalGraph {
+    /// Return width of the specified edge; in proportion to its value
+    /// relative to the highest one.
+    ///
+    /// # Arguments
+    /// * `edge` - edge of this graph
+    fn edge_width(&self, edge: &Edge) -> f64 {
+        match edge.value {
+            Some(value) if self.max_value > 0.0 => MIN_EDGE_WIDTH + (MAX_EDGE_WIDTH - MIN_EDGE_WIDTH) * value / self.max_value,
+            _ => MIN_EDGE_WIDTH,
+        }
+    }
+}
+
+/// Format allowance of the specified approval as edge label e.g.
+/// `unlimited USDT`.
+///
+/// # Arguments
+/// * `entry` - approval
+fn format_allowance(entry: &ApprovalEntry) -> String {
+    if entry.is_unlimited {
+        return if entry.token_symbol.is_empty() { "unlimited".to_owned() } else { format!("unlimited {}", entry.token_symbol) };
+    }
+    crate::amount::format_amount(entry.allowance, &entry.token_symbol)
+}
+
+/// Build graph from approvals with non-zero allowance of the specified
+/// report. Value at risk of each approval is its allowance capped at owner's
+/// balance, times USD price of token; that of each token is the highest of
+/// its approvals, as each spender alone can take all of what is approved.
+///
+/// # Arguments
+/// * `report` - report of a single wallet
+/// * `balances` - balance of owner in token unit keyed by lowercased token address
+/// * `prices` - USD price keyed by lowercased token address
+fn build_graph(report: &Report, balances: &HashMap<String, f64>, prices: &HashMap<String, f64>) -> ApprovalGraph {
+    let mut approvals: Vec<&ApprovalEntry> = report.approvals.iter().filter(|a| a.allowance > 0.0).collect();
+    approvals.sort_by_cached_key(|a| (a.token_address.to_lowercase(), a.spender.to_lowercase()));
+
+    let mut graph = ApprovalGraph {
+        nodes: vec![Node { id: "wallet".to_owned(), lines: vec!["Wallet".to_owned(), report.owner.to_owned()], is_wallet: true }],
+        edges: Vec::new(),
+        max_value: 0.0,
+    };
+    let mut spender_ids: HashSet<String> = HashSet::new();
+    for token_approvals in approvals.chunk_by(|a, b| a.token_address.eq_ignore_ascii_case(&b.token_address)) {
+        let first = token_approvals[0];
+        let token_address = first.token_address.to_lowercase();
+        let (balance, price) = (balances.get(&token_address).copied(), prices.get(&token_address).copied());
+
+        let token_id = format!("t{}", token_address);
+        let token_name = if first.token_symbol.is_empty() { first.token_name.to_owned() } else { format!("{} ({})", first.token_name, first.token_symbol) };
+        let mut lines = vec![token_name, first.token_address.to_owned()];
+        if let Some(balance) = balance {
+            lines.push(format!("balance: {}", crate::amount::format_amount(balance, &first.token_symbol)));
+        }
+        graph.nodes.push(Node { id: token_id.to_owned(), lines, is_wallet: false });
+
+        let mut token_value: Option<f64> = None;
+        for a in token_approvals {
+            let spender_id = format!("s{}", a.spender.to_lowercase());
+            if spender_ids.insert(spender_id.to_owned()) {
+                let lines = vec![
+                    a.spender_label.to_owned().unwrap_or_else(|| "Unknown spender".to_owned()),
+                    a.spender.to_owned(),
+                    format!("risk={}", a.risk_score),
+                ];
+                graph.nodes.push(Node { id: spender_id.to_owned(), lines, is_wallet: false });
+            }
+
+            let value = exposure_usd(a.allowance, balance, price);
+            if let Some(value) = value {
+                token_value = Some(token_value.map_or(value, |v| v.max(value)));
+            }
+            let label = match value {
+                Some(value) => format!("${:.2}", value),
+                None => format!("{} (unpriced)", format_allowance(a)),
+            };
+            graph.edges.push(Edge { from: token_id.to_owned(), to: spender_id, label, value });
+        }
+
+        let label = match token_value {
+            Some(value) => format!("${:.2} at risk", value),
+            None => "unpriced".to_owned(),
+        };
+        // edge from wallet goes before those of its token to keep them in order
+        let index = graph.edges.len() - token_approvals.len();
+        graph.edges.insert(index, Edge { from: "wallet".to_owned(), to: token_id, label, value: token_value });
+    }
+    graph.max_value = graph.edges.iter().filter_map(|e| e.value).fold(0.0, f64::max);
+    graph
+}
+
+/// Escape a string to be quoted in Graphviz DOT. Line breaks e.g. in token
+/// name are replaced with space, as lines of label are separated by the
+/// caller.
+///
+/// # Arguments
+/// * `text` - text to escape
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace(['\r', '\n'], " ")
+}
+
+/// Format approvals with non-zero allowance of the specified report as graph
+/// in Graphviz DOT language; see `build_graph()`. Edges of unknown value are
+/// dashed.
+///
+/// # Arguments
+/// * `report` - report of a single wallet
+/// * `balances` - balance of owner in token unit keyed by lowercased token address
+/// * `prices` - USD price keyed by lowercased token address
+pub fn format_dot(report: &Report, balances: &HashMap<String, f64>, prices: &HashMap<String, f64>) -> String {
+    let graph = build_graph(report, balances, prices);
+    let mut content = "digraph approvals {\n    rankdir=LR;\n    node [shape=box];\n".to_owned();
+    for node in graph.nodes.iter() {
+        let label = node.lines.iter().map(|l| escape_dot(l)).collect::<Vec<String>>().join("\\n");
+        let shape = if node.is_wallet { ", shape=ellipse" } else { "" };
+        content.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", node.id, label, shape));
+    }
+    for edge in graph.edges.iter() {
+        let style = if edge.value.is_none() { ", style=dashed" } else { "" };
+        content.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\", penwidth={:.1}{}];\n", edge.from, edge.to, escape_dot(&edge.label), graph.edge_width(edge), style));
+    }
+    content.push('}');
+    content
+}
+
+/// Escape a string to be quoted in Mermaid. `<`, and `>` are escaped too as
+/// Mermaid renders label as HTML, and line breaks are replaced with space as
+/// they end the statement.
+///
+/// # Arguments
+/// * `text` - text to escape
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;").replace(['\r', '\n'], " ")
+}
+
+/// Format approvals with non-zero allowance of the specified report as
+/// Mermaid flowchart; see `build_graph()`. Edges of unknown value are dashed.
+///
+/// # Arguments
+/// * `report` - report of a single wallet
+/// * `balances` - balance of owner in token unit keyed by lowercased token address
+/// * `prices` - USD price keyed by lowercased token address
+pub fn format_mermaid(report: &Report, balances: &HashMap<String, f64>, prices: &HashMap<String, f64>) -> String {
+    let graph = build_graph(report, balances, prices);
+    let mut content = "flowchart LR\n".to_owned();
+    for node in graph.nodes.iter() {
+        let label = node.lines.iter().map(|l| escape_mermaid(l)).collect::<Vec<String>>().join("<br/>");
+        if node.is_wallet {
+            content.push_str(&format!("    {}([\"{}\"])\n", node.id, label));
+        }
+        else {
+            content.push_str(&format!("    {}[\"{}\"]\n", node.id, label));
+        }
+    }
+    for edge in graph.edges.iter() {
+        let arrow = if edge.value.is_none() { "-.->" } else { "-->" };
+        content.push_str(&format!("    {} {}|\"{}\"| {}\n", edge.from, arrow, escape_mermaid(&edge.label), edge.to));
+    }
+    for (i, edge) in graph.edges.iter().enumerate() {
+        content.push_str(&format!("    linkStyle {} stroke-width:{:.1}px\n", i, graph.edge_width(edge)));
+    }
+    content.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: &str = "0x1111111111111111111111111111111111111111";
+    const USDT: &str = "0x55d398326f99059ff775485246999027b3197955";
+    const CAKE: &str = "0x0e09fabb73bd3ade0a17ecc321fd13a19e81ce82";
+    const ROUTER: &str = "0x10ed43c718714eb63d5aa57b78b54704e256024e";
+    const DRAINER: &str = "0x2222222222222222222222222222222222222222";
+
+    fn entry(token_address: &str, token_name: &str, spender: &str, spender_label: Option<&str>, allowance: f64) -> ApprovalEntry {
+        ApprovalEntry {
+            chain: "bsc".to_owned(),
+            owner: OWNER.to_owned(),
+            token_address: token_address.to_owned(),
+            token_name: token_name.to_owned(),
+            spender: spender.to_owned(),
+            spender_label: spender_label.map(|l| l.to_owned()),
+            allowance,
+            ..Default::default()
+        }
+    }
+
+    /// Report with USDT approved to 2 spenders, of which one is revoked, and
+    /// CAKE approved to a spender whose label, and token name need escaping.
+    fn report() -> Report {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            chain: "bsc".to_owned(),
+            owner: OWNER.to_owned(),
+            generated_at: 0,
+            approvals: vec![
+                entry(USDT, "Tether USD", ROUTER, Some("PancakeSwap: Router"), 100.0),
+                entry(USDT, "Tether USD", DRAINER, None, 0.0),
+                entry(CAKE, "Cake\nToken", DRAINER, Some("<b>\"Fake\"</b>"), 5.0),
+            ],
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            at_block: None,
+            delegation: None,
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn format_dot_weights_edges_by_value_at_risk() {
+        let balances = HashMap::from([(USDT.to_owned(), 50.0), (CAKE.to_owned(), 5.0)]);
+        let prices = HashMap::from([(USDT.to_owned(), 1.0)]);
+        let dot = format_dot(&report(), &balances, &prices);
+
+        assert!(dot.starts_with("digraph approvals {\n"));
+        assert!(dot.ends_with('}'));
+        // allowance is capped at balance, and the highest edge is the widest
+        assert!(dot.contains(&format!("\"wallet\" -> \"t{}\" [label=\"$50.00 at risk\", penwidth=8.0];", USDT)));
+        assert!(dot.contains(&format!("\"t{}\" -> \"s{}\" [label=\"$50.00\", penwidth=8.0];", USDT, ROUTER)));
+        // unpriced edges are dashed at the minimum width
+        assert!(dot.contains(&format!("\"t{}\" -> \"s{}\" [label=\"5 (unpriced)\", penwidth=1.0, style=dashed];", CAKE, DRAINER)));
+        // revoked approval is left out
+        assert!(!dot.contains(&format!("\"t{}\" -> \"s{}\"", USDT, DRAINER)));
+        assert!(dot.contains("[label=\"Cake Token\\n"));
+        assert!(dot.contains("[label=\"<b>\\\"Fake\\\"</b>\\n"));
+    }
+
+    #[test]
+    fn format_mermaid_escapes_labels() {
+        let balances = HashMap::from([(USDT.to_owned(), 50.0)]);
+        let prices = HashMap::from([(USDT.to_owned(), 1.0)]);
+        let mermaid = format_mermaid(&report(), &balances, &prices);
+        let lines: Vec<&str> = mermaid.lines().collect();
+
+        assert_eq!(lines[0], "flowchart LR");
+        assert_eq!(lines[1], format!("    wallet([\"Wallet<br/>{}\"])", OWNER));
+        assert!(lines.contains(&format!("    t{}[\"Cake Token<br/>{}\"]", CAKE, CAKE).as_str()));
+        assert!(lines.contains(&format!("    s{}[\"#lt;b#gt;#quot;Fake#quot;#lt;/b#gt;<br/>{}<br/>risk=0\"]", DRAINER, DRAINER).as_str()));
+        assert!(lines.contains(&format!("    t{} -.->|\"5 (unpriced)\"| s{}", CAKE, DRAINER).as_str()));
+        assert!(lines.contains(&"    linkStyle 0 stroke-width:1.0px"));
+        assert_eq!(lines.iter().filter(|l| l.starts_with("    linkStyle")).count(), 4);
+    }
+}
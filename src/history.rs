@@ -0,0 +1,51 @@
+use ::evmscan::prelude::ChainType;
+
+use appvkek_core::history::AllowanceEvent;
+use appvkek_core::labels::Labels;
+use appvkek_core::scanner::{format_token_name, make_spender_allowance};
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+
+use crate::amount;
+use crate::timestamp::format_timestamp_with_age;
+
+/// Print allowance history of a spender on a token contract to stdout,
+/// followed by its current allowance.
+///
+/// # Arguments
+/// * `chain` - chain type; for explorer links
+/// * `events` - changes of allowance from `get_allowance_history()`
+/// * `token` - result from `query()` of token contract with only the spender
+/// * `token_address` - token contract address
+/// * `spender` - spender address
+/// * `owner_address` - owner wallet address; for display
+/// * `labels` - labels of well-known addresses
+pub fn print_history(chain: ChainType, events: &[AllowanceEvent], token: &QueryResult, token_address: &str, spender: &str, owner_address: &str, labels: &Labels) {
+    // amounts are in token unit if its decimals are known, otherwise raw
+    let (token_name, decimals, symbol) = match token {
+        Ok(r) => (format_token_name(r), r.decimals, r.symbol.as_str()),
+        Err(e) => {
+            tracing::warn!("{}; amounts are shown in raw unit", e.error);
+            ("Unknown".to_owned(), 0, "")
+        }
+    };
+
+    println!("History of approvals to {} on [{}] {} by {}", labels.format(spender), token_name, labels.format(token_address), owner_address);
+    if events.is_empty() {
+        println!("No approval events found");
+    }
+    for event in events {
+        let amount = match make_spender_allowance(event.amount, decimals, ApprovalRecord::default()) {
+            Ok(sa) if sa.is_unlimited && !amount::is_human() => format!("{} (unlimited)", sa.allowance),
+            Ok(sa) => amount::format_allowance(sa.allowance, sa.is_unlimited, symbol),
+            Err(_) => event.amount.to_string(),
+        };
+        println!("{} - {:<8} {} - block {} - {}", format_timestamp_with_age(event.timestamp), event.kind.name(), amount, event.block_number, explorer_tx_url(chain, &event.tx_hash));
+    }
+
+    if let Ok(r) = token {
+        if let Some(sa) = r.spender_allowances.get(spender) {
+            println!("Current allowance: {}", amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+
+/// Exit code when interrupted by Ctrl-C; as of shells (128 + SIGINT)
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Whether Ctrl-C has been pressed
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Guard to install handler only once
+static INSTALL: Once = Once::new();
+
+/// Install handler of Ctrl-C. The first Ctrl-C only marks the process as
+/// interrupted so that ongoing scan stops, and reports results completed so
+/// far. The second one exits immediately. Installing more than once is a
+/// no-op.
+pub fn install() {
+    INSTALL.call_once(|| {
+        tokio::spawn(async {
+            loop {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+                if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                    std::process::exit(EXIT_INTERRUPTED);
+                }
+                eprintln!("Interrupted; stopping with results completed so far. Press Ctrl-C again to exit immediately");
+            }
+        });
+    });
+}
+
+/// Return whether Ctrl-C has been pressed.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Wait until Ctrl-C is pressed.
+pub async fn interrupted() {
+    while !is_interrupted() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
@@ -0,0 +1,170 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use appvkek_core::notify::post_webhook;
+use appvkek_core::report::{self, Report};
+use appvkek_core::util::*;
+
+use crate::args::{CommonArgs, RiskArgs};
+use crate::config::TokenOverrides;
+use crate::Links;
+
+/// Scan of a single wallet on one or more chains.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    /// Name of job to show in output; wallet address if not specified
+    pub name: Option<String>,
+
+    /// Wallet address to scan
+    pub wallet_address: String,
+
+    /// Chains to scan wallet on; same values as of `--chain`
+    pub chains: Vec<String>,
+
+    /// Webhook URL to POST report to when there is any approval with risk
+    /// score at least `min_risk`
+    pub webhook_url: Option<String>,
+
+    /// Minimum risk score of approvals to report, and to notify of
+    #[serde(default)]
+    pub min_risk: u32,
+}
+
+/// Content of jobs file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobsFile {
+    /// Chain name to URL of its RPC endpoint; can be comma-separated for
+    /// multiple endpoints. Chains not in it use `--rpc-url`, or the default.
+    #[serde(default)]
+    pub rpc_urls: HashMap<String, String>,
+
+    /// Jobs to run
+    #[serde(default)]
+    pub jobs: Vec<Job>,
+}
+
+/// Load, and validate jobs file.
+///
+/// # Arguments
+/// * `path` - path to jobs file in TOML
+pub fn load_jobs(path: &str) -> Result<JobsFile, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error reading jobs file ({}); err={}", path, e)),
+    };
+    let mut jobs_file = match toml::from_str::<JobsFile>(&content) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error parsing jobs file ({}); err={}", path, e)),
+    };
+
+    if jobs_file.jobs.is_empty() {
+        return Err(format!("Error no jobs in jobs file ({})", path));
+    }
+    for name in jobs_file.rpc_urls.keys() {
+        crate::parse_chain(name).map_err(|_| format!("Error invalid chain '{}' in rpc_urls of jobs file ({}); possible values are 'bsc', 'ethereum', or 'polygon'", name, path))?;
+    }
+    for job in jobs_file.jobs.iter_mut() {
+        if !validate_address_format(&job.wallet_address) {
+            return Err(format!("Error wallet_address is not in the correct format in jobs file ({}); addr={}", path, job.wallet_address));
+        }
+        job.wallet_address = job.wallet_address.to_lowercase();
+        if job.chains.is_empty() {
+            return Err(format!("Error no chains for wallet {} in jobs file ({})", job.wallet_address, path));
+        }
+        for chain in job.chains.iter_mut() {
+            let parsed = crate::parse_chain(chain).map_err(|_| format!("Error invalid chain '{}' for wallet {} in jobs file ({}); possible values are 'bsc', 'ethereum', or 'polygon'", chain, job.wallet_address, path))?;
+            *chain = chain_name(parsed).to_owned();
+        }
+        if job.min_risk > 100 {
+            return Err(format!("Error min_risk must be between 0 and 100 for wallet {} in jobs file ({})", job.wallet_address, path));
+        }
+    }
+
+    Ok(jobs_file)
+}
+
+/// Return the specified report with only approvals at or above `min_risk`
+/// with non-zero allowance; what a job notifies of.
+///
+/// # Arguments
+/// * `report` - report of wallet
+/// * `min_risk` - minimum risk score of approvals
+fn filter_report(report: &Report, min_risk: u32) -> Report {
+    Report {
+        approvals: report.approvals.iter()
+            .filter(|a| a.allowance > 0.0 && a.risk_score >= min_risk)
+            .cloned()
+            .collect(),
+        ..report.clone()
+    }
+}
+
+/// Run all jobs of the specified jobs file. Jobs are grouped by chain, and
+/// wallets of each chain are scanned in a single session so that they share
+/// caches, and rate limiting of RPC endpoint. Report of each job is printed,
+/// and POSTed to its webhook if it has any approval at or above its
+/// `min_risk`.
+///
+/// # Arguments
+/// * `jobs_file` - jobs to run
+/// * `common` - common options from command line; chain, and RPC endpoint are set per chain
+/// * `risk` - options of risk assessment
+/// * `token_overrides` - token metadata overrides from config file
+/// * `show_progress` - whether to show progress bar
+pub async fn run_jobs(jobs_file: &JobsFile, common: &mut CommonArgs, risk: &RiskArgs, token_overrides: &TokenOverrides, show_progress: bool) {
+    let client = crate::create_client(common);
+    let default_rpc_urls = common.rpc_urls.clone();
+
+    // chains in order of their first appearance
+    let mut chains: Vec<&String> = Vec::new();
+    for chain in jobs_file.jobs.iter().flat_map(|j| j.chains.iter()) {
+        if !chains.contains(&chain) {
+            chains.push(chain);
+        }
+    }
+
+    let mut is_first = true;
+    for chain in chains {
+        let jobs: Vec<&Job> = jobs_file.jobs.iter().filter(|j| j.chains.contains(chain)).collect();
+        let mut addresses: Vec<String> = jobs.iter().map(|j| j.wallet_address.to_owned()).collect();
+        addresses.sort();
+        addresses.dedup();
+
+        common.chain = Some(chain.to_owned());
+        common.rpc_urls = match jobs_file.rpc_urls.get(chain) {
+            Some(urls) => urls.split(',').map(|u| u.trim().to_owned()).filter(|u| !u.is_empty()).collect(),
+            None => default_rpc_urls.clone(),
+        };
+        let mut session = crate::setup(common, token_overrides, &addresses).await;
+        let mut wallet_results = crate::scan(&mut session, &addresses, false, false, false, show_progress).await;
+        crate::exit_if_interrupted();
+        for (address, results) in addresses.iter().zip(wallet_results.iter_mut()) {
+            crate::assess(&mut session, address, risk, results).await;
+        }
+
+        for job in jobs {
+            let results = match addresses.iter().position(|a| *a == job.wallet_address) {
+                Some(i) => &wallet_results[i],
+                None => continue,
+            };
+
+            if !is_first {
+                println!();
+            }
+            is_first = false;
+            println!("=== Job {} on {} ===", job.name.to_owned().unwrap_or_else(|| to_checksum_address(&job.wallet_address)), chain);
+            crate::print_report(results, session.chain, &job.wallet_address, job.min_risk, false, &session.labels, Links::None, false);
+
+            let mut report = filter_report(&report::build_report(session.chain, &job.wallet_address, results, &session.labels), job.min_risk);
+            report.delegation = session.delegations.get(&job.wallet_address).map(|d| to_checksum_address(d));
+            if let Some(url) = job.webhook_url.as_deref().filter(|_| !report.approvals.is_empty()) {
+                if let Err(e) = post_webhook(&client, url, &report).await {
+                    tracing::error!("{}", e);
+                }
+            }
+        }
+        crate::print_problems(&session, &addresses, &wallet_results);
+    }
+}
@@ -0,0 +1,113 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::sync::{Arc, OnceLock};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::prelude::*;
+
+/// Provider of OpenTelemetry tracer if `--otel-endpoint` is specified; kept
+/// to flush spans not yet exported on exit
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Return the maximum level of diagnostics to write for the specified
+/// verbosity flags.
+///
+/// # Arguments
+/// * `verbose` - number of occurrences of `-v`
+/// * `quiet` - whether `-q` is specified
+fn level_from_flags(verbose: u64, quiet: bool) -> LevelFilter {
+    if quiet {
+        return LevelFilter::ERROR;
+    }
+    match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Create OpenTelemetry tracer provider exporting spans in batches via OTLP
+/// over HTTP to the specified collector endpoint.
+///
+/// # Arguments
+/// * `endpoint` - URL of collector endpoint e.g. `http://localhost:4318/v1/traces`
+fn create_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = match opentelemetry_otlp::SpanExporter::builder().with_http().with_endpoint(endpoint).build() {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error creating OTLP exporter ({}); err={}", endpoint, e)),
+    };
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(opentelemetry_sdk::Resource::builder().with_service_name("appvkek").build())
+        .build())
+}
+
+/// Initialize logging of diagnostics to stderr, and optionally to a file.
+/// Diagnostics of dependencies are only written at `-vvv`.
+///
+/// If `otel_endpoint` is specified, spans of scan phases (info), and RPC
+/// calls (debug) of appvkek are also exported via OTLP regardless of
+/// verbosity.
+///
+/// # Arguments
+/// * `verbose` - number of occurrences of `-v`
+/// * `quiet` - whether `-q` is specified
+/// * `log_file` - path to file to also write diagnostics into; appended if exists
+/// * `otel_endpoint` - URL of OTLP collector endpoint to export spans to
+pub fn init(verbose: u64, quiet: bool, log_file: Option<&str>, otel_endpoint: Option<&str>) -> Result<(), String> {
+    let level = level_from_flags(verbose, quiet);
+    let filter = Targets::new()
+        .with_default(if verbose >= 3 { LevelFilter::DEBUG } else { level.min(LevelFilter::WARN) })
+        .with_target("appvkek", level)
+        .with_target("appvkek_core", level);
+
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = match std::fs::OpenOptions::new().create(true).append(true).open(path) {
+                Ok(res) => res,
+                Err(e) => return Err(format!("Error opening log file ({}); err={}", path, e)),
+            };
+            Some(tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Arc::new(file)))
+        },
+        None => None,
+    };
+
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .without_time()
+        .with_target(verbose >= 2)
+        .with_writer(std::io::stderr);
+
+    let otel_layer = match otel_endpoint {
+        Some(endpoint) => {
+            let provider = create_tracer_provider(endpoint)?;
+            let layer = tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("appvkek"))
+                .with_filter(Targets::new()
+                    .with_target("appvkek", LevelFilter::DEBUG)
+                    .with_target("appvkek_core", LevelFilter::DEBUG));
+            let _ = TRACER_PROVIDER.set(provider);
+            Some(layer)
+        },
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr_layer.and_then(file_layer).with_filter(filter))
+        .with(otel_layer)
+        .init();
+
+    Ok(())
+}
+
+/// Flush spans not yet exported, and shut down OpenTelemetry tracer provider
+/// if any. Call before exiting the process.
+pub fn shutdown() {
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Error exporting traces; err={}", e);
+        }
+    }
+}
@@ -1,266 +1,2012 @@
-use ::evmscan::evmscan;
 use ::evmscan::environ::Context;
 use ::evmscan::prelude::*;
-use ::evmscan::prelude::U256 as BSCU256;    // for floating-point representation for U256
-use clap::Parser;
-use std::collections::HashMap;
+use clap::{CommandFactory, Parser};
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
 
-mod types;
-mod util;
+use appvkek_core::{analyzer, codecache, creationcache, debugdump, ens, estimate, exploits, explorer, hdwallet, labels, malicious, prices, replay, report, risk, state, transport, ratelimit};
+use appvkek_core::error::{AppvkekError, ScanWarning};
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::fees::{suggest_fees, FeePreset};
+use appvkek_core::import::ImportedApprovals;
+use appvkek_core::labels::Labels;
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::provider::AtBlock;
+use appvkek_core::scanner::*;
+use appvkek_core::types::*;
+use appvkek_core::util::*;
 
-use types::*;
-use util::*;
+mod amount;
+mod apikey;
+mod args;
+mod bench;
+mod checkpoint;
+mod clipboard;
+mod config;
+mod confirm;
+mod doctor;
+mod db;
+mod export;
+mod failon;
+mod graph;
+mod history;
+mod interrupt;
+mod jobs;
+mod logging;
+mod progress;
+mod qr;
+mod queue;
+mod revoke;
+mod snapshot;
+mod spender;
+mod timestamp;
+mod watch;
 
-// to avoid having to relying on reading external file
-// currently contains "name", "decimals", "allowance", and "approve" (this one is not used yet)
-static ABI_STR: &'static str = r#"[{"inputs":[],"name":"name","outputs":[{"internalType":"string","name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"decimals","outputs":[{"internalType":"uint8","name":"","type":"uint8"}],"stateMutability":"view","type":"function"},{"name":"allowance","inputs":[{"internalType":"address","name":"owner","type":"address"},{"internalType":"address","name":"spender","type":"address"}],"outputs":[{"internalType":"uint256","name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"name":"approve","inputs":[{"internalType":"address","name":"spender","type":"address"},{"internalType":"uint256","name":"amount","type":"uint256"}],"outputs":[{"internalType":"bool","name":"","type":"bool"}],"stateMutability":"nonpayable","type":"function"}]"#;
+use args::*;
+use failon::FailOn;
 
-/// Make query for information towards token contract address, and associated
-/// spender addresses with their allowance balances.
+/// Print a single result from `query()` to stdout. Approvals to burn
+/// addresses are left out unless `show_burn` as of `remove_burn_approvals()`.
 ///
-/// Return `TokenContractWithSpenderAllowances` structure, otherwise return
-/// tuple of `(token_contract_address, error_message)`.
+/// # Arguments
+/// * `res` - result from `query()`
+/// * `labels` - labels of well-known addresses
+/// * `show_burn` - whether to print approvals to burn addresses
+fn print_result(res: &QueryResult, labels: &Labels, show_burn: bool) {
+    match res {
+        Ok(r) => {
+            let spenders: Vec<_> = r.spender_allowances.iter().filter(|(spender, _)| show_burn || !is_burn_address(spender)).collect();
+            if spenders.is_empty() && !r.spender_allowances.is_empty() {
+                return;
+            }
+            println!("[{}] {}", format_token_name(r), labels.format(&r.address));
+            for (spender, spender_allowance) in spenders {
+                println!("  * {} - {}", labels.format(spender), amount::format_allowance(spender_allowance.allowance, spender_allowance.is_unlimited, &r.symbol));
+            }
+        },
+        Err(e) => {
+            println!("[Error] {} - {}", to_checksum_address(&e.token_address), e.error);
+        }
+    }
+}
+
+/// Print all results as a report prioritized by risk score.
+/// Token contracts are sorted by the highest risk score among their spenders,
+/// and spenders are sorted by their risk score. Approvals with risk score lower
+/// than `min_risk`, or to known spenders if `unknown_only` are not printed.
+/// Errors are printed separately by `print_problems()`.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `chain` - chain type; for explorer links
+/// * `owner_address` - owner wallet address; for revoke.cash links
+/// * `min_risk` - minimum risk score of approvals to print
+/// * `unknown_only` - whether to print only approvals to unknown spenders as of `risk::is_unknown_spender()`
+/// * `labels` - labels of well-known addresses
+/// * `links` - links to append
+/// * `show_share` - whether to print allowance as share of balance, and total supply; see `--percent`
+#[allow(clippy::too_many_arguments)]
+fn print_report(results: &[QueryResult], chain: ChainType, owner_address: &str, min_risk: u32, unknown_only: bool, labels: &Labels, links: Links, show_share: bool) {
+    let mut tokens: Vec<(&TokenContractWithSpenderAllowances, Vec<(&String, &SpenderAllowance)>)> = results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .map(|r| {
+            let mut spenders: Vec<(&String, &SpenderAllowance)> = r.spender_allowances.iter()
+                .filter(|(spender, sa)| sa.risk.score >= min_risk && (!unknown_only || risk::is_unknown_spender(labels, spender, sa)))
+                .collect();
+            spenders.sort_by_key(|(_, sa)| std::cmp::Reverse(sa.risk.score));
+            (r, spenders)
+        })
+        .filter(|(_, spenders)| !spenders.is_empty())
+        .collect();
+    tokens.sort_by_key(|(_, spenders)| std::cmp::Reverse(spenders[0].1.risk.score));
+
+    for (r, spenders) in tokens {
+        if links == Links::None {
+            println!("[{}] {}", format_token_name(r), labels.format(&r.address));
+        }
+        else {
+            println!("[{}] {} - {}", format_token_name(r), labels.format(&r.address), explorer_token_url(chain, &r.address));
+        }
+        for (spender, sa) in spenders {
+            // timestamp is unknown e.g. approval made by permit, or imported without it
+            let age = if sa.approval.timestamp > 0 { format_age(sa.approval.timestamp) } else { "at unknown time".to_owned() };
+            if sa.risk.reasons.is_empty() {
+                println!("  * {} - {} (risk={}) - granted {}", labels.format(spender), amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol), sa.risk.score, age);
+            }
+            else {
+                println!("  * {} - {} (risk={}: {}) - granted {}", labels.format(spender), amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol), sa.risk.score, sa.risk.reasons.join(", "), age);
+            }
+            if links != Links::None {
+                println!("      spender: {}", explorer_address_url(chain, spender));
+            }
+            if let Some(share) = amount::format_share(r, sa).filter(|_| show_share) {
+                println!("      share: {}", share);
+            }
+            // approval may have been made by means other than approve transaction e.g. permit
+            if sa.approval.block_number > 0 {
+                if sa.approval.tx_hash.is_empty() {
+                    println!("      approved: block {} at {}", sa.approval.block_number, timestamp::format_timestamp(sa.approval.timestamp));
+                }
+                else {
+                    println!("      approved: block {} at {} - {}", sa.approval.block_number, timestamp::format_timestamp(sa.approval.timestamp), explorer_tx_url(chain, &sa.approval.tx_hash));
+                }
+                if let Some(issue) = &sa.approval.issue {
+                    println!("      calldata issue: {}", issue);
+                }
+            }
+            if let Some(proxy) = &sa.proxy {
+                println!("      implementation: {} via {}", labels.format(&proxy.implementation), proxy.kind);
+            }
+            if let Some(control) = &sa.control {
+                if let Some(owner) = &control.owner {
+                    println!("      owner: {}", labels.format(owner));
+                }
+                if let Some(admin) = &control.admin {
+                    println!("      admin: {}", labels.format(admin));
+                }
+            }
+            if let Some(activity) = &sa.activity {
+                match activity.last_active {
+                    Some(last_active) => println!("      activity: last active {}, {} txs in last {} days", format_age(last_active), activity.recent_tx_count, explorer::RECENT_ACTIVITY_DAYS),
+                    None => println!("      activity: no transactions"),
+                }
+            }
+            if let Some(creation) = &sa.creation {
+                match creation.timestamp {
+                    Some(created) => println!("      created: {} by {} - {}", timestamp::format_timestamp_with_age(created), labels.format(&creation.creator), explorer_tx_url(chain, &creation.tx_hash)),
+                    None => println!("      created: by {} - {}", labels.format(&creation.creator), explorer_tx_url(chain, &creation.tx_hash)),
+                }
+            }
+            if let Some(exploit) = &sa.exploit {
+                println!("      EXPLOITED: {} on {} - {}", exploit.name, exploit.date, exploit.reference);
+            }
+            if links == Links::Revoke && sa.allowance > 0.0 {
+                println!("      revoke: {}", revokecash_url(chain_id(chain), owner_address));
+            }
+        }
+    }
+}
+
+/// Return description of the specified warning to print.
+///
+/// # Arguments
+/// * `warning` - warning of skipped transaction
+fn format_warning(warning: &ScanWarning) -> String {
+    format!("transaction {} was skipped - {}", warning.tx_hash, warning.error)
+}
+
+/// Print errors of token contracts which failed to be queried, and warnings
+/// of transactions skipped while collecting approvals, of all wallets as a
+/// single section at the end of report. Nothing is printed if there is none.
+///
+/// # Arguments
+/// * `session` - session
+/// * `owner_addresses` - owner wallet addresses; lowercased
+/// * `wallet_results` - results of each wallet from `scan()`
+fn print_problems(session: &Session, owner_addresses: &[String], wallet_results: &[Vec<QueryResult>]) {
+    let prefix = |owner_address: &str| {
+        if owner_addresses.len() > 1 { format!("{}: ", to_checksum_address(owner_address)) } else { String::new() }
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    for (owner_address, results) in owner_addresses.iter().zip(wallet_results.iter()) {
+        for e in results.iter().filter_map(|res| res.as_ref().err()) {
+            lines.push(format!("[Error] {}{} - {}", prefix(owner_address), to_checksum_address(&e.token_address), e.error));
+        }
+    }
+    if let Ok(warnings) = session.warnings.lock() {
+        for (owner_address, warning) in warnings.iter() {
+            lines.push(format!("[Warning] {}{}", prefix(owner_address), format_warning(warning)));
+        }
+    }
+    if lines.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("=== Errors and warnings ===");
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+/// Print warnings of transactions skipped while collecting approvals to
+/// stderr; for subcommands whose output isn't a report.
+///
+/// # Arguments
+/// * `session` - session
+fn print_warnings(session: &Session) {
+    if let Ok(warnings) = session.warnings.lock() {
+        for (_, warning) in warnings.iter() {
+            eprintln!("Warning {}", format_warning(warning));
+        }
+    }
+}
+
+/// Print usage of explorer API, and RPC endpoints made by this run to stderr
+/// unless there was none.
+fn print_usage() {
+    let usage = appvkek_core::usage::current();
+    if !usage.is_empty() {
+        eprintln!("Usage: {}", usage);
+    }
+}
+
+/// Everything needed to scan a wallet, set up from common options.
+pub struct Session {
+    /// Chain type
+    pub chain: ChainType,
+
+    /// web3 instance
+    pub web3: Web3Type,
+
+    /// Semaphore bounding the number of in-flight RPC calls
+    pub semaphore: Semaphore,
+
+    /// Maximum number of in-flight RPC calls
+    pub concurrency: usize,
+
+    /// Rate limit of RPC calls per second; 0 means unlimited
+    pub rpc_rate_limit: f64,
+
+    /// evmscan's context
+    pub ctx: Context,
+
+    /// Labels of well-known addresses
+    pub labels: Labels,
+
+    /// Cache of token metadata
+    pub metadata_cache: MetadataCache,
+
+    /// Record of failed token contracts
+    pub failed_contracts: FailedContracts,
+
+    /// Whether to batch queries via Multicall3 contract
+    pub use_multicall: bool,
+
+    /// Wallet addresses which are contract wallets, or EOAs delegated via
+    /// EIP-7702; lowercased
+    pub contract_wallets: HashSet<String>,
+
+    /// Wallet addresses which have delegated via EIP-7702 to their delegate
+    /// contract addresses; both lowercased
+    pub delegations: HashMap<String, String>,
+
+    /// Block number to query token contracts at; latest block if `None`
+    pub at_block: Option<u64>,
+
+    /// Whether to abort on the first transaction which can't be parsed
+    /// instead of skipping it with warning
+    pub fail_fast: bool,
+
+    /// Warnings of transactions skipped while collecting approvals, along
+    /// with wallet address (lowercased) they belong to
+    pub warnings: std::sync::Mutex<Vec<(String, ScanWarning)>>,
+
+    /// Approvals to query instead of collecting them from transaction
+    /// history; see `--import`
+    pub imported: Option<ImportedApprovals>,
+
+    /// HTTP client for requests other than those to RPC endpoints, and
+    /// explorer; see `create_client()`
+    pub http_client: reqwest::Client,
+}
+
+/// Links to append to human-readable output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Links {
+    /// No link
+    None,
+
+    /// Links to explorer website for each token, and spender
+    Explorer,
+
+    /// Links to explorer website, and to revoke.cash for each approval
+    Revoke,
+}
+
+/// Parse value of `--links` flag. Exit the process on error.
+///
+/// # Arguments
+/// * `value` - one of 'explorer', or 'revoke'; `None` if not specified
+fn parse_links(value: Option<&str>) -> Links {
+    match value.map(|v| v.to_lowercase()).as_deref() {
+        None => Links::None,
+        Some("explorer") => Links::Explorer,
+        Some("revoke") => Links::Revoke,
+        Some(_) => {
+            eprintln!("Error invalid value for --links.
+Possible values are 'explorer', or 'revoke'.");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse value of `--chain` flag.
+///
+/// # Arguments
+/// * `value` - one of 'bsc', 'ethereum', or 'polygon'
+fn parse_chain(value: &str) -> Result<ChainType, String> {
+    match value.to_lowercase().as_str() {
+        "bsc" => Ok(ChainType::BSC),
+        "ethereum" => Ok(ChainType::Ethereum),
+        "polygon" => Ok(ChainType::Polygon),
+        _ => Err("Error invalid value for --chain.
+Possible values are 'bsc', 'ethereum', or 'polygon'.".to_owned()),
+    }
+}
+
+/// Return the specified wallet address lowercased, or exit the process if
+/// it's not specified either on command line or in profile.
+///
+/// # Arguments
+/// * `address` - wallet address from command line or profile
+fn require_address(address: Option<String>) -> String {
+    match address {
+        Some(res) => normalize_address(&res),
+        None => {
+            eprintln!("Error --wallet-address is required unless `wallet_address` is set in profile");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Return the specified wallet addresses lowercased and deduplicated, or
+/// exit the process if none is specified either on command line or in profile.
+///
+/// # Arguments
+/// * `addresses` - wallet addresses from command line or profile
+fn require_addresses(addresses: Vec<String>) -> Vec<String> {
+    let mut ret: Vec<String> = Vec::new();
+    for address in addresses {
+        let address = normalize_address(&address);
+        if !ret.contains(&address) {
+            ret.push(address);
+        }
+    }
+    if ret.is_empty() {
+        eprintln!("Error --wallet-address is required unless `wallet_address` is set in profile");
+        std::process::exit(1);
+    }
+    ret
+}
+
+/// Return the specified address (or ENS name) lowercased, along with its
+/// EIP-3770 chain prefix if any. Address in mixed case has to have valid
+/// EIP-55 checksum, otherwise exit the process.
+///
+/// # Arguments
+/// * `address` - address or ENS name from command line or profile; optionally
+///   prefixed with short name of chain e.g. `eth:0x...`
+fn normalize_address(address: &str) -> String {
+    let unprefixed = match split_chain_prefix(address) {
+        Ok((_, res)) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if validate_address_format(unprefixed) && !validate_address_checksum(unprefixed) {
+        eprintln!("Error address has invalid EIP-55 checksum ({}); use all lowercase to skip checksum validation", address);
+        std::process::exit(1);
+    }
+    address.to_lowercase()
+}
+
+/// Strip EIP-3770 chain prefix off the specified address e.g. `eth:0x...`,
+/// and set chain to that of the prefix if chain is not specified yet. Exit
+/// the process if it's of another chain than the one specified.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `address` - address or ENS name; optionally prefixed with short name of chain
+fn strip_chain_prefix(common: &mut CommonArgs, address: &str) -> String {
+    let (prefix_chain, unprefixed) = match split_chain_prefix(address) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(prefix_chain) = prefix_chain {
+        match common.chain.as_deref().map(parse_chain) {
+            None => common.chain = Some(chain_name(prefix_chain).to_owned()),
+            Some(Ok(chain)) if chain_id(chain) == chain_id(prefix_chain) => (),
+            _ => {
+                eprintln!("Error chain prefix of address ({}) doesn't match chain {}; all addresses have to be of the same chain", address, common.chain.as_deref().unwrap_or_default());
+                std::process::exit(1);
+            }
+        }
+    }
+    unprefixed.to_owned()
+}
+
+/// Set chain from EIP-3770 chain prefix of wallet addresses specified on
+/// command line if `--chain` is not specified. It's applied before profile,
+/// so that prefix takes precedence over chain of profile.
+///
+/// # Arguments
+/// * `common` - common options from command line
+/// * `addresses` - wallet addresses from command line
+fn infer_chain<'a>(common: &mut CommonArgs, addresses: impl IntoIterator<Item = &'a String>) {
+    for address in addresses {
+        strip_chain_prefix(common, address);
+    }
+}
+
+/// Start dumping, or replaying RPC and explorer traffic if specified. Exit
+/// the process on error.
+///
+/// # Arguments
+/// * `common` - common options
+fn start_debug_rpc(common: &CommonArgs) {
+    if let Some(path) = common.debug_rpc.as_deref() {
+        if let Err(e) = debugdump::open(path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = common.replay.as_deref() {
+        if let Err(e) = replay::open(path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Create web3 instance of the specified chain from common options.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `chain` - chain type
+/// * `rpc_urls` - RPC endpoints; default endpoint of chain if empty
+fn try_create_rpc(common: &CommonArgs, chain: ChainType, rpc_urls: &[String]) -> Result<Web3Type, AppvkekError> {
+    let (default_rps, default_burst) = default_rpc_rate_limit(chain);
+    let rpc_limiter = ratelimit::TokenBucket::new(common.rpc_rate_limit.unwrap_or(default_rps), common.rpc_burst.unwrap_or(default_burst));
+    create_web3(chain, rpc_urls, !common.no_rpc_batch, transport::RetryPolicy {
+        max_retries: common.rpc_retries,
+        base_delay: std::time::Duration::from_millis(common.rpc_retry_backoff),
+        max_delay: RPC_MAX_RETRY_DELAY,
+    }, std::time::Duration::from_secs(common.rpc_timeout.unwrap_or(common.timeout)), rpc_limiter, &common.rpc_rate_limits, resolve_proxy(common.proxy.as_deref()).as_deref())
+}
+
+/// Create web3 instance of the specified chain from common options. Exit the
+/// process on error.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `chain` - chain type
+/// * `rpc_urls` - RPC endpoints; default endpoint of chain if empty
+fn create_rpc(common: &CommonArgs, chain: ChainType, rpc_urls: &[String]) -> Web3Type {
+    // started here as ENS names are resolved before `setup()`
+    start_debug_rpc(common);
+    match try_create_rpc(common, chain, rpc_urls) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Probe latency of RPC endpoints if multiple are specified, so that
+/// requests go to the fastest one from the start. Exit the process if none
+/// of them responds.
+///
+/// # Arguments
+/// * `web3` - web3 instance
+async fn probe_rpc_endpoints(web3: &Web3Type) {
+    let probes = web3.transport().probe_endpoints().await;
+    if probes.is_empty() {
+        return;
+    }
+    for probe in probes.iter() {
+        match &probe.result {
+            Ok(latency) => tracing::info!("RPC endpoint {} responded in {} ms", probe.name, latency.as_millis()),
+            Err(e) => tracing::info!("RPC endpoint {} failed to respond; err={}", probe.name, e),
+        }
+    }
+    if probes.iter().all(|p| p.result.is_err()) {
+        eprintln!("Error none of {} RPC endpoints responded; check --rpc-url", probes.len());
+        std::process::exit(1);
+    }
+    tracing::info!("Using RPC endpoint {}", web3.transport().selected_endpoint());
+}
+
+/// Apply timeout, proxy, and rate limit of the chain of common options to all
+/// explorer API calls. Exit the process on error.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `chain` - chain type
+fn configure_explorer(common: &CommonArgs, chain: ChainType) {
+    explorer::set_timeout(std::time::Duration::from_secs(common.explorer_timeout.unwrap_or(common.timeout)));
+    let (requests_per_second, burst) = common.explorer_rate_limits.get(chain_name(chain)).copied().unwrap_or(explorer::DEFAULT_RATE_LIMIT);
+    explorer::set_rate_limit(requests_per_second, burst);
+    if let Some(proxy) = resolve_proxy(common.proxy.as_deref()) {
+        if let Err(e) = explorer::set_proxy(&proxy) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Create HTTP client for requests other than those to RPC endpoints, and
+/// explorer e.g. price queries, and webhooks with timeout, and proxy of common
+/// options applied. Exit the process on error.
+///
+/// # Arguments
+/// * `common` - common options
+fn create_client(common: &CommonArgs) -> reqwest::Client {
+    match create_http_client(std::time::Duration::from_secs(common.timeout), resolve_proxy(common.proxy.as_deref()).as_deref()) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Resolve ENS names among the specified wallet addresses via Ethereum
+/// mainnet regardless of target chain. Exit the process on error.
+///
+/// Return addresses in the same order with duplicates removed, along with
+/// map of address to ENS name it was resolved from.
+///
+/// Chain prefix of them is stripped off, and chain is set to that of the
+/// prefix if it's not specified yet.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `inputs` - wallet addresses or ENS names; lowercased, optionally prefixed
+///   with short name of chain
+async fn resolve_wallets(common: &mut CommonArgs, inputs: Vec<String>) -> (Vec<String>, HashMap<String, String>) {
+    let mut addresses: Vec<String> = Vec::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut ens_web3: Option<Web3Type> = None;
+
+    let inputs: Vec<String> = inputs.iter().map(|i| strip_chain_prefix(common, i)).collect();
+    let common = &*common;
+    for input in inputs {
+        let address = if ens::is_ens_name(&input) {
+            // only use the specified RPC endpoint if it's of Ethereum
+            let web3 = ens_web3.get_or_insert_with(|| {
+                let rpc_urls: &[String] = match common.chain.as_deref().map(parse_chain) {
+                    Some(Ok(ChainType::Ethereum)) => &common.rpc_urls,
+                    _ => &[],
+                };
+                create_rpc(common, ChainType::Ethereum, rpc_urls)
+            });
+            match ens::resolve_name(web3, &input).await {
+                Ok(Some(res)) => {
+                    tracing::info!("Resolved {} to {}", input, res);
+                    names.insert(res.to_owned(), input);
+                    res
+                },
+                Ok(None) => {
+                    eprintln!("Error ENS name ({}) doesn't resolve to any address", input);
+                    std::process::exit(1);
+                },
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        else {
+            input
+        };
+
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    (addresses, names)
+}
+
+/// Resolve the specified wallet address if it's ENS name. See
+/// `resolve_wallets()`.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `input` - wallet address or ENS name; lowercased
+async fn resolve_wallet(common: &mut CommonArgs, input: String) -> (String, HashMap<String, String>) {
+    let (mut addresses, names) = resolve_wallets(common, vec![input]).await;
+    (addresses.remove(0), names)
+}
+
+/// Derive wallets from `--xpub`, or `--mnemonic` read from stdin if either
+/// is specified. Exit the process on error.
+///
+/// # Arguments
+/// * `args` - options of `scan` subcommand
+fn derive_wallets(args: &ScanArgs) -> Vec<hdwallet::DerivedAddress> {
+    let root = if let Some(xpub) = &args.xpub {
+        hdwallet::ExtendedKey::from_xpub(xpub)
+    }
+    else if args.mnemonic {
+        let read = confirm::read_secret("Enter mnemonic: ").and_then(|mnemonic| {
+            let passphrase = if args.mnemonic_passphrase { confirm::read_secret("Enter passphrase: ")? } else { String::new() };
+            Ok((mnemonic, passphrase))
+        });
+        match read {
+            Ok((mnemonic, passphrase)) => hdwallet::ExtendedKey::from_mnemonic(&mnemonic, &passphrase),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    else {
+        return Vec::new();
+    };
+
+    let default_path = if args.mnemonic { hdwallet::DEFAULT_MNEMONIC_PATH } else { hdwallet::DEFAULT_XPUB_PATH };
+    match root.and_then(|r| hdwallet::derive_addresses(&r, args.derivation_path.as_deref().unwrap_or(default_path), args.derive_count)) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Format wallet address along with its label from address book if any
+/// e.g. `0xd8dA... (my hot wallet)`, otherwise along with ENS name it was
+/// resolved from if any e.g. `vitalik.eth (0xd8dA...)`; address is EIP-55
+/// checksummed.
+///
+/// # Arguments
+/// * `address` - wallet address
+/// * `names` - map of address to ENS name from `resolve_wallets()`
+/// * `labels` - labels including user's address book
+fn format_wallet(address: &str, names: &HashMap<String, String>, labels: &Labels) -> String {
+    match (labels.address_book_label(address), names.get(address)) {
+        (Some(label), _) => format!("{} ({})", to_checksum_address(address), label),
+        (None, Some(name)) => format!("{} ({})", name, to_checksum_address(address)),
+        (None, None) => to_checksum_address(address),
+    }
+}
+
+/// Set up session from common options, and check that all wallet addresses
+/// are EOAs. Exit the process on error.
+///
+/// # Arguments
+/// * `common` - common options
+/// * `token_overrides` - metadata overrides of token contracts of all chains
+/// * `addresses` - wallet addresses
+async fn setup(common: &CommonArgs, token_overrides: &config::TokenOverrides, addresses: &[String]) -> Session {
+    let chain = match common.chain.as_deref().map(parse_chain) {
+        Some(Ok(res)) => res,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+        None => {
+            eprintln!("Error --chain is required unless `chain` is set in profile");
+            std::process::exit(1);
+        }
+    };
+
+    if common.concurrency == 0 {
+        eprintln!("Error --concurrency must be at least 1");
+        std::process::exit(1);
+    }
+    if [Some(common.timeout), common.rpc_timeout, common.explorer_timeout].contains(&Some(0)) {
+        eprintln!("Error --timeout, --rpc-timeout, and --explorer-timeout must be at least 1");
+        std::process::exit(1);
+    }
+
+    let api_key = match apikey::select_apikey(chain, common.api_key.as_deref()) {
+        Ok(res) => res,
+        // it's redacted from recorded requests
+        Err(_) if common.replay.is_some() => String::new(),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut metadata_cache = MetadataCache::load(chain, !common.no_metadata_cache);
+    for (name, overrides) in token_overrides.iter() {
+        if parse_chain(name).is_err() {
+            eprintln!("Error invalid chain '{}' in token_overrides of config file; possible values are 'bsc', 'ethereum', or 'polygon'", name);
+            std::process::exit(1);
+        }
+        if name.to_lowercase() != chain_name(chain) {
+            continue;
+        }
+        if let Err(e) = metadata_cache.set_overrides(overrides.clone()) {
+            eprintln!("Error in token_overrides of config file; {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let web3 = create_rpc(common, chain, &common.rpc_urls);
+    probe_rpc_endpoints(&web3).await;
+    configure_explorer(common, chain);
+    let semaphore = Semaphore::new(common.concurrency);
+
+    let ctx = Context::create(chain, api_key);
+
+    // check if input addresses are in correct format, and are actually EOA
+    // unless contract wallets are allowed; ERC-4337 smart accounts are
+    // always allowed as they are wallets by definition. EOAs delegated via
+    // EIP-7702 can approve through delegated code i.e. via internal calls,
+    // so they are scanned as contract wallets.
+    let mut contract_wallets: HashSet<String> = HashSet::new();
+    let mut delegations: HashMap<String, String> = HashMap::new();
+    for address in addresses {
+        match perform_check_is_eoa(&web3, address).await {
+            Ok(is_eoa) => {
+                if !is_eoa {
+                    match get_delegation(&web3, address).await {
+                        Ok(Some(delegate)) => {
+                            tracing::info!("{} has delegated to {} via EIP-7702; collecting its approvals from event logs", to_checksum_address(address), to_checksum_address(&delegate));
+                            contract_wallets.insert(address.to_owned());
+                            delegations.insert(address.to_owned(), delegate);
+                            continue;
+                        },
+                        Ok(None) => (),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                    if !common.allow_contract_wallet {
+                        match is_smart_account(&ctx, address) {
+                            Ok(true) => (),
+                            Ok(false) => {
+                                eprintln!("Error input address is not EOA ({}); use --allow-contract-wallet to scan contract wallet", address);
+                                std::process::exit(1);
+                            },
+                            // without knowing, it'd be wrongly reported as not a wallet
+                            Err(e) => {
+                                eprintln!("Error checking whether input address is ERC-4337 smart account ({}); use --allow-contract-wallet to scan it regardless; err={}", address, e);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    contract_wallets.insert(address.to_owned());
+                }
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if common.update_labels {
+        if let Err(e) = labels::update_labels() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    let mut labels = match Labels::load(chain, common.labels_file.as_deref()) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = labels.load_address_book(common.address_book.as_deref()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    Session {
+        chain,
+        web3,
+        semaphore,
+        concurrency: common.concurrency,
+        rpc_rate_limit: common.rpc_rate_limit.unwrap_or(default_rpc_rate_limit(chain).0),
+        ctx,
+        labels,
+        metadata_cache,
+        failed_contracts: FailedContracts::load(chain, !common.retry_failed),
+        use_multicall: !common.no_multicall,
+        contract_wallets,
+        delegations,
+        at_block: None,
+        fail_fast: common.fail_fast,
+        warnings: Default::default(),
+        imported: None,
+        http_client: create_client(common),
+    }
+}
+
+/// Query token contracts at the specified block instead of the latest block.
+/// Failures at a historical block e.g. token contracts not yet deployed
+/// don't say anything about now, so they are neither skipped nor recorded.
+///
+/// # Arguments
+/// * `session` - session
+/// * `at_block` - block number; latest block if `None`
+fn set_at_block(session: &mut Session, at_block: Option<u64>) {
+    if at_block.is_some() {
+        session.failed_contracts = FailedContracts::default();
+    }
+    session.at_block = at_block;
+}
+
+/// Query approvals imported from the specified file instead of collecting
+/// them from transaction history. Exit the process on error.
+///
+/// # Arguments
+/// * `session` - session
+/// * `path` - path to import file; approvals are collected as usual if `None`
+fn set_imported(session: &mut Session, path: Option<&str>) {
+    let path = match path {
+        Some(res) => res,
+        None => return,
+    };
+    match ImportedApprovals::load(path) {
+        Ok(res) => {
+            tracing::info!("Imported {} approvals from {}", res.len(), path);
+            session.imported = Some(res);
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Collect approvals of the wallet. Approvals collected by previous scan are
+/// resumed from, and only newer transactions are fetched unless
+/// `full_rescan`. Approvals of contract wallets are collected from event logs
+/// instead. Exit the process on error.
+///
+/// Transactions which can't be parsed are skipped, and recorded into
+/// `session.warnings` unless `--fail-fast`.
+///
+/// If collection doesn't complete, its progress is saved into checkpoint of
+/// the wallet. Collection continues from progress in `checkpoint` if any.
+///
+/// If approvals are imported via `--import`, those of the wallet are returned
+/// as is without fetching anything, and without touching scan state.
+///
+/// # Arguments
+/// * `session` - session
+/// * `owner_address` - owner wallet address; lowercased
+/// * `full_rescan` - whether to ignore approvals collected by previous scans
+/// * `checkpoint` - checkpoint of the wallet; loaded via `--resume`, or empty
+/// * `show_progress` - whether to report explorer pages fetched to stderr
+fn collect(session: &Session, owner_address: &str, full_rescan: bool, checkpoint: &mut checkpoint::Checkpoint, show_progress: bool) -> ApprovalsMap {
+    if let Some(imported) = session.imported.as_ref() {
+        checkpoint.collected = None;
+        let approvals = imported.approvals_of(session.chain, owner_address);
+        if approvals.is_empty() {
+            tracing::warn!("No imported approval of {} on {}", to_checksum_address(owner_address), chain_name(session.chain));
+        }
+        return approvals;
+    }
+
+    // resume from approvals collected by previous scan, and only fetch newer transactions
+    let scan_state_file = state::scan_state_file_path(session.chain, owner_address);
+    let mut scan_state = state::ScanState::default();
+    if !full_rescan {
+        match state::load_state::<state::ScanState>(&scan_state_file) {
+            Ok(Some(res)) => scan_state = res,
+            Ok(None) => (),
+            Err(e) => tracing::warn!("{}; doing full scan instead", e),
+        }
+    }
+    if let Some(progress) = checkpoint.collected.take() {
+        tracing::info!("Resuming collection of approvals of {} from block {}", to_checksum_address(owner_address), progress.next_block);
+        scan_state.last_scanned_block = progress.next_block.checked_sub(1);
+        scan_state.approvals = progress.approvals;
+    }
+
+    let start_block = scan_state.last_scanned_block.map(|b| b + 1).unwrap_or(0);
+    let bar = progress::fetch_spinner(show_progress, &to_checksum_address(owner_address));
+    let explorer = progress::ProgressExplorer { inner: &session.ctx, bar: &bar, last_block: Default::default() };
+    let mut warnings = Vec::new();
+    let collected = if session.contract_wallets.contains(owner_address) {
+        collect_user_operation_approvals(&explorer, owner_address, start_block, &mut scan_state.approvals)
+    }
+    else {
+        collect_approvals(&explorer, owner_address, start_block, &mut scan_state.approvals, (!session.fail_fast).then_some(&mut warnings))
+    };
+    bar.finish_and_clear();
+    if let Ok(mut session_warnings) = session.warnings.lock() {
+        session_warnings.extend(warnings.into_iter().map(|w| (owner_address.to_owned(), w)));
+    }
+    match collected {
+        Ok(last_block) => {
+            scan_state.last_scanned_block = last_block.or(scan_state.last_scanned_block);
+            if let Err(e) = state::save_state(&scan_state_file, &scan_state) {
+                tracing::warn!("{}", e);
+            }
+        },
+        Err(e) => {
+            // the highest block fetched may be partially processed, so continue from it
+            checkpoint.at_block = session.at_block;
+            checkpoint.collected = Some(checkpoint::CollectProgress {
+                next_block: explorer.last_block.get().unwrap_or(start_block),
+                approvals: scan_state.approvals,
+            });
+            match checkpoint::save_checkpoint(session.chain, owner_address, checkpoint) {
+                Ok(path) => eprintln!("Saved progress of collecting approvals of {} into checkpoint ({}); use --resume to continue", to_checksum_address(owner_address), path),
+                Err(e) => tracing::warn!("{}", e),
+            }
+
+            // nothing to query as collection is incomplete
+            if interrupt::is_interrupted() {
+                return ApprovalsMap::new();
+            }
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    scan_state.approvals
+}
+
+/// Collect all token contracts the wallet has ever sent or received from its
+/// token transfers, which include those it has never approved anything on.
+/// As with `collect()`, only newer transfers are fetched unless
+/// `full_rescan`. Exit the process on error.
 ///
-/// # Note
-/// As the query needs to live long enough, thus its function's arguments need
-/// to live long enough as well e.g. address is in `String` not `&str`.
+/// # Arguments
+/// * `session` - session
+/// * `owner_address` - owner wallet address; lowercased
+/// * `full_rescan` - whether to ignore tokens collected by previous scans
+/// * `show_progress` - whether to show spinner of pages fetched
+fn collect_holdings(session: &Session, owner_address: &str, full_rescan: bool, show_progress: bool) -> HashSet<String> {
+    let scan_state_file = state::scan_state_file_path(session.chain, owner_address);
+    let mut scan_state = match state::load_state::<state::ScanState>(&scan_state_file) {
+        Ok(res) => res.unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("{}; doing full scan instead", e);
+            state::ScanState::default()
+        }
+    };
+    if full_rescan {
+        scan_state.last_transfer_block = None;
+        scan_state.held_tokens.clear();
+    }
+
+    let start_block = scan_state.last_transfer_block.map(|b| b + 1).unwrap_or(0);
+    let bar = progress::fetch_spinner(show_progress, &to_checksum_address(owner_address));
+    let explorer = progress::ProgressExplorer { inner: &session.ctx, bar: &bar, last_block: Default::default() };
+    let collected = collect_held_tokens(&explorer, owner_address, start_block, &mut scan_state.held_tokens);
+    bar.finish_and_clear();
+    match collected {
+        Ok(last_block) => {
+            scan_state.last_transfer_block = last_block.or(scan_state.last_transfer_block);
+            if let Err(e) = state::save_state(&scan_state_file, &scan_state) {
+                tracing::warn!("{}", e);
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(if interrupt::is_interrupted() { interrupt::EXIT_INTERRUPTED } else { 1 });
+        }
+    }
+
+    scan_state.held_tokens
+}
+
+/// Print estimated cost of querying the collected approvals, then ask
+/// whether to proceed. Exit the process if declined, or if stdin is not a
+/// terminal as there is no one to ask.
 ///
 /// # Arguments
-/// * `web3` - web3 instance
-/// * `contract_address` - token contract address to interact with
-/// * `owner_address` - owner wallet address
-/// * `spenders` - all spender addresses associated with such token contract address
-async fn query(web3: &Web3Type, contract_address: String, owner_address: String, spenders: Vec<String>) -> Result<TokenContractWithSpenderAllowances, (String, String)> {
-    let contract = match create_contract(&web3, &contract_address, &ABI_STR) {
-        Ok(res) => res,
+/// * `session` - session
+/// * `approvals` - collected approvals of each wallet
+async fn confirm_estimate(session: &Session, approvals: &[ApprovalsMap]) {
+    let res = estimate::estimate_query(approvals, session.use_multicall, &session.metadata_cache, &session.failed_contracts);
+
+    // round trip of a single cheap call stands for all of them
+    let start = std::time::Instant::now();
+    let latency = match session.web3.eth().block_number().await {
+        Ok(_) => Some(start.elapsed()),
         Err(e) => {
-            let err_msg = format!("{}", e);
-            return Err( (contract_address, err_msg) );
+            tracing::warn!("Error measuring round trip time of RPC endpoint; err={}", e);
+            None
         }
     };
+    let duration = res.duration(session.rpc_rate_limit, session.concurrency, latency.unwrap_or_default());
+
+    let mut summary = format!("Estimate: {} approvals to {} spenders across {} token contracts", res.num_approvals, res.num_spenders, res.num_tokens);
+    if res.num_skipped > 0 {
+        summary.push_str(&format!(" ({} skipped as they failed before)", res.num_skipped));
+    }
+    summary.push_str(&format!("\n  metadata cached: {} of {} token contracts", res.num_cached, res.num_tokens));
+    summary.push_str(&format!("\n  RPC calls: {}{}", res.rpc_calls, if session.use_multicall { " (batched via Multicall3)" } else { "" }));
+    let rate = if session.rpc_rate_limit > 0.0 { format!("{} calls/s", session.rpc_rate_limit) } else { "unlimited rate".to_owned() };
+    let round_trip = latency.map(|l| format!("{} ms round trip", l.as_millis())).unwrap_or_else(|| "unknown round trip".to_owned());
+    summary.push_str(&format!("\n  time: {} at {}, concurrency {}, {}", format_duration(duration), rate, session.concurrency, round_trip));
+    summary.push_str("\n  risk assessment of spenders, and retries make more RPC calls than estimated");
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!("{}", summary);
+        std::process::exit(0);
+    }
+    match confirm::confirm(&summary, false) {
+        Ok(true) => (),
+        Ok(false) => {
+            eprintln!("Aborted; no token contract queried");
+            std::process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Format the specified duration roughly e.g. `2m 5s`, or `3.2s`.
+///
+/// # Arguments
+/// * `duration` - duration
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+    else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
 
-    // 1. multiple top-level queries starting from here...
-    let name_f = web3_query_no_params::<String>(&contract, "name");
-    let decimals_f = web3_query_no_params::<u8>(&contract, "decimals");
+/// Collect approvals of all wallets, then query their allowance balances.
+/// Approvals are collected one wallet at a time to stay within explorer's
+/// rate limit, then all wallets are queried concurrently. Exit the process
+/// on error.
+///
+/// Progress of each wallet is saved into its checkpoint as it goes, so that
+/// it can be continued with `resume` if the scan doesn't complete. It's
+/// removed once the scan completes without transient errors.
+///
+/// On Ctrl-C, it stops, and returns results completed so far; see
+/// `interrupt::is_interrupted()`.
+///
+/// Return results of each wallet in the same order as `owner_addresses`.
+///
+/// # Arguments
+/// * `session` - session
+/// * `owner_addresses` - owner wallet addresses; lowercased
+/// * `full_rescan` - whether to ignore approvals collected by previous scans
+/// * `resume` - whether to continue from checkpoint of each wallet if any
+/// * `estimate` - whether to print estimated cost of querying, and ask whether
+///   to proceed once approvals are collected
+/// * `show_progress` - whether to report progress to stderr
+async fn scan(session: &mut Session, owner_addresses: &[String], full_rescan: bool, resume: bool, estimate: bool, show_progress: bool) -> Vec<Vec<QueryResult>> {
+    // report progress as explorer pages are fetched, and each token contract
+    // completes, so long scans don't look like a hang
+    let show_progress = show_progress && std::io::stderr().is_terminal();
+    interrupt::install();
 
-    let (name, decimals) = futures::join!(name_f, decimals_f);
+    let mut checkpoints: Vec<checkpoint::Checkpoint> = owner_addresses.iter()
+        .map(|owner_address| {
+            if !resume {
+                return Default::default();
+            }
+            match checkpoint::load_checkpoint(session.chain, owner_address) {
+                Ok(Some(res)) if res.at_block == session.at_block => res,
+                Ok(Some(_)) => {
+                    tracing::warn!("Checkpoint of {} was made with different --at-block; ignoring it", to_checksum_address(owner_address));
+                    Default::default()
+                },
+                Ok(None) => Default::default(),
+                Err(e) => {
+                    tracing::warn!("{}; ignoring it", e);
+                    Default::default()
+                }
+            }
+        })
+        .collect();
+    let mut approvals: Vec<ApprovalsMap> = owner_addresses.iter().zip(checkpoints.iter_mut())
+        .map(|(owner_address, checkpoint)| {
+            let _span = tracing::info_span!("collect", wallet = owner_address.as_str()).entered();
+            collect(session, owner_address, full_rescan, checkpoint, show_progress)
+        })
+        .collect();
 
-    if name.is_err() {
-        let err_msg = format!("Error in querying top-level query (name); err={}", name.unwrap_err());
-        return Err( (contract_address, err_msg) );
+    // token contracts queried before are not queried again
+    let resumed: Vec<Vec<QueryResult>> = checkpoints.iter().zip(approvals.iter_mut())
+        .map(|(checkpoint, ct_txs)| checkpoint.take_results(ct_txs))
+        .collect();
+    for (owner_address, resumed) in owner_addresses.iter().zip(resumed.iter()).filter(|(_, r)| !r.is_empty()) {
+        tracing::info!("Resuming {} with {} token contracts queried before", to_checksum_address(owner_address), resumed.len());
     }
-    if decimals.is_err() {
-        let err_msg = format!("Error in querying top-level query (decimals); err={}", decimals.unwrap_err());
-        return Err( (contract_address, err_msg) );
+
+    // explorer is not metered the way RPC endpoints are, so only querying is estimated
+    if estimate {
+        confirm_estimate(session, &approvals).await;
     }
 
-    let mut result_struct = TokenContractWithSpenderAllowances {
-        name: name.unwrap(),
-        address: contract_address.to_owned(),
-        decimals: decimals.unwrap(),
-        spender_allowances: HashMap::new(),
+    let num_tokens: usize = approvals.iter().map(|ct_txs| ct_txs.len()).sum();
+    let bar = progress::query_bar(show_progress, num_tokens);
+    let rpc_calls_before = session.web3.transport().stats().rpc_calls;
+
+    // each wallet queries with its own copy of caches, which are merged back once all complete.
+    // results are also kept as they complete, so they are still there if interrupted.
+    let completed: Vec<std::sync::Mutex<Vec<QueryResult>>> = resumed.into_iter().map(std::sync::Mutex::new).collect();
+    let session_ref = &*session;
+    let futs = owner_addresses.iter().zip(approvals).zip(completed.iter()).map(|((owner_address, ct_txs), completed)| {
+        let mut metadata_cache = session_ref.metadata_cache.detached();
+        let mut failed_contracts = session_ref.failed_contracts.detached();
+        let bar = &bar;
+        let span = tracing::info_span!("query", wallet = owner_address.as_str(), tokens = ct_txs.len());
+        async move {
+            let rpc = AtBlock { inner: &session_ref.web3, block: session_ref.at_block };
+            query_all(&rpc, &session_ref.semaphore, ct_txs, owner_address, session_ref.use_multicall, &mut metadata_cache, &mut failed_contracts, |res| {
+                if let Ok(mut completed) = completed.lock() {
+                    completed.push(res.clone());
+                    if completed.len() % checkpoint::CHECKPOINT_INTERVAL == 0 {
+                        let checkpoint = checkpoint::Checkpoint::from_results(session_ref.at_block, &completed);
+                        if let Err(e) = checkpoint::save_checkpoint(session_ref.chain, owner_address, &checkpoint) {
+                            tracing::warn!("{}", e);
+                        }
+                    }
+                }
+                bar.set_message(format!("{} RPC calls", session_ref.web3.transport().stats().rpc_calls - rpc_calls_before));
+                bar.inc(1);
+            }).await
+        }.instrument(span)
+    });
+    let is_finished = tokio::select! {
+        _ = futures::future::join_all(futs) => true,
+        _ = interrupt::interrupted() => false,
     };
+    bar.finish_and_clear();
 
-    // 2. spender' allowances
-    // make query to get current allowanced balance
-    for spender in spenders {
-        let allowance_balance_res = query_allowance_balance(&contract, &owner_address.to_owned(), &spender).await;
+    let results: Vec<Vec<QueryResult>> = completed.into_iter().map(|c| c.into_inner().unwrap_or_default()).collect();
+    for ((owner_address, results), checkpoint) in owner_addresses.iter().zip(results.iter()).zip(checkpoints) {
+        let has_transient_errors = results.iter().any(|res| matches!(res, Err(e) if e.error.is_transient()));
+        if is_finished && !has_transient_errors && checkpoint.collected.is_none() {
+            checkpoint::remove_checkpoint(session.chain, owner_address);
+            continue;
+        }
 
-        // check back results
-        let allowance_balance = match allowance_balance_res {
-            Ok(res) => res,
-            Err(e) => {
-                let err_msg = format!("Error querying for allowance balance for contract-addr={}, owner-addr={}, spender-addr={}; err={}", contract_address, owner_address, &spender, e);
-                return Err( (contract_address, err_msg) );
-            }
+        let checkpoint = checkpoint::Checkpoint {
+            collected: checkpoint.collected,
+            ..checkpoint::Checkpoint::from_results(session.at_block, results)
         };
+        match checkpoint::save_checkpoint(session.chain, owner_address, &checkpoint) {
+            Ok(path) => eprintln!("Saved {} token contracts queried so far of {} into checkpoint ({}); use --resume to continue", checkpoint.tokens.len(), to_checksum_address(owner_address), path),
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+
+    let all_results: Vec<QueryResult> = results.iter().flatten().cloned().collect();
+    session.metadata_cache.update(&all_results);
+    if let Err(e) = session.metadata_cache.save() {
+        tracing::warn!("{}", e);
+    }
+    session.failed_contracts.update(&all_results);
+    if let Err(e) = session.failed_contracts.save() {
+        tracing::warn!("{}", e);
+    }
+
+    results
+}
 
-        // floating-point ready representation for U256
-        let allowance_bal_fp = match BSCU256::from_dec_str(&allowance_balance.to_string()) {
+/// Exit the process if scan was interrupted by Ctrl-C; for subcommands whose
+/// output would be misleading if partial.
+fn exit_if_interrupted() {
+    if interrupt::is_interrupted() {
+        eprintln!("Scan was interrupted; nothing is output as results are partial");
+        std::process::exit(interrupt::EXIT_INTERRUPTED);
+    }
+}
+
+/// Everything needed to assess risk of approvals, set up from risk options.
+pub struct RiskContext {
+    /// Known-malicious address database
+    pub malicious_db: malicious::MaliciousDb,
+
+    /// Exploited-contract dataset of the chain
+    pub exploit_db: exploits::ExploitDb,
+
+    /// Options for risk assessment
+    pub opts: risk::RiskOptions,
+
+    /// Cache of `eth_getCode` results of spenders
+    pub code_cache: codecache::CodeCache,
+
+    /// Cache of creation records of spender contracts
+    pub creation_cache: creationcache::CreationCache,
+
+    /// External analyzers to run after built-in assessment
+    pub analyzers: analyzer::AnalyzerRegistry,
+}
+
+impl RiskContext {
+    /// Set up risk assessment of the specified chain from risk options. Exit
+    /// the process on error.
+    ///
+    /// # Arguments
+    /// * `chain` - chain type
+    /// * `risk_args` - options of risk assessment
+    pub fn load(chain: ChainType, risk_args: &RiskArgs) -> RiskContext {
+        let mut malicious_sources = risk_args.malicious_lists.to_owned();
+        if !risk_args.no_default_malicious_list {
+            malicious_sources.insert(0, malicious::SCAMSNIFFER_ADDRESS_LIST_URL.to_owned());
+        }
+        let exploit_db = match exploits::ExploitDb::load(chain, risk_args.exploits_file.as_deref()) {
             Ok(res) => res,
             Err(e) => {
-                let err_msg = format!("Error converting from web3::types::U256 to bscscan::prelude::U256 for floating-point representation ability; err={}", e);
-                return Err( (contract_address, err_msg) );
+                eprintln!("{}", e);
+                std::process::exit(1);
             }
         };
+        let mut analyzers = analyzer::AnalyzerRegistry::default();
+        for path in risk_args.analyzers.iter() {
+            analyzers.register(Box::new(analyzer::CommandAnalyzer::new(path)));
+        }
 
-        result_struct.spender_allowances.insert(spender.to_owned(), allowance_bal_fp.to_f64_lossy() / 10_f64.powf(result_struct.decimals.into()));
+        RiskContext {
+            malicious_db: malicious::MaliciousDb::load(&malicious_sources),
+            exploit_db,
+            opts: risk::RiskOptions {
+                check_verification: !risk_args.skip_verification_check,
+                stale_days: risk_args.stale_days,
+                check_activity: !risk_args.skip_activity_check,
+                dormant_days: risk_args.dormant_days,
+                check_creation: !risk_args.skip_creation_check,
+            },
+            code_cache: codecache::CodeCache::load(chain, std::time::Duration::from_secs(risk_args.code_cache_ttl * 60 * 60)),
+            creation_cache: creationcache::CreationCache::load(chain, true),
+            analyzers,
+        }
     }
 
-    Ok(result_struct)
+    /// Assess risk of all approvals in the specified results by built-in
+    /// assessment, then by external analyzers.
+    ///
+    /// # Arguments
+    /// * `web3` - web3 instance
+    /// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+    /// * `ctx` - evmscan's context
+    /// * `owner_address` - owner wallet address; lowercased
+    /// * `labels` - labels of well-known addresses
+    /// * `prices` - USD price of tokens keyed by lowercased address; value at risk isn't scored if `None`
+    /// * `results` - results of the wallet from `query_all()`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn assess(&mut self, web3: &Web3Type, semaphore: &Semaphore, ctx: &Context, owner_address: &str, labels: &Labels, prices: Option<&HashMap<String, f64>>, results: &mut [QueryResult]) {
+        let risk_sources = risk::RiskSources {
+            malicious_db: &self.malicious_db,
+            exploit_db: &self.exploit_db,
+            labels,
+            prices,
+        };
+        risk::assess_all(web3, semaphore, ctx, results, &risk_sources, &self.opts, &mut self.code_cache, &mut self.creation_cache).await;
+
+        let analysis_ctx = analyzer::AnalysisContext {
+            chain: ctx.chain,
+            owner_address,
+            labels,
+        };
+        self.analyzers.run(&analysis_ctx, results);
+    }
 }
 
-/// Select and return api key for selected chain type.
-/// The program needs environment variables as follows to be defined to cover
-/// all API platforms which one of them will be used at runtime depending on
-/// which chain has been selected.
+/// Query current USD price of the specified tokens with timeout, and proxy of
+/// common options applied. Return `None` with warning on error, or without
+/// querying if replaying as prices are not part of recorded traffic.
 ///
-/// * `bsc` - require environment variable `TRACPLS_BSCSCAN_APIKEY`
-/// * `ethereum` - require environment variable `TRACPLS_ETHERSCAN_APIKEY`
-/// * `polygon` - require environment variable `TRACPLS_POLYGONSCAN_APIKEY`
-///
-/// If such environment variable after selected has not defined yet, then
-/// this function will panic.
+/// # Arguments
+/// * `session` - session
+/// * `token_addresses` - token contract addresses
+/// * `consequence` - what is left out without prices e.g. `value at risk is not scored`
+async fn fetch_prices(session: &Session, token_addresses: &[String], consequence: &str) -> Option<HashMap<String, f64>> {
+    if replay::is_replaying() {
+        tracing::info!("Token prices are not queried while replaying; {}", consequence);
+        return None;
+    }
+    match prices::fetch_usd_prices(&session.http_client, session.chain, token_addresses).await {
+        Ok(res) => Some(res),
+        Err(e) => {
+            tracing::warn!("Error querying token prices; {}; err={}", consequence, e);
+            None
+        },
+    }
+}
+
+/// Assess risk of all approvals in the specified results. Exit the process
+/// on error.
+/// Approvals to well-known burn addresses are removed first unless
+/// `--show-burn`, as they are harmless noise.
+/// Unless `--skip-value-check`, balance of owner is queried into the results,
+/// and current prices are fetched to score value at risk.
 ///
 /// # Arguments
-/// * `chain` - chain type
-fn select_apikey(chain: ChainType) -> String {
-    match chain {
-        ChainType::BSC => std::env::var("APPVKEK_BSCSCAN_APIKEY").expect("Required environment variable 'APPVKEK_BSCSCAN_APIKEY' to be defined"),
-        ChainType::Ethereum => std::env::var("APPVKEK_ETHERSCAN_APIKEY").expect("Required environment variable 'APPVKEK_ETHERSCAN_APIKEY' to be defined"),
-        ChainType::Polygon => std::env::var("APPVKEK_POLYGONSCAN_APIKEY").expect("Required environment variable 'APPVKEK_POLYGONSCAN_APIKEY' to be defined"),
+/// * `session` - session
+/// * `owner_address` - owner wallet address; lowercased
+/// * `risk_args` - options of risk assessment
+/// * `results` - results of the wallet from `scan()`
+#[tracing::instrument(skip_all, fields(wallet = owner_address))]
+async fn assess(session: &mut Session, owner_address: &str, risk_args: &RiskArgs, results: &mut Vec<QueryResult>) {
+    if !risk_args.show_burn {
+        let num_removed = remove_burn_approvals(results);
+        if num_removed > 0 {
+            tracing::info!("Hid {} approvals to burn addresses of {}; use --show-burn to show them", num_removed, to_checksum_address(owner_address));
+        }
+    }
+
+    if matches!(session.chain, ChainType::Ethereum) && !risk_args.skip_ens_lookup {
+        let mut spenders: Vec<String> = results.iter()
+            .filter_map(|res| res.as_ref().ok())
+            .flat_map(|r| r.spender_allowances.keys())
+            .filter(|spender| session.labels.ens_name(spender).is_none())
+            .cloned()
+            .collect();
+        spenders.sort();
+        spenders.dedup();
+        let names = ens::lookup_addresses(&session.web3, &session.semaphore, &spenders).await;
+        session.labels.add_ens_names(names);
+    }
+
+    // value at risk needs balance of owner, and price of each token
+    let mut prices: Option<HashMap<String, f64>> = None;
+    if !risk_args.skip_value_check {
+        let rpc = AtBlock { inner: &session.web3, block: session.at_block };
+        if let Err(e) = query_balance_and_supply(&rpc, &session.semaphore, results, owner_address, session.use_multicall).await {
+            tracing::warn!("Error querying balance and total supply; err={}", e);
+        }
+        let token_addresses: Vec<String> = results.iter().filter_map(|res| res.as_ref().ok()).map(|r| r.address.to_lowercase()).collect();
+        prices = fetch_prices(session, &token_addresses, "value at risk is not scored").await;
     }
+
+    let mut risk_ctx = RiskContext::load(session.chain, risk_args);
+    risk_ctx.assess(&session.web3, &session.semaphore, &session.ctx, owner_address, &session.labels, prices.as_ref(), results).await;
 }
 
 #[tokio::main]
 async fn main() {
     let cmd_args = CommandlineArgs::parse();
 
-    // validate value of chain flag option
-    let chain_value = cmd_args.chain.to_lowercase();
-    let chain: Option<ChainType>;
-    if chain_value == "bsc" {
-        chain = Some(ChainType::BSC);
-    }
-    else if chain_value == "ethereum" {
-        chain = Some(ChainType::Ethereum);
-    }
-    else if chain_value == "polygon" {
-        chain = Some(ChainType::Polygon);
+    // completion script doesn't depend on environment, nor config file
+    if let Command::Completions(args) = &cmd_args.command {
+        clap_complete::generate(args.shell, &mut CommandlineArgs::command(), "appvkek", &mut std::io::stdout());
+        return;
     }
-    else {
-        eprintln!("Error invalid value for --chain.
-Possible values are 'bsc', 'ethereum', or 'polygon'.");
+
+    if let Err(e) = logging::init(cmd_args.verbose, cmd_args.quiet, cmd_args.log_file.as_deref(), cmd_args.otel_endpoint.as_deref()) {
+        eprintln!("{}", e);
         std::process::exit(1);
     }
+    let show_progress = !cmd_args.quiet;
+    timestamp::set_utc(cmd_args.utc);
+    amount::set_human(cmd_args.human);
+    apikey::load_dotenv();
 
-    let web3 = create_web3(chain.unwrap()); 
-    // check if input address is in correct format, and is actually EOA
-    match perform_check_is_eoa(&web3, &cmd_args.address).await {
-        Ok(is_eoa) => {
-            if !is_eoa {
-                eprintln!("Error input address is not EOA");
-                std::process::exit(1);
-            }
-        },
+    let profile = match config::load_profile(cmd_args.config.as_deref(), cmd_args.profile.as_deref()) {
+        Ok(res) => res,
         Err(e) => {
             eprintln!("{}", e);
             std::process::exit(1);
         }
-    }
+    };
 
-    let ctx = Context::create(chain.unwrap(), select_apikey(chain.unwrap()));
-    let accounts = evmscan::accounts();
+    // scan prints it before exiting with status of its own; bench, and doctor
+    // report requests on their own
+    let is_usage_printed = !cmd_args.quiet && !matches!(cmd_args.command, Command::Scan(_) | Command::Bench(_) | Command::Doctor(_));
 
-    // HashMap for token contract to HashMap of spender addresses
-    type DummyType = u8;
-    const DUMMY_VALUE: DummyType = 0;
-    let mut ct_txs: HashMap<String, HashMap<String, DummyType>> = HashMap::new();
-    // make sure to make it lowercased.
-    let owner_address = cmd_args.address.to_lowercase();
+    match cmd_args.command {
+        Command::Scan(mut args) => {
+            infer_chain(&mut args.common, &args.addresses);
+            profile.apply_common(&mut args.common);
+            profile.apply_risk(&mut args.risk);
+            let fail_on = match args.fail_on.as_deref().map(FailOn::parse) {
+                Some(Ok(res)) => Some(res),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+                None => None,
+            };
+            let links = parse_links(args.links.as_deref());
 
-    #[allow(dead_code)]
-    let mut start_time = std::time::Instant::now();
-    if cmd_args.execution_time {
-        measure_start(&mut start_time);
-    }
+            let mut scan_db = match args.db.as_deref().map(db::ScanDb::open) {
+                Some(Ok(res)) => Some(res),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+                None => None,
+            };
+            let previous = match args.diff_with.as_deref().map(snapshot::load_snapshot) {
+                Some(Ok(res)) => Some(res),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+                None => None,
+            };
 
-    // get all transactions
-    match accounts.get_list_normal_transactions(&ctx, &owner_address) {
-        Ok(txs) => {
-            for tx in txs {
-                // 0x095ea7b3 is method-id for approve method
-                if &tx.from == &owner_address && !tx.is_error && tx.input.starts_with("0x095ea7b3") {
-                    if !ct_txs.contains_key(&tx.to) {
-                        ct_txs.insert(tx.to.to_owned(), HashMap::new());
+            // derived wallets replace the default wallet of profile
+            let derived = derive_wallets(&args);
+            let inputs = if derived.is_empty() {
+                require_addresses(profile.wallet_addresses(std::mem::take(&mut args.addresses)))
+            }
+            else {
+                args.addresses.iter().map(|a| normalize_address(a)).collect()
+            };
+            let (mut addresses, mut names) = resolve_wallets(&mut args.common, inputs).await;
+            for d in derived {
+                if !addresses.contains(&d.address) {
+                    addresses.push(d.address.to_owned());
+                }
+                names.insert(d.address, format!("#{} {}", d.index, d.path));
+            }
+            let mut session = setup(&args.common, &profile.token_overrides, &addresses).await;
+            set_at_block(&mut session, args.at_block);
+            set_imported(&mut session, args.import.as_deref());
+
+            #[allow(unused_assignments)]
+            let mut start_time = std::time::Instant::now();
+            if args.common.execution_time {
+                measure_start(&mut start_time);
+            }
+
+            let mut wallet_results = scan(&mut session, &addresses, args.full_rescan, args.resume, args.estimate, show_progress).await;
+            for (address, results) in addresses.iter().zip(wallet_results.iter_mut()) {
+                assess(&mut session, address, &args.risk, results).await;
+                // balances have been queried by risk assessment unless it skipped valuing
+                if (args.percent || args.by_spender) && args.risk.skip_value_check {
+                    let rpc = AtBlock { inner: &session.web3, block: session.at_block };
+                    if let Err(e) = query_balance_and_supply(&rpc, &session.semaphore, results, address, session.use_multicall).await {
+                        tracing::warn!("Error querying balance and total supply; err={}", e);
                     }
+                }
+            }
 
-                    // get the spender from the first argument
-                    let arguments = match parse_256_method_arguments(&tx.input) {
-                        Ok(res) => {
-                            // it should contains at least 2 elements
-                            // method-id, spender, and amount for approve() method
-                            if res.len() < 2 {
-                                eprintln!("Error parsing arguments for hex-string from approve() method call.
-It should contain at least three arguments for approve() method signature.");
-                                std::process::exit(1);
-                            }
+            let reports: Vec<report::Report> = addresses.iter().zip(wallet_results.iter())
+                .map(|(address, results)| report::Report {
+                    at_block: session.at_block,
+                    delegation: session.delegations.get(address).map(|d| to_checksum_address(d)),
+                    ..report::build_report(session.chain, address, results, &session.labels)
+                })
+                .collect();
+            if let Some(block) = session.at_block {
+                println!("Allowances as of block {}", block);
+            }
+            // partial results would show approvals not yet queried as removed
+            let is_interrupted = interrupt::is_interrupted();
+            if let Some(previous) = previous.filter(|_| !is_interrupted) {
+                snapshot::print_diff(&report::diff_reports(&previous, &reports), addresses.len() > 1, false);
+            }
+            let token_prices = if args.by_spender {
+                let token_addresses: Vec<String> = wallet_results.iter()
+                    .flat_map(|results| results.iter().filter_map(|res| res.as_ref().ok()))
+                    .map(|r| r.address.to_lowercase())
+                    .collect::<HashSet<String>>()
+                    .into_iter()
+                    .collect();
+                fetch_prices(&session, &token_addresses, "exposure is left out").await
+            }
+            else {
+                None
+            };
+            for (i, (address, results)) in addresses.iter().zip(wallet_results.iter()).enumerate() {
+                if args.diff_with.is_some() && !is_interrupted {
+                    break;
+                }
 
-                            res
-                        },
-                        Err(e) => {
-                            eprintln!("Error parsing arguments of {}; err={}", tx.to, e);
-                            std::process::exit(1);
-                        }
+                // group report by wallet only when there are many of them, or it's named
+                if addresses.len() > 1 || names.contains_key(address) {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("=== Wallet {} ===", format_wallet(address, &names, &session.labels));
+                }
+                if let Some(delegate) = session.delegations.get(address) {
+                    println!("EIP-7702 delegation: {}", session.labels.format(delegate));
+                }
+                if args.by_protocol || args.by_spender {
+                    let unknown_spenders: HashSet<String> = results.iter()
+                        .filter_map(|res| res.as_ref().ok())
+                        .flat_map(|r| r.spender_allowances.iter())
+                        .filter(|(spender, sa)| risk::is_unknown_spender(&session.labels, spender, sa))
+                        .map(|(spender, _)| spender.to_lowercase())
+                        .collect();
+                    let report = report::Report {
+                        approvals: reports[i].approvals.iter()
+                            .filter(|a| a.risk_score >= args.min_risk && (!args.unknown_only || unknown_spenders.contains(&a.spender.to_lowercase())))
+                            .cloned()
+                            .collect(),
+                        ..reports[i].clone()
                     };
+                    if args.by_protocol {
+                        snapshot::print_protocol_exposures(&report::aggregate_by_protocol(&[report]), false);
+                    }
+                    else {
+                        let balances: HashMap<String, f64> = results.iter()
+                            .filter_map(|res| res.as_ref().ok())
+                            .filter_map(|r| r.balance.map(|balance| (r.address.to_lowercase(), balance)))
+                            .collect();
+                        let summaries = report::summarize_by_spender(&report, &balances, token_prices.as_ref().unwrap_or(&HashMap::new()));
+                        snapshot::print_spender_summaries(&summaries, token_prices.is_some());
+                    }
+                }
+                else {
+                    print_report(results, session.chain, address, args.min_risk, args.unknown_only, &session.labels, links, args.percent);
+                }
+            }
+            print_problems(&session, &addresses, &wallet_results);
 
-                    // cleanup first argument to get address (64 chars to 40 chars
-                    // by remove first 24 chars)
-                    let mut spender_addr = arguments[0][24..].to_owned();
-                    spender_addr.insert_str(0, "0x");
+            if !cmd_args.quiet {
+                print_usage();
+            }
+            // evaluated on partial results too, as a match is conclusive regardless
+            let fail_on_code = fail_on.and_then(|fail_on| {
+                let is_matched = wallet_results.iter()
+                    .flatten()
+                    .filter_map(|res| res.as_ref().ok())
+                    .flat_map(|r| r.spender_allowances.values())
+                    .any(|sa| fail_on.matches(sa));
+                let has_errors = wallet_results.iter().flatten().any(|res| res.is_err());
+                failon::exit_code(is_matched, !has_errors && !is_interrupted)
+            });
+            if is_interrupted {
+                eprintln!("Report is partial as the scan was interrupted");
+                std::process::exit(fail_on_code.unwrap_or(interrupt::EXIT_INTERRUPTED));
+            }
 
-                    if let Some(val_hashmap) = ct_txs.get_mut(&tx.to) {
-                        // use index-0 as it is spender address
-                        if !(*val_hashmap).contains_key(&spender_addr) {
-                            (*val_hashmap).insert(spender_addr, DUMMY_VALUE);
-                        }
+            if let Some(scan_db) = scan_db.as_mut() {
+                for report in reports.iter() {
+                    if let Err(e) = scan_db.record(report) {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
                     }
                 }
             }
+
+            if let Some(path) = &args.snapshot {
+                if let Err(e) = snapshot::save_snapshot(path, reports) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            if args.common.execution_time {
+                measure_end(&start_time, true);
+            }
+
+            if let Some(code) = fail_on_code {
+                logging::shutdown();
+                std::process::exit(code);
+            }
         },
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
-        }
-    }
+        Command::Watch(mut args) => {
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            profile.apply_risk(&mut args.risk);
+            let (address, _) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let mut session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+            if let Some(ws_url) = args.ws_url.as_deref().filter(|u| !u.starts_with("ws://") && !u.starts_with("wss://")) {
+                eprintln!("Error --ws-url has to start with ws:// or wss://; url={}", ws_url);
+                std::process::exit(1);
+            }
+            let watch_opts = watch::WatchOptions {
+                interval: std::time::Duration::from_secs(args.interval),
+                webhook_url: args.webhook_url.to_owned(),
+                use_multicall: session.use_multicall,
+                use_logs: session.contract_wallets.contains(&address),
+                threshold: args.webhook_threshold,
+                min_risk: args.webhook_min_risk,
+                show_burn: args.risk.show_burn,
+                labels: std::mem::take(&mut session.labels),
+                state_file: if args.daemon {
+                    Some(args.state_file.to_owned().unwrap_or_else(|| state::default_state_file_path(session.chain, &address)))
+                }
+                else {
+                    None
+                },
+                db: args.db.to_owned(),
+                fail_fast: args.common.fail_fast,
+                ws_url: args.ws_url.to_owned(),
+                print_replacement_tx: args.replacement_tx,
+                http_client: session.http_client.clone(),
+            };
+            let mut risk_ctx = RiskContext::load(session.chain, &args.risk);
+            watch::run(&session.web3, &session.semaphore, &session.ctx, &address, &watch_opts, &mut session.metadata_cache, &mut session.failed_contracts, &mut risk_ctx).await;
+        },
+        Command::Revoke(mut args) => {
+            if args.copy && !std::io::stdin().is_terminal() {
+                eprintln!("Error --copy requires stdin to be a terminal to step through transactions");
+                std::process::exit(1);
+            }
+            let fee_preset = match args.fee_preset.as_deref().map(FeePreset::parse).transpose() {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
 
-    // to avoid rate limit, this number would change if use different public node
-    // experimentation, or consulting document for rate limit is needed
-    const RPC_RATE_LIMIT: usize = 2000;
-    let num_outputs_array = (ct_txs.len() as f64 / RPC_RATE_LIMIT as f64).ceil() as usize;
-    let mut running_added_item = 0;
-    
-    // convert HashMap into Vec of tuple
-    let ct_txs_vec = Vec::from_iter(ct_txs.into_iter().map(|(key,val)| (key,val)));
+            // queue is executed without scanning, for its wallet on its chain
+            let mut execute_queue: Option<queue::RevokeQueue> = None;
+            if args.execute {
+                let path = args.queue.as_deref().unwrap_or_default();
+                let queue = match queue::load_queue(path) {
+                    Ok(Some(res)) => res,
+                    Ok(None) => {
+                        eprintln!("Error queue ({}) doesn't exist; create it via revoke --queue first", path);
+                        std::process::exit(1);
+                    },
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+                args.common.chain.get_or_insert_with(|| queue.chain.to_owned());
+                args.address.get_or_insert_with(|| queue.owner.to_owned());
+                execute_queue = Some(queue);
+            }
 
-    for i in 0..num_outputs_array {
-        let mut outputs = Vec::with_capacity(RPC_RATE_LIMIT);
-    
-        // collect items for each chunk
-        while running_added_item < RPC_RATE_LIMIT && i * RPC_RATE_LIMIT + running_added_item < ct_txs_vec.len() {
-            let (ct, spenders) = &ct_txs_vec[i * RPC_RATE_LIMIT + running_added_item];
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            profile.apply_risk(&mut args.risk);
+            let (address, names) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let mut session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
 
-            let spenders_collected = spenders.clone().into_keys().collect::<Vec::<String>>();
+            let (results, filter) = match execute_queue {
+                Some(mut queue) => {
+                    let path = args.queue.as_deref().unwrap_or_default();
+                    if queue.owner != address || queue.chain != chain_name(session.chain) {
+                        eprintln!("Error queue ({}) is of {} on {}; not {} on {}", path, to_checksum_address(&queue.owner), queue.chain, to_checksum_address(&address), chain_name(session.chain));
+                        std::process::exit(1);
+                    }
 
-            outputs.push(query(&web3, ct.to_owned(), owner_address.to_owned(), spenders_collected));
-            running_added_item = running_added_item + 1;
-        }
+                    let mut results = query_all(&session.web3, &session.semaphore, queue.pending_approvals(), &address, session.use_multicall, &mut session.metadata_cache, &mut session.failed_contracts, |_| ()).await;
+                    for e in results.iter().filter_map(|res| res.as_ref().err()) {
+                        tracing::warn!("Error querying {}; its revokes are left pending; err={}", to_checksum_address(&e.token_address), e.error);
+                    }
+                    let num_revoked = queue.update(&mut results);
+                    if let Err(e) = queue::save_queue(path, &queue) {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    eprintln!("{} of {} revokes in queue ({}) are done; {} found revoked since the last run", queue.count(queue::QueueStatus::Revoked), queue.items.len(), path, num_revoked);
 
-        // async and wait
-        let results = futures::future::join_all(outputs).await;
-        for res in results {
-            match res {
-                Ok(r) => {
-                    println!("[{}] {}", r.name, r.address);
-                    for (spender, allowance) in r.spender_allowances {
-                        println!("  * {} - {}", spender, allowance);
+                    // revokes in queue were selected when they were queued
+                    let filter = revoke::RevokeFilter {
+                        tokens: Vec::new(),
+                        spenders: Vec::new(),
+                        min_risk: 0,
+                    };
+                    (results, filter)
+                },
+                None => {
+                    let mut results = scan(&mut session, std::slice::from_ref(&address), args.full_rescan, args.resume, args.estimate, show_progress).await.remove(0);
+                    exit_if_interrupted();
+                    assess(&mut session, &address, &args.risk, &mut results).await;
+
+                    let filter = revoke::RevokeFilter {
+                        tokens: args.tokens.iter().map(|t| strip_chain_prefix(&mut args.common, &normalize_address(t))).collect(),
+                        spenders: args.spenders.iter().map(|s| strip_chain_prefix(&mut args.common, &normalize_address(s))).collect(),
+                        min_risk: args.min_risk,
+                    };
+                    (results, filter)
+                }
+            };
+
+            if let (Some(path), false) = (args.queue.as_deref(), args.execute) {
+                print_warnings(&session);
+                let res = queue::load_or_create_queue(path, session.chain, &address)
+                    .and_then(|mut queue| {
+                        let num_added = queue.add(&results, &filter);
+                        queue::save_queue(path, &queue)?;
+                        Ok((num_added, queue.count(queue::QueueStatus::Pending)))
+                    });
+                match res {
+                    Ok((num_added, num_pending)) => println!("Queued {} approvals of {} into {}; {} pending in total, execute them via revoke --queue {} --execute", num_added, format_wallet(&address, &names, &session.labels), path, num_pending, path),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            else {
+                let tokens = revoke::tokens_to_revoke(&results, &filter);
+                let fees = match fee_preset {
+                    Some(preset) => match suggest_fees(&session.web3, preset).await {
+                        Ok(res) => Some(res),
+                        Err(e) => {
+                            eprintln!("{}; chain may not support EIP-1559 fees", e);
+                            std::process::exit(1);
+                        }
+                    },
+                    None => None,
+                };
+                let mut preview = revoke::build_preview(&session.web3, &session.semaphore, session.chain, &results, &filter, &address, fees).await;
+                if args.nonces {
+                    match appvkek_core::revoke::plan_nonces(&session.web3, &address, args.replace).await {
+                        Ok(res) => preview.nonces = Some(res),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
                     }
+                }
+                let gasless = match args.gasless {
+                    true => Some(revoke::GaslessRevoke {
+                        chain: session.chain,
+                        owner_address: address.to_owned(),
+                        permits: appvkek_core::revoke::query_permit_domains(&session.web3, &session.semaphore, &tokens, &address, chain_id(session.chain)).await,
+                        deadline: unix_timestamp() + args.permit_ttl * 60,
+                    }),
+                    false => None,
+                };
+                print_warnings(&session);
+                let res = revoke::print_revoke_txs(&results, &filter, &format_wallet(&address, &names, &session.labels), &session.labels, &preview, args.yes, gasless.as_ref(), args.qr)
+                    .and_then(|txs| match args.copy {
+                        true => clipboard::copy_revoke_txs(&txs, &session.labels),
+                        false => Ok(()),
+                    });
+                if let Err(e) = res {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+        Command::RelayRevoke(mut args) => {
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            let (address, _) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let (token_address, spender) = (strip_chain_prefix(&mut args.common, &normalize_address(&args.token)), strip_chain_prefix(&mut args.common, &normalize_address(&args.spender)));
+            for input in [&token_address, &spender] {
+                if !validate_address_format(input) {
+                    eprintln!("Error invalid address format ({})", input);
+                    std::process::exit(1);
+                }
+            }
+            if args.deadline <= unix_timestamp() {
+                eprintln!("Error permit has expired at {}; sign a new one via revoke --gasless", timestamp::format_timestamp(args.deadline));
+                std::process::exit(1);
+            }
+            let api_key = match apikey::select_relay_apikey(args.relay_api_key.as_deref()) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+
+            let domain = match appvkek_core::revoke::query_permit_domain(&session.web3, &token_address, &address, chain_id(session.chain)).await {
+                Ok(Some(res)) => res,
+                Ok(None) => {
+                    eprintln!("Error token contract doesn't support EIP-2612 permit ({}); revoke it with transaction instead", to_checksum_address(&token_address));
+                    std::process::exit(1);
+                },
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let tx = match appvkek_core::revoke::build_permit_revoke_tx(&domain, &address, &spender, args.deadline, &args.signature) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            match appvkek_core::relay::submit_gelato_sponsored_call(&session.http_client, chain_id(session.chain), &tx, &api_key).await {
+                Ok(task_id) => {
+                    println!("Submitted revoke of {} on {} to Gelato Relay; task id {}", session.labels.format(&spender), session.labels.format(&token_address), task_id);
+                    println!("  status: {}/{}", appvkek_core::relay::GELATO_TASK_STATUS_URL, task_id);
                 },
                 Err(e) => {
-                    println!("[Error] {} - {}", e.0, e.1);
+                    eprintln!("{}", e);
+                    std::process::exit(1);
                 }
             }
-        }
+        },
+        Command::Export(mut args) => {
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            profile.apply_risk(&mut args.risk);
+            let format = match export::ExportFormat::parse(args.format.as_deref().or(profile.format.as_deref()).unwrap_or("json")) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
 
-        if i * RPC_RATE_LIMIT + running_added_item >= ct_txs_vec.len() {
-            break;
-        }
+            let (address, _) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let mut session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+            set_at_block(&mut session, args.at_block);
+
+            #[allow(unused_assignments)]
+            let mut start_time = std::time::Instant::now();
+            if args.common.execution_time {
+                measure_start(&mut start_time);
+            }
+
+            let mut results = scan(&mut session, std::slice::from_ref(&address), args.full_rescan, args.resume, args.estimate, show_progress).await.remove(0);
+            exit_if_interrupted();
+            assess(&mut session, &address, &args.risk, &mut results).await;
+            // JSON output has them as structured entries instead
+            if format != export::ExportFormat::Json {
+                print_warnings(&session);
+            }
+            let mut token_prices: HashMap<String, f64> = HashMap::new();
+            if format.is_graph() {
+                // balances have been queried by risk assessment unless it skipped valuing
+                if args.risk.skip_value_check {
+                    let rpc = AtBlock { inner: &session.web3, block: session.at_block };
+                    if let Err(e) = query_balance_and_supply(&rpc, &session.semaphore, &mut results, &address, session.use_multicall).await {
+                        tracing::warn!("Error querying balance and total supply; err={}", e);
+                    }
+                }
+                let token_addresses: Vec<String> = results.iter().filter_map(|res| res.as_ref().ok()).map(|r| r.address.to_lowercase()).collect();
+                token_prices = fetch_prices(&session, &token_addresses, "value at risk is left out").await.unwrap_or_default();
+            }
+            if let Err(e) = export::export(&results, format, args.output.as_deref(), &session, &address, &token_prices) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+
+            if args.common.execution_time {
+                // stdout may be the exported content, so don't print into it
+                eprintln!("(elapsed = {:.2} secs)", measure_end(&start_time, false));
+            }
+        },
+        Command::Diff(args) => {
+            let (old, new) = match snapshot::load_snapshot(&args.old).and_then(|old| Ok((old, snapshot::load_snapshot(&args.new)?))) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let is_multi_wallet = old.iter().chain(new.iter()).map(|r| r.owner.to_lowercase()).collect::<HashSet<String>>().len() > 1;
+            snapshot::print_diff(&report::diff_reports(&old, &new), is_multi_wallet, args.chain_prefix);
+        },
+        Command::Aggregate(args) => {
+            let mut reports: Vec<report::Report> = Vec::new();
+            for path in args.snapshots.iter() {
+                match snapshot::load_snapshot(path) {
+                    Ok(res) => reports.extend(res),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            if args.by_protocol {
+                snapshot::print_protocol_exposures(&report::aggregate_by_protocol(&reports), args.chain_prefix);
+            }
+            else {
+                snapshot::print_exposures(&report::aggregate_by_spender(&reports), args.chain_prefix);
+            }
+        },
+        Command::History(mut args) => {
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            let (address, names) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let (token_address, spender) = (strip_chain_prefix(&mut args.common, &normalize_address(&args.token)), strip_chain_prefix(&mut args.common, &normalize_address(&args.spender)));
+            for input in [&token_address, &spender] {
+                if !validate_address_format(input) {
+                    eprintln!("Error invalid address format ({})", input);
+                    std::process::exit(1);
+                }
+            }
+            let session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+
+            let events = match appvkek_core::history::get_allowance_history(&session.ctx, &address, &token_address, &spender) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            // current allowance, and metadata for amounts in token unit
+            let spenders = HashMap::from([(spender.to_owned(), ApprovalRecord::default())]);
+            let token = query(&session.web3, &session.semaphore, token_address.to_owned(), address.to_owned(), spenders, session.metadata_cache.get(&token_address)).await;
+            history::print_history(session.chain, &events, &token, &token_address, &spender, &format_wallet(&address, &names, &session.labels), &session.labels);
+        },
+        Command::Spender(mut args) => {
+            infer_chain(&mut args.common, &args.address);
+            profile.apply_common(&mut args.common);
+            let (address, names) = resolve_wallet(&mut args.common, require_address(profile.wallet_address(args.address))).await;
+            let spender = strip_chain_prefix(&mut args.common, &normalize_address(&args.spender));
+            if !validate_address_format(&spender) {
+                eprintln!("Error invalid address format ({})", spender);
+                std::process::exit(1);
+            }
+            let links = parse_links(args.links.as_deref());
+            let mut session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+
+            // spender may have been approved by means other than approve transaction e.g. permit,
+            // so query it on every token contract the wallet has approved anything on, or has ever held
+            let show_progress = show_progress && std::io::stderr().is_terminal();
+            let ct_txs = collect(&session, &address, false, &mut Default::default(), show_progress);
+            let held_tokens = collect_holdings(&session, &address, false, show_progress);
+            print_warnings(&session);
+            let ct_txs = spender::spender_approvals(&ct_txs, &held_tokens, &spender);
+            let results = query_all(&session.web3, &session.semaphore, ct_txs, &address, session.use_multicall, &mut session.metadata_cache, &mut session.failed_contracts, |_| {}).await;
+            spender::print_spender_allowances(&results, session.chain, &spender, &address, &format_wallet(&address, &names, &session.labels), &session.labels, links);
+        },
+        Command::Doctor(mut args) => {
+            profile.apply_common(&mut args.common);
+            let chain = match args.common.chain.as_deref().map(parse_chain) {
+                Some(Ok(res)) => res,
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+                None => {
+                    eprintln!("Error --chain is required unless `chain` is set in profile");
+                    std::process::exit(1);
+                }
+            };
+            start_debug_rpc(&args.common);
+            configure_explorer(&args.common, chain);
+
+            let web3 = try_create_rpc(&args.common, chain, &args.common.rpc_urls);
+            let checks = doctor::run(chain, args.common.api_key.as_deref(), web3).await;
+            if !doctor::print_checks(&checks) {
+                logging::shutdown();
+                std::process::exit(1);
+            }
+        },
+        Command::RunJobs(mut args) => {
+            profile.apply_common(&mut args.common);
+            profile.apply_risk(&mut args.risk);
+            let jobs_file = match jobs::load_jobs(&args.jobs_file) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            jobs::run_jobs(&jobs_file, &mut args.common, &args.risk, &profile.token_overrides, show_progress).await;
+        },
+        Command::Bench(mut args) => {
+            profile.apply_common(&mut args.common);
+            // bench defaults to fixture wallet
+            let address = profile.wallet_address(args.address).unwrap_or_else(|| bench::FIXTURE_WALLET_ADDRESS.to_owned()).to_lowercase();
+            let session = setup(&args.common, &profile.token_overrides, std::slice::from_ref(&address)).await;
+            bench::run(&session.web3, &session.semaphore, &session.ctx, &address, session.use_multicall).await;
+        },
+        Command::SetApiKey(args) => {
+            let chain = match parse_chain(&args.chain) {
+                Ok(res) => res,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            if std::io::stdin().is_terminal() {
+                eprint!("Enter API key for {}: ", chain_name(chain));
+            }
+            let mut api_key = String::new();
+            if let Err(e) = std::io::stdin().read_line(&mut api_key) {
+                eprintln!("Error reading API key from stdin; err={}", e);
+                std::process::exit(1);
+            }
+            let api_key = api_key.trim();
+            if api_key.is_empty() {
+                eprintln!("Error API key is empty");
+                std::process::exit(1);
+            }
 
-        // reset states
-        running_added_item = 0;
+            if let Err(e) = apikey::set_keyring_apikey(chain, api_key) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            println!("Stored API key for {} into OS keyring", chain_name(chain));
+        },
+        // handled before loading profile
+        Command::Completions(_) => (),
     }
 
-    if cmd_args.execution_time {
-        measure_end(&start_time, true);
+    if is_usage_printed {
+        print_usage();
     }
+    logging::shutdown();
 }
@@ -0,0 +1,124 @@
+use ::evmscan::environ::Context;
+use ::evmscan::types::evm_types::EvmNormalTransactionResponseSuccessVariantResult;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::cell::Cell;
+use std::time::Duration;
+
+use appvkek_core::error::AppvkekError;
+use appvkek_core::explorer::ContractCreation;
+use appvkek_core::provider::ExplorerApi;
+use appvkek_core::types::{EventLog, TokenTransfer};
+
+/// Template of spinner shown while fetching pages from explorer
+static FETCH_TEMPLATE: &str = "{spinner} Fetching history of {prefix}: {pos} pages";
+
+/// Template of progress bar shown while querying token contracts
+static QUERY_TEMPLATE: &str = "{bar:30} {pos}/{len} token contracts, {msg} [{elapsed_precise}, ETA {eta}]";
+
+/// Create spinner counting explorer pages fetched for the specified wallet.
+/// It's hidden if not `enabled`.
+///
+/// # Arguments
+/// * `enabled` - whether to draw it to stderr
+/// * `owner_address` - owner wallet address; for display
+pub fn fetch_spinner(enabled: bool, owner_address: &str) -> ProgressBar {
+    if !enabled {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new_spinner().with_prefix(owner_address.to_owned());
+    if let Ok(style) = ProgressStyle::with_template(FETCH_TEMPLATE) {
+        bar.set_style(style);
+    }
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+/// Create progress bar of the specified number of token contracts to query.
+/// It's hidden if not `enabled`.
+///
+/// # Arguments
+/// * `enabled` - whether to draw it to stderr
+/// * `num_tokens` - total number of token contracts
+pub fn query_bar(enabled: bool, num_tokens: usize) -> ProgressBar {
+    if !enabled || num_tokens == 0 {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(num_tokens as u64);
+    if let Ok(style) = ProgressStyle::with_template(QUERY_TEMPLATE) {
+        bar.set_style(style);
+    }
+    bar.set_message("0 RPC calls");
+    bar
+}
+
+/// Explorer API which advances progress bar on each page fetched, and stops
+/// fetching once interrupted by Ctrl-C.
+pub struct ProgressExplorer<'a> {
+    /// Explorer API to delegate to
+    pub inner: &'a Context,
+
+    /// Progress bar to advance
+    pub bar: &'a ProgressBar,
+
+    /// Highest block number of transactions, event logs, or token transfers
+    /// fetched so far
+    pub last_block: Cell<Option<u64>>,
+}
+
+impl ExplorerApi for ProgressExplorer<'_> {
+    fn txlist_page(&self, address: &str, start_block: u64, page: usize, offset: usize, sort: &str) -> Result<Vec<EvmNormalTransactionResponseSuccessVariantResult>, AppvkekError> {
+        if crate::interrupt::is_interrupted() {
+            return Err(AppvkekError::Explorer("Error fetching transactions; interrupted".to_owned()));
+        }
+        let res = self.inner.txlist_page(address, start_block, page, offset, sort);
+        if let Ok(txs) = &res {
+            self.last_block.set(self.last_block.get().max(txs.iter().map(|tx| tx.block_number).max()));
+        }
+        self.bar.inc(1);
+        res
+    }
+
+    fn approval_logs_page(&self, owner_address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        if crate::interrupt::is_interrupted() {
+            return Err(AppvkekError::Explorer("Error fetching event logs; interrupted".to_owned()));
+        }
+        let res = self.inner.approval_logs_page(owner_address, start_block, page, offset);
+        if let Ok(logs) = &res {
+            self.last_block.set(self.last_block.get().max(logs.iter().map(|log| log.block_number).max()));
+        }
+        self.bar.inc(1);
+        res
+    }
+
+    fn user_operation_logs_page(&self, sender: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<EventLog>, AppvkekError> {
+        if crate::interrupt::is_interrupted() {
+            return Err(AppvkekError::Explorer("Error fetching event logs; interrupted".to_owned()));
+        }
+        let res = self.inner.user_operation_logs_page(sender, start_block, page, offset);
+        if let Ok(logs) = &res {
+            self.last_block.set(self.last_block.get().max(logs.iter().map(|log| log.block_number).max()));
+        }
+        self.bar.inc(1);
+        res
+    }
+
+    fn tokentx_page(&self, address: &str, start_block: u64, page: usize, offset: usize) -> Result<Vec<TokenTransfer>, AppvkekError> {
+        if crate::interrupt::is_interrupted() {
+            return Err(AppvkekError::Explorer("Error fetching token transfers; interrupted".to_owned()));
+        }
+        let res = self.inner.tokentx_page(address, start_block, page, offset);
+        if let Ok(transfers) = &res {
+            self.last_block.set(self.last_block.get().max(transfers.iter().map(|t| t.block_number).max()));
+        }
+        self.bar.inc(1);
+        res
+    }
+
+    fn is_contract_verified(&self, address: &str) -> Result<bool, AppvkekError> {
+        self.inner.is_contract_verified(address)
+    }
+
+    fn contract_creation(&self, address: &str) -> Result<Option<ContractCreation>, AppvkekError> {
+        self.inner.contract_creation(address)
+    }
+}
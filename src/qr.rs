@@ -0,0 +1,20 @@
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+
+/// Render the specified data as QR code to print on terminal; two rows of
+/// modules per line. Colors are inverted for dark background of terminals,
+/// with quiet zone around it, so that it scans as is.
+///
+/// # Arguments
+/// * `data` - data to encode e.g. EIP-681 URI
+pub fn render_qr(data: &str) -> Result<String, String> {
+    let code = match QrCode::new(data.as_bytes()) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error encoding QR code ({}); err={}", data, e)),
+    };
+    Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
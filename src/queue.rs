@@ -0,0 +1,199 @@
+use ::evmscan::prelude::ChainType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use appvkek_core::scanner::format_token_name;
+use appvkek_core::state::{load_state, save_state};
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+
+use crate::revoke::{matching_approvals, RevokeFilter};
+
+/// Status of a revoke in queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    /// Allowance is still non-zero as of the last check
+    Pending,
+
+    /// Allowance has been found to be zero; nothing left to do
+    Revoked,
+}
+
+/// Approval to revoke in queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueItem {
+    /// Token contract address; lowercased
+    pub token_address: String,
+
+    /// Token name for display e.g. `Tether USD (USDT)`
+    pub token_name: String,
+
+    /// Spender address; lowercased
+    pub spender: String,
+
+    /// Risk score of approval when it was queued
+    pub risk_score: u32,
+
+    /// Record of the latest approve transaction when it was queued
+    pub approval: ApprovalRecord,
+
+    /// Status of revoke
+    pub status: QueueStatus,
+
+    /// Unix timestamp (in seconds) when status was last checked, or it was
+    /// queued
+    pub updated_at: u64,
+}
+
+/// Revokes saved via `revoke --queue` to be executed later via `--execute`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeQueue {
+    /// Chain name e.g. `bsc`
+    pub chain: String,
+
+    /// Owner wallet address; lowercased
+    pub owner: String,
+
+    /// Unix timestamp (in seconds) when queue was created
+    pub created_at: u64,
+
+    /// Revokes in the order they were queued
+    pub items: Vec<QueueItem>,
+}
+
+impl RevokeQueue {
+    /// Add all matching approvals with non-zero allowance into queue as
+    /// pending. Those already in queue are left as they are, so that statuses
+    /// tracked so far are kept; unless revoked ones have been approved again
+    /// since, which become pending again.
+    /// Return the number of approvals added.
+    ///
+    /// # Arguments
+    /// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+    /// * `filter` - which approvals to revoke
+    pub fn add(&mut self, results: &[QueryResult], filter: &RevokeFilter) -> usize {
+        let mut approvals = matching_approvals(results, filter);
+        approvals.sort_by_key(|(_, _, sa)| std::cmp::Reverse(sa.risk.score));
+
+        let now = unix_timestamp();
+        let mut num_added = 0;
+        for (r, spender, sa) in approvals {
+            let (token_address, spender) = (r.address.to_lowercase(), spender.to_lowercase());
+            if let Some(item) = self.items.iter_mut().find(|item| item.token_address == token_address && item.spender == spender) {
+                // approved again since it was revoked
+                if item.status == QueueStatus::Revoked && sa.approval.block_number > item.approval.block_number {
+                    item.approval = sa.approval.clone();
+                    item.risk_score = sa.risk.score;
+                    item.status = QueueStatus::Pending;
+                    item.updated_at = now;
+                    num_added += 1;
+                }
+                continue;
+            }
+            self.items.push(QueueItem {
+                token_address,
+                token_name: format_token_name(r),
+                spender,
+                risk_score: sa.risk.score,
+                approval: sa.approval.clone(),
+                status: QueueStatus::Pending,
+                updated_at: now,
+            });
+            num_added += 1;
+        }
+        num_added
+    }
+
+    /// Return approvals of pending revokes to query their current allowance.
+    pub fn pending_approvals(&self) -> ApprovalsMap {
+        let mut approvals: ApprovalsMap = HashMap::new();
+        for item in self.items.iter().filter(|item| item.status == QueueStatus::Pending) {
+            approvals.entry(item.token_address.to_owned()).or_default().insert(item.spender.to_owned(), item.approval.clone());
+        }
+        approvals
+    }
+
+    /// Update status of pending revokes from their current allowance; those
+    /// with zero allowance are marked as revoked. Pending revokes whose token
+    /// contract couldn't be queried are left pending to be checked again on
+    /// the next run.
+    /// Risk score of each queried approval is set to that of its revoke, as
+    /// they are not assessed again.
+    /// Return the number of revokes marked as revoked.
+    ///
+    /// # Arguments
+    /// * `results` - results from `query_all()` of `pending_approvals()`
+    pub fn update(&mut self, results: &mut [QueryResult]) -> usize {
+        let now = unix_timestamp();
+        let mut num_revoked = 0;
+        for item in self.items.iter_mut().filter(|item| item.status == QueueStatus::Pending) {
+            let sa = results.iter_mut()
+                .filter_map(|res| res.as_mut().ok())
+                .find(|r| r.address.to_lowercase() == item.token_address)
+                .and_then(|r| r.spender_allowances.get_mut(&item.spender));
+            let sa = match sa {
+                Some(res) => res,
+                None => continue,
+            };
+
+            sa.risk.score = item.risk_score;
+            item.updated_at = now;
+            if sa.allowance == 0.0 {
+                item.status = QueueStatus::Revoked;
+                num_revoked += 1;
+            }
+        }
+        num_revoked
+    }
+
+    /// Return the number of revokes with the specified status.
+    ///
+    /// # Arguments
+    /// * `status` - status of revoke
+    pub fn count(&self, status: QueueStatus) -> usize {
+        self.items.iter().filter(|item| item.status == status).count()
+    }
+}
+
+/// Load queue from the specified file; `None` if there is none.
+///
+/// # Arguments
+/// * `path` - path to queue file
+pub fn load_queue(path: &str) -> Result<Option<RevokeQueue>, String> {
+    load_state::<RevokeQueue>(path).map_err(|e| e.to_string())
+}
+
+/// Load queue of the specified wallet from the specified file, or create an
+/// empty one if there is none. Error if the file is of another wallet, or
+/// chain, so that revokes of different wallets are not mixed up.
+///
+/// # Arguments
+/// * `path` - path to queue file
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+pub fn load_or_create_queue(path: &str, chain: ChainType, owner_address: &str) -> Result<RevokeQueue, String> {
+    match load_queue(path)? {
+        Some(queue) => {
+            if queue.owner != owner_address || queue.chain != chain_name(chain) {
+                return Err(format!("Error queue ({}) is of {} on {}; use another file for {} on {}", path, to_checksum_address(&queue.owner), queue.chain, to_checksum_address(owner_address), chain_name(chain)));
+            }
+            Ok(queue)
+        },
+        None => Ok(RevokeQueue {
+            chain: chain_name(chain).to_owned(),
+            owner: owner_address.to_owned(),
+            created_at: unix_timestamp(),
+            items: Vec::new(),
+        }),
+    }
+}
+
+/// Save the specified queue into the specified file.
+///
+/// # Arguments
+/// * `path` - path to queue file
+/// * `queue` - queue
+pub fn save_queue(path: &str, queue: &RevokeQueue) -> Result<(), String> {
+    save_state(path, queue).map_err(|e| e.to_string())
+}
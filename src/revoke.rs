@@ -0,0 +1,279 @@
+use ::evmscan::prelude::*;
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+
+use appvkek_core::fees::Fees;
+use appvkek_core::labels::Labels;
+use appvkek_core::provider::ChainRpc;
+use appvkek_core::revoke::{build_permit_typed_data, build_revoke_tx, check_restrictions, estimate_revoke_costs, NoncePlan, PermitDomain, RevokeCost, RevokeTx, TokenRestriction};
+use appvkek_core::scanner::format_token_name;
+use appvkek_core::types::*;
+use appvkek_core::util::{chain_id, chain_name, format_abbreviated, native_symbol, to_checksum_address};
+
+/// Which approvals to revoke.
+pub struct RevokeFilter {
+    /// Lowercased token contract addresses to revoke approvals of; all if empty
+    pub tokens: Vec<String>,
+
+    /// Lowercased spender addresses to revoke approvals to; all if empty
+    pub spenders: Vec<String>,
+
+    /// Minimum risk score of approvals to revoke
+    pub min_risk: u32,
+}
+
+impl RevokeFilter {
+    /// Return whether approval of the specified spender on the specified
+    /// token contract should be revoked.
+    ///
+    /// # Arguments
+    /// * `token_address` - token contract address
+    /// * `spender` - spender address
+    /// * `sa` - spender's allowance
+    pub fn matches(&self, token_address: &str, spender: &str, sa: &SpenderAllowance) -> bool {
+        sa.allowance > 0.0
+            && sa.risk.score >= self.min_risk
+            && (self.tokens.is_empty() || self.tokens.contains(&token_address.to_lowercase()))
+            && (self.spenders.is_empty() || self.spenders.contains(&spender.to_lowercase()))
+    }
+}
+
+/// Checks of approvals to revoke shown in summary before confirmation, so
+/// that mistakes are caught before transactions are sent.
+pub struct RevokePreview {
+    /// Chain type
+    pub chain: ChainType,
+
+    /// Restricted token contracts (lowercased) as returned by
+    /// `check_restrictions()`
+    pub restrictions: HashMap<String, TokenRestriction>,
+
+    /// Estimated cost of each revoke transaction, or why estimation failed;
+    /// keyed by token contract, and spender addresses both lowercased
+    pub costs: HashMap<(String, String), Result<RevokeCost, String>>,
+
+    /// Nonces to print along with transactions if any; see `--nonces`
+    pub nonces: Option<NoncePlan>,
+
+    /// Fees to print along with transactions if any; see `--fee-preset`
+    pub fees: Option<Fees>,
+}
+
+/// Revokes to be signed as EIP-2612 permits, and relayed instead of sent as
+/// transactions from owner wallet; see `--gasless`.
+pub struct GaslessRevoke {
+    /// Chain type
+    pub chain: ChainType,
+
+    /// Owner wallet address; lowercased
+    pub owner_address: String,
+
+    /// Permit domains of token contracts (lowercased) supporting EIP-2612
+    /// permit as returned by `query_permit_domains()`
+    pub permits: HashMap<String, PermitDomain>,
+
+    /// Unix timestamp (in seconds) after which permits are rejected
+    pub deadline: u64,
+}
+
+/// Return all matching approvals with non-zero allowance along with their
+/// token contracts.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `filter` - which approvals to revoke
+pub fn matching_approvals<'a>(results: &'a [QueryResult], filter: &RevokeFilter) -> Vec<(&'a TokenContractWithSpenderAllowances, &'a String, &'a SpenderAllowance)> {
+    results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .flat_map(|r| r.spender_allowances.iter().map(move |(spender, sa)| (r, spender, sa)))
+        .filter(|(r, spender, sa)| filter.matches(&r.address, spender, sa))
+        .collect()
+}
+
+/// Return token contract addresses having approvals to revoke; lowercased,
+/// without duplicates.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `filter` - which approvals to revoke
+pub fn tokens_to_revoke(results: &[QueryResult], filter: &RevokeFilter) -> Vec<String> {
+    results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .filter(|r| r.spender_allowances.iter().any(|(spender, sa)| filter.matches(&r.address, spender, sa)))
+        .map(|r| r.address.to_lowercase())
+        .collect::<std::collections::BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// Check token contracts of all matching approvals for restrictions, and
+/// estimate cost of each revoke transaction sent from the owner wallet.
+///
+/// # Arguments
+/// * `rpc` - chain RPC
+/// * `semaphore` - semaphore limiting concurrent requests
+/// * `chain` - chain type
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `filter` - which approvals to revoke
+/// * `owner_address` - owner wallet address; lowercased
+/// * `fees` - fees to send transactions with; current gas price if `None`
+pub async fn build_preview<R: ChainRpc + ?Sized>(rpc: &R, semaphore: &Semaphore, chain: ChainType, results: &[QueryResult], filter: &RevokeFilter, owner_address: &str, fees: Option<Fees>) -> RevokePreview {
+    let restrictions = check_restrictions(rpc, semaphore, &tokens_to_revoke(results, filter), owner_address).await;
+
+    let (keys, txs): (Vec<(String, String)>, Vec<RevokeTx>) = matching_approvals(results, filter).into_iter()
+        .filter_map(|(r, spender, _)| {
+            let tx = build_revoke_tx(&r.address, spender).ok()?;
+            Some(((tx.token_address.to_owned(), tx.spender.to_owned()), tx))
+        })
+        .unzip();
+    let costs = estimate_revoke_costs(rpc, semaphore, owner_address, &txs, fees.as_ref()).await;
+
+    RevokePreview {
+        chain,
+        restrictions,
+        costs: keys.into_iter().zip(costs.into_iter().map(|res| res.map_err(|e| e.to_string()))).collect(),
+        nonces: None,
+        fees,
+    }
+}
+
+/// Format estimated cost of revoke transaction e.g. `gas ~46K (~0.00023 BNB)`.
+///
+/// # Arguments
+/// * `cost` - estimated cost
+/// * `chain` - chain type
+fn format_cost(cost: &RevokeCost, chain: ChainType) -> String {
+    match cost.fee {
+        Some(fee) => {
+            let fee = format!("{:.6}", fee);
+            let fee = fee.trim_end_matches('0').trim_end_matches('.');
+            format!("gas ~{} (~{} {})", format_abbreviated(cost.gas as f64), if fee == "0" { "<0.000001" } else { fee }, native_symbol(chain))
+        },
+        None => format!("gas ~{}", format_abbreviated(cost.gas as f64)),
+    }
+}
+
+/// Return why revoke on token contract with the specified restriction would
+/// likely revert.
+///
+/// # Arguments
+/// * `restriction` - restriction of token contract
+fn format_restriction(restriction: &TokenRestriction) -> String {
+    match (restriction.is_paused, restriction.is_owner_blacklisted) {
+        (true, true) => "token is paused, and has blacklisted this wallet".to_owned(),
+        (true, false) => "token is paused".to_owned(),
+        _ => "token has blacklisted this wallet".to_owned(),
+    }
+}
+
+/// Print unsigned transactions which revoke all matching approvals with
+/// non-zero allowance to stdout, riskiest first. Each of them is to be sent
+/// from the owner wallet. Summary of them is printed to stderr first as
+/// what each does in words along with its estimated cost, and confirmation
+/// is asked unless `assume_yes`.
+/// With `gasless`, approvals on token contracts supporting EIP-2612 permit
+/// are printed as typed data to sign instead, along with `relay-revoke`
+/// command to submit the signature. With `qr`, each transaction is also
+/// printed as EIP-681 URI along with its QR code.
+/// Return transactions printed, in the same order.
+/// Approvals on token contracts which are paused, or have blacklisted the
+/// owner wallet are warned as their revokes would likely revert, and placed
+/// last so the others go through first. So are those whose gas estimation
+/// failed, as they would revert for some other reason.
+/// With nonces planned in `preview`, each transaction is printed with its
+/// nonce, consecutive in the order they are printed. With fees in `preview`,
+/// each is printed with them; bumped for those replacing pending ones.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed by `risk::assess_all()`
+/// * `filter` - which approvals to revoke
+/// * `owner_address` - owner wallet address
+/// * `labels` - labels of well-known addresses
+/// * `preview` - checks of approvals as returned by `build_preview()`
+/// * `assume_yes` - whether to skip confirmation
+/// * `gasless` - permits to print instead of transactions if any
+/// * `qr` - whether to print EIP-681 URI, and QR code of each transaction
+#[allow(clippy::too_many_arguments)]
+pub fn print_revoke_txs(results: &[QueryResult], filter: &RevokeFilter, owner_address: &str, labels: &Labels, preview: &RevokePreview, assume_yes: bool, gasless: Option<&GaslessRevoke>, qr: bool) -> Result<Vec<RevokeTx>, String> {
+    let restrictions = &preview.restrictions;
+    let cost_of = |r: &TokenContractWithSpenderAllowances, spender: &str| preview.costs.get(&(r.address.to_lowercase(), spender.to_lowercase()));
+    let mut approvals = matching_approvals(results, filter);
+    approvals.sort_by_key(|(r, spender, sa)| (restrictions.contains_key(&r.address.to_lowercase()) || matches!(cost_of(r, spender), Some(Err(_))), std::cmp::Reverse(sa.risk.score)));
+
+    if approvals.is_empty() {
+        println!("No matching approvals to revoke");
+        return Ok(Vec::new());
+    }
+
+    let mut summary = format!("About to revoke {} approvals of {}", approvals.len(), owner_address);
+    for (r, spender, sa) in approvals.iter() {
+        summary.push_str(&format!("\n  * Revoke {} allowance of [{}] for {} (risk={})", crate::amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol), format_token_name(r), labels.format(spender), sa.risk.score));
+        if let Some(restriction) = restrictions.get(&r.address.to_lowercase()) {
+            summary.push_str(&format!(" - WARNING: {}; revoke would likely revert", format_restriction(restriction)));
+        }
+        let is_permit = gasless.is_some_and(|g| g.permits.contains_key(&r.address.to_lowercase()));
+        if gasless.is_some() && !is_permit {
+            summary.push_str(" - no permit support; needs gas");
+        }
+        match cost_of(r, spender) {
+            Some(Ok(cost)) if !is_permit => summary.push_str(&format!(" - {}", format_cost(cost, preview.chain))),
+            Some(Err(e)) if !is_permit => summary.push_str(&format!(" - WARNING: gas estimation failed; revoke would likely revert ({})", e)),
+            _ => (),
+        }
+    }
+    if let Some(plan) = preview.nonces.as_ref().filter(|p| p.num_pending > 0) {
+        match plan.num_replacing {
+            0 => summary.push_str(&format!("\nWARNING: {} transactions of wallet are pending; revokes are queued behind them from nonce {}. Use --replace if they are stuck", plan.num_pending, plan.next)),
+            n => summary.push_str(&format!("\nFirst {} revokes replace pending transactions of wallet from nonce {} with higher gas price", n, plan.next)),
+        }
+    }
+    if !crate::confirm::confirm(&summary, assume_yes)? {
+        return Err("Aborted; no revoke transactions generated".to_owned());
+    }
+
+    match gasless {
+        Some(g) => {
+            let num_permits = approvals.iter().filter(|(r, _, _)| g.permits.contains_key(&r.address.to_lowercase())).count();
+            println!("Sign the following {} permits with {} via eth_signTypedData_v4, then submit each signature via relay-revoke; {} others need transactions sent from it", num_permits, owner_address, approvals.len() - num_permits);
+        },
+        None => println!("Send the following {} transactions from {} to revoke approvals", approvals.len(), owner_address),
+    }
+    let mut txs: Vec<RevokeTx> = Vec::new();
+    for (r, spender, sa) in approvals {
+        println!("[{}] {} - revoke {} - {} (risk={})", format_token_name(r), labels.format(&r.address), labels.format(spender), crate::amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol), sa.risk.score);
+        if let Some((g, domain)) = gasless.and_then(|g| Some(g).zip(g.permits.get(&r.address.to_lowercase()))) {
+            println!("  typed data: {}", build_permit_typed_data(domain, &g.owner_address, spender, g.deadline));
+            println!("  relay:      appvkek relay-revoke -c {} -a {} --token {} --spender {} --deadline {} --signature <signature>", chain_name(g.chain), to_checksum_address(&g.owner_address), to_checksum_address(&r.address), to_checksum_address(spender), g.deadline);
+            continue;
+        }
+
+        let tx = build_revoke_tx(&r.address, spender)?;
+        println!("  to:    {}", to_checksum_address(&tx.token_address));
+        println!("  value: 0");
+        println!("  data:  {}", tx.data);
+        let is_replacing = preview.nonces.as_ref().is_some_and(|plan| plan.is_replacing(txs.len()));
+        if let Some(plan) = preview.nonces.as_ref() {
+            println!("  nonce: {}", plan.nonce_of(txs.len()));
+        }
+        match (preview.fees, preview.nonces.as_ref().and_then(|plan| plan.replacement_gas_price)) {
+            (Some(fees), _) => {
+                let fees = if is_replacing { fees.bumped() } else { fees };
+                println!("  max fee per gas:          {} wei", fees.max_fee_per_gas);
+                println!("  max priority fee per gas: {} wei", fees.max_priority_fee_per_gas);
+            },
+            (None, Some(gas_price)) if is_replacing => println!("  gas price: {} wei (replaces pending transaction)", gas_price),
+            _ => (),
+        }
+        if let Some(restriction) = restrictions.get(&r.address.to_lowercase()) {
+            println!("  warning: {}; revoke would likely revert", format_restriction(restriction));
+        }
+        if qr {
+            let uri = tx.to_eip681_uri(chain_id(preview.chain));
+            println!("  uri:   {}", uri);
+            println!("{}", crate::qr::render_qr(&uri)?);
+        }
+        txs.push(tx);
+    }
+
+    Ok(txs)
+}
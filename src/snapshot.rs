@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use appvkek_core::report::*;
+use appvkek_core::util::chain_short_name;
+
+/// Content of snapshot file; report of a single wallet as of JSON output of
+/// `export`, or array of them for multiple wallets.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum SnapshotFile {
+    Single(Box<Report>),
+    Multiple(Vec<Report>),
+}
+
+/// Load reports from the specified snapshot file.
+///
+/// # Arguments
+/// * `path` - path to snapshot file
+pub fn load_snapshot(path: &str) -> Result<Vec<Report>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error reading snapshot ({}); err={}", path, e)),
+    };
+    match serde_json::from_str::<SnapshotFile>(&content) {
+        Ok(SnapshotFile::Single(report)) => Ok(vec![*report]),
+        Ok(SnapshotFile::Multiple(reports)) => Ok(reports),
+        Err(e) => Err(format!("Error parsing snapshot ({}); err={}", path, e)),
+    }
+}
+
+/// Save the specified reports into snapshot file; as a single report if
+/// there is only one of them.
+///
+/// # Arguments
+/// * `path` - path to snapshot file
+/// * `reports` - reports of all scanned wallets
+pub fn save_snapshot(path: &str, mut reports: Vec<Report>) -> Result<(), String> {
+    let file = if reports.len() == 1 {
+        SnapshotFile::Single(Box::new(reports.remove(0)))
+    }
+    else {
+        SnapshotFile::Multiple(reports)
+    };
+    let content = match serde_json::to_string_pretty(&file) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Error serializing snapshot; err={}", e)),
+    };
+    match std::fs::write(path, content) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error writing snapshot ({}); err={}", path, e)),
+    }
+}
+
+/// Format address of the specified chain for display; prefixed with EIP-3770
+/// short name of chain e.g. `bnb:0x...` if `chain_prefix`.
+///
+/// # Arguments
+/// * `address` - address
+/// * `chain` - chain name e.g. `bsc`
+/// * `chain_prefix` - whether to prefix address with short name of chain
+fn format_address(address: &str, chain: &str, chain_prefix: bool) -> String {
+    match crate::parse_chain(chain) {
+        Ok(chain) if chain_prefix => format!("{}:{}", chain_short_name(chain), address),
+        _ => address.to_owned(),
+    }
+}
+
+/// Format approval for display e.g. `[BUSD Token (BUSD)] 0x... - 0x... (label)`.
+///
+/// # Arguments
+/// * `entry` - approval
+/// * `chain_prefix` - whether to prefix addresses with short name of chain
+fn format_entry(entry: &ApprovalEntry, chain_prefix: bool) -> String {
+    let token_name = if entry.token_symbol.is_empty() { entry.token_name.to_owned() } else { format!("{} ({})", entry.token_name, entry.token_symbol) };
+    let spender = format_address(&entry.spender, &entry.chain, chain_prefix);
+    let spender = match &entry.spender_label {
+        Some(label) => format!("{} ({})", spender, label),
+        None => spender,
+    };
+    format!("[{}] {} - {}", token_name, format_address(&entry.token_address, &entry.chain, chain_prefix), spender)
+}
+
+/// Format allowance for display; `unlimited` if it's effectively unlimited,
+/// and abbreviated with token symbol if `--human`.
+///
+/// # Arguments
+/// * `entry` - approval
+fn format_allowance(entry: &ApprovalEntry) -> String {
+    if entry.is_unlimited && !crate::amount::is_human() {
+        return "unlimited".to_owned();
+    }
+    crate::amount::format_allowance(entry.allowance, entry.is_unlimited, &entry.token_symbol)
+}
+
+/// Print difference between two snapshots to stdout.
+///
+/// # Arguments
+/// * `diff` - difference from `diff_reports()`
+/// * `is_multi_wallet` - whether to include owner of each approval
+/// * `chain_prefix` - whether to prefix addresses with short name of chain
+pub fn print_diff(diff: &ReportDiff, is_multi_wallet: bool, chain_prefix: bool) {
+    let owner = |entry: &ApprovalEntry| if is_multi_wallet { format!(" (owner {})", format_address(&entry.owner, &entry.chain, chain_prefix)) } else { String::new() };
+
+    for entry in diff.added.iter() {
+        println!("[Added] {} - {} (risk={}){}", format_entry(entry, chain_prefix), format_allowance(entry), entry.risk_score, owner(entry));
+    }
+    for entry in diff.removed.iter() {
+        println!("[Removed] {} - {} (risk={}){}", format_entry(entry, chain_prefix), format_allowance(entry), entry.risk_score, owner(entry));
+    }
+    for change in diff.changed.iter() {
+        println!("[Changed] {} - {} -> {} (risk={} -> {}){}", format_entry(&change.after, chain_prefix), format_allowance(&change.before), format_allowance(&change.after), change.before.risk_score, change.after.risk_score, owner(&change.after));
+    }
+    println!("{} added, {} removed, {} changed", diff.added.len(), diff.removed.len(), diff.changed.len());
+}
+
+/// Print exposure to each spender aggregated across chains to stdout.
+///
+/// # Arguments
+/// * `exposures` - exposures from `aggregate_by_spender()`
+/// * `chain_prefix` - whether to list spender address prefixed with short name
+///   of each chain
+pub fn print_exposures(exposures: &[SpenderExposure], chain_prefix: bool) {
+    for e in exposures.iter() {
+        let spender = if chain_prefix {
+            e.chains.iter().map(|c| format_address(&e.spender, c, true)).collect::<Vec<String>>().join(", ")
+        }
+        else {
+            e.spender.to_owned()
+        };
+        let spender = match &e.spender_label {
+            Some(label) => format!("{} ({})", spender, label),
+            None => spender,
+        };
+        println!("[{}] {} - {} approvals, {} unlimited (risk={})", e.chains.join(", "), spender, e.num_approvals, e.num_unlimited, e.max_risk_score);
+    }
+    let num_cross_chain = exposures.iter().filter(|e| e.chains.len() > 1).count();
+    println!("{} spenders, {} of them on multiple chains", exposures.len(), num_cross_chain);
+}
+
+/// Print exposure to each protocol to stdout, along with its approvals
+/// grouped by token, and total allowance of each token.
+///
+/// # Arguments
+/// * `exposures` - exposures from `aggregate_by_protocol()`
+/// * `chain_prefix` - whether to prefix addresses with short name of chain
+///   instead of listing chain of each token
+pub fn print_protocol_exposures(exposures: &[ProtocolExposure], chain_prefix: bool) {
+    let is_multi_chain = !chain_prefix && exposures.iter()
+        .flat_map(|e| e.approvals.iter())
+        .map(|a| a.chain.as_str())
+        .collect::<HashSet<&str>>()
+        .len() > 1;
+
+    for (i, e) in exposures.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{} - {} contracts, {} approvals, {} unlimited (risk={})", e.protocol.as_deref().unwrap_or("Unlabeled"), e.num_spenders, e.num_approvals, e.num_unlimited, e.max_risk_score);
+
+        // approvals are sorted by chain, then token already
+        for token_approvals in e.approvals.chunk_by(|a, b| a.chain == b.chain && a.token_address.eq_ignore_ascii_case(&b.token_address)) {
+            let first = &token_approvals[0];
+            let token_name = if first.token_symbol.is_empty() { first.token_name.to_owned() } else { format!("{} ({})", first.token_name, first.token_symbol) };
+            let chain = if is_multi_chain { format!("[{}] ", first.chain) } else { String::new() };
+            let total = if token_approvals.iter().any(|a| a.is_unlimited) {
+                "unlimited".to_owned()
+            }
+            else {
+                format!("{} in total", crate::amount::format_amount(token_approvals.iter().map(|a| a.allowance).sum(), &first.token_symbol))
+            };
+            println!("  {}[{}] {} - {}", chain, token_name, format_address(&first.token_address, &first.chain, chain_prefix), total);
+            for a in token_approvals {
+                let spender = format_address(&a.spender, &a.chain, chain_prefix);
+                let spender = match &a.spender_label {
+                    Some(label) => format!("{} ({})", spender, label),
+                    None => spender,
+                };
+                println!("    * {} - {} (risk={})", spender, format_allowance(a), a.risk_score);
+            }
+        }
+    }
+}
+
+
+/// Print summary of each spender across tokens of a single wallet to stdout.
+///
+/// # Arguments
+/// * `summaries` - summaries from `summarize_by_spender()`
+/// * `has_prices` - whether USD prices are known at all; exposure is left
+///   out otherwise
+pub fn print_spender_summaries(summaries: &[SpenderSummary], has_prices: bool) {
+    for s in summaries.iter() {
+        let spender = match &s.spender_label {
+            Some(label) => format!("{} ({})", s.spender, label),
+            None => s.spender.to_owned(),
+        };
+        let exposure = if !has_prices {
+            String::new()
+        }
+        else if s.num_unpriced > 0 {
+            format!(", ${:.2} exposed + {} unpriced", s.exposure_usd, s.num_unpriced)
+        }
+        else {
+            format!(", ${:.2} exposed", s.exposure_usd)
+        };
+        println!("{} - {} tokens, {} unlimited{} (risk={})", spender, s.num_tokens, s.num_unlimited, exposure, s.max_risk_score);
+    }
+    println!("{} spenders", summaries.len());
+}
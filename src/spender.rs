@@ -0,0 +1,87 @@
+use ::evmscan::prelude::ChainType;
+use std::collections::{HashMap, HashSet};
+
+use appvkek_core::labels::Labels;
+use appvkek_core::scanner::format_token_name;
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+
+use crate::amount;
+use crate::Links;
+use crate::timestamp::format_timestamp_with_age;
+
+/// Return approvals to query allowance of the specified spender on every
+/// token contract of `ct_txs`, and `held_tokens`, regardless of spenders
+/// approved on it. Approval record is kept if the spender has been seen
+/// approved on it.
+///
+/// # Arguments
+/// * `ct_txs` - collected approvals of the wallet
+/// * `held_tokens` - token contracts the wallet has ever held; lowercased
+/// * `spender` - spender address; lowercased
+pub fn spender_approvals(ct_txs: &ApprovalsMap, held_tokens: &HashSet<String>, spender: &str) -> ApprovalsMap {
+    let mut approvals: ApprovalsMap = held_tokens.iter()
+        .map(|ct| (ct.to_owned(), HashMap::from([(spender.to_owned(), ApprovalRecord::default())])))
+        .collect();
+    for (ct, spenders) in ct_txs.iter() {
+        let approval = spenders.get(spender).cloned().unwrap_or_default();
+        approvals.insert(ct.to_owned(), HashMap::from([(spender.to_owned(), approval)]));
+    }
+    approvals
+}
+
+/// Print token contracts on which the spender has non-zero allowance to
+/// stdout, unlimited ones first.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` of approvals from `spender_approvals()`
+/// * `chain` - chain type; for explorer links
+/// * `spender` - spender address; lowercased
+/// * `owner_address` - owner wallet address; lowercased
+/// * `owner_name` - owner wallet address formatted for display
+/// * `labels` - labels of well-known addresses
+/// * `links` - links to append
+pub fn print_spender_allowances(results: &[QueryResult], chain: ChainType, spender: &str, owner_address: &str, owner_name: &str, labels: &Labels, links: Links) {
+    let mut tokens: Vec<(&TokenContractWithSpenderAllowances, &SpenderAllowance)> = results.iter()
+        .filter_map(|res| res.as_ref().ok())
+        .filter_map(|r| r.spender_allowances.get(spender).map(|sa| (r, sa)))
+        .filter(|(_, sa)| sa.allowance > 0.0)
+        .collect();
+    tokens.sort_by(|(a, a_sa), (b, b_sa)| b_sa.is_unlimited.cmp(&a_sa.is_unlimited).then_with(|| a.name.cmp(&b.name)));
+
+    println!("Tokens of {} approved to {}", owner_name, labels.format(spender));
+    if links != Links::None {
+        println!("  spender: {}", explorer_address_url(chain, spender));
+    }
+    for (r, sa) in tokens.iter() {
+        if sa.is_unlimited && !amount::is_human() {
+            println!("[{}] {} - {} (unlimited)", format_token_name(r), labels.format(&r.address), sa.allowance);
+        }
+        else {
+            println!("[{}] {} - {}", format_token_name(r), labels.format(&r.address), amount::format_allowance(sa.allowance, sa.is_unlimited, &r.symbol));
+        }
+        if links != Links::None {
+            println!("      token: {}", explorer_token_url(chain, &r.address));
+        }
+        if links == Links::Revoke {
+            println!("      revoke: {}", revokecash_url(chain_id(chain), owner_address));
+        }
+        // approval may have been made by means other than approve transaction e.g. permit
+        if sa.approval.block_number > 0 {
+            if sa.approval.tx_hash.is_empty() {
+                println!("      approved: block {} at {}", sa.approval.block_number, format_timestamp_with_age(sa.approval.timestamp));
+            }
+            else {
+                println!("      approved: block {} at {} - {}", sa.approval.block_number, format_timestamp_with_age(sa.approval.timestamp), explorer_tx_url(chain, &sa.approval.tx_hash));
+            }
+            if let Some(issue) = &sa.approval.issue {
+                println!("      calldata issue: {}", issue);
+            }
+        }
+    }
+
+    for e in results.iter().filter_map(|res| res.as_ref().err()) {
+        println!("[Error] {} - {}", to_checksum_address(&e.token_address), e.error);
+    }
+    println!("{} of {} token contracts have non-zero allowance", tokens.len(), results.len());
+}
@@ -0,0 +1,43 @@
+use chrono::{Local, SecondsFormat, TimeZone, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use appvkek_core::util::format_age;
+
+/// Whether to show timestamps in UTC instead of local time
+static USE_UTC: AtomicBool = AtomicBool::new(false);
+
+/// Show timestamps in UTC instead of local time from now on.
+///
+/// # Arguments
+/// * `use_utc` - whether to show timestamps in UTC
+pub fn set_utc(use_utc: bool) {
+    USE_UTC.store(use_utc, Ordering::Relaxed);
+}
+
+/// Format the specified unix timestamp as ISO-8601 in local time, or in UTC
+/// if set by `set_utc()` e.g. `2022-04-15T12:20:00+07:00`, or
+/// `2022-04-15T05:20:00Z`.
+///
+/// # Arguments
+/// * `timestamp` - unix timestamp in seconds
+pub fn format_timestamp(timestamp: u64) -> String {
+    let utc = match Utc.timestamp_opt(timestamp as i64, 0).single() {
+        Some(res) => res,
+        None => return timestamp.to_string(),
+    };
+    if USE_UTC.load(Ordering::Relaxed) {
+        utc.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+    else {
+        utc.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+/// Format the specified unix timestamp as ISO-8601 along with its age e.g.
+/// `2022-04-15T05:20:00Z (2 years ago)`.
+///
+/// # Arguments
+/// * `timestamp` - unix timestamp in seconds
+pub fn format_timestamp_with_age(timestamp: u64) -> String {
+    format!("{} ({})", format_timestamp(timestamp), format_age(timestamp))
+}
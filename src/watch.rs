@@ -0,0 +1,483 @@
+use ::evmscan::environ::Context;
+use ::evmscan::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use appvkek_core::failures::FailedContracts;
+use appvkek_core::labels::Labels;
+use appvkek_core::mempool::{build_replacement_tx, monitor_pending_approvals, PendingApproval};
+use appvkek_core::metadata::MetadataCache;
+use appvkek_core::notify::{post_webhook, WebhookEvent, WebhookEventKind};
+use appvkek_core::report::build_report;
+use appvkek_core::state::*;
+use appvkek_core::types::*;
+use appvkek_core::util::*;
+use appvkek_core::scanner;
+
+use crate::RiskContext;
+
+/// Options for watch mode.
+pub struct WatchOptions {
+    /// Interval between each scan
+    pub interval: Duration,
+
+    /// Webhook URL to POST event to
+    pub webhook_url: Option<String>,
+
+    /// Whether to batch queries via Multicall3 contract
+    pub use_multicall: bool,
+
+    /// Whether to collect approvals from event logs; for contract wallets
+    pub use_logs: bool,
+
+    /// Allowance threshold (in token unit) to report when crossed
+    pub threshold: Option<f64>,
+
+    /// Minimum risk score to report an existing approval once its score
+    /// crosses it
+    pub min_risk: Option<u32>,
+
+    /// Whether to keep approvals to well-known burn addresses; see
+    /// `--show-burn`
+    pub show_burn: bool,
+
+    /// Labels of well-known addresses
+    pub labels: Labels,
+
+    /// Path to state file to persist state into; only set in daemon mode
+    pub state_file: Option<String>,
+
+    /// Path to SQLite database to record results of every round into
+    pub db: Option<String>,
+
+    /// Whether to fail the round on the first transaction which can't be
+    /// parsed instead of skipping it with warning
+    pub fail_fast: bool,
+
+    /// WebSocket URL of RPC endpoint to monitor pending approvals via
+    pub ws_url: Option<String>,
+
+    /// Whether to print transaction replacing each pending approval
+    pub print_replacement_tx: bool,
+
+    /// HTTP client to POST webhook events with; see `create_client()`
+    pub http_client: reqwest::Client,
+}
+
+/// Delay before reconnecting to WebSocket endpoint once monitoring of
+/// pending transactions failed
+const MEMPOOL_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Change of a single approval found between two snapshots.
+struct ApprovalChange {
+    /// Kind of change
+    kind: WebhookEventKind,
+
+    /// Token contract address
+    token_address: String,
+
+    /// Spender address
+    spender: String,
+
+    /// Current allowance balance
+    allowance: f64,
+
+    /// Previous allowance balance if any
+    previous_allowance: Option<f64>,
+
+    /// Current risk score
+    risk_score: u32,
+
+    /// Previous risk score if any
+    previous_risk_score: Option<u32>,
+}
+
+/// Build a snapshot from results of a scan which have been assessed by
+/// `risk::assess_all()`.
+/// Token contracts which failed to be queried will retain their entries from
+/// `prev` snapshot so that they won't be reported as new in the next round.
+///
+/// # Arguments
+/// * `results` - results from `query_all()` which have been assessed
+/// * `prev` - previous snapshot if any
+fn build_snapshot(results: Vec<QueryResult>, prev: Option<&mut Snapshot>) -> Snapshot {
+    let mut snapshot: Snapshot = HashMap::new();
+    let mut prev = prev;
+
+    for res in results {
+        match res {
+            Ok(r) => {
+                let risk_scores = r.spender_allowances.iter()
+                    .map(|(spender, sa)| (spender.to_owned(), sa.risk.score))
+                    .collect();
+                let spender_allowances = r.spender_allowances.into_iter()
+                    .map(|(spender, sa)| (spender, sa.allowance))
+                    .collect();
+                snapshot.insert(r.address, TokenSnapshot { name: r.name, spender_allowances, risk_scores });
+            },
+            Err(e) => {
+                if let Some(prev_entry) = prev.as_mut().and_then(|p| p.remove(&e.token_address)) {
+                    snapshot.insert(e.token_address, prev_entry);
+                }
+            }
+        }
+    }
+
+    snapshot
+}
+
+/// Compare two snapshots, then return all events found.
+/// An approval is new if it's absent from, or has zero allowance in the
+/// previous snapshot i.e. approved again after revoked. An existing approval
+/// crosses risk threshold only if its previous score is known, so that
+/// state files of older versions don't re-alert on everything.
+///
+/// # Arguments
+/// * `prev` - previous snapshot
+/// * `curr` - current snapshot
+/// * `threshold` - allowance threshold to report when crossed
+/// * `min_risk` - minimum risk score to report when crossed
+fn diff_snapshots(prev: &Snapshot, curr: &Snapshot, threshold: Option<f64>, min_risk: Option<u32>) -> Vec<ApprovalChange> {
+    let mut events = Vec::new();
+
+    for (token_address, token) in curr.iter() {
+        let prev_token = prev.get(token_address);
+        for (spender, allowance) in token.spender_allowances.iter() {
+            let prev_allowance = prev_token.and_then(|t| t.spender_allowances.get(spender)).copied();
+            let prev_risk_score = prev_token.and_then(|t| t.risk_scores.get(spender)).copied();
+            let risk_score = token.risk_scores.get(spender).copied().unwrap_or_default();
+            let change = |kind: WebhookEventKind| ApprovalChange {
+                kind,
+                token_address: token_address.to_owned(),
+                spender: spender.to_owned(),
+                allowance: *allowance,
+                previous_allowance: prev_allowance,
+                risk_score,
+                previous_risk_score: prev_risk_score,
+            };
+
+            if *allowance <= 0.0 {
+                continue;
+            }
+            match prev_allowance {
+                Some(prev_allowance) if prev_allowance > 0.0 => {
+                    if let Some(threshold) = threshold {
+                        if prev_allowance < threshold && *allowance >= threshold {
+                            events.push(change(WebhookEventKind::ThresholdCrossed));
+                        }
+                    }
+                    if let (Some(min_risk), Some(prev_risk_score)) = (min_risk, prev_risk_score) {
+                        if prev_risk_score < min_risk && risk_score >= min_risk {
+                            events.push(change(WebhookEventKind::RiskCrossed));
+                        }
+                    }
+                },
+                _ => events.push(change(WebhookEventKind::NewApproval)),
+            }
+        }
+    }
+
+    events
+}
+
+/// Report the specified pending approval to stdout, and to webhook if
+/// specified. Transaction replacing it is printed as well if
+/// `print_replacement_tx`.
+///
+/// # Arguments
+/// * `client` - HTTP client
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+/// * `labels` - labels of well-known addresses
+/// * `webhook_url` - webhook URL to POST event to
+/// * `print_replacement_tx` - whether to print transaction replacing it
+/// * `pending` - pending approval
+async fn report_pending_approval(client: &reqwest::Client, chain: ChainType, owner_address: &str, labels: &Labels, webhook_url: Option<&str>, print_replacement_tx: bool, pending: PendingApproval) {
+    let token_name = pending.token_name.to_owned().unwrap_or_default();
+    let amount = match pending.token_amount() {
+        Some(res) => crate::amount::format_allowance(res, is_unlimited_allowance(pending.amount), ""),
+        None => format!("{} (raw)", pending.amount),
+    };
+    let verb = if pending.is_increase { "increase by " } else { "" };
+    println!("[Pending] [{}] {} - {} - {}{} - {}", token_name, labels.format(&pending.token_address), labels.format(&pending.spender), verb, amount, explorer_tx_url(chain, &pending.tx_hash));
+
+    if print_replacement_tx {
+        match build_replacement_tx(&pending) {
+            Ok(tx) => {
+                println!("  Send the following from {} before it confirms to replace it with revoke", owner_address);
+                println!("  to:    {}", to_checksum_address(&tx.revoke.token_address));
+                println!("  value: 0");
+                println!("  data:  {}", tx.revoke.data);
+                println!("  nonce: {}", tx.nonce);
+                if let Some(gas_price) = tx.gas_price {
+                    println!("  gas price: {} wei", gas_price);
+                }
+                if let Some(max_fee) = tx.max_fee_per_gas {
+                    println!("  max fee: {} wei", max_fee);
+                }
+                if let Some(max_priority_fee) = tx.max_priority_fee_per_gas {
+                    println!("  max priority fee: {} wei", max_priority_fee);
+                }
+            },
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+
+    if let Some(url) = webhook_url {
+        let event = WebhookEvent {
+            event: WebhookEventKind::PendingApproval,
+            chain: chain_name(chain).to_owned(),
+            owner: to_checksum_address(owner_address),
+            token_address: to_checksum_address(&pending.token_address),
+            token_name,
+            spender: to_checksum_address(&pending.spender),
+            allowance: pending.token_amount().unwrap_or_default(),
+            previous_allowance: None,
+            risk_score: None,
+            previous_risk_score: None,
+            tx_hash: Some(pending.tx_hash.to_owned()),
+            timestamp: unix_timestamp(),
+        };
+
+        if let Err(e) = post_webhook(client, url, &event).await {
+            tracing::error!("{}", e);
+        }
+    }
+}
+
+/// Monitor pending transactions of owner wallet via WebSocket endpoint, and
+/// report approvals among them. It reconnects whenever connection or
+/// subscription fails.
+///
+/// This function never returns.
+///
+/// # Arguments
+/// * `client` - HTTP client
+/// * `ws_url` - WebSocket URL of RPC endpoint
+/// * `chain` - chain type
+/// * `owner_address` - owner wallet address; lowercased
+/// * `labels` - labels of well-known addresses
+/// * `webhook_url` - webhook URL to POST event to
+/// * `print_replacement_tx` - whether to print transaction replacing each pending approval
+async fn watch_mempool(client: reqwest::Client, ws_url: String, chain: ChainType, owner_address: String, labels: Labels, webhook_url: Option<String>, print_replacement_tx: bool) {
+    loop {
+        let res = monitor_pending_approvals(&ws_url, &owner_address, |pending| {
+            report_pending_approval(&client, chain, &owner_address, &labels, webhook_url.as_deref(), print_replacement_tx, pending)
+        }).await;
+        if let Err(e) = res {
+            tracing::error!("{}", e);
+        }
+
+        tokio::time::sleep(MEMPOOL_RECONNECT_DELAY).await;
+    }
+}
+
+/// Run watch mode which periodically re-scans for approvals of owner address.
+/// The first scan establishes the baseline, and its result is printed as in
+/// normal mode. Subsequent scans report only the changes.
+///
+/// Only transactions newer than the last scanned block are fetched from the
+/// explorer on each round, but allowances of all known approvals are queried
+/// again as they can change without owner's transactions.
+///
+/// Risk of approvals is assessed on every round, so that an existing
+/// approval whose spender turns risky e.g. listed as malicious is reported.
+///
+/// In daemon mode, the state is loaded from state file at start, and saved
+/// after every round so that a restart won't re-report all approvals.
+///
+/// With WebSocket endpoint specified, pending transactions of owner are
+/// monitored alongside, and approvals among them are reported as soon as
+/// they are seen in mempool.
+///
+/// This function never returns.
+///
+/// # Arguments
+/// * `web3` - web3 instance
+/// * `semaphore` - semaphore bounding the number of in-flight RPC calls
+/// * `ctx` - evmscan's context
+/// * `owner_address` - owner wallet address; lowercased
+/// * `opts` - watch options
+/// * `metadata_cache` - cache of token metadata
+/// * `failed_contracts` - record of failed token contracts
+/// * `risk_ctx` - risk assessment to apply on every round
+#[allow(clippy::too_many_arguments)]
+pub async fn run(web3: &Web3Type, semaphore: &Semaphore, ctx: &Context, owner_address: &str, opts: &WatchOptions, metadata_cache: &mut MetadataCache, failed_contracts: &mut FailedContracts, risk_ctx: &mut RiskContext) {
+    let client = &opts.http_client;
+    let mut state = WatchState::default();
+
+    if let Some(path) = &opts.state_file {
+        match load_state::<WatchState>(path) {
+            Ok(Some(res)) => {
+                tracing::info!("Resuming from state file ({}); last scanned block={}", path, res.last_scanned_block.map(|b| b.to_string()).unwrap_or_else(|| "none".to_owned()));
+                state = res;
+            },
+            Ok(None) => (),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut scan_db = match opts.db.as_deref().map(crate::db::ScanDb::open) {
+        Some(Ok(res)) => Some(res),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+        None => None,
+    };
+
+    if let Some(ws_url) = &opts.ws_url {
+        tokio::spawn(watch_mempool(client.clone(), ws_url.to_owned(), ctx.chain, owner_address.to_owned(), opts.labels.clone(), opts.webhook_url.to_owned(), opts.print_replacement_tx));
+    }
+
+    loop {
+        let start_block = state.last_scanned_block.map(|b| b + 1).unwrap_or(0);
+
+        let mut warnings = Vec::new();
+        let collected = if opts.use_logs {
+            scanner::collect_user_operation_approvals(ctx, owner_address, start_block, &mut state.approvals)
+        }
+        else {
+            scanner::collect_approvals(ctx, owner_address, start_block, &mut state.approvals, (!opts.fail_fast).then_some(&mut warnings))
+        };
+        for w in warnings.iter() {
+            tracing::warn!("Transaction {} was skipped; {}", w.tx_hash, w.error);
+        }
+        match collected {
+            Ok(last_block) => {
+                if last_block.is_some() {
+                    state.last_scanned_block = last_block;
+                }
+
+                let is_first_scan = state.baseline.is_none();
+                let mut results = scanner::query_all(web3, semaphore, state.approvals.clone(), owner_address, opts.use_multicall, metadata_cache, failed_contracts, |res| {
+                    if is_first_scan {
+                        crate::print_result(res, &opts.labels, opts.show_burn);
+                    }
+                }).await;
+                if !opts.show_burn {
+                    scanner::remove_burn_approvals(&mut results);
+                }
+                risk_ctx.assess(web3, semaphore, ctx, owner_address, &opts.labels, None, &mut results).await;
+
+                if let Some(scan_db) = scan_db.as_mut() {
+                    if let Err(e) = scan_db.record(&build_report(ctx.chain, owner_address, &results, &opts.labels)) {
+                        tracing::error!("{}", e);
+                    }
+                }
+
+                let snapshot = build_snapshot(results, state.baseline.as_mut());
+
+                if let Some(prev) = &state.baseline {
+                    for change in diff_snapshots(prev, &snapshot, opts.threshold, opts.min_risk) {
+                        let token_name = snapshot.get(&change.token_address).map(|t| t.name.to_owned()).unwrap_or_default();
+
+                        match change.kind {
+                            WebhookEventKind::NewApproval => println!("[New] [{}] {} - {} - {}", token_name, opts.labels.format(&change.token_address), opts.labels.format(&change.spender), crate::amount::format_amount(change.allowance, "")),
+                            WebhookEventKind::ThresholdCrossed => println!("[Threshold] [{}] {} - {} - {}", token_name, opts.labels.format(&change.token_address), opts.labels.format(&change.spender), crate::amount::format_amount(change.allowance, "")),
+                            WebhookEventKind::RiskCrossed => println!("[Risk] [{}] {} - {} - {} (risk={} -> {})", token_name, opts.labels.format(&change.token_address), opts.labels.format(&change.spender), crate::amount::format_amount(change.allowance, ""), change.previous_risk_score.unwrap_or_default(), change.risk_score),
+                            WebhookEventKind::PendingApproval => (),
+                        }
+
+                        if let Some(url) = &opts.webhook_url {
+                            let event = WebhookEvent {
+                                event: change.kind,
+                                chain: chain_name(ctx.chain).to_owned(),
+                                owner: to_checksum_address(owner_address),
+                                token_address: to_checksum_address(&change.token_address),
+                                token_name,
+                                spender: to_checksum_address(&change.spender),
+                                allowance: change.allowance,
+                                previous_allowance: change.previous_allowance,
+                                risk_score: Some(change.risk_score),
+                                previous_risk_score: change.previous_risk_score,
+                                tx_hash: None,
+                                timestamp: unix_timestamp(),
+                            };
+
+                            if let Err(e) = post_webhook(client, url, &event).await {
+                                tracing::error!("{}", e);
+                            }
+                        }
+                    }
+                }
+
+                state.baseline = Some(snapshot);
+
+                if let Some(path) = &opts.state_file {
+                    if let Err(e) = save_state(path, &state) {
+                        tracing::error!("{}", e);
+                    }
+                }
+            },
+            Err(e) => {
+                tracing::error!("{}", e);
+            }
+        }
+
+        tokio::time::sleep(opts.interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0x55d398326f99059ff775485246999027b3197955";
+    const SPENDER: &str = "0x10ed43c718714eb63d5aa57b78b54704e256024e";
+
+    fn snapshot(allowance: f64, risk_score: Option<u32>) -> Snapshot {
+        HashMap::from([(TOKEN.to_owned(), TokenSnapshot {
+            name: "Tether USD".to_owned(),
+            spender_allowances: HashMap::from([(SPENDER.to_owned(), allowance)]),
+            risk_scores: risk_score.map(|score| HashMap::from([(SPENDER.to_owned(), score)])).unwrap_or_default(),
+        })])
+    }
+
+    fn kinds(events: &[ApprovalChange]) -> Vec<WebhookEventKind> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn diff_snapshots_reports_new_approval() {
+        let events = diff_snapshots(&Snapshot::new(), &snapshot(1.0, Some(0)), None, None);
+        assert_eq!(kinds(&events), vec![WebhookEventKind::NewApproval]);
+        assert_eq!(events[0].previous_allowance, None);
+
+        assert!(diff_snapshots(&Snapshot::new(), &snapshot(0.0, Some(0)), None, None).is_empty());
+    }
+
+    #[test]
+    fn diff_snapshots_reports_approval_again_after_revoke() {
+        let events = diff_snapshots(&snapshot(0.0, Some(0)), &snapshot(f64::MAX, Some(0)), None, None);
+        assert_eq!(kinds(&events), vec![WebhookEventKind::NewApproval]);
+        assert_eq!(events[0].previous_allowance, Some(0.0));
+
+        assert!(diff_snapshots(&snapshot(1.0, Some(0)), &snapshot(0.0, Some(0)), None, None).is_empty());
+    }
+
+    #[test]
+    fn diff_snapshots_reports_threshold_crossed() {
+        let events = diff_snapshots(&snapshot(1.0, Some(0)), &snapshot(100.0, Some(0)), Some(100.0), None);
+        assert_eq!(kinds(&events), vec![WebhookEventKind::ThresholdCrossed]);
+
+        assert!(diff_snapshots(&snapshot(1.0, Some(0)), &snapshot(100.0, Some(0)), None, None).is_empty());
+        assert!(diff_snapshots(&snapshot(100.0, Some(0)), &snapshot(200.0, Some(0)), Some(100.0), None).is_empty());
+    }
+
+    #[test]
+    fn diff_snapshots_reports_risk_crossed() {
+        let events = diff_snapshots(&snapshot(1.0, Some(20)), &snapshot(1.0, Some(100)), None, Some(50));
+        assert_eq!(kinds(&events), vec![WebhookEventKind::RiskCrossed]);
+        assert_eq!((events[0].previous_risk_score, events[0].risk_score), (Some(20), 100));
+
+        // already above, unknown previous score as of older state file, or no minimum
+        assert!(diff_snapshots(&snapshot(1.0, Some(60)), &snapshot(1.0, Some(100)), None, Some(50)).is_empty());
+        assert!(diff_snapshots(&snapshot(1.0, None), &snapshot(1.0, Some(100)), None, Some(50)).is_empty());
+        assert!(diff_snapshots(&snapshot(1.0, Some(20)), &snapshot(1.0, Some(100)), None, None).is_empty());
+    }
+}